@@ -0,0 +1,6 @@
+-- Cancellation columns the voucher code has always read but no migration
+-- created; databases that have them already are left as they are
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS cancelled BOOLEAN DEFAULT FALSE,
+ADD COLUMN IF NOT EXISTS cancelled_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN IF NOT EXISTS cancel_tx_hash VARCHAR(66);
@@ -0,0 +1,11 @@
+-- Track failed password attempts per code across all IPs
+ALTER TABLE claim_attempts
+ADD COLUMN IF NOT EXISTS failure_reason VARCHAR(20);
+
+-- Creator-initiated lockout resets only count failures after this point
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS lockout_reset_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_claim_attempts_password_failures
+ON claim_attempts(voucher_code, attempted_at)
+WHERE failure_reason = 'invalid_password';
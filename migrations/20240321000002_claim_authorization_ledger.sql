@@ -0,0 +1,21 @@
+-- Ledger of every claim authorization signed by the backend
+CREATE TABLE IF NOT EXISTS claim_authorizations (
+    id BIGSERIAL PRIMARY KEY,
+    voucher_id VARCHAR(66) NOT NULL,
+    voucher_code VARCHAR(16) NOT NULL,
+    recipient_address VARCHAR(42) NOT NULL,
+    deadline TIMESTAMP WITH TIME ZONE NOT NULL,
+    signature_hash VARCHAR(66) NOT NULL, -- keccak256 of the signature, never the signature itself
+    status VARCHAR(20) NOT NULL DEFAULT 'live' CHECK (status IN ('live', 'superseded', 'expired')),
+    ip_address VARCHAR(45),
+    issued_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    superseded_at TIMESTAMP WITH TIME ZONE,
+    superseded_by BIGINT REFERENCES claim_authorizations(id)
+);
+
+-- At most one live authorization per voucher
+CREATE UNIQUE INDEX IF NOT EXISTS idx_claim_authorizations_one_live
+ON claim_authorizations(voucher_id)
+WHERE status = 'live';
+
+CREATE INDEX IF NOT EXISTS idx_claim_authorizations_voucher ON claim_authorizations(voucher_id, issued_at DESC);
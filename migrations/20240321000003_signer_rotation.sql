@@ -0,0 +1,14 @@
+-- Which backend key signed each claim authorization
+ALTER TABLE claim_authorizations
+ADD COLUMN IF NOT EXISTS signer_address VARCHAR(42);
+
+CREATE INDEX IF NOT EXISTS idx_claim_authorizations_signer
+ON claim_authorizations(signer_address)
+WHERE status = 'live';
+
+-- Signer roles changed at runtime; these override the configured roles on startup
+CREATE TABLE IF NOT EXISTS backend_signers (
+    address VARCHAR(42) PRIMARY KEY,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('signing', 'verifying', 'retired')),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
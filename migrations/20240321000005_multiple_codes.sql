@@ -0,0 +1,11 @@
+-- A voucher may have several codes, each revocable on its own
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS label VARCHAR(100), -- creator note, e.g. who the code was sent to
+ADD COLUMN IF NOT EXISTS revoked_at TIMESTAMP WITH TIME ZONE,
+ADD COLUMN IF NOT EXISTS replaced_by VARCHAR(16); -- code issued when this one was rotated
+
+CREATE INDEX IF NOT EXISTS idx_voucher_codes_active
+ON voucher_codes(voucher_id)
+WHERE revoked_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_claim_authorizations_code ON claim_authorizations(voucher_code);
@@ -0,0 +1,5 @@
+-- Room for longer checksummed codes (stored without dashes)
+ALTER TABLE voucher_codes ALTER COLUMN code TYPE VARCHAR(32);
+ALTER TABLE voucher_codes ALTER COLUMN replaced_by TYPE VARCHAR(32);
+ALTER TABLE claim_attempts ALTER COLUMN voucher_code TYPE VARCHAR(32);
+ALTER TABLE claim_authorizations ALTER COLUMN voucher_code TYPE VARCHAR(32);
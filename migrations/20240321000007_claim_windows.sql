@@ -0,0 +1,11 @@
+-- Optional window in which a voucher may be claimed; voucher-level, so the
+-- same on every code of a voucher
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS claimable_from TIMESTAMP WITH TIME ZONE,
+ADD COLUMN IF NOT EXISTS claimable_until TIMESTAMP WITH TIME ZONE,
+ADD COLUMN IF NOT EXISTS expiry_notified_at TIMESTAMP WITH TIME ZONE;
+
+-- Expired vouchers whose creator has not been told yet
+CREATE INDEX IF NOT EXISTS idx_voucher_codes_pending_expiry
+ON voucher_codes(claimable_until)
+WHERE claimable_until IS NOT NULL AND expiry_notified_at IS NULL AND claimed = FALSE AND cancelled = FALSE;
@@ -0,0 +1,16 @@
+-- Campaigns group vouchers created together, e.g. for a marketing event
+CREATE TABLE IF NOT EXISTS campaigns (
+    id BIGSERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    creator_address VARCHAR(42) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_campaigns_creator ON campaigns(creator_address);
+
+-- Voucher-level like the claim window: the same on every code of a voucher
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS campaign_id BIGINT REFERENCES campaigns(id);
+
+CREATE INDEX IF NOT EXISTS idx_voucher_codes_campaign ON voucher_codes(campaign_id);
@@ -0,0 +1,17 @@
+-- Creator-defined recipients for a voucher: either an explicit address list
+-- or the root of a Merkle tree of addresses
+CREATE TABLE IF NOT EXISTS voucher_recipient_restrictions (
+    voucher_id VARCHAR(66) PRIMARY KEY,
+    allowed_addresses TEXT[], -- lowercase
+    merkle_root VARCHAR(66),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CHECK ((allowed_addresses IS NULL) <> (merkle_root IS NULL))
+);
+
+-- Campaign policy: each recipient may claim at most one of its vouchers
+ALTER TABLE campaigns
+ADD COLUMN IF NOT EXISTS one_claim_per_recipient BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS idx_claim_authorizations_recipient
+ON claim_authorizations(LOWER(recipient_address))
+WHERE status = 'live';
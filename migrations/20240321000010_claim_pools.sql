@@ -0,0 +1,24 @@
+-- A claim pool hands out one of its vouchers to each claimer of a single
+-- public code, first come first served
+CREATE TABLE IF NOT EXISTS claim_pools (
+    id BIGSERIAL PRIMARY KEY,
+    code VARCHAR(32) UNIQUE NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    creator_address VARCHAR(42) NOT NULL,
+    password_hash VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Claimed state comes from voucher_codes; this only tracks reservations
+CREATE TABLE IF NOT EXISTS claim_pool_vouchers (
+    voucher_id VARCHAR(66) PRIMARY KEY, -- a voucher is in at most one pool
+    pool_id BIGINT NOT NULL REFERENCES claim_pools(id),
+    position INTEGER NOT NULL,
+    reserved_by VARCHAR(42), -- lowercase recipient
+    reserved_until TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_claim_pool_vouchers_pool ON claim_pool_vouchers(pool_id, position);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_claim_pool_vouchers_reserved_by
+ON claim_pool_vouchers(pool_id, reserved_by)
+WHERE reserved_by IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Optional gift note per code, encrypted under a key derived from the code
+-- (and its password, if any); the content type is stored in the clear
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS note_ciphertext BYTEA,
+ADD COLUMN IF NOT EXISTS note_content_type VARCHAR(32);
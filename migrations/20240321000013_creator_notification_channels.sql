@@ -0,0 +1,23 @@
+-- Where creators want to hear about their vouchers: at most one channel of
+-- each kind per creator
+CREATE TABLE IF NOT EXISTS creator_notification_channels (
+    id BIGSERIAL PRIMARY KEY,
+    creator_address VARCHAR(42) NOT NULL, -- lowercase
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('webhook', 'email')),
+    target TEXT NOT NULL, -- webhook URL or email address
+    secret VARCHAR(128), -- HMAC key for webhook payload signatures
+    muted_events TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (creator_address, kind)
+);
+
+-- Claims and cancellations reach creators by relaying the webhook event log
+ALTER TABLE webhook_events ADD COLUMN IF NOT EXISTS creator_notified_at TIMESTAMP WITH TIME ZONE;
+
+-- Events from before creator channels existed are not relayed
+UPDATE webhook_events SET creator_notified_at = created_at WHERE creator_notified_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_webhook_events_creator_pending
+    ON webhook_events(id)
+    WHERE creator_notified_at IS NULL AND event_type IN ('voucher.claimed', 'voucher.cancelled');
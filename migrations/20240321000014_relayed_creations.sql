@@ -0,0 +1,25 @@
+-- Vouchers created by the backend relayer from a creator's permit and
+-- creation intent; tx_status follows voucher_codes.claim_tx_status
+CREATE TABLE IF NOT EXISTS relayed_creations (
+    id BIGSERIAL PRIMARY KEY,
+    creator_address VARCHAR(42) NOT NULL, -- lowercase
+    amount VARCHAR(78) NOT NULL,
+    relayer_fee VARCHAR(78) NOT NULL DEFAULT '0', -- NBGN wei paid to the relayer
+    deadline BIGINT NOT NULL,
+    intent_hash VARCHAR(66) NOT NULL UNIQUE, -- each signed intent is relayed once
+    policy_decision VARCHAR(20) NOT NULL,
+    policy_reason TEXT,
+    tx_hash VARCHAR(66),
+    tx_status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (tx_status IN ('pending', 'confirmed', 'failed')),
+    error TEXT,
+    voucher_id VARCHAR(66), -- from the VoucherCreated log once confirmed
+    client_ip VARCHAR(45),
+    submitted_at TIMESTAMP WITH TIME ZONE,
+    settled_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_relayed_creations_creator ON relayed_creations(creator_address);
+CREATE INDEX IF NOT EXISTS idx_relayed_creations_pending
+    ON relayed_creations(id)
+    WHERE tx_status = 'pending' AND tx_hash IS NOT NULL;
@@ -0,0 +1,31 @@
+-- Gas the relayer may spend on a campaign's sponsored transactions, in wei;
+-- NULL leaves the campaign unbudgeted
+ALTER TABLE campaigns
+ADD COLUMN IF NOT EXISTS sponsorship_budget_wei NUMERIC(78, 0);
+
+-- Every sponsorship decision with the rule behind it; quotas, budgets and
+-- spend caps are counted from the sponsored rows
+CREATE TABLE IF NOT EXISTS sponsorship_decisions (
+    id BIGSERIAL PRIMARY KEY,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('claim', 'creation')),
+    -- Voucher code for claims, creation intent hash for creations
+    subject VARCHAR(128) NOT NULL,
+    creator_address VARCHAR(42) NOT NULL,
+    recipient_address VARCHAR(42),
+    campaign_id BIGINT REFERENCES campaigns(id),
+    amount NUMERIC(78, 0) NOT NULL,
+    estimated_cost_wei NUMERIC(78, 0),
+    decision VARCHAR(20) NOT NULL CHECK (decision IN ('sponsored', 'fee', 'rejected')),
+    rule VARCHAR(40),
+    reason TEXT NOT NULL,
+    client_ip VARCHAR(45),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sponsorship_decisions_created ON sponsorship_decisions(created_at);
+CREATE INDEX IF NOT EXISTS idx_sponsorship_decisions_creator
+    ON sponsorship_decisions(creator_address, created_at) WHERE decision = 'sponsored';
+CREATE INDEX IF NOT EXISTS idx_sponsorship_decisions_recipient
+    ON sponsorship_decisions(recipient_address, created_at) WHERE decision = 'sponsored';
+CREATE INDEX IF NOT EXISTS idx_sponsorship_decisions_campaign
+    ON sponsorship_decisions(campaign_id) WHERE decision = 'sponsored';
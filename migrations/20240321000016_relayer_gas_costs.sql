@@ -0,0 +1,23 @@
+-- What each mined relayer transaction cost, from its receipt. On Arbitrum
+-- gas_used includes the gas charged for posting the transaction to L1
+-- (gasUsedForL1), all paid at the effective gas price
+CREATE TABLE IF NOT EXISTS relayer_gas_costs (
+    id BIGSERIAL PRIMARY KEY,
+    tx_hash VARCHAR(66) NOT NULL UNIQUE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('claim', 'creation')),
+    succeeded BOOLEAN NOT NULL,
+    voucher_id VARCHAR(66),
+    creator_address VARCHAR(42), -- lowercase
+    campaign_id BIGINT REFERENCES campaigns(id),
+    block_number BIGINT,
+    gas_used NUMERIC(78, 0) NOT NULL,
+    l1_gas_used NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    effective_gas_price NUMERIC(78, 0) NOT NULL,
+    cost_wei NUMERIC(78, 0) NOT NULL,
+    l1_cost_wei NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_relayer_gas_costs_created ON relayer_gas_costs(created_at);
+CREATE INDEX IF NOT EXISTS idx_relayer_gas_costs_creator ON relayer_gas_costs(creator_address);
+CREATE INDEX IF NOT EXISTS idx_relayer_gas_costs_campaign ON relayer_gas_costs(campaign_id);
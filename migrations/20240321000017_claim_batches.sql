@@ -0,0 +1,16 @@
+-- Gasless claims sent together in one Multicall3 transaction; voucher_ids
+-- is in call order
+CREATE TABLE IF NOT EXISTS claim_batches (
+    id BIGSERIAL PRIMARY KEY,
+    tx_hash VARCHAR(66) NOT NULL UNIQUE,
+    voucher_ids TEXT[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS claim_batch_id BIGINT REFERENCES claim_batches(id);
+
+-- A batch transaction is costed once per claim in it
+ALTER TABLE relayer_gas_costs DROP CONSTRAINT IF EXISTS relayer_gas_costs_tx_hash_key;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_relayer_gas_costs_tx_voucher
+    ON relayer_gas_costs(tx_hash, COALESCE(voucher_id, ''));
@@ -0,0 +1,101 @@
+-- One lifecycle status per voucher, shared by all its codes:
+--   active     claimable
+--   claiming   a gasless claim is being sent or mined
+--   claimed    claimed on-chain
+--   expired    claim window closed unclaimed; a new window reopens it
+--   withdrawn  deleted by its creator, still funded on-chain until cancelled
+--   cancelled  cancelled on-chain
+-- claimed and cancelled stay as flags derived from it
+ALTER TABLE voucher_codes
+ADD COLUMN IF NOT EXISTS status VARCHAR(20);
+
+-- Deleting a voucher used to set cancelled without an on-chain cancellation
+UPDATE voucher_codes v
+SET status = CASE
+    WHEN v.claimed THEN 'claimed'
+    WHEN v.cancelled AND v.cancel_tx_hash IS NULL AND EXISTS (
+        SELECT 1 FROM audit_log a
+        WHERE a.action = 'voucher.deleted' AND a.target_type = 'voucher' AND a.target_id = v.voucher_id
+    ) THEN 'withdrawn'
+    WHEN v.cancelled THEN 'cancelled'
+    WHEN v.claim_tx_status IN ('submitting', 'pending') THEN 'claiming'
+    WHEN v.claimable_until < NOW() THEN 'expired'
+    ELSE 'active'
+END
+WHERE v.status IS NULL;
+
+UPDATE voucher_codes
+SET claimed = (status = 'claimed'), cancelled = (status = 'cancelled')
+WHERE claimed <> (status = 'claimed') OR cancelled <> (status = 'cancelled');
+
+ALTER TABLE voucher_codes
+ALTER COLUMN status SET DEFAULT 'active',
+ALTER COLUMN status SET NOT NULL;
+
+ALTER TABLE voucher_codes DROP CONSTRAINT IF EXISTS voucher_codes_status_check;
+ALTER TABLE voucher_codes DROP CONSTRAINT IF EXISTS voucher_codes_claimed_status;
+ALTER TABLE voucher_codes DROP CONSTRAINT IF EXISTS voucher_codes_cancelled_status;
+ALTER TABLE voucher_codes
+ADD CONSTRAINT voucher_codes_status_check
+    CHECK (status IN ('active', 'claiming', 'claimed', 'expired', 'withdrawn', 'cancelled')),
+ADD CONSTRAINT voucher_codes_claimed_status CHECK (claimed = (status = 'claimed')),
+ADD CONSTRAINT voucher_codes_cancelled_status CHECK (cancelled = (status = 'cancelled'));
+
+CREATE INDEX IF NOT EXISTS idx_voucher_codes_status ON voucher_codes(status);
+
+-- Mirrors VoucherStatus::can_become; claimed and cancelled are final
+CREATE OR REPLACE FUNCTION voucher_status_transition() RETURNS trigger AS $$
+BEGIN
+    IF (OLD.status, NEW.status) NOT IN (
+        ('active', 'claiming'), ('active', 'claimed'), ('active', 'expired'),
+        ('active', 'withdrawn'), ('active', 'cancelled'),
+        ('claiming', 'active'), ('claiming', 'claimed'), ('claiming', 'cancelled'),
+        ('expired', 'active'), ('expired', 'claimed'), ('expired', 'withdrawn'), ('expired', 'cancelled'),
+        ('withdrawn', 'claimed'), ('withdrawn', 'cancelled')
+    ) THEN
+        RAISE EXCEPTION 'voucher % cannot go from % to %', NEW.voucher_id, OLD.status, NEW.status;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS voucher_codes_status_transition ON voucher_codes;
+CREATE TRIGGER voucher_codes_status_transition
+BEFORE UPDATE OF status ON voucher_codes
+FOR EACH ROW
+WHEN (OLD.status IS DISTINCT FROM NEW.status)
+EXECUTE FUNCTION voucher_status_transition();
+
+-- Every status a voucher went through; from_status is NULL when it entered
+-- the system, or for the status it had when this table was created
+CREATE TABLE IF NOT EXISTS voucher_events (
+    id BIGSERIAL PRIMARY KEY,
+    voucher_id VARCHAR(66) NOT NULL,
+    from_status VARCHAR(20),
+    to_status VARCHAR(20) NOT NULL,
+    actor_type VARCHAR(10) NOT NULL CHECK (actor_type IN ('address', 'ip', 'admin', 'system')),
+    actor VARCHAR(66) NOT NULL,
+    tx_hash VARCHAR(66),
+    detail JSONB,
+    occurred_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_voucher_events_voucher ON voucher_events(voucher_id, occurred_at, id);
+
+INSERT INTO voucher_events (voucher_id, to_status, actor_type, actor)
+SELECT DISTINCT ON (voucher_id) voucher_id, status, 'system', 'migration'
+FROM voucher_codes
+WHERE NOT EXISTS (SELECT 1 FROM voucher_events e WHERE e.voucher_id = voucher_codes.voucher_id)
+ORDER BY voucher_id, (revoked_at IS NULL) DESC, created_at ASC;
+
+CREATE OR REPLACE FUNCTION voucher_events_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'voucher_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS voucher_events_no_modify ON voucher_events;
+CREATE TRIGGER voucher_events_no_modify
+BEFORE UPDATE OR DELETE ON voucher_events
+FOR EACH ROW EXECUTE FUNCTION voucher_events_append_only();
@@ -0,0 +1,6 @@
+-- A sponsored transaction that failed before it was sent no longer counts
+-- against quotas, budgets or the spend cap
+ALTER TABLE sponsorship_decisions DROP CONSTRAINT IF EXISTS sponsorship_decisions_decision_check;
+ALTER TABLE sponsorship_decisions
+ADD CONSTRAINT sponsorship_decisions_decision_check
+    CHECK (decision IN ('sponsored', 'fee', 'rejected', 'unsent'));
@@ -0,0 +1,488 @@
+use actix_web::{web, HttpResponse, Result, HttpRequest};
+use serde::Deserialize;
+use serde_json::json;
+use ethers::types::Address;
+use crate::api::request_context;
+use crate::config::AdminConfig;
+use crate::db::gas_models::{GasCostTotals, GasReportQuery};
+use crate::db::sponsorship_models::{CampaignBudgetRequest, DecisionQuery};
+use crate::db::webhook_models::{CreateSubscriptionRequest, DeliveryQuery};
+use crate::services::audit::{AuditLog, AuditQuery};
+use crate::services::auth;
+use crate::services::gas_accounting::GasAccounting;
+use crate::services::key_rotation::KeyRotationService;
+use crate::services::sponsorship::SponsorshipPolicy;
+use crate::services::webhooks::WebhookService;
+use tracing::{info, warn};
+
+#[derive(Debug, Deserialize)]
+pub struct RotationRequest {
+    pub new_signer: String,
+}
+
+// Reject requests without the configured X-API-Key; admin routes are off when no key is set
+pub fn require_admin(req: &HttpRequest, config: &AdminConfig) -> Option<HttpResponse> {
+    let Some(configured) = config.api_key.as_deref() else {
+        return Some(HttpResponse::NotFound().finish());
+    };
+
+    let provided = req.headers()
+        .get("X-API-Key")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("");
+
+    if !auth::admin_key_matches(provided, configured) {
+        return Some(HttpResponse::Unauthorized().json(json!({
+            "error": "Invalid admin API key"
+        })));
+    }
+
+    None
+}
+
+// GET /api/admin/signers - Configured backend keys, their roles and the on-chain signer
+pub async fn list_signers(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    rotation: web::Data<KeyRotationService>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match rotation.list_signers().await {
+        Ok(signers) => Ok(HttpResponse::Ok().json(json!({ "signers": signers }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to list signers",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// POST /api/admin/signers/rotation/prepare - Build the updateBackendSigner transaction
+pub async fn prepare_rotation(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    rotation: web::Data<KeyRotationService>,
+    body: web::Json<RotationRequest>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    let Ok(new_signer) = body.new_signer.parse::<Address>() else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid signer address"
+        })));
+    };
+
+    match rotation.prepare_rotation(new_signer) {
+        Ok(transaction) => Ok(HttpResponse::Ok().json(json!({
+            "transaction": transaction,
+            "message": "Submit this transaction from the contract owner, then call /api/admin/signers/rotation/activate"
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to prepare signer rotation",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// POST /api/admin/signers/rotation/activate - Switch signing keys once the contract accepts the new one
+pub async fn activate_rotation(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    rotation: web::Data<KeyRotationService>,
+    audit: web::Data<AuditLog>,
+    body: web::Json<RotationRequest>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    let Ok(new_signer) = body.new_signer.parse::<Address>() else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid signer address"
+        })));
+    };
+
+    match rotation.activate(new_signer).await {
+        Ok(previous) => {
+            if previous != new_signer {
+                audit.record_or_log(
+                    &request_context::admin_actor(&req),
+                    "signer.activated",
+                    "backend_signer",
+                    &format!("{:?}", new_signer),
+                    Some(json!({ "signing": format!("{:?}", previous) })),
+                    Some(json!({ "signing": format!("{:?}", new_signer) })),
+                ).await;
+            }
+            info!("Activated backend signer {:?}", new_signer);
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "signing": format!("{:?}", new_signer),
+                "previous": format!("{:?}", previous)
+            })))
+        }
+        Err(e) => {
+            warn!("Failed to activate backend signer: {}", e);
+            Ok(HttpResponse::Conflict().json(json!({
+                "error": "Failed to activate signer",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
+// POST /api/admin/signers/{address}/retire - Void a key and its live authorizations
+pub async fn retire_signer(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    rotation: web::Data<KeyRotationService>,
+    audit: web::Data<AuditLog>,
+    address: web::Path<String>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    let Ok(address) = address.parse::<Address>() else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid signer address"
+        })));
+    };
+
+    let previous_role = rotation.role_of(address);
+
+    match rotation.retire(address).await {
+        Ok(superseded) => {
+            audit.record_or_log(
+                &request_context::admin_actor(&req),
+                "signer.retired",
+                "backend_signer",
+                &format!("{:?}", address),
+                Some(json!({ "role": previous_role })),
+                Some(json!({ "role": "retired", "superseded_authorizations": superseded })),
+            ).await;
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "superseded_authorizations": superseded
+            })))
+        }
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to retire signer",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/admin/audit - Query the audit log, newest first
+pub async fn query_audit_log(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    audit: web::Data<AuditLog>,
+    query: web::Query<AuditQuery>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match audit.query(&query).await {
+        Ok(entries) => Ok(HttpResponse::Ok().json(json!({ "entries": entries }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to query audit log",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// POST /api/admin/webhooks - Subscribe an integrator endpoint to events
+pub async fn create_webhook(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    webhooks: web::Data<WebhookService>,
+    body: web::Json<CreateSubscriptionRequest>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match webhooks.create_subscription(
+        &body.url,
+        &body.events,
+        body.description.as_deref(),
+        &request_context::admin_actor(&req)
+    ).await {
+        Ok(subscription) => Ok(HttpResponse::Ok().json(json!({
+            "secret": subscription.secret,
+            "subscription": subscription,
+            "message": "Store the secret now; it is used to sign payloads and is not shown again"
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to create webhook subscription",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/admin/webhooks - All subscriptions, without their secrets
+pub async fn list_webhooks(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    webhooks: web::Data<WebhookService>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match webhooks.list_subscriptions().await {
+        Ok(subscriptions) => Ok(HttpResponse::Ok().json(json!({ "subscriptions": subscriptions }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to list webhook subscriptions",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// DELETE /api/admin/webhooks/{id} - Stop deliveries; the delivery log is kept
+pub async fn deactivate_webhook(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    webhooks: web::Data<WebhookService>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match webhooks.deactivate_subscription(*id, &request_context::admin_actor(&req)).await {
+        Ok(true) => Ok(HttpResponse::Ok().json(json!({ "success": true }))),
+        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Active subscription not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to deactivate webhook subscription",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/admin/webhooks/{id}/deliveries - Delivery log of a subscription, newest first
+pub async fn list_webhook_deliveries(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    webhooks: web::Data<WebhookService>,
+    id: web::Path<i64>,
+    query: web::Query<DeliveryQuery>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match webhooks.list_deliveries(*id, &query).await {
+        Ok(deliveries) => Ok(HttpResponse::Ok().json(json!({ "deliveries": deliveries }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to list webhook deliveries",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/admin/webhooks/deliveries/{id}/attempts - Every HTTP attempt of a delivery
+pub async fn list_webhook_attempts(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    webhooks: web::Data<WebhookService>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match webhooks.list_attempts(*id).await {
+        Ok(attempts) => Ok(HttpResponse::Ok().json(json!({ "attempts": attempts }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to list delivery attempts",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// POST /api/admin/webhooks/deliveries/{id}/redeliver - Queue a delivery again now
+pub async fn redeliver_webhook(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    webhooks: web::Data<WebhookService>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match webhooks.redeliver(*id, &request_context::admin_actor(&req)).await {
+        Ok(true) => {
+            info!("Webhook delivery {} queued for redelivery", id);
+            Ok(HttpResponse::Ok().json(json!({ "success": true })))
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Delivery not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to queue redelivery",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/admin/sponsorship/decisions - Sponsorship decisions and their reasons, newest first
+pub async fn list_sponsorship_decisions(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    sponsorship: web::Data<SponsorshipPolicy>,
+    query: web::Query<DecisionQuery>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match sponsorship.list_decisions(&query).await {
+        Ok(decisions) => Ok(HttpResponse::Ok().json(json!({ "decisions": decisions }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to list sponsorship decisions",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/admin/campaigns/{id}/sponsorship - Gas budget and sponsored spend of a campaign
+pub async fn get_campaign_sponsorship(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    sponsorship: web::Data<SponsorshipPolicy>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match sponsorship.campaign_sponsorship(*id).await {
+        Ok(Some(campaign)) => Ok(HttpResponse::Ok().json(campaign)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Campaign not found"
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to load campaign sponsorship",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// PUT /api/admin/campaigns/{id}/sponsorship - Set or remove a campaign's gas budget
+pub async fn set_campaign_sponsorship(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    sponsorship: web::Data<SponsorshipPolicy>,
+    id: web::Path<i64>,
+    body: web::Json<CampaignBudgetRequest>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    match sponsorship.set_campaign_budget(*id, body.budget_wei.as_deref(), &request_context::admin_actor(&req)).await {
+        Ok(Some(campaign)) => {
+            info!("Campaign {} sponsorship budget set to {:?}", id, campaign.budget_wei);
+            Ok(HttpResponse::Ok().json(campaign))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Campaign not found"
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to set campaign sponsorship budget",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/admin/gas/daily - Relayer gas spend per UTC day, newest first
+pub async fn gas_report_daily(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    gas: web::Data<GasAccounting>,
+    query: web::Query<GasReportQuery>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    gas_report(gas.daily(&query).await, gas.totals(&query).await)
+}
+
+// GET /api/admin/gas/campaigns - Relayer gas spend per campaign, costliest first
+pub async fn gas_report_by_campaign(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    gas: web::Data<GasAccounting>,
+    query: web::Query<GasReportQuery>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    gas_report(gas.by_campaign(&query).await, gas.totals(&query).await)
+}
+
+// GET /api/admin/gas/creators - Relayer gas spend per voucher creator, costliest first
+pub async fn gas_report_by_creator(
+    req: HttpRequest,
+    admin: web::Data<AdminConfig>,
+    gas: web::Data<GasAccounting>,
+    query: web::Query<GasReportQuery>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = require_admin(&req, &admin) {
+        return Ok(denied);
+    }
+
+    gas_report(gas.by_creator(&query).await, gas.totals(&query).await)
+}
+
+// Report rows with the totals over the same filter
+fn gas_report<T: serde::Serialize>(
+    rows: std::result::Result<Vec<T>, sqlx::Error>,
+    totals: std::result::Result<GasCostTotals, sqlx::Error>,
+) -> Result<HttpResponse> {
+    match (rows, totals) {
+        (Ok(rows), Ok(totals)) => Ok(HttpResponse::Ok().json(json!({
+            "report": rows,
+            "totals": totals
+        }))),
+        (Err(e), _) | (_, Err(e)) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Failed to build gas report",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+pub fn configure_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/admin")
+            .route("/signers", web::get().to(list_signers))
+            .route("/signers/rotation/prepare", web::post().to(prepare_rotation))
+            .route("/signers/rotation/activate", web::post().to(activate_rotation))
+            .route("/signers/{address}/retire", web::post().to(retire_signer))
+            .route("/audit", web::get().to(query_audit_log))
+            .route("/webhooks", web::post().to(create_webhook))
+            .route("/webhooks", web::get().to(list_webhooks))
+            .route("/webhooks/{id}", web::delete().to(deactivate_webhook))
+            .route("/webhooks/{id}/deliveries", web::get().to(list_webhook_deliveries))
+            .route("/webhooks/deliveries/{id}/attempts", web::get().to(list_webhook_attempts))
+            .route("/webhooks/deliveries/{id}/redeliver", web::post().to(redeliver_webhook))
+            .route("/sponsorship/decisions", web::get().to(list_sponsorship_decisions))
+            .route("/campaigns/{id}/sponsorship", web::get().to(get_campaign_sponsorship))
+            .route("/campaigns/{id}/sponsorship", web::put().to(set_campaign_sponsorship))
+            .route("/gas/daily", web::get().to(gas_report_daily))
+            .route("/gas/campaigns", web::get().to(gas_report_by_campaign))
+            .route("/gas/creators", web::get().to(gas_report_by_creator))
+    );
+}
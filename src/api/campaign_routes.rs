@@ -0,0 +1,243 @@
+use actix_web::{web, HttpResponse, Result, HttpRequest};
+use serde_json::json;
+use crate::api::request_context;
+use crate::db::campaign_models::*;
+use crate::db::voucher_models::CreatorAuth;
+use crate::services::auth;
+use crate::services::campaign::{self, CampaignService};
+use tracing::{info, warn};
+
+// POST /api/campaigns - Creator starts a campaign
+pub async fn create_campaign(
+    service: web::Data<CampaignService>,
+    req: web::Json<CreateCampaignRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    match service.create_campaign(
+        &req.name,
+        req.description.as_deref(),
+        req.one_claim_per_recipient,
+        &req.creator,
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(campaign) => {
+            info!("Created campaign {} for {}", campaign.id, campaign.creator_address);
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "campaign": campaign
+            })))
+        }
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to create campaign",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/campaigns - Campaigns of the signing creator
+pub async fn list_campaigns(
+    service: web::Data<CampaignService>,
+    query: web::Query<CreatorAuth>,
+) -> Result<HttpResponse> {
+    let address = query.address.to_lowercase();
+    if let Err(e) = auth::verify_action_signature(
+        &query.address,
+        "list_campaigns",
+        &address,
+        query.timestamp,
+        &query.signature,
+    ) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.list_campaigns(&address).await {
+        Ok(campaigns) => Ok(HttpResponse::Ok().json(json!({
+            "campaigns": campaigns
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// POST /api/campaigns/{id}/links - Create links for many vouchers at once
+pub async fn bulk_link(
+    service: web::Data<CampaignService>,
+    id: web::Path<i64>,
+    req: web::Json<BulkLinkRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let params = campaign::bulk_link_params(&req.voucher_ids, req.password_policy, req.password.as_deref(), &req.window);
+    let campaign = match authorize_campaign_action(&service, *id, "bulk_link", Some(&params), &req.creator).await {
+        Ok(campaign) => campaign,
+        Err(response) => return Ok(response),
+    };
+
+    match service.bulk_link(
+        &campaign,
+        &req.voucher_ids,
+        req.password_policy,
+        req.password.as_deref(),
+        &req.window,
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(results) => {
+            let linked = results.iter().filter(|r| r.error.is_none()).count();
+            Ok(HttpResponse::Ok().json(json!({
+                "campaign_id": campaign.id,
+                "linked": linked,
+                "failed": results.len() - linked,
+                "results": results
+            })))
+        }
+        Err(e) => {
+            warn!("Bulk link for campaign {} failed: {}", campaign.id, e);
+            Ok(HttpResponse::BadRequest().json(json!({
+                "error": "Failed to create voucher links",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
+// PUT /api/campaigns/{id}/policy - Change the campaign's claim policy
+pub async fn set_campaign_policy(
+    service: web::Data<CampaignService>,
+    id: web::Path<i64>,
+    req: web::Json<CampaignPolicyRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let campaign = match authorize_campaign_action(&service, *id, "update_campaign", None, &req.creator).await {
+        Ok(campaign) => campaign,
+        Err(response) => return Ok(response),
+    };
+
+    match service.set_policy(
+        &campaign,
+        req.one_claim_per_recipient,
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(()) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "campaign_id": campaign.id,
+            "one_claim_per_recipient": req.one_claim_per_recipient
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// GET /api/campaigns/{id}/stats - Claimed, cancelled, expired and outstanding counts and value
+pub async fn get_campaign_stats(
+    service: web::Data<CampaignService>,
+    id: web::Path<i64>,
+    query: web::Query<CreatorAuth>,
+) -> Result<HttpResponse> {
+    let campaign = match authorize_campaign_action(&service, *id, "view_campaign", None, &query).await {
+        Ok(campaign) => campaign,
+        Err(response) => return Ok(response),
+    };
+
+    match service.stats(campaign.id).await {
+        Ok(stats) => Ok(HttpResponse::Ok().json(json!({
+            "campaign": campaign,
+            "stats": stats
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// GET /api/campaigns/{id}/export?format=csv|json|sheet - Codes and links of every campaign voucher
+pub async fn export_campaign(
+    service: web::Data<CampaignService>,
+    id: web::Path<i64>,
+    query: web::Query<CreatorAuth>,
+    export: web::Query<ExportQuery>,
+) -> Result<HttpResponse> {
+    let campaign = match authorize_campaign_action(&service, *id, "export_campaign", None, &query).await {
+        Ok(campaign) => campaign,
+        Err(response) => return Ok(response),
+    };
+
+    let rows = match service.export_rows(campaign.id).await {
+        Ok(rows) => rows,
+        Err(_e) => {
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "error": "Database error"
+            })));
+        }
+    };
+
+    match export.format {
+        ExportFormat::Csv => Ok(HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"campaign-{}.csv\"", campaign.id),
+            ))
+            .body(campaign::export_csv(&rows))),
+        ExportFormat::Json => Ok(HttpResponse::Ok().json(json!({
+            "campaign": campaign,
+            "codes": rows
+        }))),
+        ExportFormat::Sheet => match service.printable_sheet(&campaign, &rows) {
+            Ok(html) => Ok(HttpResponse::Ok()
+                .content_type("text/html; charset=utf-8")
+                .body(html)),
+            Err(e) => Ok(HttpResponse::ServiceUnavailable().json(json!({
+                "error": "Printable sheet unavailable",
+                "message": e.to_string()
+            }))),
+        },
+    }
+}
+
+pub fn configure_campaign_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/campaigns")
+            .route("", web::post().to(create_campaign))
+            .route("", web::get().to(list_campaigns))
+            .route("/{id}/links", web::post().to(bulk_link))
+            .route("/{id}/policy", web::put().to(set_campaign_policy))
+            .route("/{id}/stats", web::get().to(get_campaign_stats))
+            .route("/{id}/export", web::get().to(export_campaign))
+    );
+}
+
+// Load a campaign and check the creator's signature for the action on it
+async fn authorize_campaign_action(
+    service: &CampaignService,
+    id: i64,
+    action: &str,
+    params: Option<&serde_json::Value>,
+    creator: &CreatorAuth,
+) -> std::result::Result<Campaign, HttpResponse> {
+    let campaign = match service.get_campaign(id).await {
+        Ok(Some(campaign)) => campaign,
+        Ok(None) => {
+            return Err(HttpResponse::NotFound().json(json!({
+                "error": "Campaign not found"
+            })));
+        }
+        Err(_e) => {
+            return Err(HttpResponse::InternalServerError().json(json!({
+                "error": "Database error"
+            })));
+        }
+    };
+
+    if let Err(e) = CampaignService::authorize_campaign_creator(&campaign, action, params, creator) {
+        return Err(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    Ok(campaign)
+}
@@ -1,9 +1,11 @@
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{web, HttpRequest, HttpResponse, Result};
 use sqlx::PgPool;
 use serde_json::json;
 use ethers::types::Signature;
 use ethers::utils::hash_message;
+use crate::api::request_context;
 use crate::db::models::{UserProfile, SetUsernameRequest, Transaction};
+use crate::services::audit::AuditLog;
 use crate::services::cache::CacheService;
 use std::time::Duration;
 use std::str::FromStr;
@@ -55,7 +57,9 @@ pub async fn get_user_profile(
 pub async fn set_username(
     pool: web::Data<PgPool>,
     cache: web::Data<CacheService>,
+    audit: web::Data<AuditLog>,
     req: web::Json<SetUsernameRequest>,
+    http_req: HttpRequest,
 ) -> Result<HttpResponse> {
     // Verify signature
     let message = hash_message(&req.message);
@@ -82,6 +86,14 @@ pub async fn set_username(
         return Ok(HttpResponse::BadRequest().json(json!({ "error": "Username already taken" })));
     }
 
+    let previous: Option<(Option<String>,)> = sqlx::query_as(
+        "SELECT username FROM users WHERE address = $1"
+    )
+    .bind(req.address.to_lowercase())
+    .fetch_optional(pool.get_ref())
+    .await
+    .map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
+
     // Update username
     sqlx::query(
         r#"
@@ -97,6 +109,15 @@ pub async fn set_username(
     .await
     .map_err(|_| actix_web::error::ErrorInternalServerError("Database error"))?;
 
+    audit.record_or_log(
+        &request_context::address_actor(&http_req, &req.address),
+        "user.username_set",
+        "user",
+        &req.address.to_lowercase(),
+        previous.map(|(username,)| json!({ "username": username })),
+        Some(json!({ "username": req.username })),
+    ).await;
+
     // Clear cache
     let _ = cache.delete(&format!("user_profile:{}", req.address.to_lowercase())).await;
 
@@ -1,3 +1,10 @@
+pub mod admin_routes;
+pub mod campaign_routes;
 pub mod handlers;
+pub mod notification_routes;
+pub mod pool_routes;
+pub mod relay_routes;
+pub mod request_context;
 pub mod routes;
+pub mod tx_routes;
 pub mod voucher_routes;
\ No newline at end of file
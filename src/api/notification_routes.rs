@@ -0,0 +1,117 @@
+use actix_web::{web, HttpResponse, Result, HttpRequest};
+use serde_json::json;
+use crate::api::request_context;
+use crate::db::notification_models::*;
+use crate::db::voucher_models::CreatorAuth;
+use crate::services::auth;
+use crate::services::notification_channels::NotificationChannelService;
+use tracing::info;
+
+// GET /api/notifications/channels - Channels of the signing creator
+pub async fn list_channels(
+    service: web::Data<NotificationChannelService>,
+    query: web::Query<CreatorAuth>,
+) -> Result<HttpResponse> {
+    let address = query.address.to_lowercase();
+    if let Some(response) = unauthorized(&query, "list_notification_channels", &address) {
+        return Ok(response);
+    }
+
+    match service.list_channels(&address).await {
+        Ok(channels) => Ok(HttpResponse::Ok().json(json!({
+            "channels": channels
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// PUT /api/notifications/channels - Register or update a channel; the
+// signature covers the target, kind and muted events so none can be changed
+pub async fn set_channel(
+    service: web::Data<NotificationChannelService>,
+    req: web::Json<SetChannelRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let params = json!({ "kind": req.kind, "muted_events": req.muted_events });
+    if let Err(e) = auth::verify_action_signature_with_params(
+        &req.creator.address,
+        "set_notification_channel",
+        &req.target,
+        &params,
+        req.creator.timestamp,
+        &req.creator.signature,
+    ) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.set_channel(
+        &req.creator.address,
+        &req.kind,
+        &req.target,
+        &req.muted_events,
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(channel) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "secret": channel.secret,
+            "channel": channel,
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to set notification channel",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// DELETE /api/notifications/channels/{kind} - Stop notifications on a channel
+pub async fn delete_channel(
+    service: web::Data<NotificationChannelService>,
+    kind: web::Path<String>,
+    query: web::Query<CreatorAuth>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Some(response) = unauthorized(&query, "delete_notification_channel", &kind) {
+        return Ok(response);
+    }
+
+    match service.delete_channel(
+        &query.address,
+        &kind,
+        &request_context::address_actor(&http_req, &query.address)
+    ).await {
+        Ok(true) => {
+            info!("Deleted {} notification channel of {}", kind, query.address);
+            Ok(HttpResponse::Ok().json(json!({ "success": true })))
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Notification channel not found"
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+pub fn configure_notification_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/notifications/channels")
+            .route("", web::get().to(list_channels))
+            .route("", web::put().to(set_channel))
+            .route("/{kind}", web::delete().to(delete_channel))
+    );
+}
+
+// 401 unless the creator signed the action
+fn unauthorized(creator: &CreatorAuth, action: &str, target: &str) -> Option<HttpResponse> {
+    auth::verify_action_signature(&creator.address, action, target, creator.timestamp, &creator.signature)
+        .err()
+        .map(|e| HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })))
+}
@@ -0,0 +1,186 @@
+use actix_web::{web, HttpResponse, Result, HttpRequest};
+use serde_json::json;
+use crate::api::request_context::{self, client_ip};
+use crate::api::voucher_routes::{claim_error_response, is_valid_ethereum_address};
+use crate::db::pool_models::*;
+use crate::middleware::rate_limiter::RedisRateLimiter;
+use crate::services::pool::{PoolError, PoolService};
+use tracing::{info, warn};
+
+// POST /api/pools - Creator shares many vouchers behind one code
+pub async fn create_pool(
+    service: web::Data<PoolService>,
+    req: web::Json<CreatePoolRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    match service.create_pool(
+        &req.name,
+        &req.voucher_ids,
+        req.password.as_deref(),
+        &req.creator,
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(pool) => {
+            info!("Created claim pool {} for {}", pool.id, pool.creator_address);
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "pool": pool,
+                "code": service.display_code(&pool.code),
+                "link": service.pool_path(&pool.code),
+                "pool_url": service.pool_url(&pool.code),
+            })))
+        }
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to create pool",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// GET /api/pools/{code} - Public pool details and remaining stock
+pub async fn get_pool(
+    service: web::Data<PoolService>,
+    code: web::Path<String>,
+) -> Result<HttpResponse> {
+    let pool = match load_pool(&service, &code).await {
+        Ok(pool) => pool,
+        Err(response) => return Ok(response),
+    };
+
+    match service.stock(pool.id).await {
+        Ok(stock) => Ok(HttpResponse::Ok().json(json!({
+            "name": pool.name,
+            "code": service.display_code(&pool.code),
+            "created_at": pool.created_at,
+            "hasPassword": pool.password_hash.is_some(),
+            "stock": stock,
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// POST /api/pools/{code}/claim - Reserve a voucher and sign its claim
+pub async fn claim_from_pool(
+    service: web::Data<PoolService>,
+    limiter: web::Data<RedisRateLimiter>,
+    code: web::Path<String>,
+    req: web::Json<PoolClaimRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    if !is_valid_ethereum_address(&req.recipient_address) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid recipient address"
+        })));
+    }
+
+    let ip = client_ip(&http_req);
+
+    // Skip rate limiting for localhost in development
+    if !ip.starts_with("127.0.0.1") && !ip.starts_with("::1") && !ip.starts_with("localhost") {
+        let rate_limit_key = format!("pool_claim:{}", ip);
+
+        // Rate limit: 10 pool claims per IP per hour
+        match limiter.check_rate_limit(&rate_limit_key, 10, 3600).await {
+            Ok(result) if !result.allowed => {
+                return Ok(HttpResponse::TooManyRequests().json(json!({
+                    "error": "Too many claim attempts",
+                    "retry_after": result.retry_after
+                })));
+            }
+            _ => {}
+        }
+    }
+
+    let pool = match load_pool(&service, &code).await {
+        Ok(pool) => pool,
+        Err(response) => return Ok(response),
+    };
+
+    match service.claim(
+        &pool,
+        &req.recipient_address,
+        req.password.as_deref(),
+        &ip,
+        &request_context::anonymous_actor(&http_req)
+    ).await {
+        Ok(authorization) => {
+            info!("Reserved a voucher of pool {} for {}", pool.id, req.recipient_address);
+            Ok(HttpResponse::Ok().json(authorization))
+        }
+        Err(e) => {
+            warn!("Failed to claim from pool {}: {}", pool.id, e);
+            if let Some(response) = pool_error_response(e.as_ref()) {
+                return Ok(response);
+            }
+            if let Some(response) = claim_error_response(e.as_ref()) {
+                return Ok(response);
+            }
+            Ok(HttpResponse::BadRequest().json(json!({
+                "error": "Failed to generate claim authorization",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
+pub fn configure_pool_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/pools")
+            .route("", web::post().to(create_pool))
+            .route("/{code}", web::get().to(get_pool))
+            .route("/{code}/claim", web::post().to(claim_from_pool))
+    );
+}
+
+async fn load_pool(
+    service: &PoolService,
+    code: &str,
+) -> std::result::Result<ClaimPool, HttpResponse> {
+    let Some(code) = service.normalize_code(code) else {
+        return Err(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid pool code",
+            "error_code": "invalid_code",
+            "message": "Check the code for typos"
+        })));
+    };
+
+    match service.get_pool_by_code(&code).await {
+        Ok(Some(pool)) => Ok(pool),
+        Ok(None) => Err(pool_not_found()),
+        Err(_e) => Err(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+fn pool_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "error": "Pool not found",
+        "error_code": "pool_not_found"
+    }))
+}
+
+// 401 for a wrong or missing pool password, 410 once every voucher is taken
+// and 409 for recipients who already claimed from the pool
+fn pool_error_response(e: &(dyn std::error::Error + 'static)) -> Option<HttpResponse> {
+    match e.downcast_ref::<PoolError>()? {
+        PoolError::PasswordRequired => Some(HttpResponse::Unauthorized().json(json!({
+            "error": "Password required",
+            "error_code": "password_required"
+        }))),
+        PoolError::InvalidPassword => Some(HttpResponse::Unauthorized().json(json!({
+            "error": "Invalid password",
+            "error_code": "invalid_password"
+        }))),
+        PoolError::Exhausted => Some(HttpResponse::Gone().json(json!({
+            "error": "No vouchers left in this pool",
+            "error_code": "pool_exhausted"
+        }))),
+        PoolError::AlreadyClaimed => Some(HttpResponse::Conflict().json(json!({
+            "error": "Recipient has already claimed from this pool",
+            "error_code": "pool_already_claimed"
+        }))),
+    }
+}
@@ -0,0 +1,152 @@
+use actix_web::{web, HttpResponse, Result, HttpRequest};
+use ethers::types::Address;
+use serde_json::json;
+use crate::api::request_context::{self, client_ip};
+use crate::db::relay_models::*;
+use crate::middleware::rate_limiter::RedisRateLimiter;
+use crate::services::relayed_creation::{RelayError, RelayedCreationService};
+use tracing::{info, warn};
+
+// GET /api/relay/create-voucher/quote - Fee and permit values to sign
+pub async fn quote_creation(
+    service: web::Data<RelayedCreationService>,
+    query: web::Query<CreationQuoteQuery>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let Ok(creator) = query.creator.parse::<Address>() else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid creator address"
+        })));
+    };
+
+    match service.quote(creator, &query.amount, &client_ip(&http_req)).await {
+        Ok(quote) => Ok(HttpResponse::Ok().json(quote)),
+        Err(e) => {
+            if let Some(response) = relay_error_response(e.as_ref()) {
+                return Ok(response);
+            }
+            Ok(HttpResponse::BadGateway().json(json!({
+                "error": "Failed to read permit nonce",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
+// POST /api/relay/create-voucher - Relay a permit-funded voucher creation
+pub async fn relay_creation(
+    service: web::Data<RelayedCreationService>,
+    limiter: web::Data<RedisRateLimiter>,
+    req: web::Json<RelayedCreationRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let ip = client_ip(&http_req);
+
+    // Skip rate limiting for localhost in development
+    if !ip.starts_with("127.0.0.1") && !ip.starts_with("::1") && !ip.starts_with("localhost") {
+        let rate_limit_key = format!("relay_create:{}", ip);
+
+        // Rate limit: 10 relayed creations per IP per hour
+        match limiter.check_rate_limit(&rate_limit_key, 10, 3600).await {
+            Ok(result) if !result.allowed => {
+                return Ok(HttpResponse::TooManyRequests().json(json!({
+                    "error": "Too many relayed creations",
+                    "retry_after": result.retry_after
+                })));
+            }
+            _ => {}
+        }
+    }
+
+    match service.submit(&req, &ip, &request_context::address_actor(&http_req, &req.creator)).await {
+        Ok(creation) => {
+            info!("Relayed voucher creation {} for {}", creation.id, creation.creator_address);
+            Ok(HttpResponse::Ok().json(json!({
+                "success": true,
+                "id": creation.id,
+                "tx_hash": creation.tx_hash,
+                "status": creation.tx_status,
+                "creation": creation,
+            })))
+        }
+        Err(e) => {
+            warn!("Failed to relay voucher creation for {}: {}", req.creator, e);
+            if let Some(response) = relay_error_response(e.as_ref()) {
+                return Ok(response);
+            }
+            Ok(HttpResponse::BadRequest().json(json!({
+                "error": "Failed to relay voucher creation",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
+// GET /api/relay/create-voucher/{id} - Status of a relayed creation
+pub async fn get_relayed_creation(
+    service: web::Data<RelayedCreationService>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse> {
+    match service.get_creation(*id).await {
+        Ok(Some(creation)) => Ok(HttpResponse::Ok().json(creation)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(json!({
+            "error": "Relayed creation not found"
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+pub fn configure_relay_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/relay")
+            .route("/create-voucher/quote", web::get().to(quote_creation))
+            .route("/create-voucher", web::post().to(relay_creation))
+            .route("/create-voucher/{id}", web::get().to(get_relayed_creation))
+    );
+}
+
+// 401 for an intent the creator did not sign, 403 when the policy refuses,
+// 409 for an intent already relayed and 422 when the creation would revert
+fn relay_error_response(e: &(dyn std::error::Error + 'static)) -> Option<HttpResponse> {
+    let response = match e.downcast_ref::<RelayError>()? {
+        RelayError::InvalidAmount => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_code": "invalid_amount"
+        })),
+        RelayError::InvalidDeadline { .. } => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_code": "invalid_deadline"
+        })),
+        RelayError::InvalidPermitSignature => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_code": "invalid_permit_signature"
+        })),
+        RelayError::InvalidIntentSignature => HttpResponse::Unauthorized().json(json!({
+            "error": e.to_string(),
+            "error_code": "invalid_intent_signature"
+        })),
+        RelayError::Rejected(reason) => HttpResponse::Forbidden().json(json!({
+            "error": "Relaying refused",
+            "error_code": "relay_rejected",
+            "reason": reason
+        })),
+        RelayError::FeeTooLow { required } => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string(),
+            "error_code": "fee_too_low",
+            "required_fee": required.to_string()
+        })),
+        RelayError::AlreadySubmitted { tx_hash } => HttpResponse::Conflict().json(json!({
+            "error": e.to_string(),
+            "error_code": "already_submitted",
+            "tx_hash": tx_hash
+        })),
+        RelayError::WouldRevert(reason) => HttpResponse::UnprocessableEntity().json(json!({
+            "error": "Creation would revert",
+            "error_code": "would_revert",
+            "message": reason
+        })),
+    };
+    Some(response)
+}
@@ -0,0 +1,32 @@
+use actix_web::{HttpMessage, HttpRequest};
+use crate::middleware::request_id::RequestId;
+use crate::services::audit::AuditContext;
+
+// Client IP, preferring the proxy-reported address
+pub fn client_ip(req: &HttpRequest) -> String {
+    let connection_info = req.connection_info();
+    connection_info
+        .realip_remote_addr()
+        .or_else(|| connection_info.peer_addr())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+// Id assigned by RequestIdMiddleware
+pub fn request_id(req: &HttpRequest) -> Option<String> {
+    req.extensions().get::<RequestId>().map(|id| id.0.clone())
+}
+
+// Audit actor for an unauthenticated caller
+pub fn anonymous_actor(req: &HttpRequest) -> AuditContext {
+    AuditContext::anonymous(&client_ip(req), request_id(req))
+}
+
+// Audit actor for a caller that proved control of `address`
+pub fn address_actor(req: &HttpRequest, address: &str) -> AuditContext {
+    AuditContext::address(address, &client_ip(req), request_id(req))
+}
+
+pub fn admin_actor(req: &HttpRequest) -> AuditContext {
+    AuditContext::admin(&client_ip(req), request_id(req))
+}
@@ -1,5 +1,5 @@
 use actix_web::{web, HttpResponse};
-use crate::api::{handlers, voucher_routes};
+use crate::api::{admin_routes, campaign_routes, handlers, notification_routes, pool_routes, relay_routes, tx_routes, voucher_routes};
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg
@@ -44,4 +44,22 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     
     // Configure voucher routes
     voucher_routes::configure_voucher_routes(cfg);
+
+    // Configure campaign routes
+    campaign_routes::configure_campaign_routes(cfg);
+
+    // Configure claim pool routes
+    pool_routes::configure_pool_routes(cfg);
+
+    // Configure creator notification routes
+    notification_routes::configure_notification_routes(cfg);
+
+    // Configure unsigned transaction builders
+    tx_routes::configure_tx_routes(cfg);
+
+    // Configure relayed (gasless) voucher creation
+    relay_routes::configure_relay_routes(cfg);
+
+    // Configure admin routes
+    admin_routes::configure_admin_routes(cfg);
 }
\ No newline at end of file
@@ -0,0 +1,102 @@
+use actix_web::{web, HttpResponse, Result};
+use ethers::types::{Address, H256};
+use serde_json::json;
+use crate::db::tx_models::*;
+use crate::services::tx_builder::{parse_allowance, parse_amount, ContractCall, TxBuildError, TxBuilder};
+
+// POST /api/tx/create-voucher - createVoucher(amount) on the voucher contract
+pub async fn create_voucher_tx(
+    builder: web::Data<TxBuilder>,
+    req: web::Json<AmountTxRequest>,
+) -> Result<HttpResponse> {
+    match parse_amount(&req.amount) {
+        Ok(amount) => prepare(&builder, &req.from, builder.create_voucher(amount)).await,
+        Err(e) => Ok(tx_error_response(&e)),
+    }
+}
+
+// POST /api/tx/cancel-voucher - cancelVoucher(voucherId); only the creator's
+// transaction will succeed
+pub async fn cancel_voucher_tx(
+    builder: web::Data<TxBuilder>,
+    req: web::Json<CancelVoucherTxRequest>,
+) -> Result<HttpResponse> {
+    let Ok(voucher_id) = req.voucher_id.parse::<H256>() else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid voucher ID"
+        })));
+    };
+    prepare(&builder, &req.from, builder.cancel_voucher(voucher_id)).await
+}
+
+// POST /api/tx/mint - Mint NBGN from an EURe amount
+pub async fn mint_tx(
+    builder: web::Data<TxBuilder>,
+    req: web::Json<AmountTxRequest>,
+) -> Result<HttpResponse> {
+    match parse_amount(&req.amount) {
+        Ok(amount) => prepare(&builder, &req.from, builder.mint(amount)).await,
+        Err(e) => Ok(tx_error_response(&e)),
+    }
+}
+
+// POST /api/tx/redeem - Redeem an NBGN amount for EURe
+pub async fn redeem_tx(
+    builder: web::Data<TxBuilder>,
+    req: web::Json<AmountTxRequest>,
+) -> Result<HttpResponse> {
+    match parse_amount(&req.amount) {
+        Ok(amount) => prepare(&builder, &req.from, builder.redeem(amount)).await,
+        Err(e) => Ok(tx_error_response(&e)),
+    }
+}
+
+// POST /api/tx/approve - ERC-20 approval for the voucher or NBGN contract
+pub async fn approve_tx(
+    builder: web::Data<TxBuilder>,
+    req: web::Json<ApproveTxRequest>,
+) -> Result<HttpResponse> {
+    let call = parse_allowance(&req.amount)
+        .and_then(|amount| builder.approve(req.token, req.spender, amount));
+    match call {
+        Ok(call) => prepare(&builder, &req.from, call).await,
+        Err(e) => Ok(tx_error_response(&e)),
+    }
+}
+
+pub fn configure_tx_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/tx")
+            .route("/create-voucher", web::post().to(create_voucher_tx))
+            .route("/cancel-voucher", web::post().to(cancel_voucher_tx))
+            .route("/mint", web::post().to(mint_tx))
+            .route("/redeem", web::post().to(redeem_tx))
+            .route("/approve", web::post().to(approve_tx))
+    );
+}
+
+async fn prepare(builder: &TxBuilder, from: &str, call: ContractCall) -> Result<HttpResponse> {
+    let Ok(from) = from.parse::<Address>() else {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid from address"
+        })));
+    };
+
+    match builder.prepare(from, call).await {
+        Ok(tx) => Ok(HttpResponse::Ok().json(tx)),
+        Err(e) => Ok(tx_error_response(&e)),
+    }
+}
+
+// 400 for bad input, 502 when the node cannot supply fee data
+fn tx_error_response(e: &TxBuildError) -> HttpResponse {
+    match e {
+        TxBuildError::InvalidAmount | TxBuildError::EureNotConfigured => HttpResponse::BadRequest().json(json!({
+            "error": e.to_string()
+        })),
+        TxBuildError::Fees(_) => HttpResponse::BadGateway().json(json!({
+            "error": "Failed to fetch fee data",
+            "message": e.to_string()
+        })),
+    }
+}
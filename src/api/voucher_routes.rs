@@ -1,8 +1,15 @@
 use actix_web::{web, HttpResponse, Result, HttpRequest};
 use sqlx::PgPool;
 use serde_json::json;
+use crate::api::admin_routes::require_admin;
+use crate::api::request_context::{self, client_ip};
+use crate::config::AdminConfig;
 use crate::db::voucher_models::*;
-use crate::services::voucher::VoucherService;
+use crate::services::audit::AuditLog;
+use crate::services::lifecycle::{LifecycleError, StatusChange, VoucherStatus};
+use crate::services::qr::{self, QrOptions};
+use crate::services::timeline::TimelineService;
+use crate::services::voucher::{ClaimError, LinkError, VoucherService};
 use crate::middleware::rate_limiter::RedisRateLimiter;
 use tracing::{info, warn};
 use ethers::prelude::*;
@@ -13,27 +20,60 @@ pub async fn create_voucher_link(
     _pool: web::Data<PgPool>,
     service: web::Data<VoucherService>,
     req: web::Json<CreateLinkRequest>,
+    http_req: HttpRequest,
 ) -> Result<HttpResponse> {
-    // TODO: In production, verify voucher exists on-chain by calling getVoucher(voucher_id)
-    
+    let creator = CreatorAuth {
+        address: req.creator_address.clone(),
+        timestamp: req.timestamp,
+        signature: req.signature.clone(),
+    };
+    let ctx = request_context::address_actor(&http_req, &req.creator_address);
+
+    // Reject a bad note before the link is created
+    if let Some(note) = &req.note {
+        if let Err(e) = VoucherService::validate_note(note) {
+            return Ok(link_error_response(&e).expect("note errors are LinkErrors"));
+        }
+    }
+
     match service.create_voucher_link(
-        &req.voucher_id, 
+        &req.voucher_id,
         req.password.as_deref(),
-        req.creator_address.as_deref(),
-        req.amount.as_deref()
+        req.amount.as_deref(),
+        &req.window,
+        &creator,
+        &ctx
     ).await {
         Ok(code) => {
             info!("Created voucher link with code {} for voucher_id {}", code, req.voucher_id);
+            if let Some(note) = &req.note {
+                if let Err(e) = service.set_note(&code, Some(note), req.password.as_deref(), &ctx).await {
+                    warn!("Failed to store note for voucher link {}: {}", code, e);
+                    return Ok(link_error_response(e.as_ref()).unwrap_or_else(|| {
+                        HttpResponse::BadRequest().json(json!({
+                            "error": "Failed to store note",
+                            "message": e.to_string()
+                        }))
+                    }));
+                }
+            }
+            let link = service.claim_path(&code);
+            let claim_url = service.claim_url(&code);
+            let code = service.display_code(&code);
             Ok(HttpResponse::Ok().json(json!({
                 "success": true,
                 "code": code,
                 "shareable_code": code.clone(),
-                "shareable_link": format!("/claim/{}", code),
-                "link": format!("/claim/{}", code) // Keep for backward compatibility
+                "shareable_link": link.clone(),
+                "claim_url": claim_url,
+                "link": link // Keep for backward compatibility
             })))
         }
         Err(e) => {
             warn!("Failed to create voucher link: {}", e);
+            if let Some(response) = link_error_response(e.as_ref()) {
+                return Ok(response);
+            }
             Ok(HttpResponse::BadRequest().json(json!({
                 "error": "Failed to create voucher link",
                 "message": e.to_string()
@@ -50,12 +90,16 @@ pub async fn verify_voucher(
     req: web::Json<VerifyRequest>,
     http_req: HttpRequest,
 ) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&req.code) else {
+        return Ok(invalid_code_response());
+    };
+
     // Extract IP for rate limiting
-    let ip = extract_ip(&http_req);
+    let ip = client_ip(&http_req);
     
     // Skip rate limiting for localhost in development
     if !ip.starts_with("127.0.0.1") && !ip.starts_with("::1") && !ip.starts_with("localhost") {
-        let rate_limit_key = format!("verify:{}:{}", req.code, ip);
+        let rate_limit_key = format!("verify:{}:{}", code, ip);
         
         // Rate limit: 100 attempts per code per IP per hour (increased for dev)
         match limiter.check_rate_limit(&rate_limit_key, 100, 3600).await {
@@ -73,14 +117,14 @@ pub async fn verify_voucher(
     sqlx::query(
         "INSERT INTO claim_attempts (voucher_code, ip_address, success) VALUES ($1, $2, false)"
     )
-    .bind(&req.code)
+    .bind(&code)
     .bind(&ip)
     .execute(pool.get_ref())
     .await
     .ok();
 
     // Get voucher from DB
-    let voucher = match service.get_voucher_by_code(&req.code).await {
+    let voucher = match service.get_voucher_by_code(&code).await {
         Ok(Some(v)) => v,
         Ok(None) => {
             return Ok(HttpResponse::NotFound().json(json!({
@@ -95,39 +139,41 @@ pub async fn verify_voucher(
     };
 
     // Check password if set
-    if let Some(hash) = &voucher.password_hash {
-        if req.password.is_none() {
-            return Ok(HttpResponse::Unauthorized().json(json!({
-                "error": "Password required"
-            })));
-        }
-        
-        if !VoucherService::verify_password(req.password.as_ref().unwrap(), hash) {
-            return Ok(HttpResponse::Unauthorized().json(json!({
-                "error": "Invalid password"
-            })));
-        }
+    if let Err(e) = service.check_password(&voucher, req.password.as_deref(), &ip).await {
+        return Ok(claim_error_response(e.as_ref()).unwrap_or_else(|| {
+            HttpResponse::InternalServerError().json(json!({
+                "error": "Database error"
+            }))
+        }));
     }
 
-    // Check if already claimed
-    if voucher.claimed {
+    // Same checks as a claim: claimed, cancelled and withdrawn vouchers are closed
+    if let Err(e) = VoucherService::check_open(&voucher) {
         return Ok(HttpResponse::BadRequest().json(json!({
-            "error": "Voucher already claimed",
+            "error": e.to_string(),
+            "status": voucher.status,
             "claimed_by": voucher.claimed_by,
             "claimed_at": voucher.claimed_at
         })));
     }
 
+    if let Err(e) = VoucherService::check_claim_window(&voucher) {
+        return Ok(claim_error_response(&e).expect("claim window errors are ClaimErrors"));
+    }
+
     // Update successful attempt
     sqlx::query(
         "UPDATE claim_attempts SET success = true WHERE voucher_code = $1 AND ip_address = $2 AND attempted_at = (SELECT MAX(attempted_at) FROM claim_attempts WHERE voucher_code = $1 AND ip_address = $2)"
     )
-    .bind(&req.code)
+    .bind(&code)
     .bind(&ip)
     .execute(pool.get_ref())
     .await
     .ok();
 
+    // The note only opens with the code and password just checked
+    let note = VoucherService::open_note(&voucher, req.password.as_deref());
+
     // For now, just return the voucher info without signature generation
     Ok(HttpResponse::Ok().json(json!({
         "valid": true,
@@ -135,10 +181,14 @@ pub async fn verify_voucher(
             "voucher_id": voucher.voucher_id,
             "amount": voucher.amount.unwrap_or_else(|| "0".to_string()),
             "creator_address": voucher.creator_address,
+            "status": voucher.status,
             "claimed": voucher.claimed,
             "cancelled": voucher.cancelled,
+            "claimable_from": voucher.claimable_from,
+            "claimable_until": voucher.claimable_until,
         },
-        "hasPassword": voucher.password_hash.is_some()
+        "hasPassword": voucher.password_hash.is_some(),
+        "note": note
     })))
 }
 
@@ -150,6 +200,10 @@ pub async fn claim_voucher(
     req: web::Json<ClaimRequest>,
     http_req: HttpRequest,
 ) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&req.code) else {
+        return Ok(invalid_code_response());
+    };
+
     // Validate recipient address
     if !is_valid_ethereum_address(&req.recipient_address) {
         return Ok(HttpResponse::BadRequest().json(json!({
@@ -158,7 +212,7 @@ pub async fn claim_voucher(
     }
 
     // Rate limit by IP
-    let ip = extract_ip(&http_req);
+    let ip = client_ip(&http_req);
     
     // Skip rate limiting for localhost in development
     if !ip.starts_with("127.0.0.1") && !ip.starts_with("::1") && !ip.starts_with("localhost") {
@@ -180,7 +234,7 @@ pub async fn claim_voucher(
     sqlx::query(
         "INSERT INTO claim_attempts (voucher_code, ip_address, recipient_address, success) VALUES ($1, $2, $3, false)"
     )
-    .bind(&req.code)
+    .bind(&code)
     .bind(&ip)
     .bind(&req.recipient_address)
     .execute(pool.get_ref())
@@ -189,17 +243,22 @@ pub async fn claim_voucher(
 
     // Generate claim authorization
     match service.create_claim_authorization(
-        &req.code,
+        &code,
         &req.recipient_address,
-        req.password.as_deref()
+        req.password.as_deref(),
+        &req.merkle_proof,
+        &ip
     ).await {
         Ok(authorization) => {
             info!("Generated claim authorization for voucher {} to recipient {}", 
-                  req.code, req.recipient_address);
+                  code, req.recipient_address);
             Ok(HttpResponse::Ok().json(authorization))
         }
         Err(e) => {
             warn!("Failed to generate claim authorization: {}", e);
+            if let Some(response) = claim_error_response(e.as_ref()) {
+                return Ok(response);
+            }
             Ok(HttpResponse::BadRequest().json(json!({
                 "error": "Failed to generate claim authorization",
                 "message": e.to_string()
@@ -216,6 +275,10 @@ pub async fn execute_claim(
     req: web::Json<ClaimRequest>,
     http_req: HttpRequest,
 ) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&req.code) else {
+        return Ok(invalid_code_response());
+    };
+
     // Validate recipient address
     if !is_valid_ethereum_address(&req.recipient_address) {
         return Ok(HttpResponse::BadRequest().json(json!({
@@ -224,7 +287,7 @@ pub async fn execute_claim(
     }
 
     // Rate limit by IP
-    let ip = extract_ip(&http_req);
+    let ip = client_ip(&http_req);
     
     // Skip rate limiting for localhost in development
     if !ip.starts_with("127.0.0.1") && !ip.starts_with("::1") && !ip.starts_with("localhost") {
@@ -244,13 +307,16 @@ pub async fn execute_claim(
 
     // Execute the gasless claim
     match service.execute_claim(
-        &req.code,
+        &code,
         &req.recipient_address,
-        req.password.as_deref()
+        req.password.as_deref(),
+        &req.merkle_proof,
+        &ip,
+        &request_context::anonymous_actor(&http_req)
     ).await {
         Ok(tx_hash) => {
             info!("Executed gasless claim transaction {} for voucher {} to recipient {}", 
-                  tx_hash, req.code, req.recipient_address);
+                  tx_hash, code, req.recipient_address);
             Ok(HttpResponse::Ok().json(json!({
                 "success": true,
                 "tx_hash": tx_hash,
@@ -260,6 +326,9 @@ pub async fn execute_claim(
         }
         Err(e) => {
             warn!("Failed to execute gasless claim: {}", e);
+            if let Some(response) = claim_error_response(e.as_ref()) {
+                return Ok(response);
+            }
             Ok(HttpResponse::BadRequest().json(json!({
                 "error": "Failed to execute gasless claim",
                 "message": e.to_string()
@@ -270,10 +339,15 @@ pub async fn execute_claim(
 
 // POST /api/vouchers/claim-status - Update claim status after transaction
 pub async fn update_claim_status(
-    pool: web::Data<PgPool>,
+    _pool: web::Data<PgPool>,
     service: web::Data<VoucherService>,
     req: web::Json<ClaimStatusRequest>,
+    http_req: HttpRequest,
 ) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&req.code) else {
+        return Ok(invalid_code_response());
+    };
+
     // Validate transaction hash
     if !is_valid_tx_hash(&req.tx_hash) {
         return Ok(HttpResponse::BadRequest().json(json!({
@@ -281,35 +355,19 @@ pub async fn update_claim_status(
         })));
     }
 
-    // Get voucher to find recipient
-    let voucher = match service.get_voucher_by_code(&req.code).await {
-        Ok(Some(v)) => v,
-        _ => {
-            return Ok(HttpResponse::NotFound().json(json!({
-                "error": "Voucher not found"
-            })));
-        }
-    };
-
-    // Get recipient from last claim attempt
-    let recipient: Option<(String,)> = sqlx::query_as(
-        r#"
-        SELECT recipient_address 
-        FROM claim_attempts 
-        WHERE voucher_code = $1 
-        ORDER BY attempted_at DESC 
-        LIMIT 1
-        "#
-    )
-    .bind(&req.code)
-    .fetch_optional(pool.get_ref())
-    .await
-    .unwrap_or(None);
-
-    let claimed_by = recipient.map(|(addr,)| addr).unwrap_or_default();
+    if !matches!(service.get_voucher_by_code(&code).await, Ok(Some(_))) {
+        return Ok(HttpResponse::NotFound().json(json!({
+            "error": "Voucher not found"
+        })));
+    }
 
     // Update claim status
-    match service.update_claim_status(&req.code, &req.tx_hash, req.success, &claimed_by).await {
+    match service.update_claim_status(
+        &code,
+        &req.tx_hash,
+        req.success,
+        &request_context::anonymous_actor(&http_req)
+    ).await {
         Ok(_) => {
             Ok(HttpResponse::Ok().json(json!({
                 "success": true,
@@ -317,6 +375,12 @@ pub async fn update_claim_status(
             })))
         }
         Err(e) => {
+            if let Some(response) = e.downcast_ref::<LifecycleError>().and_then(lifecycle_error_response) {
+                return Ok(response);
+            }
+            if let Some(response) = claim_error_response(e.as_ref()) {
+                return Ok(response);
+            }
             Ok(HttpResponse::InternalServerError().json(json!({
                 "error": "Failed to update claim status",
                 "message": e.to_string()
@@ -365,9 +429,12 @@ pub async fn list_user_vouchers(
 
 // Configure all voucher routes
 pub async fn sync_voucher_status(
-    pool: web::Data<PgPool>,
+    _pool: web::Data<PgPool>,
+    audit: web::Data<AuditLog>,
+    service: web::Data<VoucherService>,
     voucher_id: web::Path<String>,
     provider: web::Data<Arc<Provider<Http>>>,
+    http_req: HttpRequest,
 ) -> Result<HttpResponse> {
     let voucher_id = voucher_id.into_inner();
     
@@ -378,14 +445,10 @@ pub async fn sync_voucher_status(
         })));
     }
     
-    // Check if voucher exists in our database
-    let voucher: Option<VoucherCode> = sqlx::query_as(
-        "SELECT * FROM voucher_codes WHERE voucher_id = $1"
-    )
-    .bind(&voucher_id)
-    .fetch_optional(pool.get_ref())
-    .await
-    .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+    // Check if voucher exists in our database; a revoked code is never "the" code
+    let voucher = service.get_voucher_by_id(&voucher_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
     
     if let Some(mut voucher) = voucher {
         // Get the voucher contract address from env
@@ -421,39 +484,50 @@ pub async fn sync_voucher_status(
         let on_chain_claimed_by_user = creator != Address::zero() && on_chain_claimed;
         
         // Update database if status differs
+        let target = if on_chain_cancelled {
+            Some(VoucherStatus::Cancelled)
+        } else if on_chain_claimed_by_user {
+            Some(VoucherStatus::Claimed)
+        } else {
+            None
+        };
+        let before = json!({ "status": voucher.status, "claimed": voucher.claimed, "cancelled": voucher.cancelled });
+        let ctx = request_context::anonymous_actor(&http_req);
+
         let mut updated = false;
-        
-        if on_chain_cancelled && !voucher.cancelled {
-            sqlx::query(
-                "UPDATE voucher_codes SET cancelled = true, cancelled_at = NOW() WHERE voucher_id = $1"
-            )
-            .bind(&voucher_id)
-            .execute(pool.get_ref())
-            .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
-            
-            voucher.cancelled = true;
-            voucher.cancelled_at = Some(chrono::Utc::now());
-            updated = true;
+        if let Some(target) = target {
+            let change = StatusChange { detail: Some(json!({ "source": "sync" })), ..Default::default() };
+            match service.lifecycle().transition(&voucher_id, target, &change, &ctx).await {
+                Ok(moved) => updated = moved.is_some(),
+                Err(e) => {
+                    if let Some(response) = lifecycle_error_response(&e) {
+                        return Ok(response);
+                    }
+                    return Err(actix_web::error::ErrorInternalServerError(e));
+                }
+            }
         }
-        
-        if on_chain_claimed_by_user && !voucher.claimed {
-            sqlx::query(
-                "UPDATE voucher_codes SET claimed = true, claimed_at = NOW() WHERE voucher_id = $1"
-            )
-            .bind(&voucher_id)
-            .execute(pool.get_ref())
-            .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
-            
-            voucher.claimed = true;
-            voucher.claimed_at = Some(chrono::Utc::now());
-            updated = true;
+        if updated {
+            voucher.status = target.map(|target| target.as_str().to_string()).unwrap_or(voucher.status);
+            voucher.claimed = target == Some(VoucherStatus::Claimed);
+            voucher.cancelled = target == Some(VoucherStatus::Cancelled);
+        }
+
+        if updated {
+            audit.record_or_log(
+                &ctx,
+                "voucher.synced",
+                "voucher",
+                &voucher_id,
+                Some(before),
+                Some(json!({ "status": voucher.status, "claimed": voucher.claimed, "cancelled": voucher.cancelled })),
+            ).await;
         }
         
         Ok(HttpResponse::Ok().json(json!({
             "voucher_id": voucher_id,
             "code": voucher.code,
+            "status": voucher.status,
             "claimed": voucher.claimed,
             "cancelled": voucher.cancelled,
             "on_chain_claimed": on_chain_claimed,
@@ -520,7 +594,7 @@ pub async fn get_wallet_address(
 
 // GET /api/vouchers/details/{voucher_id} - Get voucher details including creator
 pub async fn get_voucher_details(
-    pool: web::Data<PgPool>,
+    service: web::Data<VoucherService>,
     voucher_id: web::Path<String>,
 ) -> Result<HttpResponse> {
     let voucher_id = voucher_id.into_inner();
@@ -532,13 +606,9 @@ pub async fn get_voucher_details(
         })));
     }
     
-    let voucher: Option<VoucherCode> = sqlx::query_as(
-        "SELECT * FROM voucher_codes WHERE voucher_id = $1"
-    )
-    .bind(&voucher_id)
-    .fetch_optional(pool.get_ref())
-    .await
-    .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+    let voucher = service.get_voucher_by_id(&voucher_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
     
     if let Some(voucher) = voucher {
         Ok(HttpResponse::Ok().json(json!({
@@ -546,6 +616,7 @@ pub async fn get_voucher_details(
             "code": voucher.code,
             "creator_address": voucher.creator_address,
             "amount": voucher.amount,
+            "status": voucher.status,
             "claimed": voucher.claimed,
             "claimed_by": voucher.claimed_by,
             "cancelled": voucher.cancelled,
@@ -560,10 +631,12 @@ pub async fn get_voucher_details(
     }
 }
 
-// DELETE /api/vouchers/{voucher_id} - Soft delete a voucher
+// DELETE /api/vouchers/{voucher_id} - Creator withdraws a voucher
 pub async fn delete_voucher(
-    pool: web::Data<PgPool>,
+    audit: web::Data<AuditLog>,
+    service: web::Data<VoucherService>,
     voucher_id: web::Path<String>,
+    creator: Option<web::Query<CreatorAuth>>,
     req: HttpRequest,
 ) -> Result<HttpResponse> {
     let voucher_id = voucher_id.into_inner();
@@ -574,56 +647,488 @@ pub async fn delete_voucher(
             "error": "Invalid voucher ID format"
         })));
     }
+    let voucher_id = voucher_id.to_lowercase();
+
+    let voucher = match service.get_voucher_by_id(&voucher_id).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+        Err(_e) => {
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "error": "Database error"
+            })));
+        }
+    };
+
+    // Withdrawing is final, so only a signed request from the creator may do it
+    let Some(creator) = creator else {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": "Creator signature required"
+        })));
+    };
+    if let Err(e) = VoucherService::authorize_voucher_creator(&voucher, "delete_voucher", &creator) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    // Withdraw it from the backend; it stays funded on-chain until
+    // the creator cancels it there
+    let ctx = request_context::address_actor(&req, &creator.address);
+    match service.lifecycle().transition(&voucher_id, VoucherStatus::Withdrawn, &StatusChange::default(), &ctx).await {
+        Ok(_) => {}
+        Err(e) => {
+            if let Some(response) = lifecycle_error_response(&e) {
+                return Ok(response);
+            }
+            return Err(actix_web::error::ErrorInternalServerError(e));
+        }
+    }
+
+    audit.record_or_log(
+        &ctx,
+        "voucher.deleted",
+        "voucher",
+        &voucher_id,
+        Some(json!({ "status": voucher.status })),
+        Some(json!({ "status": VoucherStatus::Withdrawn.as_str() })),
+    ).await;
+
+    info!("Voucher {} withdrawn", voucher_id);
     
-    // Get the requester's address from header (you might want to add auth here)
-    let creator_address = req.headers()
-        .get("X-User-Address")
-        .and_then(|h| h.to_str().ok())
-        .unwrap_or("");
-    
-    // Check if voucher exists and belongs to the requester
-    let voucher: Option<VoucherCode> = sqlx::query_as(
-        "SELECT * FROM voucher_codes WHERE voucher_id = $1"
-    )
-    .bind(&voucher_id)
-    .fetch_optional(pool.get_ref())
-    .await
-    .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
-    
-    match voucher {
-        Some(v) => {
-            // Verify ownership (optional - remove if you want anyone to delete)
-            if !creator_address.is_empty() && 
-               v.creator_address.as_ref().map(|c| c.to_lowercase()) != Some(creator_address.to_lowercase()) {
-                return Ok(HttpResponse::Forbidden().json(json!({
-                    "error": "You can only delete your own vouchers"
-                })));
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "status": VoucherStatus::Withdrawn.as_str(),
+        "message": "Voucher deleted successfully; cancel it on-chain to recover the funds"
+    })))
+}
+
+// GET /api/vouchers/{code}/lockout - Creator view of the password lockout
+pub async fn get_lockout_status(
+    service: web::Data<VoucherService>,
+    code: web::Path<String>,
+    query: web::Query<CreatorAuth>,
+) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&code) else {
+        return Ok(invalid_code_response());
+    };
+
+    let voucher = match service.get_voucher_by_code(&code).await {
+        Ok(Some(v)) => v,
+        _ => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+    };
+
+    if let Err(e) = VoucherService::authorize_creator(&voucher, "view_lockout", &query) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.get_lockout_status(&code).await {
+        Ok(status) => Ok(HttpResponse::Ok().json(status)),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// POST /api/vouchers/{code}/lockout/reset - Creator clears the password lockout
+pub async fn reset_lockout(
+    service: web::Data<VoucherService>,
+    code: web::Path<String>,
+    req: web::Json<CreatorAuth>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&code) else {
+        return Ok(invalid_code_response());
+    };
+
+    let voucher = match service.get_voucher_by_code(&code).await {
+        Ok(Some(v)) => v,
+        _ => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+    };
+
+    if let Err(e) = VoucherService::authorize_creator(&voucher, "reset_lockout", &req) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.reset_lockout(&code, &request_context::address_actor(&http_req, &req.address)).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "Password lockout reset"
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// GET /api/vouchers/{code}/authorizations - Creator view of issued claim authorizations
+pub async fn list_authorizations(
+    service: web::Data<VoucherService>,
+    code: web::Path<String>,
+    query: web::Query<CreatorAuth>,
+) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&code) else {
+        return Ok(invalid_code_response());
+    };
+
+    let voucher = match service.get_voucher_by_code(&code).await {
+        Ok(Some(v)) => v,
+        _ => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+    };
+
+    if let Err(e) = VoucherService::authorize_creator(&voucher, "view_authorizations", &query) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.list_authorizations(&voucher.voucher_id).await {
+        Ok(authorizations) => Ok(HttpResponse::Ok().json(json!({
+            "voucher_id": voucher.voucher_id,
+            "authorizations": authorizations
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// GET /api/vouchers/{code}/timeline - Everything that happened to a voucher,
+// for support with the admin X-API-Key or for the creator with a signature
+pub async fn get_timeline(
+    service: web::Data<VoucherService>,
+    timeline: web::Data<TimelineService>,
+    admin: web::Data<AdminConfig>,
+    code: web::Path<String>,
+    creator: Option<web::Query<CreatorAuth>>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&code) else {
+        return Ok(invalid_code_response());
+    };
+
+    let voucher = match service.get_voucher_by_code(&code).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+        Err(_e) => {
+            return Ok(HttpResponse::InternalServerError().json(json!({
+                "error": "Database error"
+            })));
+        }
+    };
+
+    if req.headers().contains_key("X-API-Key") {
+        if let Some(denied) = require_admin(&req, &admin) {
+            return Ok(denied);
+        }
+    } else {
+        let authorized = match &creator {
+            Some(creator) => VoucherService::authorize_creator(&voucher, "view_timeline", creator),
+            None => Err("Admin API key or creator signature required".into()),
+        };
+        if let Err(e) = authorized {
+            return Ok(HttpResponse::Unauthorized().json(json!({
+                "error": "Unauthorized",
+                "message": e.to_string()
+            })));
+        }
+    }
+
+    match timeline.for_voucher(&voucher).await {
+        Ok(timeline) => Ok(HttpResponse::Ok().json(timeline)),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// GET /api/vouchers/{voucher_id}/codes - Creator view of all codes and their usage
+pub async fn list_codes(
+    service: web::Data<VoucherService>,
+    voucher_id: web::Path<String>,
+    query: web::Query<CreatorAuth>,
+) -> Result<HttpResponse> {
+    let voucher_id = voucher_id.into_inner().to_lowercase();
+
+    let voucher = match service.get_voucher_by_id(&voucher_id).await {
+        Ok(Some(v)) => v,
+        _ => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+    };
+
+    if let Err(e) = VoucherService::authorize_voucher_creator(&voucher, "list_codes", &query) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.list_codes(&voucher_id).await {
+        Ok(codes) => Ok(HttpResponse::Ok().json(json!({
+            "voucher_id": voucher_id,
+            "codes": codes
+        }))),
+        Err(_e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "error": "Database error"
+        }))),
+    }
+}
+
+// PUT /api/vouchers/{voucher_id}/recipients - Creator restricts who may claim
+pub async fn set_recipients(
+    service: web::Data<VoucherService>,
+    voucher_id: web::Path<String>,
+    req: web::Json<SetRecipientsRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let voucher_id = voucher_id.into_inner().to_lowercase();
+
+    let voucher = match service.get_voucher_by_id(&voucher_id).await {
+        Ok(Some(v)) => v,
+        _ => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+    };
+
+    if let Err(e) = VoucherService::authorize_voucher_creator(&voucher, "set_recipients", &req.creator) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.set_recipient_restriction(
+        &voucher_id,
+        req.addresses.as_deref(),
+        req.merkle_root.as_deref(),
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(restriction) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "voucher_id": voucher_id,
+            "restriction": restriction
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to set recipients",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// POST /api/vouchers/{voucher_id}/codes - Creator issues an additional code
+pub async fn add_code(
+    service: web::Data<VoucherService>,
+    voucher_id: web::Path<String>,
+    req: web::Json<AddCodeRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let voucher_id = voucher_id.into_inner().to_lowercase();
+
+    if req.label.as_ref().is_some_and(|label| label.chars().count() > 100) {
+        return Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Label must be at most 100 characters"
+        })));
+    }
+
+    let voucher = match service.get_voucher_by_id(&voucher_id).await {
+        Ok(Some(v)) => v,
+        _ => {
+            return Ok(HttpResponse::NotFound().json(json!({
+                "error": "Voucher not found"
+            })));
+        }
+    };
+
+    if let Err(e) = VoucherService::authorize_voucher_creator(&voucher, "add_code", &req.creator) {
+        return Ok(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    match service.add_code(
+        &voucher,
+        req.label.as_deref(),
+        req.password.as_deref(),
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(code) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "code": service.display_code(&code),
+            "shareable_link": service.claim_path(&code),
+            "claim_url": service.claim_url(&code)
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to add code",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// POST /api/vouchers/{voucher_id}/codes/{code}/revoke - Creator revokes one code
+pub async fn revoke_code(
+    service: web::Data<VoucherService>,
+    path: web::Path<(String, String)>,
+    req: web::Json<CreatorAuth>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let (voucher_id, code) = path.into_inner();
+
+    let voucher = match authorize_code_action(&service, &voucher_id, &code, "revoke_code", &req).await {
+        Ok(v) => v,
+        Err(response) => return Ok(response),
+    };
+
+    match service.revoke_code(&voucher.code, &request_context::address_actor(&http_req, &req.address)).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "message": "Code revoked"
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to revoke code",
+            "message": e.to_string()
+        }))),
+    }
+}
+
+// POST /api/vouchers/{voucher_id}/codes/{code}/rotate - Creator replaces a leaked code
+pub async fn rotate_code(
+    service: web::Data<VoucherService>,
+    path: web::Path<(String, String)>,
+    req: web::Json<RotateCodeRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let (voucher_id, code) = path.into_inner();
+
+    let voucher = match authorize_code_action(&service, &voucher_id, &code, "rotate_code", &req.creator).await {
+        Ok(v) => v,
+        Err(response) => return Ok(response),
+    };
+
+    match service.rotate_code(
+        &voucher.code,
+        req.password.as_deref(),
+        &request_context::address_actor(&http_req, &req.creator.address),
+    ).await {
+        Ok(new_code) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "code": service.display_code(&new_code),
+            "replaces": service.display_code(&voucher.code),
+            "shareable_link": service.claim_path(&new_code),
+            "claim_url": service.claim_url(&new_code)
+        }))),
+        Err(e) => {
+            if let Some(response) = link_error_response(e.as_ref()) {
+                return Ok(response);
             }
-            
-            // Mark as cancelled (soft delete)
-            sqlx::query(
-                "UPDATE voucher_codes SET cancelled = true, cancelled_at = NOW() WHERE voucher_id = $1"
-            )
-            .bind(&voucher_id)
-            .execute(pool.get_ref())
-            .await
-            .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
-            
-            info!("Voucher {} marked as deleted/cancelled", voucher_id);
-            
-            Ok(HttpResponse::Ok().json(json!({
-                "success": true,
-                "message": "Voucher deleted successfully"
+            Ok(HttpResponse::BadRequest().json(json!({
+                "error": "Failed to rotate code",
+                "message": e.to_string()
             })))
         }
-        None => {
-            Ok(HttpResponse::NotFound().json(json!({
-                "error": "Voucher not found"
+    }
+}
+
+// PUT /api/vouchers/{voucher_id}/codes/{code}/note - Creator sets or removes the gift note
+pub async fn set_note(
+    service: web::Data<VoucherService>,
+    path: web::Path<(String, String)>,
+    req: web::Json<SetNoteRequest>,
+    http_req: HttpRequest,
+) -> Result<HttpResponse> {
+    let (voucher_id, code) = path.into_inner();
+
+    let voucher = match authorize_code_action(&service, &voucher_id, &code, "set_note", &req.creator).await {
+        Ok(v) => v,
+        Err(response) => return Ok(response),
+    };
+
+    match service.set_note(
+        &voucher.code,
+        req.note.as_ref(),
+        req.password.as_deref(),
+        &request_context::address_actor(&http_req, &req.creator.address)
+    ).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "has_note": req.note.is_some()
+        }))),
+        Err(e) => {
+            if let Some(response) = link_error_response(e.as_ref()) {
+                return Ok(response);
+            }
+            Ok(HttpResponse::BadRequest().json(json!({
+                "error": "Failed to set note",
+                "message": e.to_string()
             })))
         }
     }
 }
 
+// GET /api/vouchers/{code}/qr?format=svg|png&size=256&ec=m - Claim URL as a QR code.
+// Whoever holds a code can already build its URL, so the code is not looked
+// up; that also keeps this from revealing which codes exist
+pub async fn get_claim_qr(
+    service: web::Data<VoucherService>,
+    code: web::Path<String>,
+    query: web::Query<QrOptions>,
+) -> Result<HttpResponse> {
+    let Some(code) = service.normalize_code(&code) else {
+        return Ok(invalid_code_response());
+    };
+
+    let Some(url) = service.claim_url(&code) else {
+        return Ok(HttpResponse::ServiceUnavailable().json(json!({
+            "error": "QR codes are not configured",
+            "message": "links.public_base_url is not set"
+        })));
+    };
+
+    match qr::render(&url, &query) {
+        Ok(image) => Ok(HttpResponse::Ok()
+            .content_type(query.content_type())
+            .insert_header(("Cache-Control", "private, max-age=3600"))
+            .body(image)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(json!({
+            "error": "Failed to render QR code",
+            "message": e.to_string()
+        }))),
+    }
+}
+
 pub fn configure_voucher_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/vouchers")
@@ -636,6 +1141,17 @@ pub fn configure_voucher_routes(cfg: &mut web::ServiceConfig) {
             .route("/user/{address}", web::get().to(list_user_vouchers))
             .route("/sync/{voucher_id}", web::post().to(sync_voucher_status))
             .route("/details/{voucher_id}", web::get().to(get_voucher_details))
+            .route("/{code}/lockout", web::get().to(get_lockout_status))
+            .route("/{code}/lockout/reset", web::post().to(reset_lockout))
+            .route("/{code}/authorizations", web::get().to(list_authorizations))
+            .route("/{code}/timeline", web::get().to(get_timeline))
+            .route("/{code}/qr", web::get().to(get_claim_qr))
+            .route("/{voucher_id}/recipients", web::put().to(set_recipients))
+            .route("/{voucher_id}/codes", web::get().to(list_codes))
+            .route("/{voucher_id}/codes", web::post().to(add_code))
+            .route("/{voucher_id}/codes/{code}/revoke", web::post().to(revoke_code))
+            .route("/{voucher_id}/codes/{code}/rotate", web::post().to(rotate_code))
+            .route("/{voucher_id}/codes/{code}/note", web::put().to(set_note))
             .route("/{voucher_id}", web::delete().to(delete_voucher))
     );
     cfg.service(
@@ -645,13 +1161,156 @@ pub fn configure_voucher_routes(cfg: &mut web::ServiceConfig) {
 }
 
 // Helper functions
-fn extract_ip(req: &HttpRequest) -> String {
-    let connection_info = req.connection_info();
-    connection_info
-        .realip_remote_addr()
-        .or_else(|| connection_info.peer_addr())
-        .unwrap_or("unknown")
-        .to_string()
+
+// Map claim failures to distinct statuses: 401 for a wrong or missing
+// password, 423 while the code is locked out, 409 while another
+// authorization is live or claim transaction in flight, or the recipient
+// already claimed from the campaign,
+// 403 before the claim window opens or for recipients outside the allowlist,
+// and 410 after the window closes
+pub(crate) fn claim_error_response(e: &(dyn std::error::Error + 'static)) -> Option<HttpResponse> {
+    match e.downcast_ref::<ClaimError>()? {
+        ClaimError::PasswordRequired => Some(HttpResponse::Unauthorized().json(json!({
+            "error": "Password required",
+            "error_code": "password_required"
+        }))),
+        ClaimError::InvalidPassword => Some(HttpResponse::Unauthorized().json(json!({
+            "error": "Invalid password",
+            "error_code": "invalid_password"
+        }))),
+        ClaimError::AuthorizationLive { deadline } => Some(HttpResponse::Conflict().json(json!({
+            "error": "A claim authorization for this voucher is already live",
+            "error_code": "authorization_live",
+            "live_until": deadline
+        }))),
+        ClaimError::NotYetClaimable { claimable_from } => Some(HttpResponse::Forbidden().json(json!({
+            "error": "Voucher is not claimable yet",
+            "error_code": "not_yet_claimable",
+            "claimable_from": claimable_from
+        }))),
+        ClaimError::Expired { claimable_until } => Some(HttpResponse::Gone().json(json!({
+            "error": "Voucher has expired",
+            "error_code": "voucher_expired",
+            "claimable_until": claimable_until
+        }))),
+        ClaimError::RecipientNotAllowed => Some(HttpResponse::Forbidden().json(json!({
+            "error": "Recipient is not allowed to claim this voucher",
+            "error_code": "recipient_not_allowed"
+        }))),
+        ClaimError::RecipientLimitReached => Some(HttpResponse::Conflict().json(json!({
+            "error": "Recipient has already claimed a voucher of this campaign",
+            "error_code": "recipient_limit_reached"
+        }))),
+        ClaimError::ClaimInProgress { tx_hash } => Some(HttpResponse::Conflict().json(json!({
+            "error": "A claim transaction for this voucher is already in progress",
+            "error_code": "claim_in_progress",
+            "tx_hash": tx_hash
+        }))),
+        ClaimError::ClaimNotOnChain => Some(HttpResponse::BadRequest().json(json!({
+            "error": "Transaction has not claimed this voucher",
+            "error_code": "claim_not_on_chain"
+        }))),
+        ClaimError::NotSponsored { reason } => Some(HttpResponse::Forbidden().json(json!({
+            "error": "Gas for this claim is not sponsored",
+            "error_code": "not_sponsored",
+            "reason": reason,
+            "message": "Claim from your own wallet with /api/vouchers/claim"
+        }))),
+        ClaimError::Locked { locked_until } => {
+            let retry_after = (*locked_until - chrono::Utc::now()).num_seconds().max(1);
+            Some(HttpResponse::build(actix_web::http::StatusCode::LOCKED)
+                .insert_header(("Retry-After", retry_after.to_string()))
+                .json(json!({
+                    "error": "Too many failed password attempts",
+                    "error_code": "voucher_locked",
+                    "locked_until": locked_until,
+                    "retry_after": retry_after
+                })))
+        }
+    }
+}
+
+// A move the voucher lifecycle does not allow, such as withdrawing a claimed
+// voucher, conflicts with its current status
+fn lifecycle_error_response(e: &LifecycleError) -> Option<HttpResponse> {
+    match e {
+        LifecycleError::NotFound => Some(HttpResponse::NotFound().json(json!({
+            "error": "Voucher not found"
+        }))),
+        LifecycleError::InvalidTransition { from, .. } => Some(HttpResponse::Conflict().json(json!({
+            "error": e.to_string(),
+            "error_code": "invalid_status",
+            "status": from.as_str()
+        }))),
+        LifecycleError::Database(_) => None,
+    }
+}
+
+// Malformed codes and typos caught by the check symbol are rejected before
+// any lookup or rate-limit charge
+fn invalid_code_response() -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({
+        "error": "Invalid voucher code",
+        "error_code": "invalid_code",
+        "message": "Check the code for typos"
+    }))
+}
+
+// Load an active code of the given voucher and check the creator's signature for it
+async fn authorize_code_action(
+    service: &VoucherService,
+    voucher_id: &str,
+    code: &str,
+    action: &str,
+    creator: &CreatorAuth,
+) -> std::result::Result<VoucherCode, HttpResponse> {
+    let Some(code) = service.normalize_code(code) else {
+        return Err(invalid_code_response());
+    };
+    let voucher = match service.get_voucher_by_code(&code).await {
+        Ok(Some(v)) if v.voucher_id.to_lowercase() == voucher_id.to_lowercase() => v,
+        _ => {
+            return Err(HttpResponse::NotFound().json(json!({
+                "error": "Code not found"
+            })));
+        }
+    };
+
+    if let Err(e) = VoucherService::authorize_creator(&voucher, action, creator) {
+        return Err(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": e.to_string()
+        })));
+    }
+
+    Ok(voucher)
+}
+
+fn link_error_response(e: &(dyn std::error::Error + 'static)) -> Option<HttpResponse> {
+    match e.downcast_ref::<LinkError>()? {
+        LinkError::Unauthorized(message) => Some(HttpResponse::Unauthorized().json(json!({
+            "error": "Unauthorized",
+            "message": message
+        }))),
+        LinkError::NotFound => Some(HttpResponse::NotFound().json(json!({
+            "error": "Voucher not found on-chain"
+        }))),
+        LinkError::AlreadyClaimed => Some(HttpResponse::Conflict().json(json!({
+            "error": "Voucher already claimed"
+        }))),
+        LinkError::AmountMismatch { on_chain } => Some(HttpResponse::BadRequest().json(json!({
+            "error": "Amount does not match the on-chain voucher",
+            "on_chain_amount": on_chain
+        }))),
+        LinkError::InvalidWindow(message) => Some(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid claim window",
+            "message": message
+        }))),
+        LinkError::InvalidNote(message) => Some(HttpResponse::BadRequest().json(json!({
+            "error": "Invalid note",
+            "message": message
+        }))),
+    }
 }
 
 fn is_valid_voucher_id(id: &str) -> bool {
@@ -659,7 +1318,7 @@ fn is_valid_voucher_id(id: &str) -> bool {
     id.len() == 66 && id.starts_with("0x") && id[2..].chars().all(|c| c.is_ascii_hexdigit())
 }
 
-fn is_valid_ethereum_address(address: &str) -> bool {
+pub(crate) fn is_valid_ethereum_address(address: &str) -> bool {
     if !address.starts_with("0x") || address.len() != 42 {
         return false;
     }
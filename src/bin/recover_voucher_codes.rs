@@ -0,0 +1,90 @@
+// Rebuild voucher_codes from chain events after data loss.
+//
+// Requires [codes] derivation = "hmac" with the same secret the server used:
+// every voucher gets back its first code. Codes added or rotated in later,
+// passwords, labels and revocations are not recoverable; creators should
+// rotate any code they had revoked.
+//
+// Usage: recover_voucher_codes [from_block] [to_block]
+use dotenv::dotenv;
+use ethers::prelude::*;
+use nbgn_backend::config::Settings;
+use nbgn_backend::db;
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::codes::CodeGenerator;
+use nbgn_backend::services::event_indexer::EventIndexer;
+use nbgn_backend::services::lifecycle::{StatusChange, VoucherLifecycle, VoucherStatus};
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let settings = Settings::new()?;
+
+    let codes = CodeGenerator::from_config(&settings.codes)?;
+    if !codes.is_deterministic() {
+        return Err("Recovery needs [codes] derivation = \"hmac\"; random codes cannot be rebuilt".into());
+    }
+
+    let pool = db::create_pool(&settings.database.url).await?;
+    db::run_migrations(&pool).await?;
+
+    let provider = Arc::new(Provider::<Http>::try_from(&settings.ethereum.rpc_url)?);
+    let voucher_contract = settings.ethereum.voucher_contract_address.parse::<Address>()?;
+
+    let mut args = std::env::args().skip(1);
+    let from_block = match args.next() {
+        Some(block) => block.parse()?,
+        None => settings.indexer.start_block,
+    };
+    let to_block = match args.next() {
+        Some(block) => block.parse()?,
+        None => provider.get_block_number().await?.as_u64(),
+    };
+
+    println!("Replaying voucher events from block {} to {}", from_block, to_block);
+    let indexer = EventIndexer::new(pool.clone(), provider.clone(), voucher_contract)
+        .with_code_generator(codes);
+    indexer.replay(from_block, to_block).await?;
+
+    // The replay marks claims from their logs; the contract covers claims
+    // made outside the replayed range
+    let abi = ethers::abi::parse_abi(&[
+        "function vouchers(bytes32) view returns (address creator, uint256 amount, bool claimed)"
+    ])?;
+    let contract = Contract::new(voucher_contract, abi, provider.clone());
+
+    let open: Vec<(String,)> = sqlx::query_as(
+        "SELECT DISTINCT voucher_id FROM voucher_codes WHERE claimed = FALSE AND cancelled = FALSE"
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    let lifecycle = VoucherLifecycle::new(pool.clone());
+    let ctx = AuditContext::system("recover_voucher_codes");
+    let mut claimed = 0;
+    for (voucher_id,) in &open {
+        let id: H256 = voucher_id.parse()?;
+        let (creator, _amount, is_claimed): (Address, U256, bool) = contract
+            .method::<_, (Address, U256, bool)>("vouchers", id)?
+            .call()
+            .await?;
+
+        if is_claimed && creator != Address::zero() {
+            lifecycle.transition(voucher_id, VoucherStatus::Claimed, &StatusChange::default(), &ctx).await?;
+            claimed += 1;
+        }
+    }
+
+    let (total,): (i64,) = sqlx::query_as("SELECT COUNT(DISTINCT voucher_id) FROM voucher_codes")
+        .fetch_one(&pool)
+        .await?;
+
+    println!("Recovered codes for {} vouchers ({} marked claimed from contract state)", total, claimed);
+    Ok(())
+}
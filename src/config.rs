@@ -1,5 +1,5 @@
 use config::{Config, ConfigError, Environment, File};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
@@ -9,6 +9,28 @@ pub struct Settings {
     pub server: ServerConfig,
     pub indexer: IndexerConfig,
     pub backend: BackendConfig,
+    #[serde(default)]
+    pub lockout: LockoutConfig,
+    #[serde(default)]
+    pub authorizations: AuthorizationConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub codes: CodeConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub expiry: ExpiryConfig,
+    #[serde(default)]
+    pub links: LinkConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub relay: RelayConfig,
+    #[serde(default)]
+    pub sponsorship: SponsorshipConfig,
+    #[serde(default)]
+    pub claim_batching: ClaimBatchConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -27,6 +49,8 @@ pub struct EthereumConfig {
     pub backup_rpc_urls: Option<String>,
     pub nbgn_contract_address: String,
     pub voucher_contract_address: String,
+    // EURe token that NBGN mints from; needed to build EURe approvals
+    pub eure_token_address: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -41,9 +65,331 @@ pub struct IndexerConfig {
     pub poll_interval_secs: u64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerKind {
+    #[default]
+    RawKey,
+    Keystore,
+    Remote,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerRole {
+    /// Signs new claim authorizations and relayed transactions; exactly one key has this role
+    #[default]
+    Signing,
+    /// No new signatures; authorizations it already issued stay live until their deadline
+    Verifying,
+    /// Authorizations it issued are void
+    Retired,
+}
+
+impl SignerRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignerRole::Signing => "signing",
+            SignerRole::Verifying => "verifying",
+            SignerRole::Retired => "retired",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "signing" => Some(SignerRole::Signing),
+            "verifying" => Some(SignerRole::Verifying),
+            "retired" => Some(SignerRole::Retired),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SignerConfig {
+    #[serde(default)]
+    pub signer: SignerKind,
+    #[serde(default)]
+    pub role: SignerRole,
+    // raw_key: hex private key
+    pub private_key: Option<String>,
+    // keystore: encrypted JSON keystore unlocked at startup
+    pub keystore_path: Option<String>,
+    pub keystore_password: Option<String>,
+    // remote: Web3Signer base URL and the address of the key it holds
+    pub remote_signer_url: Option<String>,
+    pub remote_signer_address: Option<String>,
+    // Web3Signer key identifier, defaults to remote_signer_address
+    pub remote_signer_identifier: Option<String>,
+}
+
+impl SignerConfig {
+    pub fn is_configured(&self) -> bool {
+        self.private_key.is_some() || self.keystore_path.is_some() || self.remote_signer_url.is_some()
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct BackendConfig {
-    pub private_key: String,
+    // Single key configured directly under [backend]
+    #[serde(flatten)]
+    pub key: SignerConfig,
+    // Further keys for rotation, as [[backend.signers]]
+    #[serde(default)]
+    pub signers: Vec<SignerConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AdminConfig {
+    // Expected in the X-API-Key header of /api/admin requests; admin routes are disabled when unset
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LockoutConfig {
+    /// Failed password attempts allowed per code before the first lockout
+    pub free_attempts: u32,
+    /// Length of the first lockout; doubles with every further failure
+    pub base_lockout_secs: u64,
+    pub max_lockout_secs: u64,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            free_attempts: 5,
+            base_lockout_secs: 60,
+            max_lockout_secs: 86_400,
+        }
+    }
+}
+
+/// What to do when a claim authorization is requested while another one for
+/// the same voucher is still within its deadline. Superseding only retires the
+/// old authorization in our ledger; its signature stays valid on-chain until
+/// the deadline passes.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SupersedePolicy {
+    /// Reject every new request until the live authorization expires
+    Never,
+    /// Re-issue only to the recipient of the live authorization
+    #[default]
+    SameRecipient,
+    /// Always re-issue, to any recipient
+    Always,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AuthorizationConfig {
+    #[serde(default)]
+    pub supersede: SupersedePolicy,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeDerivation {
+    /// Random codes; lost for good if the voucher_codes table is lost
+    #[default]
+    Random,
+    /// HMAC-SHA256 over the voucher id under a server secret, so codes can be
+    /// rebuilt from VoucherCreated logs
+    Hmac,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeFormat {
+    /// 16 uppercase letters and digits
+    #[default]
+    Legacy,
+    /// 19 Crockford base32 symbols plus a check symbol, shown as
+    /// XXXXX-XXXXX-XXXXX-XXXXX. Legacy codes issued earlier stay valid
+    Crockford,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CodeConfig {
+    #[serde(default)]
+    pub derivation: CodeDerivation,
+    // Key for hmac derivation; changing it changes every derived code
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub format: CodeFormat,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationConfig {
+    // Creator notifications are POSTed here as JSON; only logged when unset
+    pub webhook_url: Option<String>,
+    /// How often claims and cancellations are relayed to creator channels
+    pub relay_interval_secs: u64,
+    // Outgoing mail for email channels; creators can only register webhook
+    // channels when unset
+    pub smtp: Option<SmtpConfig>,
+    /// Let creator webhooks point at loopback and private addresses; for
+    /// local development only
+    #[serde(default)]
+    pub allow_private_channel_targets: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            relay_interval_secs: 30,
+            smtp: None,
+            allow_private_channel_targets: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender mailbox, e.g. "NBGN Vouchers <vouchers@example.com>"
+    pub from: String,
+    #[serde(default)]
+    pub tls: SmtpTls,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Plain connection upgraded with STARTTLS
+    #[default]
+    Starttls,
+    /// TLS from the first byte, usually port 465
+    Tls,
+    /// Unencrypted; only for a relay on the same host or in tests
+    None,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExpiryConfig {
+    /// How often to look for vouchers whose claim window closed unclaimed
+    pub scan_interval_secs: u64,
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        Self { scan_interval_secs: 300 }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LinkConfig {
+    // Frontend origin that claim links and QR codes point at, e.g.
+    // https://app.example.com; links stay relative when unset
+    pub public_base_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookConfig {
+    /// How often the delivery worker looks for due deliveries
+    pub delivery_interval_secs: u64,
+    /// Deliveries claimed per worker cycle
+    pub batch_size: i64,
+    /// Attempts before a delivery is marked failed
+    pub max_attempts: i32,
+    /// Delay after the first failed attempt, doubled after each further one
+    pub initial_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    pub request_timeout_secs: u64,
+    /// How often pending gasless claim transactions are checked for receipts
+    pub claim_monitor_interval_secs: u64,
+    /// A submitted claim with neither a receipt nor a known transaction after
+    /// this long is treated as dropped
+    pub claim_drop_after_secs: i64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            delivery_interval_secs: 10,
+            batch_size: 50,
+            max_attempts: 8,
+            initial_backoff_secs: 30,
+            max_backoff_secs: 6 * 3600,
+            request_timeout_secs: 10,
+            claim_monitor_interval_secs: 15,
+            claim_drop_after_secs: 1800,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RelayConfig {
+    /// Whether the relayer submits creations at all; off by default, as it
+    /// needs contracts with createVoucherWithPermit and EIP-2612 permit
+    pub enabled: bool,
+    /// Fee in NBGN wei the relayer takes from each relayed creation; "0"
+    /// sponsors them
+    pub creation_fee: String,
+    /// Latest a creation permit may expire, counted from submission
+    pub max_deadline_secs: i64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            creation_fee: "0".to_string(),
+            max_deadline_secs: 24 * 3600,
+        }
+    }
+}
+
+// Limits on the gas the relayer pays for claims and sponsored creations.
+// Days are UTC; zero disables a limit. Campaign budgets are kept per
+// campaign in the database
+#[derive(Debug, Deserialize, Clone)]
+pub struct SponsorshipConfig {
+    /// Smallest voucher amount, in NBGN wei, the relayer pays gas for
+    pub min_amount: String,
+    /// Sponsored claims per recipient per day
+    pub recipient_daily_quota: i64,
+    /// Sponsored claims of a creator's vouchers plus sponsored creations, per
+    /// creator per day
+    pub creator_daily_quota: i64,
+    /// Estimated gas spend in wei across all sponsored transactions per day
+    pub daily_spend_cap_wei: String,
+}
+
+// Gasless claims sent together as one Multicall3 aggregate3 transaction
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClaimBatchConfig {
+    pub enabled: bool,
+    /// How long the first claim of a batch waits for others to join it
+    pub window_ms: u64,
+    /// Claims per batch transaction; a full batch is sent at once
+    pub max_size: usize,
+    pub multicall_address: String,
+}
+
+impl Default for ClaimBatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_ms: 2000,
+            max_size: 50,
+            multicall_address: crate::contracts::multicall::MULTICALL3_ADDRESS.to_string(),
+        }
+    }
+}
+
+impl Default for SponsorshipConfig {
+    fn default() -> Self {
+        Self {
+            min_amount: "0".to_string(),
+            recipient_daily_quota: 0,
+            creator_daily_quota: 0,
+            daily_spend_cap_wei: "0".to_string(),
+        }
+    }
 }
 
 impl Settings {
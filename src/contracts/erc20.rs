@@ -0,0 +1,8 @@
+use ethers::prelude::abigen;
+
+abigen!(
+    ERC20,
+    r#"[
+        function approve(address spender, uint256 amount) external returns (bool)
+    ]"#
+);
@@ -1,4 +1,5 @@
-use ethers::prelude::*;
+use ethers::abi::{decode, ParamType, Token};
+use ethers::utils::{hex, id};
 use serde::{Deserialize, Serialize};
 
 // Custom errors from the NBGNVoucherClaim contract
@@ -13,23 +14,32 @@ pub enum VoucherError {
 }
 
 impl VoucherError {
-    pub fn from_revert_data(data: &[u8]) -> Option<Self> {
-        if data.len() < 4 {
-            return None;
+    const ALL: [VoucherError; 6] = [
+        VoucherError::VoucherDoesNotExist,
+        VoucherError::VoucherAlreadyClaimed,
+        VoucherError::InvalidBackendSignature,
+        VoucherError::SignatureExpired,
+        VoucherError::InvalidAmount,
+        VoucherError::TransferFailed,
+    ];
+
+    pub fn signature(&self) -> &'static str {
+        match self {
+            VoucherError::VoucherDoesNotExist => "VoucherDoesNotExist()",
+            VoucherError::VoucherAlreadyClaimed => "VoucherAlreadyClaimed()",
+            VoucherError::InvalidBackendSignature => "InvalidBackendSignature()",
+            VoucherError::SignatureExpired => "SignatureExpired()",
+            VoucherError::InvalidAmount => "InvalidAmount()",
+            VoucherError::TransferFailed => "TransferFailed()",
         }
-        
+    }
+
+    pub fn from_revert_data(data: &[u8]) -> Option<Self> {
         // Custom error selectors (first 4 bytes of keccak256(error_signature))
-        match &data[0..4] {
-            [0x3f, 0x68, 0x66, 0x85] => Some(VoucherError::VoucherDoesNotExist),
-            [0xe1, 0x38, 0x29, 0xd1] => Some(VoucherError::VoucherAlreadyClaimed),
-            [0x64, 0x86, 0x9d, 0xad] => Some(VoucherError::InvalidBackendSignature),
-            [0x0b, 0xf3, 0x18, 0x87] => Some(VoucherError::SignatureExpired),
-            [0x2c, 0x5a, 0x3a, 0xf5] => Some(VoucherError::InvalidAmount),
-            [0x90, 0xb8, 0xec, 0x18] => Some(VoucherError::TransferFailed),
-            _ => None,
-        }
+        let selector = data.get(..4)?;
+        Self::ALL.into_iter().find(|error| id(error.signature()) == selector)
     }
-    
+
     pub fn to_user_message(&self) -> &'static str {
         match self {
             VoucherError::VoucherDoesNotExist => "This voucher does not exist on-chain",
@@ -40,4 +50,38 @@ impl VoucherError {
             VoucherError::TransferFailed => "Token transfer failed - please check your wallet",
         }
     }
-}
\ No newline at end of file
+}
+
+// Why a call reverted, readable without an ABI: a contract error by name,
+// a require() message as "Error", or "Unknown" with only the raw data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedRevert {
+    pub error: String,
+    pub message: String,
+    pub data: String,
+}
+
+impl DecodedRevert {
+    pub fn decode(data: &[u8]) -> Self {
+        let (error, message) = if let Some(error) = VoucherError::from_revert_data(data) {
+            (format!("{:?}", error), error.to_user_message().to_string())
+        } else if let Some(reason) = Self::reason_string(data) {
+            ("Error".to_string(), reason)
+        } else {
+            ("Unknown".to_string(), "Reverted without a known error".to_string())
+        };
+
+        Self { error, message, data: format!("0x{}", hex::encode(data)) }
+    }
+
+    // The message of Error(string), as raised by require()
+    fn reason_string(data: &[u8]) -> Option<String> {
+        if data.get(..4)? != id("Error(string)") {
+            return None;
+        }
+        match decode(&[ParamType::String], &data[4..]).ok()?.pop()? {
+            Token::String(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
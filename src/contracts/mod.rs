@@ -1,2 +1,5 @@
+pub mod erc20;
+pub mod multicall;
 pub mod nbgn;
-pub mod errors;
\ No newline at end of file
+pub mod errors;
+pub mod voucher;
@@ -0,0 +1,14 @@
+use ethers::prelude::abigen;
+
+// Multicall3, deployed at the same address on every chain it supports; the
+// target sees Multicall3 as msg.sender
+pub const MULTICALL3_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+abigen!(
+    Multicall3,
+    r#"[
+        struct Call3 { address target; bool allowFailure; bytes callData; }
+        struct Result { bool success; bytes returnData; }
+        function aggregate3(Call3[] calls) external payable returns (Result[] returnData)
+    ]"#
+);
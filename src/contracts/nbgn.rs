@@ -83,4 +83,14 @@ pub fn get_contract(
 ) -> Result<NBGNContract, Box<dyn std::error::Error>> {
     let abi: Abi = serde_json::from_str(NBGN_ABI)?;
     Ok(Contract::new(address, abi, provider))
-}
\ No newline at end of file
+}
+// Minting pulls EURe from the caller, so it needs an EURe approval first.
+// nonces is the EIP-2612 permit nonce of an owner
+abigen!(
+    NBGNCalls,
+    r#"[
+        function mint(uint256 eureAmount) external
+        function redeem(uint256 nbgnAmount) external
+        function nonces(address owner) external view returns (uint256)
+    ]"#
+);
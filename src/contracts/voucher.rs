@@ -0,0 +1,16 @@
+use ethers::prelude::abigen;
+
+// Creator-facing calls of the voucher contract; creating pulls the NBGN
+// amount from the creator, so it needs an NBGN approval first.
+// createVoucherWithPermit is the relayed form: the contract applies the
+// creator's EIP-2612 permit for amount + relayerFee, checks the creation
+// intent signature, pays relayerFee to the sender and records the creator
+abigen!(
+    VoucherContract,
+    r#"[
+        function createVoucher(uint256 amount) external returns (bytes32)
+        function cancelVoucher(bytes32 voucherId) external
+        function claimVoucher(bytes32 voucherId, address recipient, uint256 deadline, bytes signature) external
+        function createVoucherWithPermit(address creator, uint256 amount, uint256 relayerFee, uint256 deadline, uint8 v, bytes32 r, bytes32 s, bytes intentSignature) external returns (bytes32)
+    ]"#
+);
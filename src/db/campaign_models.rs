@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::voucher_models::{ClaimWindow, CreatorAuth};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Campaign {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub creator_address: String,
+    pub created_at: DateTime<Utc>,
+    pub one_claim_per_recipient: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCampaignRequest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub one_claim_per_recipient: bool,
+    // Creator signature over the "create_campaign" action for the name
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CampaignPolicyRequest {
+    pub one_claim_per_recipient: bool,
+    // Creator signature over the "update_campaign" action for the campaign id
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+// How the links of a bulk request are protected
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordPolicy {
+    #[default]
+    None,
+    /// Every link gets the `password` of the request
+    Shared,
+    /// Every link gets its own random password, returned once in the response
+    Generated,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkLinkRequest {
+    pub voucher_ids: Vec<String>,
+    #[serde(default)]
+    pub password_policy: PasswordPolicy,
+    pub password: Option<String>,
+    #[serde(flatten)]
+    pub window: ClaimWindow,
+    // Creator signature over the "bulk_link" action for the campaign id,
+    // with the vouchers, password policy and window as params
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+// Outcome for one voucher of a bulk request; failures do not stop the others
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BulkLinkResult {
+    pub voucher_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Per-voucher counts and wei totals; cancelled excludes claimed vouchers and
+// outstanding is everything neither claimed nor cancelled
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CampaignStats {
+    pub vouchers: i64,
+    pub claimed: i64,
+    pub cancelled: i64,
+    pub expired: i64,
+    pub outstanding: i64,
+    pub total_value: String,
+    pub claimed_value: String,
+    pub cancelled_value: String,
+    pub expired_value: String,
+    pub outstanding_value: String,
+}
+
+// One active code of a campaign voucher, as exported
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CampaignExportRow {
+    pub voucher_id: String,
+    pub code: String,
+    #[sqlx(default)]
+    pub link: String,
+    pub label: Option<String>,
+    pub has_password: bool,
+    pub amount: Option<String>,
+    pub claimed: bool,
+    pub claimed_by: Option<String>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub cancelled: bool,
+    pub claimable_from: Option<DateTime<Utc>>,
+    pub claimable_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    /// Printable HTML page with a QR code per outstanding code
+    Sheet,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
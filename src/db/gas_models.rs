@@ -0,0 +1,50 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct GasReportQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    // claim or creation; both when unset
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+// Relayer spend of one report group; wei amounts are decimal strings. The
+// L1 part is included in cost_wei
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GasCostTotals {
+    pub transactions: i64,
+    pub failed: i64,
+    pub gas_used: String,
+    pub l1_gas_used: String,
+    pub cost_wei: String,
+    pub l1_cost_wei: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DailyGasCost {
+    pub day: NaiveDate,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub totals: GasCostTotals,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CampaignGasCost {
+    // None groups the transactions outside any campaign
+    pub campaign_id: Option<i64>,
+    pub campaign_name: Option<String>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub totals: GasCostTotals,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CreatorGasCost {
+    pub creator_address: Option<String>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub totals: GasCostTotals,
+}
@@ -1,5 +1,13 @@
+pub mod campaign_models;
+pub mod gas_models;
 pub mod models;
+pub mod notification_models;
+pub mod pool_models;
+pub mod relay_models;
+pub mod sponsorship_models;
+pub mod tx_models;
 pub mod voucher_models;
+pub mod webhook_models;
 
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::time::Duration;
@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::db::voucher_models::CreatorAuth;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NotificationChannel {
+    pub id: i64,
+    pub creator_address: String,
+    pub kind: String,
+    pub target: String,
+    #[serde(skip_serializing)]
+    pub secret: Option<String>,
+    pub muted_events: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Registers or replaces the creator's channel of this kind
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetChannelRequest {
+    pub kind: String,
+    pub target: String,
+    #[serde(default)]
+    pub muted_events: Vec<String>,
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
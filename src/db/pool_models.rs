@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::voucher_models::CreatorAuth;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ClaimPool {
+    pub id: i64,
+    pub code: String,
+    pub name: String,
+    pub creator_address: String,
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePoolRequest {
+    pub name: String,
+    pub voucher_ids: Vec<String>,
+    pub password: Option<String>,
+    // Creator signature over the "create_pool" action for the name, with the
+    // vouchers and password as params
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolClaimRequest {
+    pub recipient_address: String,
+    pub password: Option<String>,
+}
+
+// Vouchers of a pool by state; reserved ones have a live claim authorization
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PoolStock {
+    pub total: i64,
+    pub available: i64,
+    pub reserved: i64,
+    pub claimed: i64,
+    pub cancelled: i64,
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RelayedCreation {
+    pub id: i64,
+    pub creator_address: String,
+    pub amount: String,
+    pub relayer_fee: String,
+    pub deadline: i64,
+    pub intent_hash: String,
+    pub policy_decision: String,
+    pub policy_reason: Option<String>,
+    pub tx_hash: Option<String>,
+    pub tx_status: String,
+    pub error: Option<String>,
+    pub voucher_id: Option<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub settled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// The creator signs an EIP-2612 permit of NBGN for amount + relayer_fee to
+// the voucher contract, and the creation intent over the same values. Both
+// expire at `deadline` (unix seconds)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelayedCreationRequest {
+    pub creator: String,
+    pub amount: String,
+    pub relayer_fee: String,
+    pub deadline: i64,
+    pub permit_signature: String,
+    pub intent_signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreationQuoteQuery {
+    pub creator: String,
+    pub amount: String,
+}
+
+// What the creator has to sign for a relayed creation
+#[derive(Debug, Serialize)]
+pub struct CreationQuote {
+    pub sponsored: bool,
+    pub relayer_fee: String,
+    pub reason: String,
+    pub chain_id: u64,
+    pub voucher_contract: String,
+    pub permit_token: String,
+    pub permit_value: String,
+    pub permit_nonce: String,
+    pub max_deadline: i64,
+}
@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// One recorded sponsorship decision; wei amounts are decimal strings
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SponsorshipDecision {
+    pub id: i64,
+    pub kind: String,
+    pub subject: String,
+    pub creator_address: String,
+    pub recipient_address: Option<String>,
+    pub campaign_id: Option<i64>,
+    pub amount: String,
+    pub estimated_cost_wei: Option<String>,
+    pub decision: String,
+    pub rule: Option<String>,
+    pub reason: String,
+    pub client_ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DecisionQuery {
+    pub kind: Option<String>,
+    pub decision: Option<String>,
+    pub creator: Option<String>,
+    pub campaign_id: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CampaignBudgetRequest {
+    // Gas budget in wei; null removes the budget
+    pub budget_wei: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CampaignSponsorship {
+    pub campaign_id: i64,
+    pub budget_wei: Option<String>,
+    pub spent_wei: String,
+    pub sponsored: i64,
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+// Amounts are decimal strings in the token's smallest unit
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AmountTxRequest {
+    pub from: String,
+    pub amount: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelVoucherTxRequest {
+    pub from: String,
+    pub voucher_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalToken {
+    Nbgn,
+    Eure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalSpender {
+    /// Spends NBGN for createVoucher
+    VoucherContract,
+    /// Spends EURe for mint
+    NbgnContract,
+}
+
+// `amount` may also be "max" for an unlimited allowance, or "0" to revoke
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApproveTxRequest {
+    pub from: String,
+    pub token: ApprovalToken,
+    pub spender: ApprovalSpender,
+    pub amount: String,
+}
@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::types::Json;
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct VoucherCode {
@@ -19,6 +21,19 @@ pub struct VoucherCode {
     pub cancel_tx_hash: Option<String>,
     pub claim_tx_status: Option<String>,
     pub claim_tx_submitted_at: Option<DateTime<Utc>>,
+    pub lockout_reset_at: Option<DateTime<Utc>>,
+    pub label: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub replaced_by: Option<String>,
+    pub claimable_from: Option<DateTime<Utc>>,
+    pub claimable_until: Option<DateTime<Utc>>,
+    pub expiry_notified_at: Option<DateTime<Utc>>,
+    pub campaign_id: Option<i64>,
+    #[serde(skip_serializing)]
+    pub note_ciphertext: Option<Vec<u8>>,
+    pub note_content_type: Option<String>,
+    // A lifecycle::VoucherStatus
+    pub status: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -29,14 +44,56 @@ pub struct ClaimAttempt {
     pub attempted_at: DateTime<Utc>,
     pub success: Option<bool>,
     pub recipient_address: Option<String>,
+    pub failure_reason: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateLinkRequest {
     pub voucher_id: String,
     pub password: Option<String>,
-    pub creator_address: Option<String>,
+    pub creator_address: String,
     pub amount: Option<String>,
+    #[serde(flatten)]
+    pub window: ClaimWindow,
+    pub note: Option<GiftNote>,
+    // Creator signature over the "create_link" action for voucher_id
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+// Personal message shown to whoever verifies the code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftNote {
+    pub text: String,
+    #[serde(default = "default_note_content_type")]
+    pub content_type: String,
+}
+
+fn default_note_content_type() -> String {
+    "text/plain".to_string()
+}
+
+// Replace or, with no note, remove the note of a code. Protected codes need
+// their password, which is part of the note key
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetNoteRequest {
+    pub note: Option<GiftNote>,
+    pub password: Option<String>,
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+// When a voucher may be claimed; either end may be open
+#[derive(Debug, Default, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ClaimWindow {
+    pub claimable_from: Option<DateTime<Utc>>,
+    pub claimable_until: Option<DateTime<Utc>>,
+}
+
+impl ClaimWindow {
+    pub fn is_set(&self) -> bool {
+        self.claimable_from.is_some() || self.claimable_until.is_some()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,6 +107,9 @@ pub struct ClaimRequest {
     pub code: String,
     pub password: Option<String>,
     pub recipient_address: String,
+    // Proof of the recipient's leaf when the voucher has a Merkle allowlist
+    #[serde(default)]
+    pub merkle_proof: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,6 +129,104 @@ pub struct ClaimAuthorization {
     pub contract_address: String,
 }
 
+// Proof that a request comes from the voucher creator: a personal_sign over
+// `services::auth::creator_action_message` for the given timestamp
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatorAuth {
+    pub address: String,
+    pub timestamp: i64,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LockoutStatus {
+    pub code: String,
+    pub failed_attempts: i64,
+    pub last_failed_at: Option<DateTime<Utc>>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub locked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct IssuedAuthorization {
+    pub id: i64,
+    pub voucher_id: String,
+    pub voucher_code: String,
+    pub recipient_address: String,
+    pub deadline: DateTime<Utc>,
+    pub signature_hash: String,
+    pub status: String,
+    pub ip_address: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub superseded_at: Option<DateTime<Utc>>,
+    pub superseded_by: Option<i64>,
+    pub signer_address: Option<String>,
+}
+
+// One code of a voucher with its usage, for the creator
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct VoucherCodeSummary {
+    pub code: String,
+    pub label: Option<String>,
+    pub has_password: bool,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub replaced_by: Option<String>,
+    pub attempts: i64,
+    pub failed_password_attempts: i64,
+    pub authorizations_issued: i64,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+// Protected codes with a gift note need their password, so the note can be
+// sealed again for the new code
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotateCodeRequest {
+    pub password: Option<String>,
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddCodeRequest {
+    pub label: Option<String>,
+    pub password: Option<String>,
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+// Who may receive a voucher; exactly one of the two is set
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RecipientRestriction {
+    pub voucher_id: String,
+    pub allowed_addresses: Option<Vec<String>>,
+    pub merkle_root: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Set an address list or a Merkle root; neither lifts the restriction
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetRecipientsRequest {
+    pub addresses: Option<Vec<String>>,
+    pub merkle_root: Option<String>,
+    #[serde(flatten)]
+    pub creator: CreatorAuth,
+}
+
+// One move of a voucher between lifecycle statuses
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct VoucherEvent {
+    pub id: i64,
+    pub voucher_id: String,
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub actor_type: String,
+    pub actor: String,
+    pub tx_hash: Option<String>,
+    pub detail: Option<Json<Value>>,
+    pub occurred_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ListQuery {
     #[serde(rename = "type")]
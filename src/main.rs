@@ -14,12 +14,30 @@ mod middleware;
 mod services;
 
 use config::Settings;
-use middleware::rate_limiter::RedisRateLimiter;
+use middleware::{rate_limiter::RedisRateLimiter, request_id::RequestIdMiddleware};
 use services::{
+    audit::AuditLog,
     cache::CacheService, 
+    campaign::CampaignService,
+    claim_batcher::ClaimBatcher,
+    claim_monitor::ClaimTxMonitor,
+    codes::CodeGenerator,
     indexer::Indexer,
     event_indexer::EventIndexer,
-    voucher::VoucherService,
+    expiry::ExpiryScanner,
+    gas_accounting::GasAccounting,
+    key_rotation::KeyRotationService,
+    mailer::Mailer,
+    notification_channels::NotificationChannelService,
+    notifier::{NotificationRelay, Notifier},
+    pool::PoolService,
+    relayed_creation::{self, RelayedCreationService},
+    signer::SignerRegistry,
+    sponsorship::SponsorshipPolicy,
+    timeline::TimelineService,
+    tx_builder::TxBuilder,
+    voucher::{VoucherService, CHAIN_ID},
+    webhooks::WebhookService,
 };
 
 #[actix_web::main]
@@ -73,13 +91,107 @@ async fn main() -> std::io::Result<()> {
         .parse::<Address>()
         .expect("Invalid voucher contract address");
 
+    // Transactions frontends sign themselves
+    let mut tx_builder = TxBuilder::new(provider.clone(), voucher_contract_address, contract_address);
+    if let Some(eure_token) = &settings.ethereum.eure_token_address {
+        tx_builder = tx_builder.with_eure_token(eure_token.parse().expect("Invalid EURe token address"));
+    }
+
+    // Unlock the backend signers (raw key, keystore or remote)
+    let signers = SignerRegistry::from_config(&settings.backend, CHAIN_ID)
+        .expect("Failed to initialize backend signers");
+
+    let key_rotation = KeyRotationService::new(
+        pool.clone(),
+        signers.clone(),
+        provider.clone(),
+        voucher_contract_address,
+    );
+    key_rotation.load_persisted_roles()
+        .await
+        .expect("Failed to load backend signer roles");
+
+    let codes = CodeGenerator::from_config(&settings.codes)
+        .expect("Invalid voucher code configuration");
+    if codes.is_deterministic() {
+        info!("Voucher codes are HMAC-derived and recoverable from chain events");
+    }
+
+    let webhook_service = WebhookService::new(pool.clone(), settings.webhooks.clone());
+
+    // Relay only through contracts that have the relayed-creation functions
+    let mut relay_config = settings.relay.clone();
+    if relay_config.enabled {
+        match relayed_creation::contracts_support_relay(&provider, voucher_contract_address, contract_address).await {
+            Ok(true) => info!("Relayed voucher creation is enabled"),
+            Ok(false) => {
+                error!("Contracts lack createVoucherWithPermit or EIP-2612 nonces; relayed creation is disabled");
+                relay_config.enabled = false;
+            }
+            Err(e) => {
+                error!("Failed to read contract bytecode, relayed creation is disabled: {}", e);
+                relay_config.enabled = false;
+            }
+        }
+    }
+
+    // Decides which relayed claims and creations the backend pays gas for
+    let sponsorship = SponsorshipPolicy::from_config(pool.clone(), &settings.sponsorship, &relay_config)
+        .expect("Invalid relay or sponsorship configuration")
+        .with_provider(provider.clone());
+
+    // Gasless voucher creation through the relayer
+    let relayed_creations = RelayedCreationService::new(
+        pool.clone(),
+        signers.clone(),
+        provider.clone(),
+        voucher_contract_address,
+        contract_address,
+        Arc::new(sponsorship.clone()),
+    )
+    .with_max_deadline(relay_config.max_deadline_secs);
+
+    // Optionally send gasless claims in Multicall3 batches
+    let claim_batcher = if settings.claim_batching.enabled {
+        let (claim_batcher, batch_worker) = ClaimBatcher::new(
+            pool.clone(),
+            signers.clone(),
+            provider.clone(),
+            voucher_contract_address,
+            &settings.claim_batching,
+        )
+        .expect("Invalid claim batching configuration");
+        tokio::spawn(batch_worker.run());
+        info!("Batching gasless claims every {}ms", settings.claim_batching.window_ms);
+        Some(claim_batcher)
+    } else {
+        None
+    };
+
     // Initialize voucher service with provider
-    let voucher_service = VoucherService::new(pool.clone(), &settings.backend.private_key)
+    let mut voucher_service = VoucherService::new(pool.clone(), signers)
         .expect("Failed to initialize voucher service")
-        .with_provider(provider.clone());
+        .with_provider(provider.clone())
+        .with_lockout_policy(settings.lockout.clone())
+        .with_authorization_policy(settings.authorizations.clone())
+        .with_code_generator(codes.clone())
+        .with_public_base_url(settings.links.public_base_url.clone())
+        .with_webhooks(webhook_service.clone())
+        .with_relay_policy(Arc::new(sponsorship.clone()));
+    if let Some(claim_batcher) = claim_batcher {
+        voucher_service = voucher_service.with_claim_batcher(claim_batcher);
+    }
+
+    let campaign_service = CampaignService::new(pool.clone(), voucher_service.clone());
+    let pool_service = PoolService::new(pool.clone(), voucher_service.clone())
+        .with_code_generator(codes.clone());
+
+    let audit_log = AuditLog::new(pool.clone());
 
     // Start the indexer in the background
-    let indexer = Indexer::new(contract.clone(), pool.clone(), provider.clone());
+    let indexer = Indexer::new(contract.clone(), pool.clone(), provider.clone())
+        .with_start_block(settings.indexer.start_block)
+        .with_webhooks(webhook_service.clone());
     let _indexer_handle = {
         let indexer = indexer.clone();
         let poll_interval = settings.indexer.poll_interval_secs;
@@ -91,7 +203,9 @@ async fn main() -> std::io::Result<()> {
     };
 
     // Start the voucher event indexer
-    let event_indexer = EventIndexer::new(pool.clone(), provider.clone(), voucher_contract_address);
+    let event_indexer = EventIndexer::new(pool.clone(), provider.clone(), voucher_contract_address)
+        .with_code_generator(codes)
+        .with_webhooks(webhook_service.clone());
     let _event_indexer_handle = {
         let event_indexer = event_indexer.clone();
         let poll_interval = settings.indexer.poll_interval_secs;
@@ -102,6 +216,68 @@ async fn main() -> std::io::Result<()> {
         })
     };
 
+    // Creator notifications go to the operator webhook and to the channels
+    // creators register for themselves
+    let notification_channels = NotificationChannelService::new(pool.clone(), settings.notifications.smtp.is_some())
+        .with_private_targets(settings.notifications.allow_private_channel_targets);
+    let mut notifier = Notifier::from_config(&settings.notifications)
+        .with_channels(notification_channels.clone());
+    if let Some(smtp) = &settings.notifications.smtp {
+        notifier = notifier.with_mailer(Mailer::from_config(smtp).expect("Invalid SMTP configuration"));
+    }
+
+    // Start the scanner that tells creators about vouchers expiring unclaimed
+    let expiry_scanner = ExpiryScanner::new(pool.clone(), notifier.clone());
+    let _expiry_scanner_handle = {
+        let expiry_scanner = expiry_scanner.clone();
+        let scan_interval = settings.expiry.scan_interval_secs;
+        tokio::spawn(async move {
+            if let Err(e) = expiry_scanner.run_scan_loop(scan_interval).await {
+                error!("Expiry scanner error: {}", e);
+            }
+        })
+    };
+
+    // Start the relay that tells creators about claims and cancellations
+    let notification_relay = NotificationRelay::new(pool.clone(), notifier.clone());
+    let _notification_relay_handle = {
+        let notification_relay = notification_relay.clone();
+        let interval = settings.notifications.relay_interval_secs;
+        tokio::spawn(async move {
+            if let Err(e) = notification_relay.run_relay_loop(interval).await {
+                error!("Notification relay error: {}", e);
+            }
+        })
+    };
+
+    // Start the monitor that settles gasless claim transactions and records
+    // what they cost
+    let gas_accounting = GasAccounting::new(pool.clone());
+    let claim_monitor = ClaimTxMonitor::new(pool.clone(), provider.clone(), settings.webhooks.claim_drop_after_secs)
+        .with_webhooks(webhook_service.clone())
+        .with_gas_accounting(gas_accounting.clone());
+    let _claim_monitor_handle = {
+        let claim_monitor = claim_monitor.clone();
+        let interval = settings.webhooks.claim_monitor_interval_secs;
+        tokio::spawn(async move {
+            if let Err(e) = claim_monitor.run_monitor_loop(interval).await {
+                error!("Claim transaction monitor error: {}", e);
+            }
+        })
+    };
+
+    let timeline = TimelineService::new(pool.clone());
+
+    // Start the webhook delivery worker
+    let _webhook_delivery_handle = {
+        let webhook_service = webhook_service.clone();
+        tokio::spawn(async move {
+            if let Err(e) = webhook_service.run_delivery_loop().await {
+                error!("Webhook delivery error: {}", e);
+            }
+        })
+    };
+
     // Start HTTP server
     let server_bind = format!("{}:{}", settings.server.host, settings.server.port);
     info!("Starting HTTP server on {}", server_bind);
@@ -113,6 +289,18 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(contract.clone()))
             .app_data(web::Data::new(rate_limiter.clone()))
             .app_data(web::Data::new(voucher_service.clone()))
+            .app_data(web::Data::new(campaign_service.clone()))
+            .app_data(web::Data::new(pool_service.clone()))
+            .app_data(web::Data::new(webhook_service.clone()))
+            .app_data(web::Data::new(notification_channels.clone()))
+            .app_data(web::Data::new(tx_builder.clone()))
+            .app_data(web::Data::new(relayed_creations.clone()))
+            .app_data(web::Data::new(sponsorship.clone()))
+            .app_data(web::Data::new(gas_accounting.clone()))
+            .app_data(web::Data::new(timeline.clone()))
+            .app_data(web::Data::new(key_rotation.clone()))
+            .app_data(web::Data::new(settings.admin.clone()))
+            .app_data(web::Data::new(audit_log.clone()))
             .app_data(web::Data::new(provider.clone()))
             .wrap(
                 Cors::default()
@@ -121,11 +309,13 @@ async fn main() -> std::io::Result<()> {
                     .allowed_origin("http://localhost:5173")
                     .allowed_origin("http://localhost:5174")
                     .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"])
-                    .allowed_headers(vec!["Content-Type", "Authorization", "X-API-Key"])
+                    .allowed_headers(vec!["Content-Type", "Authorization", "X-API-Key", "X-Request-Id"])
+                    .expose_headers(vec!["X-Request-Id"])
                     .supports_credentials()
                     .max_age(3600)
             )
             .wrap(actix_middleware::Logger::default())
+            .wrap(RequestIdMiddleware)
             .configure(api::routes::configure_routes)
     })
     .bind(&server_bind)?
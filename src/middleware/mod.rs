@@ -1 +1,2 @@
-pub mod rate_limiter;
\ No newline at end of file
+pub mod rate_limiter;
+pub mod request_id;
\ No newline at end of file
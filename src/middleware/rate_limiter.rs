@@ -46,7 +46,7 @@ impl RedisRateLimiter {
         
         // Set expiry on first request
         if count == 1 {
-            conn.expire(&window_key, window_seconds as i64).await?;
+            conn.expire::<_, ()>(&window_key, window_seconds as i64).await?;
         }
         
         // Get TTL for the key
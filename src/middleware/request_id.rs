@@ -0,0 +1,91 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use futures_util::future::LocalBoxFuture;
+use rand::{thread_rng, Rng};
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+};
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Identifier tying log lines and audit entries to one HTTP request
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    fn generate() -> Self {
+        let bytes: [u8; 16] = thread_rng().gen();
+        Self(hex::encode(bytes))
+    }
+
+    // Accept a caller-supplied id only if it is short and printable
+    fn from_header(value: &str) -> Option<Self> {
+        let valid = !value.is_empty()
+            && value.len() <= 64
+            && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| Self(value.to_string()))
+    }
+}
+
+pub struct RequestIdMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddlewareService {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestIdMiddlewareService<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let request_id = req.headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|h| h.to_str().ok())
+                .and_then(RequestId::from_header)
+                .unwrap_or_else(RequestId::generate);
+
+            req.extensions_mut().insert(request_id.clone());
+
+            let mut res = service.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+                res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+
+            Ok(res)
+        })
+    }
+}
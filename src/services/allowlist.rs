@@ -0,0 +1,25 @@
+use ethers::abi::{encode, Token};
+use ethers::types::{Address, H256};
+use ethers::utils::keccak256;
+
+// Merkle allowlists follow OpenZeppelin's StandardMerkleTree for a single
+// `address` column, so creators can build them with @openzeppelin/merkle-tree:
+// leaves are keccak256(keccak256(abi.encode(address))) and pairs are hashed
+// in sorted order
+pub fn recipient_leaf(recipient: Address) -> H256 {
+    let inner = keccak256(encode(&[Token::Address(recipient)]));
+    H256::from(keccak256(inner))
+}
+
+pub fn hash_pair(a: H256, b: H256) -> H256 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut packed = [0u8; 64];
+    packed[..32].copy_from_slice(first.as_bytes());
+    packed[32..].copy_from_slice(second.as_bytes());
+    H256::from(keccak256(packed))
+}
+
+pub fn verify_proof(root: H256, recipient: Address, proof: &[H256]) -> bool {
+    let computed = proof.iter().fold(recipient_leaf(recipient), |node, sibling| hash_pair(node, *sibling));
+    computed == root
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use tracing::error;
+
+// Who performed an audited operation, and in which request
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    pub actor_type: &'static str,
+    pub actor: String,
+    pub actor_ip: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl AuditContext {
+    // Unauthenticated caller, identified by IP
+    pub fn anonymous(ip: &str, request_id: Option<String>) -> Self {
+        Self {
+            actor_type: "ip",
+            actor: ip.to_string(),
+            actor_ip: Some(ip.to_string()),
+            request_id,
+        }
+    }
+
+    // Caller that proved control of an address
+    pub fn address(address: &str, ip: &str, request_id: Option<String>) -> Self {
+        Self {
+            actor_type: "address",
+            actor: address.to_lowercase(),
+            actor_ip: Some(ip.to_string()),
+            request_id,
+        }
+    }
+
+    pub fn admin(ip: &str, request_id: Option<String>) -> Self {
+        Self {
+            actor_type: "admin",
+            actor: "admin".to_string(),
+            actor_ip: Some(ip.to_string()),
+            request_id,
+        }
+    }
+
+    // Background component acting on chain data
+    pub fn system(component: &str) -> Self {
+        Self {
+            actor_type: "system",
+            actor: component.to_string(),
+            actor_ip: None,
+            request_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub actor_type: String,
+    pub actor: String,
+    pub actor_ip: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub before_value: Option<Json<Value>>,
+    pub after_value: Option<Json<Value>>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub request_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Clone)]
+pub struct AuditLog {
+    pool: PgPool,
+}
+
+impl AuditLog {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        ctx: &AuditContext,
+        action: &str,
+        target_type: &str,
+        target_id: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log
+            (actor_type, actor, actor_ip, action, target_type, target_id, before_value, after_value, request_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#
+        )
+        .bind(ctx.actor_type)
+        .bind(&ctx.actor)
+        .bind(&ctx.actor_ip)
+        .bind(action)
+        .bind(target_type)
+        .bind(target_id)
+        .bind(before.map(Json))
+        .bind(after.map(Json))
+        .bind(&ctx.request_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // Record after the change has already been applied: a failed write is
+    // logged rather than failing an operation that cannot be undone
+    pub async fn record_or_log(
+        &self,
+        ctx: &AuditContext,
+        action: &str,
+        target_type: &str,
+        target_id: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) {
+        if let Err(e) = self.record(ctx, action, target_type, target_id, before, after).await {
+            error!("Failed to write audit log entry {} for {} {}: {}", action, target_type, target_id, e);
+        }
+    }
+
+    pub async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, sqlx::Error> {
+        sqlx::query_as::<_, AuditRecord>(
+            r#"
+            SELECT * FROM audit_log
+            WHERE ($1::text IS NULL OR LOWER(actor) = LOWER($1))
+            AND ($2::text IS NULL OR action = $2)
+            AND ($3::text IS NULL OR target_type = $3)
+            AND ($4::text IS NULL OR target_id = $4)
+            AND ($5::text IS NULL OR request_id = $5)
+            AND ($6::timestamptz IS NULL OR created_at >= $6)
+            AND ($7::timestamptz IS NULL OR created_at < $7)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $8 OFFSET $9
+            "#
+        )
+        .bind(&query.actor)
+        .bind(&query.action)
+        .bind(&query.target_type)
+        .bind(&query.target_id)
+        .bind(&query.request_id)
+        .bind(query.since)
+        .bind(query.until)
+        .bind(query.limit.unwrap_or(50).clamp(1, 500))
+        .bind(query.offset.unwrap_or(0).max(0))
+        .fetch_all(&self.pool)
+        .await
+    }
+}
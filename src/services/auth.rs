@@ -0,0 +1,91 @@
+use ethers::types::{Address, Signature};
+use ethers::utils::{hash_message, keccak256};
+use serde_json::Value;
+use std::str::FromStr;
+
+// Signed creator actions older than this are rejected to limit replay
+const SIGNATURE_MAX_AGE_SECS: i64 = 300;
+
+// Message a creator signs (personal_sign) to authorize an action on a voucher
+pub fn creator_action_message(action: &str, target: &str, timestamp: i64) -> String {
+    format!(
+        "NBGN voucher action: {}\nTarget: {}\nTimestamp: {}",
+        action, target, timestamp
+    )
+}
+
+// The action message with the request's parameters bound in, so that a
+// signature for one set of them cannot be replayed with another. Params are
+// hashed as compact JSON with object keys sorted
+pub fn creator_action_message_with_params(action: &str, target: &str, params: &Value, timestamp: i64) -> String {
+    format!(
+        "{}\nParams: 0x{}",
+        creator_action_message(action, target, timestamp),
+        hex::encode(keccak256(params.to_string()))
+    )
+}
+
+// Recover the signer of a creator action message
+pub fn recover_action_signer(
+    action: &str,
+    target: &str,
+    timestamp: i64,
+    signature: &str,
+) -> Result<Address, Box<dyn std::error::Error>> {
+    recover_signer(creator_action_message(action, target, timestamp), timestamp, signature)
+}
+
+fn recover_signer(message: String, timestamp: i64, signature: &str) -> Result<Address, Box<dyn std::error::Error>> {
+    let age = chrono::Utc::now().timestamp() - timestamp;
+    if !(-SIGNATURE_MAX_AGE_SECS..=SIGNATURE_MAX_AGE_SECS).contains(&age) {
+        return Err("Signature timestamp expired".into());
+    }
+
+    let message = hash_message(message);
+    let signature = Signature::from_str(signature)
+        .map_err(|_| "Invalid signature format")?;
+    let recovered = signature.recover(message)
+        .map_err(|_| "Failed to recover address from signature")?;
+
+    Ok(recovered)
+}
+
+fn check_signer(address: &str, recovered: Address) -> Result<(), Box<dyn std::error::Error>> {
+    if format!("0x{:x}", recovered) != address.to_lowercase() {
+        return Err("Invalid signature".into());
+    }
+    Ok(())
+}
+
+// Check that `address` signed the action message
+pub fn verify_action_signature(
+    address: &str,
+    action: &str,
+    target: &str,
+    timestamp: i64,
+    signature: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_signer(address, recover_action_signer(action, target, timestamp, signature)?)
+}
+
+// Check that `address` signed the action message with these params
+pub fn verify_action_signature_with_params(
+    address: &str,
+    action: &str,
+    target: &str,
+    params: &Value,
+    timestamp: i64,
+    signature: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let message = creator_action_message_with_params(action, target, params, timestamp);
+    check_signer(address, recover_signer(message, timestamp, signature)?)
+}
+
+// Compare an admin API key without leaking the matching prefix length
+pub fn admin_key_matches(provided: &str, configured: &str) -> bool {
+    let (provided, configured) = (provided.as_bytes(), configured.as_bytes());
+    if provided.len() != configured.len() {
+        return false;
+    }
+    provided.iter().zip(configured).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
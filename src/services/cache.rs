@@ -32,13 +32,13 @@ impl CacheService {
         let json = serde_json::to_string(value)
             .map_err(|e| RedisError::from((redis::ErrorKind::TypeError, "JSON serialization failed", e.to_string())))?;
         
-        conn.set_ex(key, json, ttl.as_secs()).await?;
+        conn.set_ex::<_, _, ()>(key, json, ttl.as_secs()).await?;
         Ok(())
     }
 
     pub async fn delete(&self, key: &str) -> Result<(), RedisError> {
         let mut conn = self.client.get_async_connection().await?;
-        conn.del(key).await?;
+        conn.del::<_, ()>(key).await?;
         Ok(())
     }
 
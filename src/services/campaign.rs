@@ -0,0 +1,440 @@
+use crate::db::campaign_models::{
+    BulkLinkResult, Campaign, CampaignExportRow, CampaignStats, PasswordPolicy,
+};
+use crate::db::voucher_models::{ClaimWindow, CreatorAuth};
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::auth;
+use crate::services::qr::{self, SheetItem};
+use crate::services::voucher::VoucherService;
+use ethers::types::U256;
+use ethers::utils::format_units;
+use rand::{thread_rng, Rng};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tracing::info;
+
+pub const MAX_BULK_LINKS: usize = 200;
+const MAX_NAME_LEN: usize = 100;
+
+const GENERATED_PASSWORD_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZabcdefghjkmnpqrstuvwxyz23456789";
+const GENERATED_PASSWORD_LEN: usize = 12;
+
+// Groups vouchers created together so they can be linked, exported and
+// tracked as one
+#[derive(Clone)]
+pub struct CampaignService {
+    pool: PgPool,
+    vouchers: VoucherService,
+    audit: AuditLog,
+}
+
+impl CampaignService {
+    pub fn new(pool: PgPool, vouchers: VoucherService) -> Self {
+        Self {
+            audit: AuditLog::new(pool.clone()),
+            pool,
+            vouchers,
+        }
+    }
+
+    pub async fn create_campaign(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        one_claim_per_recipient: bool,
+        creator: &CreatorAuth,
+        ctx: &AuditContext,
+    ) -> Result<Campaign, Box<dyn std::error::Error>> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(format!("Campaign name must be 1 to {} characters", MAX_NAME_LEN).into());
+        }
+
+        auth::verify_action_signature(
+            &creator.address,
+            "create_campaign",
+            name,
+            creator.timestamp,
+            &creator.signature,
+        )?;
+
+        let campaign: Campaign = sqlx::query_as(
+            r#"
+            INSERT INTO campaigns (name, description, creator_address, one_claim_per_recipient)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, name, description, creator_address, created_at, one_claim_per_recipient
+            "#
+        )
+        .bind(name)
+        .bind(description)
+        .bind(creator.address.to_lowercase())
+        .bind(one_claim_per_recipient)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "campaign.created",
+            "campaign",
+            &campaign.id.to_string(),
+            None,
+            Some(json!({
+                "name": campaign.name,
+                "creator_address": campaign.creator_address,
+                "one_claim_per_recipient": campaign.one_claim_per_recipient,
+            })),
+        ).await;
+
+        Ok(campaign)
+    }
+
+    pub async fn set_policy(
+        &self,
+        campaign: &Campaign,
+        one_claim_per_recipient: bool,
+        ctx: &AuditContext,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE campaigns SET one_claim_per_recipient = $1 WHERE id = $2")
+            .bind(one_claim_per_recipient)
+            .bind(campaign.id)
+            .execute(&self.pool)
+            .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "campaign.policy_updated",
+            "campaign",
+            &campaign.id.to_string(),
+            Some(json!({ "one_claim_per_recipient": campaign.one_claim_per_recipient })),
+            Some(json!({ "one_claim_per_recipient": one_claim_per_recipient })),
+        ).await;
+
+        Ok(())
+    }
+
+    pub async fn get_campaign(&self, id: i64) -> Result<Option<Campaign>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, name, description, creator_address, created_at, one_claim_per_recipient FROM campaigns WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn list_campaigns(&self, creator_address: &str) -> Result<Vec<Campaign>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, name, description, creator_address, created_at, one_claim_per_recipient FROM campaigns
+            WHERE creator_address = $1
+            ORDER BY created_at DESC
+            "#
+        )
+        .bind(creator_address.to_lowercase())
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    // Check that the request was signed by the campaign creator for this
+    // action on this campaign, and over `params` when the action has them
+    pub fn authorize_campaign_creator(
+        campaign: &Campaign,
+        action: &str,
+        params: Option<&Value>,
+        creator: &CreatorAuth,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if campaign.creator_address != creator.address.to_lowercase() {
+            return Err("Only the campaign creator can do this".into());
+        }
+
+        let target = campaign.id.to_string();
+        match params {
+            Some(params) => auth::verify_action_signature_with_params(
+                &creator.address,
+                action,
+                &target,
+                params,
+                creator.timestamp,
+                &creator.signature,
+            ),
+            None => auth::verify_action_signature(&creator.address, action, &target, creator.timestamp, &creator.signature),
+        }
+    }
+
+    // Create links for many vouchers of the campaign creator at once. Each
+    // voucher is checked on-chain like a single link; one failing voucher
+    // does not stop the rest
+    pub async fn bulk_link(
+        &self,
+        campaign: &Campaign,
+        voucher_ids: &[String],
+        policy: PasswordPolicy,
+        password: Option<&str>,
+        window: &ClaimWindow,
+        ctx: &AuditContext,
+    ) -> Result<Vec<BulkLinkResult>, Box<dyn std::error::Error>> {
+        if voucher_ids.is_empty() || voucher_ids.len() > MAX_BULK_LINKS {
+            return Err(format!("Between 1 and {} voucher ids are required", MAX_BULK_LINKS).into());
+        }
+        VoucherService::validate_claim_window(window)?;
+
+        let shared_hash = match (policy, password) {
+            (PasswordPolicy::Shared, Some(password)) if !password.is_empty() => {
+                Some(VoucherService::hash_password(password)?)
+            }
+            (PasswordPolicy::Shared, _) => {
+                return Err("password is required for the shared password policy".into());
+            }
+            (_, Some(_)) => {
+                return Err("password is only accepted with the shared password policy".into());
+            }
+            _ => None,
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut results = Vec::with_capacity(voucher_ids.len());
+        for voucher_id in voucher_ids {
+            let voucher_id = match VoucherService::normalize_voucher_id(voucher_id) {
+                Ok(id) => id,
+                Err(e) => {
+                    results.push(Self::failed(voucher_id, e.to_string()));
+                    continue;
+                }
+            };
+            if !seen.insert(voucher_id.clone()) {
+                continue;
+            }
+
+            let generated = (policy == PasswordPolicy::Generated).then(generate_password);
+            let result = match self.link_one(campaign, &voucher_id, shared_hash.as_deref(), generated.as_deref(), window, ctx).await {
+                Ok(code) => {
+                    BulkLinkResult {
+                        link: Some(self.link_for(&code)),
+                        code: Some(self.vouchers.display_code(&code)),
+                        password: generated,
+                        voucher_id,
+                        error: None,
+                    }
+                }
+                Err(e) => Self::failed(&voucher_id, e),
+            };
+            results.push(result);
+        }
+
+        let linked = results.iter().filter(|r| r.error.is_none()).count();
+        self.audit.record_or_log(
+            ctx,
+            "campaign.links_created",
+            "campaign",
+            &campaign.id.to_string(),
+            None,
+            Some(json!({
+                "requested": voucher_ids.len(),
+                "linked": linked,
+                "failed": results.len() - linked,
+                "password_policy": policy,
+            })),
+        ).await;
+
+        info!("Bulk linked {} of {} vouchers for campaign {}", linked, voucher_ids.len(), campaign.id);
+        Ok(results)
+    }
+
+    async fn link_one(
+        &self,
+        campaign: &Campaign,
+        voucher_id: &str,
+        shared_hash: Option<&str>,
+        generated: Option<&str>,
+        window: &ClaimWindow,
+        ctx: &AuditContext,
+    ) -> Result<String, String> {
+        // A voucher belongs to at most one campaign
+        let current: Option<(i64,)> = sqlx::query_as(
+            "SELECT campaign_id FROM voucher_codes WHERE voucher_id = $1 AND campaign_id IS NOT NULL LIMIT 1"
+        )
+        .bind(voucher_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        if current.is_some_and(|(id,)| id != campaign.id) {
+            return Err("Voucher belongs to another campaign".to_string());
+        }
+
+        let generated_hash = generated
+            .map(VoucherService::hash_password)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let password_hash = shared_hash.or(generated_hash.as_deref());
+
+        // Checked again as the campaign is set, for one that took it meanwhile
+        self.vouchers
+            .link_for_creator(voucher_id, password_hash, None, window, &campaign.creator_address, Some(campaign.id), ctx)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    fn failed(voucher_id: &str, error: String) -> BulkLinkResult {
+        BulkLinkResult {
+            voucher_id: voucher_id.to_string(),
+            code: None,
+            link: None,
+            password: None,
+            error: Some(error),
+        }
+    }
+
+    pub async fn stats(&self, campaign_id: i64) -> Result<CampaignStats, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            WITH vouchers AS (
+                SELECT DISTINCT ON (voucher_id)
+                    voucher_id,
+                    -- A withdrawn voucher is as good as cancelled to the campaign
+                    CASE WHEN status = 'withdrawn' THEN 'cancelled' ELSE status END AS status,
+                    COALESCE(amount, '0')::NUMERIC AS amount
+                FROM voucher_codes
+                WHERE campaign_id = $1
+                ORDER BY voucher_id
+            )
+            SELECT
+                COUNT(*) AS vouchers,
+                COUNT(*) FILTER (WHERE status = 'claimed') AS claimed,
+                COUNT(*) FILTER (WHERE status = 'cancelled') AS cancelled,
+                COUNT(*) FILTER (WHERE status = 'expired') AS expired,
+                COUNT(*) FILTER (WHERE status IN ('active', 'claiming')) AS outstanding,
+                COALESCE(SUM(amount), 0)::TEXT AS total_value,
+                COALESCE(SUM(amount) FILTER (WHERE status = 'claimed'), 0)::TEXT AS claimed_value,
+                COALESCE(SUM(amount) FILTER (WHERE status = 'cancelled'), 0)::TEXT AS cancelled_value,
+                COALESCE(SUM(amount) FILTER (WHERE status = 'expired'), 0)::TEXT AS expired_value,
+                COALESCE(SUM(amount) FILTER (WHERE status IN ('active', 'claiming')), 0)::TEXT AS outstanding_value
+            FROM vouchers
+            "#
+        )
+        .bind(campaign_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    // Active codes of every campaign voucher, with codes in display form
+    pub async fn export_rows(&self, campaign_id: i64) -> Result<Vec<CampaignExportRow>, sqlx::Error> {
+        let mut rows: Vec<CampaignExportRow> = sqlx::query_as(
+            r#"
+            SELECT voucher_id, code, label, password_hash IS NOT NULL AS has_password, amount,
+                   claimed, claimed_by, claimed_at, cancelled, claimable_from, claimable_until, created_at
+            FROM voucher_codes
+            WHERE campaign_id = $1 AND revoked_at IS NULL
+            ORDER BY voucher_id, created_at
+            "#
+        )
+        .bind(campaign_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for row in &mut rows {
+            row.link = self.link_for(&row.code);
+            row.code = self.vouchers.display_code(&row.code);
+        }
+        Ok(rows)
+    }
+
+    // Printable page with a QR code for every code still waiting to be
+    // claimed; QR codes need absolute URLs, so this requires the public base URL
+    pub fn printable_sheet(
+        &self,
+        campaign: &Campaign,
+        rows: &[CampaignExportRow],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let items: Vec<SheetItem> = rows.iter()
+            .filter(|row| !row.claimed && !row.cancelled)
+            .map(|row| {
+                if !row.link.starts_with("http") {
+                    return Err("links.public_base_url must be set to print QR codes");
+                }
+                let amount = row.amount.as_deref()
+                    .and_then(|amount| U256::from_dec_str(amount).ok())
+                    .and_then(|amount| format_units(amount, 18).ok())
+                    .map(|amount| format!("{} NBGN", amount.trim_end_matches('0').trim_end_matches('.')));
+                let caption = [amount, row.label.clone()]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" · ");
+                Ok(SheetItem { url: row.link.clone(), code: row.code.clone(), caption })
+            })
+            .collect::<Result<_, _>>()?;
+
+        qr::printable_sheet(&campaign.name, &items)
+    }
+
+    // Absolute claim URL when configured, else the relative link
+    fn link_for(&self, code: &str) -> String {
+        self.vouchers.claim_url(code).unwrap_or_else(|| self.vouchers.claim_path(code))
+    }
+}
+
+// What a bulk_link signature covers besides the campaign id; window ends
+// are unix seconds
+pub fn bulk_link_params(
+    voucher_ids: &[String],
+    password_policy: PasswordPolicy,
+    password: Option<&str>,
+    window: &ClaimWindow,
+) -> Value {
+    json!({
+        "voucher_ids": voucher_ids,
+        "password_policy": password_policy,
+        "password": password,
+        "claimable_from": window.claimable_from.map(|at| at.timestamp()),
+        "claimable_until": window.claimable_until.map(|at| at.timestamp()),
+    })
+}
+
+fn generate_password() -> String {
+    let mut rng = thread_rng();
+    (0..GENERATED_PASSWORD_LEN)
+        .map(|_| GENERATED_PASSWORD_ALPHABET[rng.gen_range(0..GENERATED_PASSWORD_ALPHABET.len())] as char)
+        .collect()
+}
+
+const CSV_HEADER: &str = "voucher_id,code,link,label,has_password,amount,claimed,claimed_by,claimed_at,cancelled,claimable_from,claimable_until,created_at";
+
+pub fn export_csv(rows: &[CampaignExportRow]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for row in rows {
+        let fields = [
+            row.voucher_id.clone(),
+            row.code.clone(),
+            row.link.clone(),
+            row.label.clone().unwrap_or_default(),
+            row.has_password.to_string(),
+            row.amount.clone().unwrap_or_default(),
+            row.claimed.to_string(),
+            row.claimed_by.clone().unwrap_or_default(),
+            row.claimed_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            row.cancelled.to_string(),
+            row.claimable_from.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            row.claimable_until.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            row.created_at.to_rfc3339(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// Quote when needed, and keep spreadsheet apps from evaluating labels as formulas
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
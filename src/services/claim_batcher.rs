@@ -0,0 +1,193 @@
+use crate::config::ClaimBatchConfig;
+use crate::contracts::multicall::{Call3, Multicall3};
+use crate::contracts::voucher::VoucherContract;
+use crate::services::signer::SignerRegistry;
+use ethers::prelude::*;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::{error, info, warn};
+
+// Gas per claim in a batch, plus the Multicall3 overhead of the transaction
+const BATCHED_CLAIM_GAS: u64 = 200_000;
+const BATCH_OVERHEAD_GAS: u64 = 50_000;
+
+// Claims waiting for the worker; a full queue makes callers wait
+const QUEUE_CAPACITY: usize = 1000;
+
+// One authorized claim, as passed to claimVoucher
+#[derive(Debug, Clone)]
+pub struct ClaimCall {
+    pub voucher_id: H256,
+    pub recipient: Address,
+    pub deadline: U256,
+    pub signature: Bytes,
+}
+
+// The batch transaction a claim was sent in
+#[derive(Debug, Clone)]
+pub struct BatchedClaim {
+    pub tx_hash: String,
+    // None if the batch could not be recorded; its claims are then settled
+    // like single claims
+    pub batch_id: Option<i64>,
+}
+
+struct ClaimJob {
+    call: ClaimCall,
+    respond: oneshot::Sender<Result<BatchedClaim, String>>,
+}
+
+// Where execute_claim hands claims to the batch worker
+#[derive(Clone)]
+pub struct ClaimBatcher {
+    sender: mpsc::Sender<ClaimJob>,
+}
+
+impl ClaimBatcher {
+    pub fn new(
+        pool: PgPool,
+        signers: SignerRegistry,
+        provider: Arc<Provider<Http>>,
+        voucher_contract: Address,
+        config: &ClaimBatchConfig,
+    ) -> Result<(Self, ClaimBatchWorker), String> {
+        let multicall = config.multicall_address.parse::<Address>()
+            .map_err(|_| format!("Invalid claim_batching.multicall_address: {}", config.multicall_address))?;
+        if config.max_size == 0 {
+            return Err("claim_batching.max_size must be at least 1".to_string());
+        }
+
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let worker = ClaimBatchWorker {
+            pool,
+            signers,
+            provider,
+            voucher_contract,
+            multicall,
+            window: Duration::from_millis(config.window_ms),
+            max_size: config.max_size,
+            receiver,
+        };
+        Ok((Self { sender }, worker))
+    }
+
+    // Wait for the claim's batch to be sent; a call that would revert is
+    // left out of the batch and fails here
+    pub async fn submit(&self, call: ClaimCall) -> Result<BatchedClaim, Box<dyn std::error::Error>> {
+        let (respond, response) = oneshot::channel();
+        self.sender.send(ClaimJob { call, respond }).await
+            .map_err(|_| "Claim batching is not running")?;
+        let batched = response.await.map_err(|_| "Claim batch was abandoned")??;
+        Ok(batched)
+    }
+}
+
+// Collects claims for up to the batch window and sends them as one
+// aggregate3 transaction
+pub struct ClaimBatchWorker {
+    pool: PgPool,
+    signers: SignerRegistry,
+    provider: Arc<Provider<Http>>,
+    voucher_contract: Address,
+    multicall: Address,
+    window: Duration,
+    max_size: usize,
+    receiver: mpsc::Receiver<ClaimJob>,
+}
+
+impl ClaimBatchWorker {
+    pub async fn run(mut self) {
+        while let Some(first) = self.receiver.recv().await {
+            let mut jobs = vec![first];
+            let close_at = Instant::now() + self.window;
+            while jobs.len() < self.max_size {
+                match timeout_at(close_at, self.receiver.recv()).await {
+                    Ok(Some(job)) => jobs.push(job),
+                    _ => break,
+                }
+            }
+            self.send_batch(jobs).await;
+        }
+    }
+
+    async fn send_batch(&self, jobs: Vec<ClaimJob>) {
+        let client = Arc::new(SignerMiddleware::new(self.provider.clone(), self.signers.signing()));
+        let voucher = VoucherContract::new(self.voucher_contract, client.clone());
+        let multicall = Multicall3::new(self.multicall, client);
+
+        let calls: Vec<Call3> = jobs.iter()
+            .map(|job| Call3 {
+                target: self.voucher_contract,
+                allow_failure: true,
+                call_data: voucher
+                    .claim_voucher(job.call.voucher_id.into(), job.call.recipient, job.call.deadline, job.call.signature.clone())
+                    .calldata()
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        // Drop the calls that would revert now, so the batch carries only
+        // claims expected to succeed
+        let simulated = match multicall.aggregate_3(calls.clone()).call().await {
+            Ok(results) => results,
+            Err(e) => {
+                error!("Failed to simulate claim batch of {}: {}", jobs.len(), e);
+                return respond_all(jobs, &format!("Failed to simulate claim batch: {}", e));
+            }
+        };
+        let mut batch = Vec::new();
+        let mut batch_calls = Vec::new();
+        for ((job, call), (success, _)) in jobs.into_iter().zip(calls).zip(simulated) {
+            if success {
+                batch.push(job);
+                batch_calls.push(call);
+            } else {
+                let _ = job.respond.send(Err("Claim would revert".to_string()));
+            }
+        }
+        if batch.is_empty() {
+            return;
+        }
+
+        let gas = BATCHED_CLAIM_GAS * batch.len() as u64 + BATCH_OVERHEAD_GAS;
+        let tx_hash = match multicall.aggregate_3(batch_calls).gas(gas).send().await {
+            Ok(pending) => format!("{:?}", pending.tx_hash()),
+            Err(e) => {
+                error!("Failed to send claim batch of {}: {}", batch.len(), e);
+                return respond_all(batch, &format!("Failed to send claim batch: {}", e));
+            }
+        };
+
+        let voucher_ids: Vec<String> = batch.iter()
+            .map(|job| format!("{:?}", job.call.voucher_id))
+            .collect();
+        let inserted: Result<(i64,), sqlx::Error> = sqlx::query_as(
+            "INSERT INTO claim_batches (tx_hash, voucher_ids) VALUES ($1, $2) RETURNING id"
+        )
+        .bind(&tx_hash)
+        .bind(&voucher_ids)
+        .fetch_one(&self.pool)
+        .await;
+        // Sent already, so the claims are tracked by their hash regardless
+        let batch_id = match inserted {
+            Ok((id,)) => Some(id),
+            Err(e) => {
+                warn!("Failed to record claim batch {}: {}", tx_hash, e);
+                None
+            }
+        };
+
+        info!("Sent claim batch {:?} of {} claims in tx {}", batch_id, batch.len(), tx_hash);
+        for job in batch {
+            let _ = job.respond.send(Ok(BatchedClaim { tx_hash: tx_hash.clone(), batch_id }));
+        }
+    }
+}
+
+fn respond_all(jobs: Vec<ClaimJob>, error: &str) {
+    for job in jobs {
+        let _ = job.respond.send(Err(error.to_string()));
+    }
+}
@@ -0,0 +1,464 @@
+use crate::contracts::errors::DecodedRevert;
+use crate::contracts::multicall::{Aggregate3Call, Aggregate3Return};
+use crate::contracts::voucher::ClaimVoucherCall;
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::event_indexer::{VoucherClaimed, VoucherCreated};
+use crate::services::gas_accounting::{CostAttribution, GasAccounting};
+use crate::services::lifecycle::{LifecycleError, StatusChange, VoucherLifecycle, VoucherStatus};
+use crate::services::relay_policy::RelayKind;
+use crate::services::webhooks::{WebhookEvent, WebhookService};
+use chrono::{DateTime, Utc};
+use ethers::abi::AbiDecode;
+use ethers::prelude::*;
+use ethers::providers::RpcError;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+#[derive(sqlx::FromRow)]
+struct PendingClaim {
+    voucher_id: String,
+    code: String,
+    claim_tx_hash: String,
+    claim_tx_submitted_at: Option<DateTime<Utc>>,
+    claim_batch_id: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PendingCreation {
+    id: i64,
+    creator_address: String,
+    tx_hash: String,
+    submitted_at: Option<DateTime<Utc>>,
+}
+
+// How a pending claim transaction ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimOutcome {
+    Confirmed,
+    Reverted,
+    Dropped,
+}
+
+impl ClaimOutcome {
+    // Decide from the receipt and, without one, whether the node still knows
+    // the transaction and how long ago it was submitted
+    pub fn from_chain(
+        receipt_status: Option<u64>,
+        known_to_node: bool,
+        submitted_at: Option<DateTime<Utc>>,
+        drop_after_secs: i64,
+    ) -> Option<Self> {
+        match receipt_status {
+            Some(1) => Some(ClaimOutcome::Confirmed),
+            Some(_) => Some(ClaimOutcome::Reverted),
+            None if known_to_node => None,
+            None => {
+                let stale = submitted_at
+                    .map(|at| (Utc::now() - at).num_seconds() >= drop_after_secs)
+                    .unwrap_or(true);
+                stale.then_some(ClaimOutcome::Dropped)
+            }
+        }
+    }
+
+    // A failed call does not revert a Multicall3 batch, so a confirmed
+    // transaction only confirms the claims whose VoucherClaimed log it carries
+    pub fn for_batched_call(self, receipt: Option<&TransactionReceipt>, voucher_id: H256) -> Self {
+        if self != ClaimOutcome::Confirmed {
+            return self;
+        }
+        let claimed = receipt.and_then(|r| claim_log(r, voucher_id)).is_some();
+        if claimed { ClaimOutcome::Confirmed } else { ClaimOutcome::Reverted }
+    }
+}
+
+// The VoucherClaimed log of `voucher_id` in a mined transaction, if it
+// claimed that voucher
+pub fn claim_log(receipt: &TransactionReceipt, voucher_id: H256) -> Option<&Log> {
+    receipt.logs.iter().find(|log| {
+        log.topics.first() == Some(&VoucherClaimed::signature())
+            && log.topics.get(1) == Some(&voucher_id)
+    })
+}
+
+// Watches gasless claim and relayed creation transactions submitted by the
+// backend until they are mined or dropped, and records the outcome
+#[derive(Clone)]
+pub struct ClaimTxMonitor {
+    pool: PgPool,
+    provider: Arc<Provider<Http>>,
+    drop_after_secs: i64,
+    audit: AuditLog,
+    webhooks: Option<WebhookService>,
+    gas_accounting: Option<GasAccounting>,
+}
+
+impl ClaimTxMonitor {
+    pub fn new(pool: PgPool, provider: Arc<Provider<Http>>, drop_after_secs: i64) -> Self {
+        Self {
+            audit: AuditLog::new(pool.clone()),
+            pool,
+            provider,
+            drop_after_secs,
+            webhooks: None,
+            gas_accounting: None,
+        }
+    }
+
+    pub fn with_webhooks(mut self, webhooks: WebhookService) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    // Record what each mined transaction cost the relayer
+    pub fn with_gas_accounting(mut self, gas_accounting: GasAccounting) -> Self {
+        self.gas_accounting = Some(gas_accounting);
+        self
+    }
+
+    pub async fn run_monitor_loop(&self, interval_secs: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            match self.check_pending().await {
+                Ok(0) => debug!("No claim transactions settled"),
+                Ok(count) => info!("Settled {} claim transactions", count),
+                Err(e) => error!("Error in claim transaction monitor: {}", e),
+            }
+            match self.check_pending_creations().await {
+                Ok(0) => debug!("No relayed creations settled"),
+                Ok(count) => info!("Settled {} relayed creations", count),
+                Err(e) => error!("Error checking relayed creations: {}", e),
+            }
+        }
+    }
+
+    pub async fn check_pending(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let pending: Vec<PendingClaim> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT ON (voucher_id) voucher_id, code, claim_tx_hash, claim_tx_submitted_at, claim_batch_id
+            FROM voucher_codes
+            WHERE claim_tx_status = 'pending' AND claim_tx_hash IS NOT NULL
+            ORDER BY voucher_id, (revoked_at IS NULL) DESC, created_at ASC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // The claims of a batch share one transaction; look it up once
+        let mut outcomes: HashMap<H256, (Option<ClaimOutcome>, Option<TransactionReceipt>)> = HashMap::new();
+        let mut settled = 0;
+        for claim in pending {
+            let Ok(tx_hash) = claim.claim_tx_hash.parse::<H256>() else {
+                warn!("Pending claim of voucher {} has a malformed tx hash", claim.voucher_id);
+                continue;
+            };
+
+            let (outcome, receipt) = match outcomes.get(&tx_hash) {
+                Some(cached) => cached.clone(),
+                None => {
+                    let fetched = self.outcome_of(tx_hash, claim.claim_tx_submitted_at).await?;
+                    outcomes.insert(tx_hash, fetched.clone());
+                    fetched
+                }
+            };
+            let Some(outcome) = outcome else {
+                continue;
+            };
+            // Batched or not, a claim is confirmed by its own log
+            let voucher_id = claim.voucher_id.parse::<H256>().unwrap_or_default();
+            let outcome = outcome.for_batched_call(receipt.as_ref(), voucher_id);
+
+            if self.settle(&claim, outcome, receipt.as_ref()).await? {
+                settled += 1;
+            }
+        }
+
+        Ok(settled)
+    }
+
+    async fn outcome_of(
+        &self,
+        tx_hash: H256,
+        submitted_at: Option<DateTime<Utc>>,
+    ) -> Result<(Option<ClaimOutcome>, Option<TransactionReceipt>), ProviderError> {
+        let receipt = self.provider.get_transaction_receipt(tx_hash).await?;
+        let receipt_status = receipt.as_ref().map(|r| r.status.map(|s| s.as_u64()).unwrap_or(0));
+        let known_to_node = receipt.is_some() || self.provider.get_transaction(tx_hash).await?.is_some();
+
+        let outcome = ClaimOutcome::from_chain(receipt_status, known_to_node, submitted_at, self.drop_after_secs);
+        Ok((outcome, receipt))
+    }
+
+    pub async fn check_pending_creations(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let pending: Vec<PendingCreation> = sqlx::query_as(
+            "SELECT id, creator_address, tx_hash, submitted_at FROM relayed_creations WHERE tx_status = 'pending' AND tx_hash IS NOT NULL"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut settled = 0;
+        for creation in pending {
+            let Ok(tx_hash) = creation.tx_hash.parse::<H256>() else {
+                warn!("Relayed creation {} has a malformed tx hash", creation.id);
+                continue;
+            };
+
+            let (outcome, receipt) = self.outcome_of(tx_hash, creation.submitted_at).await?;
+            let Some(outcome) = outcome else {
+                continue;
+            };
+
+            if self.settle_creation(&creation, outcome, receipt.as_ref()).await? {
+                settled += 1;
+            }
+        }
+
+        Ok(settled)
+    }
+
+    async fn settle_creation(
+        &self,
+        creation: &PendingCreation,
+        outcome: ClaimOutcome,
+        receipt: Option<&TransactionReceipt>,
+    ) -> Result<bool, sqlx::Error> {
+        let (status, reason) = match outcome {
+            ClaimOutcome::Confirmed => ("confirmed", None),
+            ClaimOutcome::Reverted => ("failed", Some("reverted")),
+            ClaimOutcome::Dropped => ("failed", Some("dropped")),
+        };
+        // The event indexer picks the voucher up from the same log
+        let voucher_id = receipt.and_then(|r| {
+            r.logs.iter()
+                .find(|log| log.topics.first() == Some(&VoucherCreated::signature()))
+                .and_then(|log| log.topics.get(1))
+                .map(|id| format!("{:?}", id))
+        });
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE relayed_creations
+            SET tx_status = $1, error = $2, voucher_id = $3, settled_at = NOW()
+            WHERE id = $4 AND tx_status = 'pending' AND tx_hash = $5
+            "#
+        )
+        .bind(status)
+        .bind(reason)
+        .bind(&voucher_id)
+        .bind(creation.id)
+        .bind(&creation.tx_hash)
+        .execute(&self.pool)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        if let (Some(gas_accounting), Some(receipt)) = (&self.gas_accounting, receipt) {
+            gas_accounting.record_or_log(receipt, &CostAttribution {
+                kind: RelayKind::Creation,
+                voucher_id: voucher_id.clone(),
+                creator: Some(creation.creator_address.clone()),
+                campaign_id: None,
+            }).await;
+        }
+
+        self.audit.record_or_log(
+            &AuditContext::system("claim_monitor"),
+            "voucher.relayed_creation_settled",
+            "relayed_creation",
+            &creation.id.to_string(),
+            Some(json!({ "tx_status": "pending" })),
+            Some(json!({
+                "tx_status": status,
+                "tx_hash": creation.tx_hash,
+                "voucher_id": voucher_id,
+                "reason": reason,
+            })),
+        ).await;
+
+        info!("Relayed creation tx {} ({}) is {}", creation.tx_hash, creation.id, status);
+        Ok(true)
+    }
+
+    async fn settle(
+        &self,
+        claim: &PendingClaim,
+        outcome: ClaimOutcome,
+        receipt: Option<&TransactionReceipt>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        // The backend only submits claims for the recipient it last authorized
+        let recipient: Option<(String,)> = sqlx::query_as(
+            "SELECT recipient_address FROM claim_authorizations WHERE voucher_id = $1 ORDER BY issued_at DESC LIMIT 1"
+        )
+        .bind(&claim.voucher_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let recipient = recipient.map(|(address,)| address);
+
+        let status = match outcome {
+            ClaimOutcome::Confirmed => "confirmed",
+            ClaimOutcome::Reverted | ClaimOutcome::Dropped => "failed",
+        };
+        let revert = match (outcome, receipt) {
+            (ClaimOutcome::Reverted, Some(receipt)) => self.revert_of(claim, receipt).await,
+            _ => None,
+        };
+
+        // Only the first instance to see the outcome records it
+        let mut tx = self.pool.begin().await?;
+        let updated = sqlx::query(
+            r#"
+            UPDATE voucher_codes
+            SET claim_tx_status = $1
+            WHERE voucher_id = $2 AND claim_tx_status = 'pending' AND claim_tx_hash = $3
+            "#
+        )
+        .bind(status)
+        .bind(&claim.voucher_id)
+        .bind(&claim.claim_tx_hash)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        // A failed claim reopens the voucher, unless it was cancelled meanwhile
+        let ctx = AuditContext::system("claim_monitor");
+        let mut detail = json!({ "claim_tx_status": status });
+        if let Some(revert) = &revert {
+            detail["error"] = json!(revert);
+        }
+        let change = StatusChange {
+            tx_hash: Some(claim.claim_tx_hash.clone()),
+            claimed_by: recipient.clone(),
+            detail: Some(detail),
+            ..Default::default()
+        };
+        match outcome {
+            ClaimOutcome::Confirmed => {
+                match VoucherLifecycle::transition_in(
+                    &mut tx, &claim.voucher_id, None, VoucherStatus::Claimed, &change, &ctx,
+                ).await {
+                    Err(LifecycleError::InvalidTransition { from, .. }) => {
+                        warn!("Claim tx {} confirmed for {} voucher {}", claim.claim_tx_hash, from.as_str(), claim.voucher_id);
+                    }
+                    claimed => {
+                        claimed?;
+                    }
+                }
+            }
+            ClaimOutcome::Reverted | ClaimOutcome::Dropped => {
+                VoucherLifecycle::transition_in(
+                    &mut tx, &claim.voucher_id, Some(VoucherStatus::Claiming), VoucherStatus::Active, &change, &ctx,
+                ).await?;
+            }
+        }
+        tx.commit().await?;
+
+        if let (Some(gas_accounting), Some(receipt)) = (&self.gas_accounting, receipt) {
+            if let Err(e) = gas_accounting.record_claim(
+                receipt,
+                &claim.voucher_id,
+                claim.claim_batch_id,
+                outcome == ClaimOutcome::Confirmed,
+            ).await {
+                error!("Failed to record gas cost of claim tx {}: {}", claim.claim_tx_hash, e);
+            }
+        }
+
+        let block_number = receipt.and_then(|r| r.block_number).map(|n| n.as_u64());
+        let reason = match outcome {
+            ClaimOutcome::Confirmed => None,
+            ClaimOutcome::Reverted => Some("reverted"),
+            ClaimOutcome::Dropped => Some("dropped"),
+        };
+
+        self.audit.record_or_log(
+            &ctx,
+            "voucher.claim_settled",
+            "voucher_code",
+            &claim.code,
+            Some(json!({ "claim_tx_status": "pending" })),
+            Some(json!({
+                "claim_tx_status": status,
+                "claim_tx_hash": claim.claim_tx_hash,
+                "reason": reason,
+                "error": revert,
+            })),
+        ).await;
+
+        if let Some(webhooks) = &self.webhooks {
+            let data = json!({
+                "voucher_id": claim.voucher_id,
+                "recipient": recipient,
+                "tx_hash": claim.claim_tx_hash,
+                "block_number": block_number,
+                "reason": reason,
+            });
+            match outcome {
+                ClaimOutcome::Confirmed => {
+                    webhooks.emit_or_log(
+                        WebhookEvent::ClaimTxConfirmed,
+                        &format!("claim_tx.confirmed:{}", claim.claim_tx_hash),
+                        data.clone(),
+                    ).await;
+                    webhooks.emit_or_log(
+                        WebhookEvent::VoucherClaimed,
+                        &format!("voucher.claimed:{}", claim.voucher_id),
+                        json!({
+                            "voucher_id": claim.voucher_id,
+                            "claimed_by": recipient,
+                            "tx_hash": claim.claim_tx_hash,
+                        }),
+                    ).await;
+                }
+                ClaimOutcome::Reverted | ClaimOutcome::Dropped => {
+                    webhooks.emit_or_log(
+                        WebhookEvent::ClaimTxFailed,
+                        &format!("claim_tx.failed:{}", claim.claim_tx_hash),
+                        data,
+                    ).await;
+                }
+            }
+        }
+
+        info!("Claim tx {} of voucher {} is {}", claim.claim_tx_hash, claim.voucher_id, status);
+        Ok(true)
+    }
+
+    // Why a claim reverted, found by replaying its transaction on the state
+    // before its block; a batched claim is picked out of the Multicall3
+    // results. None when the node cannot tell or the replay succeeds
+    async fn revert_of(&self, claim: &PendingClaim, receipt: &TransactionReceipt) -> Option<DecodedRevert> {
+        let sent = self.provider.get_transaction(receipt.transaction_hash).await.ok()??;
+        let block = receipt.block_number?.saturating_sub(U64::one());
+        let call: TypedTransaction = TransactionRequest::new()
+            .from(sent.from)
+            .to(sent.to?)
+            .data(sent.input.clone())
+            .value(sent.value)
+            .gas(sent.gas)
+            .into();
+
+        let Ok(batch) = Aggregate3Call::decode(&sent.input) else {
+            let e = self.provider.call(&call, Some(block.into())).await.err()?;
+            return Some(DecodedRevert::decode(&RpcError::as_error_response(&e)?.as_revert_data()?));
+        };
+
+        let voucher_id = claim.voucher_id.parse::<H256>().ok()?;
+        let index = batch.calls.iter().position(|call| {
+            ClaimVoucherCall::decode(&call.call_data).is_ok_and(|claim| claim.voucher_id == voucher_id.0)
+        })?;
+        let returned = self.provider.call(&call, Some(block.into())).await.ok()?;
+        let results = Aggregate3Return::decode(&returned).ok()?.return_data;
+        let (success, data) = results.get(index)?;
+        (!success).then(|| DecodedRevert::decode(data))
+    }
+}
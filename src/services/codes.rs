@@ -0,0 +1,171 @@
+use crate::config::{CodeConfig, CodeDerivation, CodeFormat};
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, Rng};
+use sha2::Sha256;
+
+const MIN_SECRET_LEN: usize = 32;
+
+const LEGACY_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const LEGACY_LENGTH: usize = 16;
+
+// Crockford base32: no I, L, O or U
+const CROCKFORD_ALPHABET: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CROCKFORD_PAYLOAD: usize = 19;
+const CROCKFORD_GROUP: usize = 5;
+
+#[derive(Clone, Default)]
+enum Derivation {
+    #[default]
+    Random,
+    Hmac(Vec<u8>),
+}
+
+// Produces voucher codes in the configured format, either random or derived
+// from the voucher id, and parses codes typed in by users
+#[derive(Clone, Default)]
+pub struct CodeGenerator {
+    derivation: Derivation,
+    format: CodeFormat,
+}
+
+impl CodeGenerator {
+    pub fn from_config(config: &CodeConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let derivation = match config.derivation {
+            CodeDerivation::Random => Derivation::Random,
+            CodeDerivation::Hmac => {
+                let secret = config.secret.as_deref()
+                    .ok_or("codes.secret is required for hmac code derivation")?;
+                if secret.len() < MIN_SECRET_LEN {
+                    return Err(format!("codes.secret must be at least {} bytes", MIN_SECRET_LEN).into());
+                }
+                Derivation::Hmac(secret.as_bytes().to_vec())
+            }
+        };
+
+        Ok(Self {
+            derivation,
+            format: config.format,
+        })
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        matches!(self.derivation, Derivation::Hmac(_))
+    }
+
+    // Code number `index` of a voucher, in canonical form: 0 for its first
+    // code, then one more for every code added or rotated in afterwards
+    pub fn code_for(&self, voucher_id: &str, index: i64) -> String {
+        let (alphabet, length) = match self.format {
+            CodeFormat::Legacy => (LEGACY_ALPHABET, LEGACY_LENGTH),
+            CodeFormat::Crockford => (CROCKFORD_ALPHABET, CROCKFORD_PAYLOAD),
+        };
+
+        let symbols = match &self.derivation {
+            Derivation::Random => {
+                let mut rng = thread_rng();
+                (0..length).map(|_| rng.gen_range(0..alphabet.len())).collect()
+            }
+            Derivation::Hmac(secret) => derive_symbols(secret, voucher_id, index, alphabet.len(), length),
+        };
+
+        let mut code: String = symbols.iter().map(|&s| alphabet[s] as char).collect();
+        if self.format == CodeFormat::Crockford {
+            code.push(CROCKFORD_ALPHABET[luhn_check_symbol(&symbols)] as char);
+        }
+        code
+    }
+
+    // Random code in the configured format, for codes not tied to a voucher
+    // and so never rebuilt from chain events
+    pub fn random_code(&self) -> String {
+        let random = Self { derivation: Derivation::Random, format: self.format };
+        random.code_for("", 0)
+    }
+
+    // Canonical form of a user-supplied code, or None if it cannot be valid.
+    // Case, dashes and spaces are ignored; Crockford codes also read O as 0
+    // and I or L as 1, and must carry a correct check symbol
+    pub fn normalize(&self, input: &str) -> Option<String> {
+        let cleaned: String = input.chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        // Legacy codes stay valid after switching formats
+        if cleaned.len() == LEGACY_LENGTH && cleaned.bytes().all(|b| LEGACY_ALPHABET.contains(&b)) {
+            return Some(cleaned);
+        }
+
+        if self.format != CodeFormat::Crockford || cleaned.len() != CROCKFORD_PAYLOAD + 1 {
+            return None;
+        }
+
+        let symbols: Vec<usize> = cleaned.chars()
+            .map(|c| match c {
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            })
+            .map(|c| CROCKFORD_ALPHABET.iter().position(|&a| a as char == c))
+            .collect::<Option<_>>()?;
+
+        let (payload, check) = symbols.split_at(CROCKFORD_PAYLOAD);
+        if luhn_check_symbol(payload) != check[0] {
+            return None;
+        }
+
+        Some(symbols.iter().map(|&s| CROCKFORD_ALPHABET[s] as char).collect())
+    }
+
+    // Form shown to users; Crockford codes are split into dash-separated groups
+    pub fn display(&self, code: &str) -> String {
+        if code.len() != CROCKFORD_PAYLOAD + 1 {
+            return code.to_string();
+        }
+
+        code.as_bytes()
+            .chunks(CROCKFORD_GROUP)
+            .map(|group| String::from_utf8_lossy(group).into_owned())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+}
+
+// Uniform symbols from HMAC output, rejecting bytes that would bias them
+fn derive_symbols(secret: &[u8], voucher_id: &str, index: i64, radix: usize, length: usize) -> Vec<usize> {
+    let message = format!("nbgn-voucher-code:v1:{}:{}", voucher_id.to_lowercase(), index);
+    let limit = 256 / radix * radix;
+
+    let mut symbols = Vec::with_capacity(length);
+    let mut block = 0u32;
+    while symbols.len() < length {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+        mac.update(message.as_bytes());
+        mac.update(&block.to_be_bytes());
+
+        for byte in mac.finalize().into_bytes() {
+            if (byte as usize) < limit && symbols.len() < length {
+                symbols.push(byte as usize % radix);
+            }
+        }
+        block += 1;
+    }
+
+    symbols
+}
+
+// Luhn mod 32 check symbol: catches every single-symbol typo and most
+// swaps of adjacent symbols
+fn luhn_check_symbol(payload: &[usize]) -> usize {
+    let n = CROCKFORD_ALPHABET.len();
+    let sum: usize = payload.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &symbol)| {
+            let addend = if i % 2 == 0 { symbol * 2 } else { symbol };
+            addend / n + addend % n
+        })
+        .sum();
+
+    (n - sum % n) % n
+}
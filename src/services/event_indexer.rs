@@ -1,10 +1,13 @@
 use crate::db::voucher_models::VoucherCode;
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::codes::CodeGenerator;
+use crate::services::lifecycle::{LifecycleError, StatusChange, VoucherLifecycle, VoucherStatus};
+use crate::services::webhooks::{WebhookEvent, WebhookService};
 use ethers::prelude::*;
+use serde_json::json;
 use sqlx::PgPool;
 use std::sync::Arc;
-use tracing::{info, error, debug};
-use rand::{thread_rng, Rng};
-use rand::distributions::Alphanumeric;
+use tracing::{info, error, debug, warn};
 
 // Define the VoucherCreated event structure
 #[derive(Debug, Clone, EthEvent)]
@@ -28,22 +31,52 @@ pub struct VoucherCancelled {
     pub amount: U256,
 }
 
+// Define the VoucherClaimed event structure; batched claims are told apart
+// by it, since a failed call does not revert the batch
+#[derive(Debug, Clone, EthEvent)]
+#[ethevent(name = "VoucherClaimed", abi = "VoucherClaimed(bytes32,address,uint256)")]
+pub struct VoucherClaimed {
+    #[ethevent(indexed)]
+    pub voucher_id: H256,
+    #[ethevent(indexed)]
+    pub recipient: Address,
+    pub amount: U256,
+}
+
 #[derive(Clone)]
 pub struct EventIndexer {
     pool: PgPool,
     provider: Arc<Provider<Http>>,
     voucher_contract: Address,
+    audit: AuditLog,
+    lifecycle: VoucherLifecycle,
+    codes: CodeGenerator,
+    webhooks: Option<WebhookService>,
 }
 
 impl EventIndexer {
     pub fn new(pool: PgPool, provider: Arc<Provider<Http>>, voucher_contract: Address) -> Self {
         Self {
+            audit: AuditLog::new(pool.clone()),
+            lifecycle: VoucherLifecycle::new(pool.clone()),
             pool,
             provider,
             voucher_contract,
+            codes: CodeGenerator::default(),
+            webhooks: None,
         }
     }
 
+    pub fn with_code_generator(mut self, codes: CodeGenerator) -> Self {
+        self.codes = codes;
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: WebhookService) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
     pub async fn get_last_indexed_block(&self) -> Result<u64, Box<dyn std::error::Error>> {
         let result: Option<(i64,)> = sqlx::query_as(
             "SELECT last_indexed_block FROM sync_status WHERE id = 2" // id=2 for voucher indexer
@@ -78,7 +111,7 @@ impl EventIndexer {
     pub async fn index_voucher_events(&self, from_block: u64, to_block: u64) -> Result<(), Box<dyn std::error::Error>> {
         info!("Indexing voucher events from block {} to {}", from_block, to_block);
 
-        // Get both VoucherCreated and VoucherCancelled events
+        // Get VoucherCreated, VoucherCancelled and VoucherClaimed events
         let filter = Filter::new()
             .address(self.voucher_contract)
             .from_block(from_block)
@@ -106,8 +139,8 @@ impl EventIndexer {
                 .await?;
 
                 if existing.is_none() {
-                    // Generate new voucher code
-                    let code = generate_voucher_code();
+                    // First code of the voucher
+                    let code = self.codes.code_for(&voucher_id_hex, 0);
                     
                     // Get block timestamp
                     let block = self.provider.get_block(log.block_number.unwrap()).await?;
@@ -115,6 +148,7 @@ impl EventIndexer {
                     let on_chain_created_at = chrono::DateTime::from_timestamp(timestamp as i64, 0);
 
                     // Store mapping
+                    let mut tx = self.pool.begin().await?;
                     sqlx::query(
                         r#"
                         INSERT INTO voucher_codes 
@@ -127,12 +161,47 @@ impl EventIndexer {
                     .bind(format!("{:?}", event.creator))
                     .bind(event.amount.to_string())
                     .bind(on_chain_created_at)
-                    .execute(&self.pool)
+                    .execute(&mut *tx)
                     .await?;
+                    VoucherLifecycle::record_created(
+                        &mut *tx,
+                        &voucher_id_hex,
+                        on_chain_created_at,
+                        &AuditContext::system("event_indexer"),
+                    ).await?;
+                    tx.commit().await?;
+
+                    self.audit.record_or_log(
+                        &AuditContext::system("event_indexer"),
+                        "voucher.link_created",
+                        "voucher_code",
+                        &code,
+                        None,
+                        Some(json!({
+                            "voucher_id": voucher_id_hex,
+                            "has_password": false,
+                            "creator_address": format!("{:?}", event.creator),
+                            "amount": event.amount.to_string(),
+                        })),
+                    ).await;
 
                     info!("Created voucher code {} for voucher_id {} from creator {}", 
                           code, voucher_id_hex, event.creator);
                 }
+
+                if let Some(webhooks) = &self.webhooks {
+                    webhooks.emit_or_log(
+                        WebhookEvent::VoucherCreated,
+                        &format!("voucher.created:{}", voucher_id_hex),
+                        json!({
+                            "voucher_id": voucher_id_hex,
+                            "creator": format!("{:?}", event.creator),
+                            "amount": event.amount.to_string(),
+                            "tx_hash": log.transaction_hash.map(|hash| format!("{:?}", hash)),
+                            "block_number": log.block_number.map(|number| number.as_u64()),
+                        }),
+                    ).await;
+                }
             } else if let Ok(event) = parse_log::<VoucherCancelled>(log.clone()) {
                 let voucher_id_hex = format!("0x{}", hex::encode(event.voucher_id.as_bytes()));
                 
@@ -144,25 +213,117 @@ impl EventIndexer {
                 let timestamp = block.and_then(|b| Some(b.timestamp.as_u64())).unwrap_or(0);
                 let cancelled_at = chrono::DateTime::from_timestamp(timestamp as i64, 0);
                 
-                // Update voucher as cancelled
-                let result = sqlx::query(
-                    r#"
-                    UPDATE voucher_codes 
-                    SET cancelled = true, 
-                        cancelled_at = $1,
-                        cancel_tx_hash = $2
-                    WHERE voucher_id = $3
-                    "#
-                )
-                .bind(cancelled_at)
-                .bind(format!("{:?}", log.transaction_hash.unwrap()))
-                .bind(&voucher_id_hex)
-                .execute(&self.pool)
-                .await?;
-                
-                if result.rows_affected() > 0 {
-                    info!("Marked voucher {} as cancelled by creator {}", 
-                          voucher_id_hex, event.creator);
+                // Update voucher as cancelled; replayed logs find it cancelled already
+                let cancel_tx_hash = format!("{:?}", log.transaction_hash.unwrap());
+                let cancelled = self.lifecycle.transition(
+                    &voucher_id_hex,
+                    VoucherStatus::Cancelled,
+                    &StatusChange {
+                        tx_hash: Some(cancel_tx_hash.clone()),
+                        occurred_at: cancelled_at,
+                        ..Default::default()
+                    },
+                    &AuditContext::system("event_indexer"),
+                ).await;
+
+                match cancelled {
+                    Ok(Some(_)) => {
+                        self.audit.record_or_log(
+                            &AuditContext::system("event_indexer"),
+                            "voucher.cancelled",
+                            "voucher",
+                            &voucher_id_hex,
+                            None,
+                            Some(json!({
+                                "cancelled": true,
+                                "cancel_tx_hash": cancel_tx_hash,
+                            })),
+                        ).await;
+                        info!("Marked voucher {} as cancelled by creator {}", 
+                              voucher_id_hex, event.creator);
+                    }
+                    Ok(None) | Err(LifecycleError::NotFound) => {}
+                    Err(LifecycleError::InvalidTransition { from, .. }) => {
+                        warn!("Cancellation of {} voucher {} ignored", from.as_str(), voucher_id_hex);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+
+                if let Some(webhooks) = &self.webhooks {
+                    webhooks.emit_or_log(
+                        WebhookEvent::VoucherCancelled,
+                        &format!("voucher.cancelled:{}", voucher_id_hex),
+                        json!({
+                            "voucher_id": voucher_id_hex,
+                            "creator": format!("{:?}", event.creator),
+                            "amount": event.amount.to_string(),
+                            "tx_hash": log.transaction_hash.map(|hash| format!("{:?}", hash)),
+                            "block_number": log.block_number.map(|number| number.as_u64()),
+                            "cancelled_at": cancelled_at,
+                        }),
+                    ).await;
+                }
+            } else if let Ok(event) = parse_log::<VoucherClaimed>(log.clone()) {
+                let voucher_id_hex = format!("0x{}", hex::encode(event.voucher_id.as_bytes()));
+
+                info!("Processing VoucherClaimed event for voucher {} by recipient {}",
+                      voucher_id_hex, event.recipient);
+
+                // Get block timestamp
+                let block = self.provider.get_block(log.block_number.unwrap()).await?;
+                let timestamp = block.map(|b| b.timestamp.as_u64()).unwrap_or(0);
+                let claimed_at = chrono::DateTime::from_timestamp(timestamp as i64, 0);
+
+                // Claims sent from the recipient's own wallet only show up here;
+                // relayed ones may be settled by the claim monitor first
+                let claimed_by = format!("{:?}", event.recipient);
+                let claim_tx_hash = format!("{:?}", log.transaction_hash.unwrap());
+                let claimed = self.lifecycle.transition(
+                    &voucher_id_hex,
+                    VoucherStatus::Claimed,
+                    &StatusChange {
+                        tx_hash: Some(claim_tx_hash.clone()),
+                        claimed_by: Some(claimed_by.clone()),
+                        occurred_at: claimed_at,
+                        ..Default::default()
+                    },
+                    &AuditContext::system("event_indexer"),
+                ).await;
+
+                match claimed {
+                    Ok(Some(_)) => {
+                        self.audit.record_or_log(
+                            &AuditContext::system("event_indexer"),
+                            "voucher.claimed",
+                            "voucher",
+                            &voucher_id_hex,
+                            None,
+                            Some(json!({
+                                "claimed": true,
+                                "claimed_by": claimed_by,
+                                "claim_tx_hash": claim_tx_hash,
+                            })),
+                        ).await;
+                        info!("Marked voucher {} as claimed by {}", voucher_id_hex, claimed_by);
+                    }
+                    Ok(None) | Err(LifecycleError::NotFound) => {}
+                    Err(LifecycleError::InvalidTransition { from, .. }) => {
+                        warn!("Claim of {} voucher {} ignored", from.as_str(), voucher_id_hex);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+
+                // Same key as the claim monitor, so a relayed claim is announced once
+                if let Some(webhooks) = &self.webhooks {
+                    webhooks.emit_or_log(
+                        WebhookEvent::VoucherClaimed,
+                        &format!("voucher.claimed:{}", voucher_id_hex),
+                        json!({
+                            "voucher_id": voucher_id_hex,
+                            "claimed_by": claimed_by,
+                            "tx_hash": claim_tx_hash,
+                        }),
+                    ).await;
                 }
             }
         }
@@ -188,28 +349,24 @@ impl EventIndexer {
         let current_block = self.provider.get_block_number().await?.as_u64();
 
         if current_block > last_indexed {
-            // Index in batches of 1000 blocks
-            let batch_size = 1000u64;
-            let mut from_block = last_indexed + 1;
-
-            while from_block <= current_block {
-                let to_block = (from_block + batch_size - 1).min(current_block);
-                self.index_voucher_events(from_block, to_block).await?;
-                self.update_last_indexed_block(to_block).await?;
-                from_block = to_block + 1;
-            }
+            self.replay(last_indexed + 1, current_block).await?;
         }
 
         Ok(())
     }
-}
 
-// Generate a 16-character alphanumeric voucher code
-pub fn generate_voucher_code() -> String {
-    thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(16)
-        .map(char::from)
-        .collect::<String>()
-        .to_uppercase()
-}
\ No newline at end of file
+    // Index a block range in batches of 1000 blocks, recording progress after each
+    pub async fn replay(&self, from_block: u64, to_block: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let batch_size = 1000u64;
+        let mut from = from_block;
+
+        while from <= to_block {
+            let to = (from + batch_size - 1).min(to_block);
+            self.index_voucher_events(from, to).await?;
+            self.update_last_indexed_block(to).await?;
+            from = to + 1;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::lifecycle::{StatusChange, VoucherLifecycle, VoucherStatus};
+use crate::services::notifier::{CreatorNotification, Notifier};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{debug, error, info};
+
+const SCAN_BATCH: i64 = 100;
+
+#[derive(sqlx::FromRow)]
+struct ExpiredVoucher {
+    voucher_id: String,
+    creator_address: Option<String>,
+    amount: Option<String>,
+    claimable_until: DateTime<Utc>,
+}
+
+// Tells creators when a voucher's claim window closes without a claim
+#[derive(Clone)]
+pub struct ExpiryScanner {
+    pool: PgPool,
+    notifier: Notifier,
+    audit: AuditLog,
+    lifecycle: VoucherLifecycle,
+}
+
+impl ExpiryScanner {
+    pub fn new(pool: PgPool, notifier: Notifier) -> Self {
+        Self {
+            audit: AuditLog::new(pool.clone()),
+            lifecycle: VoucherLifecycle::new(pool.clone()),
+            pool,
+            notifier,
+        }
+    }
+
+    pub async fn run_scan_loop(&self, scan_interval_secs: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(scan_interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            match self.scan_expired().await {
+                Ok(0) => debug!("Expiry scan found nothing to notify"),
+                Ok(count) => info!("Notified creators of {} expired vouchers", count),
+                Err(e) => error!("Error in expiry scan: {}", e),
+            }
+        }
+    }
+
+    // Expire every active voucher whose window closed since the last scan and
+    // notify its creator; expired vouchers whose notification failed are retried
+    pub async fn scan_expired(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let expired: Vec<ExpiredVoucher> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT ON (voucher_id) voucher_id, creator_address, amount, claimable_until
+            FROM voucher_codes
+            WHERE claimable_until < NOW()
+              AND expiry_notified_at IS NULL
+              AND status IN ('active', 'expired')
+            ORDER BY voucher_id
+            LIMIT $1
+            "#
+        )
+        .bind(SCAN_BATCH)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let ctx = AuditContext::system("expiry_scanner");
+        let mut notified = 0;
+        for expired in expired {
+            let ExpiredVoucher { voucher_id, creator_address, amount, claimable_until } = expired;
+            self.lifecycle.transition_from(
+                &voucher_id,
+                VoucherStatus::Active,
+                VoucherStatus::Expired,
+                &StatusChange { occurred_at: Some(claimable_until), ..Default::default() },
+                &ctx,
+            ).await?;
+
+            // Mark first so another instance scanning at the same time skips it
+            let marked = sqlx::query(
+                "UPDATE voucher_codes SET expiry_notified_at = NOW() WHERE voucher_id = $1 AND expiry_notified_at IS NULL"
+            )
+            .bind(&voucher_id)
+            .execute(&self.pool)
+            .await?;
+            if marked.rows_affected() == 0 {
+                continue;
+            }
+
+            let notification = CreatorNotification {
+                event: "voucher.expired".to_string(),
+                creator_address: creator_address.clone().unwrap_or_default(),
+                voucher_id: voucher_id.clone(),
+                occurred_at: claimable_until,
+                data: json!({ "amount": amount, "claimable_until": claimable_until }),
+            };
+
+            let delivered = match self.notifier.notify(&notification).await {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("Failed to notify creator of expired voucher {}: {}", voucher_id, e);
+                    false
+                }
+            };
+            if !delivered {
+                // Leave it for the next scan
+                sqlx::query("UPDATE voucher_codes SET expiry_notified_at = NULL WHERE voucher_id = $1")
+                    .bind(&voucher_id)
+                    .execute(&self.pool)
+                    .await?;
+                continue;
+            }
+
+            self.audit.record_or_log(
+                &ctx,
+                "voucher.expired",
+                "voucher",
+                &voucher_id,
+                None,
+                Some(json!({ "claimable_until": claimable_until, "creator_notified": creator_address })),
+            ).await;
+            notified += 1;
+        }
+
+        Ok(notified)
+    }
+}
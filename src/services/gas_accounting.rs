@@ -0,0 +1,267 @@
+use crate::db::gas_models::*;
+use crate::services::relay_policy::RelayKind;
+use ethers::types::{TransactionReceipt, U256};
+use sqlx::PgPool;
+use tracing::{error, info};
+
+// What a mined transaction cost the relayer. Arbitrum reports the gas spent
+// on posting it to L1 as gasUsedForL1, already counted in gasUsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasCost {
+    pub gas_used: U256,
+    pub l1_gas_used: U256,
+    pub effective_gas_price: U256,
+    pub cost_wei: U256,
+    pub l1_cost_wei: U256,
+}
+
+impl GasCost {
+    // None for receipts without gas figures, which cost nothing we can tell
+    pub fn from_receipt(receipt: &TransactionReceipt) -> Option<Self> {
+        let gas_used = receipt.gas_used?;
+        let effective_gas_price = receipt.effective_gas_price?;
+        let l1_gas_used = receipt.other
+            .get_deserialized::<U256>("gasUsedForL1")
+            .and_then(Result::ok)
+            .unwrap_or_default()
+            .min(gas_used);
+
+        Some(Self {
+            gas_used,
+            l1_gas_used,
+            effective_gas_price,
+            cost_wei: gas_used * effective_gas_price,
+            l1_cost_wei: l1_gas_used * effective_gas_price,
+        })
+    }
+
+    // The share of call `index` of `calls` in a batch transaction, split
+    // evenly with the remainder on the first call so shares add up
+    pub fn share(&self, index: usize, calls: usize) -> Self {
+        let calls = U256::from(calls.max(1));
+        let split = |total: U256| {
+            let share = total / calls;
+            if index == 0 { share + total % calls } else { share }
+        };
+        let gas_used = split(self.gas_used);
+        let l1_gas_used = split(self.l1_gas_used);
+
+        Self {
+            gas_used,
+            l1_gas_used,
+            effective_gas_price: self.effective_gas_price,
+            cost_wei: gas_used * self.effective_gas_price,
+            l1_cost_wei: l1_gas_used * self.effective_gas_price,
+        }
+    }
+}
+
+// Who a relayer transaction was spent on
+#[derive(Debug, Clone)]
+pub struct CostAttribution {
+    pub kind: RelayKind,
+    pub voucher_id: Option<String>,
+    pub creator: Option<String>,
+    pub campaign_id: Option<i64>,
+}
+
+const TOTALS: &str = r#"
+    COUNT(*) AS transactions,
+    COUNT(*) FILTER (WHERE NOT succeeded) AS failed,
+    COALESCE(SUM(gas_used), 0)::TEXT AS gas_used,
+    COALESCE(SUM(l1_gas_used), 0)::TEXT AS l1_gas_used,
+    COALESCE(SUM(cost_wei), 0)::TEXT AS cost_wei,
+    COALESCE(SUM(l1_cost_wei), 0)::TEXT AS l1_cost_wei
+"#;
+
+const FILTER: &str = r#"
+    ($1::timestamptz IS NULL OR g.created_at >= $1)
+    AND ($2::timestamptz IS NULL OR g.created_at < $2)
+    AND ($3::text IS NULL OR g.kind = $3)
+"#;
+
+// Records the gas of every mined relayer transaction and reports the spend
+#[derive(Clone)]
+pub struct GasAccounting {
+    pool: PgPool,
+}
+
+impl GasAccounting {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        receipt: &TransactionReceipt,
+        attribution: &CostAttribution,
+    ) -> Result<Option<GasCost>, sqlx::Error> {
+        let Some(cost) = GasCost::from_receipt(receipt) else {
+            return Ok(None);
+        };
+        let succeeded = receipt.status.map(|s| s.as_u64()) == Some(1);
+        self.insert(receipt, &cost, attribution, succeeded).await?;
+        Ok(Some(cost))
+    }
+
+    async fn insert(
+        &self,
+        receipt: &TransactionReceipt,
+        cost: &GasCost,
+        attribution: &CostAttribution,
+        succeeded: bool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO relayer_gas_costs
+                (tx_hash, kind, succeeded, voucher_id, creator_address, campaign_id, block_number,
+                 gas_used, l1_gas_used, effective_gas_price, cost_wei, l1_cost_wei)
+            VALUES ($1, $2, $3, $4, LOWER($5), $6, $7,
+                    $8::NUMERIC, $9::NUMERIC, $10::NUMERIC, $11::NUMERIC, $12::NUMERIC)
+            ON CONFLICT (tx_hash, COALESCE(voucher_id, '')) DO NOTHING
+            "#
+        )
+        .bind(format!("{:?}", receipt.transaction_hash))
+        .bind(attribution.kind.as_str())
+        .bind(succeeded)
+        .bind(&attribution.voucher_id)
+        .bind(&attribution.creator)
+        .bind(attribution.campaign_id)
+        .bind(receipt.block_number.map(|n| n.as_u64() as i64))
+        .bind(cost.gas_used.to_string())
+        .bind(cost.l1_gas_used.to_string())
+        .bind(cost.effective_gas_price.to_string())
+        .bind(cost.cost_wei.to_string())
+        .bind(cost.l1_cost_wei.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        info!(
+            "Relayer tx {:?} cost {} wei ({} wei for L1)",
+            receipt.transaction_hash, cost.cost_wei, cost.l1_cost_wei
+        );
+        Ok(())
+    }
+
+    // A gasless claim, charged to the voucher's creator and campaign; a
+    // batched claim gets its share of the batch transaction, and succeeded
+    // only if its own call did
+    pub async fn record_claim(
+        &self,
+        receipt: &TransactionReceipt,
+        voucher_id: &str,
+        batch_id: Option<i64>,
+        succeeded: bool,
+    ) -> Result<(), sqlx::Error> {
+        let Some(mut cost) = GasCost::from_receipt(receipt) else {
+            return Ok(());
+        };
+        if let Some(batch_id) = batch_id {
+            let position: Option<(Option<i32>, Option<i32>)> = sqlx::query_as(
+                "SELECT array_position(voucher_ids, $2), cardinality(voucher_ids) FROM claim_batches WHERE id = $1"
+            )
+            .bind(batch_id)
+            .bind(voucher_id)
+            .fetch_optional(&self.pool)
+            .await?;
+            if let Some((Some(position), Some(calls))) = position {
+                cost = cost.share(position as usize - 1, calls as usize);
+            }
+        }
+
+        let voucher: Option<(Option<String>, Option<i64>)> = sqlx::query_as(
+            "SELECT creator_address, campaign_id FROM voucher_codes WHERE voucher_id = $1 LIMIT 1"
+        )
+        .bind(voucher_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let (creator, campaign_id) = voucher.unwrap_or_default();
+
+        self.insert(receipt, &cost, &CostAttribution {
+            kind: RelayKind::Claim,
+            voucher_id: Some(voucher_id.to_string()),
+            creator,
+            campaign_id,
+        }, succeeded).await
+    }
+
+    // Accounting never holds up settling a transaction
+    pub async fn record_or_log(&self, receipt: &TransactionReceipt, attribution: &CostAttribution) {
+        if let Err(e) = self.record(receipt, attribution).await {
+            error!("Failed to record gas cost of {:?}: {}", receipt.transaction_hash, e);
+        }
+    }
+
+    pub async fn totals(&self, query: &GasReportQuery) -> Result<GasCostTotals, sqlx::Error> {
+        sqlx::query_as(&format!("SELECT {} FROM relayer_gas_costs g WHERE {}", TOTALS, FILTER))
+            .bind(query.since)
+            .bind(query.until)
+            .bind(&query.kind)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    pub async fn daily(&self, query: &GasReportQuery) -> Result<Vec<DailyGasCost>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            SELECT (g.created_at AT TIME ZONE 'UTC')::date AS day, {}
+            FROM relayer_gas_costs g
+            WHERE {}
+            GROUP BY day
+            ORDER BY day DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            TOTALS, FILTER
+        ))
+        .bind(query.since)
+        .bind(query.until)
+        .bind(&query.kind)
+        .bind(query.limit.unwrap_or(90).clamp(1, 1000))
+        .bind(query.offset.unwrap_or(0).max(0))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn by_campaign(&self, query: &GasReportQuery) -> Result<Vec<CampaignGasCost>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            SELECT g.campaign_id, c.name AS campaign_name, {}
+            FROM relayer_gas_costs g
+            LEFT JOIN campaigns c ON c.id = g.campaign_id
+            WHERE {}
+            GROUP BY g.campaign_id, c.name
+            ORDER BY SUM(g.cost_wei) DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            TOTALS, FILTER
+        ))
+        .bind(query.since)
+        .bind(query.until)
+        .bind(&query.kind)
+        .bind(query.limit.unwrap_or(50).clamp(1, 500))
+        .bind(query.offset.unwrap_or(0).max(0))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn by_creator(&self, query: &GasReportQuery) -> Result<Vec<CreatorGasCost>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            SELECT g.creator_address, {}
+            FROM relayer_gas_costs g
+            WHERE {}
+            GROUP BY g.creator_address
+            ORDER BY SUM(g.cost_wei) DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            TOTALS, FILTER
+        ))
+        .bind(query.since)
+        .bind(query.until)
+        .bind(&query.kind)
+        .bind(query.limit.unwrap_or(50).clamp(1, 500))
+        .bind(query.offset.unwrap_or(0).max(0))
+        .fetch_all(&self.pool)
+        .await
+    }
+}
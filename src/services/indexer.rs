@@ -1,14 +1,61 @@
-use crate::contracts::nbgn::NBGNContract;
+use crate::contracts::nbgn::{Burned, Minted, NBGNContract, Redeemed};
+use crate::services::webhooks::{WebhookEvent, WebhookService};
 use ethers::prelude::*;
+use serde_json::json;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::{info, error, debug};
 
+// A Minted, Redeemed or Burned log as stored in `transactions`
+struct TokenEvent {
+    transaction_type: &'static str,
+    webhook: WebhookEvent,
+    user: Address,
+    eure_amount: U256,
+    nbgn_amount: U256,
+}
+
+impl TokenEvent {
+    fn parse(log: &Log) -> Option<Self> {
+        if let Ok(event) = parse_log::<Minted>(log.clone()) {
+            return Some(Self {
+                transaction_type: "mint",
+                webhook: WebhookEvent::NbgnMinted,
+                user: event.user,
+                eure_amount: event.eure_amount,
+                nbgn_amount: event.nbgn_amount,
+            });
+        }
+        if let Ok(event) = parse_log::<Redeemed>(log.clone()) {
+            return Some(Self {
+                transaction_type: "redeem",
+                webhook: WebhookEvent::NbgnRedeemed,
+                user: event.user,
+                eure_amount: event.eure_amount,
+                nbgn_amount: event.nbgn_amount,
+            });
+        }
+        if let Ok(event) = parse_log::<Burned>(log.clone()) {
+            // The EURe refunded for the burn
+            return Some(Self {
+                transaction_type: "burn",
+                webhook: WebhookEvent::NbgnBurned,
+                user: event.user,
+                eure_amount: event.refund_amount,
+                nbgn_amount: event.nbgn_amount,
+            });
+        }
+        None
+    }
+}
+
 #[derive(Clone)]
 pub struct Indexer {
     contract: NBGNContract,
     pool: PgPool,
     provider: Arc<Provider<Http>>,
+    start_block: u64,
+    webhooks: Option<WebhookService>,
 }
 
 impl Indexer {
@@ -17,9 +64,22 @@ impl Indexer {
             contract,
             pool,
             provider,
+            start_block: 0,
+            webhooks: None,
         }
     }
 
+    // First block worth scanning, e.g. the contract's deployment block
+    pub fn with_start_block(mut self, start_block: u64) -> Self {
+        self.start_block = start_block;
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: WebhookService) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
     pub async fn get_last_indexed_block(&self) -> Result<u64, Box<dyn std::error::Error>> {
         let result: Option<(i64,)> = sqlx::query_as(
             "SELECT last_indexed_block FROM sync_status WHERE id = 1"
@@ -54,8 +114,61 @@ impl Indexer {
     pub async fn index_events(&self, from_block: u64, to_block: u64) -> Result<(), Box<dyn std::error::Error>> {
         info!("Indexing events from block {} to {}", from_block, to_block);
 
-        // Note: In a real implementation, you would query the events here
-        // For now, we'll just update the last indexed block
+        let filter = Filter::new()
+            .address(self.contract.address())
+            .from_block(from_block)
+            .to_block(to_block);
+
+        let logs = self.provider.get_logs(&filter).await?;
+
+        for log in logs {
+            let Some(event) = TokenEvent::parse(&log) else {
+                continue;
+            };
+            let (Some(tx_hash), Some(block_number)) = (log.transaction_hash, log.block_number) else {
+                continue;
+            };
+            let tx_hash = format!("{:?}", tx_hash);
+
+            let block = self.provider.get_block(block_number).await?;
+            let timestamp = block.map(|b| b.timestamp.as_u64()).unwrap_or(0);
+            let timestamp = chrono::DateTime::from_timestamp(timestamp as i64, 0);
+
+            sqlx::query(
+                r#"
+                INSERT INTO transactions
+                (tx_hash, block_number, timestamp, user_address, transaction_type, eure_amount, nbgn_amount)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (tx_hash) DO NOTHING
+                "#
+            )
+            .bind(&tx_hash)
+            .bind(block_number.as_u64() as i64)
+            .bind(timestamp)
+            .bind(format!("{:?}", event.user))
+            .bind(event.transaction_type)
+            .bind(event.eure_amount.to_string())
+            .bind(event.nbgn_amount.to_string())
+            .execute(&self.pool)
+            .await?;
+
+            if let Some(webhooks) = &self.webhooks {
+                let log_index = log.log_index.map(|index| index.as_u64()).unwrap_or_default();
+                webhooks.emit_or_log(
+                    event.webhook,
+                    &format!("{}:{}:{}", event.webhook.as_str(), tx_hash, log_index),
+                    json!({
+                        "user": format!("{:?}", event.user),
+                        "eure_amount": event.eure_amount.to_string(),
+                        "nbgn_amount": event.nbgn_amount.to_string(),
+                        "tx_hash": tx_hash,
+                        "block_number": block_number.as_u64(),
+                        "timestamp": timestamp,
+                    }),
+                ).await;
+            }
+        }
+
         self.update_last_indexed_block(to_block).await?;
 
         Ok(())
@@ -81,7 +194,7 @@ impl Indexer {
         if current_block > last_indexed {
             // Index in batches of 1000 blocks
             let batch_size = 1000u64;
-            let mut from_block = last_indexed + 1;
+            let mut from_block = (last_indexed + 1).max(self.start_block);
 
             while from_block <= current_block {
                 let to_block = (from_block + batch_size - 1).min(current_block);
@@ -92,4 +205,4 @@ impl Indexer {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
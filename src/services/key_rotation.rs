@@ -0,0 +1,191 @@
+use crate::config::SignerRole;
+use crate::services::signer::SignerRegistry;
+use ethers::prelude::*;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// Transaction for the contract owner to sign and submit
+#[derive(Debug, Serialize)]
+pub struct UnsignedTransaction {
+    pub to: String,
+    pub data: String,
+    pub value: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignerInfo {
+    pub address: String,
+    pub role: SignerRole,
+    pub on_chain: bool,
+}
+
+// Moves the backend signing key between configured keys, in step with the
+// voucher contract's backendSigner
+#[derive(Clone)]
+pub struct KeyRotationService {
+    pool: PgPool,
+    signers: SignerRegistry,
+    provider: Arc<Provider<Http>>,
+    voucher_contract: Address,
+}
+
+impl KeyRotationService {
+    pub fn new(
+        pool: PgPool,
+        signers: SignerRegistry,
+        provider: Arc<Provider<Http>>,
+        voucher_contract: Address,
+    ) -> Self {
+        Self {
+            pool,
+            signers,
+            provider,
+            voucher_contract,
+        }
+    }
+
+    fn contract_abi() -> Result<ethers::abi::Abi, Box<dyn std::error::Error>> {
+        Ok(ethers::abi::parse_abi(&[
+            "function backendSigner() view returns (address)",
+            "function updateBackendSigner(address newSigner) external",
+        ])?)
+    }
+
+    // Apply roles persisted by earlier rotations over the configured ones
+    pub async fn load_persisted_roles(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT address, role FROM backend_signers ORDER BY (role = 'signing') DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for (address, role) in rows {
+            let (Ok(parsed), Some(role)) = (address.parse::<Address>(), SignerRole::parse(&role)) else {
+                warn!("Ignoring invalid persisted signer role {} for {}", role, address);
+                continue;
+            };
+            if self.signers.role_of(parsed).is_none() {
+                warn!("Persisted signer {} is no longer configured", address);
+                continue;
+            }
+            self.signers.set_role(parsed, role)?;
+        }
+
+        info!("Backend signing key is {:?}", self.signers.signing().address());
+        Ok(())
+    }
+
+    async fn persist_roles(&self) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for (address, role) in self.signers.list() {
+            sqlx::query(
+                r#"
+                INSERT INTO backend_signers (address, role)
+                VALUES ($1, $2)
+                ON CONFLICT (address)
+                DO UPDATE SET role = $2, updated_at = NOW()
+                "#
+            )
+            .bind(format!("{:?}", address))
+            .bind(role.as_str())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    pub fn role_of(&self, address: Address) -> Option<SignerRole> {
+        self.signers.role_of(address)
+    }
+
+    // The signer the voucher contract currently accepts
+    pub async fn on_chain_signer(&self) -> Result<Address, Box<dyn std::error::Error>> {
+        let contract = Contract::new(self.voucher_contract, Self::contract_abi()?, self.provider.clone());
+        let signer: Address = contract
+            .method::<_, Address>("backendSigner", ())?
+            .call()
+            .await?;
+        Ok(signer)
+    }
+
+    pub async fn list_signers(&self) -> Result<Vec<SignerInfo>, Box<dyn std::error::Error>> {
+        let on_chain = self.on_chain_signer().await.ok();
+
+        Ok(self.signers.list()
+            .into_iter()
+            .map(|(address, role)| SignerInfo {
+                address: format!("{:?}", address),
+                role,
+                on_chain: on_chain == Some(address),
+            })
+            .collect())
+    }
+
+    // Step 1: the owner submits updateBackendSigner(new_signer) on the voucher contract
+    pub fn prepare_rotation(&self, new_signer: Address) -> Result<UnsignedTransaction, Box<dyn std::error::Error>> {
+        match self.signers.role_of(new_signer) {
+            None => return Err("New signer is not configured on this backend".into()),
+            Some(SignerRole::Signing) => return Err("New signer is already the signing key".into()),
+            Some(SignerRole::Retired) => return Err("New signer has been retired".into()),
+            Some(SignerRole::Verifying) => {}
+        }
+
+        let data = Self::contract_abi()?
+            .function("updateBackendSigner")?
+            .encode_input(&[ethers::abi::Token::Address(new_signer)])?;
+
+        Ok(UnsignedTransaction {
+            to: format!("{:?}", self.voucher_contract),
+            data: format!("0x{}", hex::encode(data)),
+            value: "0".to_string(),
+            description: format!("updateBackendSigner({:?})", new_signer),
+        })
+    }
+
+    // Step 2: once the contract accepts the new key, sign with it. The old key
+    // drops to verifying so its live authorizations run out normally
+    pub async fn activate(&self, new_signer: Address) -> Result<Address, Box<dyn std::error::Error>> {
+        let on_chain = self.on_chain_signer().await?;
+        if on_chain != new_signer {
+            return Err(format!(
+                "Contract backendSigner is {:?}, not {:?}; submit the prepared update first",
+                on_chain, new_signer
+            ).into());
+        }
+
+        let previous = self.signers.signing().address();
+        if previous == new_signer {
+            return Ok(previous);
+        }
+
+        self.signers.set_role(new_signer, SignerRole::Signing)?;
+        self.persist_roles().await?;
+
+        info!("Backend signing key rotated from {:?} to {:?}", previous, new_signer);
+        Ok(previous)
+    }
+
+    // Retire a key: live authorizations it signed are superseded so the
+    // vouchers can be authorized again with the active key
+    pub async fn retire(&self, address: Address) -> Result<u64, Box<dyn std::error::Error>> {
+        self.signers.set_role(address, SignerRole::Retired)?;
+        self.persist_roles().await?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE claim_authorizations
+            SET status = 'superseded', superseded_at = NOW()
+            WHERE status = 'live' AND LOWER(signer_address) = LOWER($1)
+            "#
+        )
+        .bind(format!("{:?}", address))
+        .execute(&self.pool)
+        .await?;
+
+        info!("Retired backend signer {:?}, superseded {} live authorizations", address, result.rows_affected());
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,230 @@
+use crate::db::voucher_models::VoucherEvent;
+use crate::services::audit::AuditContext;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::{PgPool, Postgres, Transaction};
+
+// Where a voucher is in its life; every code of a voucher shares it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoucherStatus {
+    Active,
+    Claiming,
+    Claimed,
+    Expired,
+    Withdrawn,
+    Cancelled,
+}
+
+impl VoucherStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoucherStatus::Active => "active",
+            VoucherStatus::Claiming => "claiming",
+            VoucherStatus::Claimed => "claimed",
+            VoucherStatus::Expired => "expired",
+            VoucherStatus::Withdrawn => "withdrawn",
+            VoucherStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        match status {
+            "active" => Some(VoucherStatus::Active),
+            "claiming" => Some(VoucherStatus::Claiming),
+            "claimed" => Some(VoucherStatus::Claimed),
+            "expired" => Some(VoucherStatus::Expired),
+            "withdrawn" => Some(VoucherStatus::Withdrawn),
+            "cancelled" => Some(VoucherStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    // The transitions the voucher_status_transition trigger allows. The chain
+    // has the last word: a withdrawn or expired voucher can still be claimed
+    // with an authorization issued earlier, or cancelled by its creator
+    pub fn can_become(self, next: Self) -> bool {
+        use VoucherStatus::*;
+        matches!(
+            (self, next),
+            (Active, Claiming | Claimed | Expired | Withdrawn | Cancelled)
+                | (Claiming, Active | Claimed | Cancelled)
+                | (Expired, Active | Claimed | Withdrawn | Cancelled)
+                | (Withdrawn, Claimed | Cancelled)
+        )
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LifecycleError {
+    #[error("Voucher not found")]
+    NotFound,
+    #[error("Voucher is {} and cannot become {}", .from.as_str(), .to.as_str())]
+    InvalidTransition { from: VoucherStatus, to: VoucherStatus },
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+// What moved a voucher, kept on its codes and in its event. Claimed and
+// cancelled take tx_hash as their claim or cancel transaction
+#[derive(Debug, Clone, Default)]
+pub struct StatusChange {
+    pub tx_hash: Option<String>,
+    pub claimed_by: Option<String>,
+    // When it happened, if not now; chain events pass the block time
+    pub occurred_at: Option<DateTime<Utc>>,
+    pub detail: Option<Value>,
+}
+
+// Moves vouchers between statuses and records every move in voucher_events
+#[derive(Clone)]
+pub struct VoucherLifecycle {
+    pool: PgPool,
+}
+
+impl VoucherLifecycle {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Move a voucher to `to`. Returns the status it left, or None if it
+    // already was there
+    pub async fn transition(
+        &self,
+        voucher_id: &str,
+        to: VoucherStatus,
+        change: &StatusChange,
+        ctx: &AuditContext,
+    ) -> Result<Option<VoucherStatus>, LifecycleError> {
+        let mut tx = self.pool.begin().await?;
+        let from = Self::transition_in(&mut tx, voucher_id, None, to, change, ctx).await?;
+        tx.commit().await?;
+        Ok(from)
+    }
+
+    // Move a voucher from `from` to `to`, leaving it alone in any other status
+    pub async fn transition_from(
+        &self,
+        voucher_id: &str,
+        from: VoucherStatus,
+        to: VoucherStatus,
+        change: &StatusChange,
+        ctx: &AuditContext,
+    ) -> Result<bool, LifecycleError> {
+        let mut tx = self.pool.begin().await?;
+        let moved = Self::transition_in(&mut tx, voucher_id, Some(from), to, change, ctx).await?;
+        tx.commit().await?;
+        Ok(moved.is_some())
+    }
+
+    // Both of the above inside the caller's transaction; `expected` limits
+    // the move to vouchers in that status
+    pub async fn transition_in(
+        tx: &mut Transaction<'_, Postgres>,
+        voucher_id: &str,
+        expected: Option<VoucherStatus>,
+        to: VoucherStatus,
+        change: &StatusChange,
+        ctx: &AuditContext,
+    ) -> Result<Option<VoucherStatus>, LifecycleError> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT status FROM voucher_codes WHERE voucher_id = $1 ORDER BY created_at FOR UPDATE"
+        )
+        .bind(voucher_id)
+        .fetch_all(&mut **tx)
+        .await?;
+        let from = rows.first()
+            .and_then(|(status,)| VoucherStatus::parse(status))
+            .ok_or(LifecycleError::NotFound)?;
+
+        if from == to || expected.is_some_and(|expected| expected != from) {
+            return Ok(None);
+        }
+        if !from.can_become(to) {
+            return Err(LifecycleError::InvalidTransition { from, to });
+        }
+
+        let occurred_at = change.occurred_at.unwrap_or_else(Utc::now);
+        sqlx::query(
+            r#"
+            UPDATE voucher_codes
+            SET status = $2,
+                claimed = ($2 = 'claimed'),
+                claimed_by = CASE WHEN $2 = 'claimed' THEN COALESCE($3, claimed_by) ELSE claimed_by END,
+                claimed_at = CASE WHEN $2 = 'claimed' THEN COALESCE(claimed_at, $4) ELSE claimed_at END,
+                claim_tx_hash = CASE WHEN $2 = 'claimed' THEN COALESCE($5, claim_tx_hash) ELSE claim_tx_hash END,
+                cancelled = ($2 = 'cancelled'),
+                cancelled_at = CASE WHEN $2 = 'cancelled' THEN $4 ELSE cancelled_at END,
+                cancel_tx_hash = CASE WHEN $2 = 'cancelled' THEN COALESCE($5, cancel_tx_hash) ELSE cancel_tx_hash END
+            WHERE voucher_id = $1
+            "#
+        )
+        .bind(voucher_id)
+        .bind(to.as_str())
+        .bind(&change.claimed_by)
+        .bind(occurred_at)
+        .bind(&change.tx_hash)
+        .execute(&mut **tx)
+        .await?;
+
+        Self::record(&mut **tx, voucher_id, Some(from), to, change, ctx).await?;
+        Ok(Some(from))
+    }
+
+    // Every move of a voucher, oldest first
+    pub async fn events(&self, voucher_id: &str) -> Result<Vec<VoucherEvent>, sqlx::Error> {
+        sqlx::query_as::<_, VoucherEvent>(
+            "SELECT * FROM voucher_events WHERE voucher_id = $1 ORDER BY occurred_at, id"
+        )
+        .bind(voucher_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    // A voucher entering the system, as active
+    pub async fn record_created<'e, E>(
+        executor: E,
+        voucher_id: &str,
+        occurred_at: Option<DateTime<Utc>>,
+        ctx: &AuditContext,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let change = StatusChange { occurred_at, ..Default::default() };
+        Self::record(executor, voucher_id, None, VoucherStatus::Active, &change, ctx).await
+    }
+
+    async fn record<'e, E>(
+        executor: E,
+        voucher_id: &str,
+        from: Option<VoucherStatus>,
+        to: VoucherStatus,
+        change: &StatusChange,
+        ctx: &AuditContext,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO voucher_events
+                (voucher_id, from_status, to_status, actor_type, actor, tx_hash, detail, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#
+        )
+        .bind(voucher_id)
+        .bind(from.map(|from| from.as_str()))
+        .bind(to.as_str())
+        .bind(ctx.actor_type)
+        .bind(&ctx.actor)
+        .bind(&change.tx_hash)
+        .bind(change.detail.clone().map(Json))
+        .bind(change.occurred_at.unwrap_or_else(Utc::now))
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
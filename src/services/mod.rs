@@ -1,4 +1,28 @@
+pub mod allowlist;
+pub mod audit;
+pub mod auth;
 pub mod cache;
+pub mod campaign;
+pub mod claim_batcher;
+pub mod claim_monitor;
+pub mod codes;
 pub mod indexer;
+pub mod key_rotation;
+pub mod lifecycle;
+pub mod mailer;
+pub mod notes;
+pub mod notification_channels;
+pub mod notifier;
+pub mod pool;
+pub mod qr;
+pub mod relay_policy;
+pub mod relayed_creation;
 pub mod event_indexer;
-pub mod voucher;
\ No newline at end of file
+pub mod expiry;
+pub mod gas_accounting;
+pub mod signer;
+pub mod sponsorship;
+pub mod timeline;
+pub mod tx_builder;
+pub mod voucher;
+pub mod webhooks;
\ No newline at end of file
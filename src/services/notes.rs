@@ -0,0 +1,104 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use rand::RngCore;
+
+use crate::db::voucher_models::GiftNote;
+
+pub const MAX_NOTE_BYTES: usize = 1000;
+pub const NOTE_CONTENT_TYPES: &[&str] = &["text/plain", "text/markdown"];
+
+// Sealed layout: version | salt | nonce | ciphertext with tag
+const NOTE_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NoteError {
+    #[error("Failed to derive the note key")]
+    KeyDerivation,
+    #[error("Failed to encrypt the note")]
+    Encrypt,
+    #[error("Note could not be decrypted")]
+    Undecryptable,
+}
+
+// The key comes from the claim code and, for protected codes, the password.
+// Codes are stored in voucher_codes, so a database dump opens every note of
+// an unprotected code; only a password, which is stored hashed, keeps a note
+// sealed from whoever holds the dump
+fn note_key(code: &str, password: Option<&str>, salt: &[u8]) -> Result<Aes256Gcm, NoteError> {
+    let mut secret = code.as_bytes().to_vec();
+    if let Some(password) = password {
+        secret.push(0);
+        secret.extend_from_slice(password.as_bytes());
+    }
+
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(&secret, salt, &mut key)
+        .map_err(|_| NoteError::KeyDerivation)?;
+    Aes256Gcm::new_from_slice(&key).map_err(|_| NoteError::KeyDerivation)
+}
+
+// Encrypt a note for a code; the content type is bound as associated data
+pub fn seal(code: &str, password: Option<&str>, note: &GiftNote) -> Result<Vec<u8>, NoteError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let cipher = note_key(code, password, &salt)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload {
+            msg: note.text.as_bytes(),
+            aad: note.content_type.as_bytes(),
+        })
+        .map_err(|_| NoteError::Encrypt)?;
+
+    let mut sealed = Vec::with_capacity(1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+    sealed.push(NOTE_VERSION);
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+pub fn open(code: &str, password: Option<&str>, content_type: &str, sealed: &[u8]) -> Result<GiftNote, NoteError> {
+    if sealed.len() < 1 + SALT_LEN + NONCE_LEN || sealed[0] != NOTE_VERSION {
+        return Err(NoteError::Undecryptable);
+    }
+    let (salt, rest) = sealed[1..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = note_key(code, password, salt)?;
+    let text = cipher
+        .decrypt(Nonce::from_slice(nonce), Payload {
+            msg: ciphertext,
+            aad: content_type.as_bytes(),
+        })
+        .map_err(|_| NoteError::Undecryptable)?;
+
+    Ok(GiftNote {
+        text: String::from_utf8(text).map_err(|_| NoteError::Undecryptable)?,
+        content_type: content_type.to_string(),
+    })
+}
+
+// Non-empty, within the size limit, an allowed type, and free of control
+// characters other than line breaks and tabs
+pub fn validate(note: &GiftNote) -> Result<(), String> {
+    if !NOTE_CONTENT_TYPES.contains(&note.content_type.as_str()) {
+        return Err(format!("Note content type must be one of: {}", NOTE_CONTENT_TYPES.join(", ")));
+    }
+    if note.text.trim().is_empty() {
+        return Err("Note must not be empty".to_string());
+    }
+    if note.text.len() > MAX_NOTE_BYTES {
+        return Err(format!("Note must be at most {} bytes", MAX_NOTE_BYTES));
+    }
+    if note.text.chars().any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t')) {
+        return Err("Note must not contain control characters".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use crate::db::notification_models::NotificationChannel;
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::webhooks;
+use lettre::Address;
+use rand::RngCore;
+use serde_json::json;
+use sqlx::PgPool;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tracing::info;
+
+// What creators are told about; each channel can mute any of these
+pub const CREATOR_EVENTS: [&str; 3] = ["voucher.claimed", "voucher.cancelled", "voucher.expired"];
+
+// Whether the backend may send a creator's webhook to `ip`. Loopback,
+// private, link-local (cloud metadata included), shared, multicast and
+// reserved ranges are refused, and IPv6 forms of IPv4 addresses are judged
+// as the IPv4 address
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_ipv4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ipv4(v4);
+            }
+            let segments = v6.segments();
+            // NAT64 (64:ff9b::/96) carries an IPv4 address in its last 32 bits
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., hi, lo] = segments;
+                return is_public_ipv4(Ipv4Addr::from(((hi as u32) << 16) | lo as u32));
+            }
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00 // unique local
+                || (segments[0] & 0xffc0) == 0xfe80 // link-local
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8)) // documentation
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (b & 0xc0) == 64) // shared address space
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (b & 0xfe) == 18) // benchmarking
+        || a >= 240) // reserved
+}
+
+// The host of a webhook target and the addresses it resolves to, provided
+// every one of them is public. Callers connect to these addresses, so a
+// later lookup cannot point the request elsewhere
+pub async fn resolve_public_target(url: &str) -> Result<(String, Vec<SocketAddr>), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "Invalid webhook URL".to_string())?;
+    let host = parsed.host_str().ok_or("Webhook URL must have a host")?.to_string();
+    let port = parsed.port_or_known_default().ok_or("Webhook URL must have a port")?;
+
+    let lookup = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((lookup, port))
+        .await
+        .map_err(|_| format!("Cannot resolve {}", host))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("Cannot resolve {}", host));
+    }
+    if let Some(blocked) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!("Webhook host {} resolves to non-public address {}", host, blocked.ip()));
+    }
+    Ok((host, addrs))
+}
+
+// Where creators want their voucher notifications sent
+#[derive(Clone)]
+pub struct NotificationChannelService {
+    pool: PgPool,
+    audit: AuditLog,
+    email_enabled: bool,
+    allow_private_targets: bool,
+}
+
+impl NotificationChannelService {
+    pub fn new(pool: PgPool, email_enabled: bool) -> Self {
+        Self {
+            audit: AuditLog::new(pool.clone()),
+            pool,
+            email_enabled,
+            allow_private_targets: false,
+        }
+    }
+
+    pub fn with_private_targets(mut self, allow: bool) -> Self {
+        self.allow_private_targets = allow;
+        self
+    }
+
+    pub fn validate_channel(
+        kind: &str,
+        target: &str,
+        muted_events: &[String],
+        email_enabled: bool,
+    ) -> Result<(), String> {
+        match kind {
+            "webhook" => webhooks::validate_url(target)?,
+            "email" => {
+                if !email_enabled {
+                    return Err("Email notifications are not configured".to_string());
+                }
+                target.parse::<Address>().map_err(|_| "Invalid email address".to_string())?;
+            }
+            _ => return Err(format!("Unknown channel kind: {}", kind)),
+        }
+        if let Some(unknown) = muted_events.iter().find(|e| !CREATOR_EVENTS.contains(&e.as_str())) {
+            return Err(format!("Unknown event: {}", unknown));
+        }
+        Ok(())
+    }
+
+    // Register the creator's channel of this kind, or replace its target and
+    // opt-outs. A webhook channel keeps its signing secret across updates
+    pub async fn set_channel(
+        &self,
+        creator_address: &str,
+        kind: &str,
+        target: &str,
+        muted_events: &[String],
+        ctx: &AuditContext,
+    ) -> Result<NotificationChannel, Box<dyn std::error::Error>> {
+        Self::validate_channel(kind, target, muted_events, self.email_enabled)?;
+        if kind == "webhook" && !self.allow_private_targets {
+            resolve_public_target(target).await?;
+        }
+
+        let mut muted_events = muted_events.to_vec();
+        muted_events.sort();
+        muted_events.dedup();
+
+        let secret = (kind == "webhook").then(|| {
+            let mut secret = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut secret);
+            format!("whsec_{}", hex::encode(secret))
+        });
+
+        let channel: NotificationChannel = sqlx::query_as(
+            r#"
+            INSERT INTO creator_notification_channels (creator_address, kind, target, secret, muted_events)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (creator_address, kind) DO UPDATE
+            SET target = EXCLUDED.target,
+                muted_events = EXCLUDED.muted_events,
+                secret = COALESCE(creator_notification_channels.secret, EXCLUDED.secret),
+                updated_at = NOW()
+            RETURNING *
+            "#
+        )
+        .bind(creator_address.to_lowercase())
+        .bind(kind)
+        .bind(target)
+        .bind(secret)
+        .bind(&muted_events)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "notification_channel.set",
+            "notification_channel",
+            &channel.id.to_string(),
+            None,
+            Some(json!({ "kind": channel.kind, "muted_events": channel.muted_events })),
+        ).await;
+
+        info!("Set {} notification channel for {}", channel.kind, channel.creator_address);
+        Ok(channel)
+    }
+
+    pub async fn list_channels(&self, creator_address: &str) -> Result<Vec<NotificationChannel>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM creator_notification_channels WHERE creator_address = $1 ORDER BY kind")
+            .bind(creator_address.to_lowercase())
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn delete_channel(
+        &self,
+        creator_address: &str,
+        kind: &str,
+        ctx: &AuditContext,
+    ) -> Result<bool, sqlx::Error> {
+        let deleted: Option<(i64,)> = sqlx::query_as(
+            "DELETE FROM creator_notification_channels WHERE creator_address = $1 AND kind = $2 RETURNING id"
+        )
+        .bind(creator_address.to_lowercase())
+        .bind(kind)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some((id,)) = deleted else {
+            return Ok(false);
+        };
+        self.audit.record_or_log(
+            ctx,
+            "notification_channel.deleted",
+            "notification_channel",
+            &id.to_string(),
+            Some(json!({ "kind": kind })),
+            None,
+        ).await;
+        Ok(true)
+    }
+}
@@ -0,0 +1,302 @@
+use crate::config::NotificationConfig;
+use crate::db::notification_models::NotificationChannel;
+use crate::services::mailer::Mailer;
+use crate::services::notification_channels::{resolve_public_target, NotificationChannelService};
+use crate::services::webhooks::sign_payload;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+const RELAY_BATCH: i64 = 100;
+
+// Something a voucher creator should hear about
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatorNotification {
+    pub event: String,
+    pub creator_address: String,
+    pub voucher_id: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+// Subject and plain-text body of the email sent for a notification
+pub fn render_email(notification: &CreatorNotification) -> (String, String) {
+    let subject = match notification.event.as_str() {
+        "voucher.claimed" => "Your NBGN voucher was claimed",
+        "voucher.cancelled" => "Your NBGN voucher was cancelled",
+        "voucher.expired" => "Your NBGN voucher expired unclaimed",
+        _ => "Update on your NBGN voucher",
+    };
+
+    let mut body = format!("Voucher: {}\n", notification.voucher_id);
+    for (key, label) in [
+        ("amount", "Amount"),
+        ("claimed_by", "Claimed by"),
+        ("claimable_until", "Claim window closed"),
+        ("tx_hash", "Transaction"),
+    ] {
+        if let Some(value) = notification.data.get(key).and_then(Value::as_str) {
+            body.push_str(&format!("{}: {}\n", label, value));
+        }
+    }
+    body.push_str(&format!(
+        "\nYou get this email because {} registered this address for voucher notifications. \
+         Mute \"{}\" on the email channel or remove the channel to stop them.\n",
+        notification.creator_address, notification.event
+    ));
+
+    (subject.to_string(), body)
+}
+
+// Delivers creator notifications to the configured webhook, or only logs them,
+// and to whatever channels the creator registered
+#[derive(Clone)]
+pub struct Notifier {
+    client: reqwest::Client,
+    webhook_url: Option<String>,
+    channels: Option<NotificationChannelService>,
+    mailer: Option<Mailer>,
+    allow_private_targets: bool,
+}
+
+impl Notifier {
+    pub fn from_config(config: &NotificationConfig) -> Self {
+        Self {
+            client: Self::client_builder().build().unwrap_or_default(),
+            webhook_url: config.webhook_url.clone(),
+            channels: None,
+            mailer: None,
+            allow_private_targets: config.allow_private_channel_targets,
+        }
+    }
+
+    // Redirects are not followed: a creator's endpoint could otherwise send
+    // us on to an address its own URL was refused for
+    fn client_builder() -> reqwest::ClientBuilder {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+    }
+
+    pub fn with_channels(mut self, channels: NotificationChannelService) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    pub fn with_mailer(mut self, mailer: Mailer) -> Self {
+        self.mailer = Some(mailer);
+        self
+    }
+
+    // Creator channels are best effort; only a failure of the configured
+    // webhook is returned, so callers retry without repeating channel messages
+    pub async fn notify(&self, notification: &CreatorNotification) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(channels) = &self.channels {
+            match channels.list_channels(&notification.creator_address).await {
+                Ok(channels) => {
+                    self.deliver(&channels, notification).await;
+                }
+                Err(e) => error!(
+                    "Failed to load notification channels of {}: {}",
+                    notification.creator_address, e
+                ),
+            }
+        }
+
+        let Some(url) = &self.webhook_url else {
+            info!(
+                "Notification {} for creator {} (voucher {}); no channel configured",
+                notification.event, notification.creator_address, notification.voucher_id
+            );
+            return Ok(());
+        };
+
+        self.client
+            .post(url)
+            .json(notification)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    // Send to every channel that has not muted the event; returns how many
+    // took the message
+    pub async fn deliver(&self, channels: &[NotificationChannel], notification: &CreatorNotification) -> usize {
+        let mut delivered = 0;
+        for channel in channels.iter().filter(|c| !c.muted_events.contains(&notification.event)) {
+            let result = match channel.kind.as_str() {
+                "webhook" => self.post_to_channel(channel, notification).await,
+                "email" => self.email(channel, notification).await,
+                _ => continue,
+            };
+            match result {
+                Ok(()) => delivered += 1,
+                Err(e) => warn!(
+                    "Failed to send {} to {} channel {} of {}: {}",
+                    notification.event, channel.kind, channel.id, channel.creator_address, e
+                ),
+            }
+        }
+        delivered
+    }
+
+    // Signed like integrator webhooks, with the channel's own secret
+    async fn post_to_channel(
+        &self,
+        channel: &NotificationChannel,
+        notification: &CreatorNotification,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let body = serde_json::to_vec(notification)?;
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(channel.secret.as_deref().unwrap_or_default(), timestamp, &body);
+
+        // Checked again on every delivery, as DNS may have changed since
+        // the channel was registered
+        let client = if self.allow_private_targets {
+            self.client.clone()
+        } else {
+            let (host, addrs) = resolve_public_target(&channel.target).await?;
+            Self::client_builder().resolve_to_addrs(&host, &addrs).build()?
+        };
+
+        let response = client
+            .post(&channel.target)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-NBGN-Event", &notification.event)
+            .header("X-NBGN-Timestamp", timestamp.to_string())
+            .header("X-NBGN-Signature", signature)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        // Redirects are not followed, so one is not a delivery
+        if response.status().is_redirection() {
+            return Err(format!("channel answered with a redirect ({})", response.status()).into());
+        }
+
+        Ok(())
+    }
+
+    async fn email(
+        &self,
+        channel: &NotificationChannel,
+        notification: &CreatorNotification,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(mailer) = &self.mailer else {
+            return Err("SMTP is not configured".into());
+        };
+        let (subject, body) = render_email(notification);
+        mailer.send(&channel.target, &subject, body).await
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct RelayedEvent {
+    id: i64,
+    event_type: String,
+    payload: Json<Value>,
+    created_at: Option<DateTime<Utc>>,
+    creator_address: Option<String>,
+}
+
+// Tells creators about claims and cancellations by following the webhook
+// event log, which already holds each of them exactly once
+#[derive(Clone)]
+pub struct NotificationRelay {
+    pool: PgPool,
+    notifier: Notifier,
+}
+
+impl NotificationRelay {
+    pub fn new(pool: PgPool, notifier: Notifier) -> Self {
+        Self { pool, notifier }
+    }
+
+    pub async fn run_relay_loop(&self, interval_secs: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            match self.relay_pending().await {
+                Ok(0) => debug!("No voucher events to relay to creators"),
+                Ok(count) => info!("Relayed {} voucher events to creators", count),
+                Err(e) => error!("Error relaying voucher events: {}", e),
+            }
+        }
+    }
+
+    pub async fn relay_pending(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let events: Vec<RelayedEvent> = sqlx::query_as(
+            r#"
+            SELECT e.id, e.event_type, e.payload, e.created_at,
+                   COALESCE(
+                       (SELECT v.creator_address FROM voucher_codes v
+                        WHERE v.voucher_id = e.payload->>'voucher_id' AND v.creator_address IS NOT NULL
+                        LIMIT 1),
+                       e.payload->>'creator'
+                   ) AS creator_address
+            FROM webhook_events e
+            WHERE e.creator_notified_at IS NULL
+              AND e.event_type IN ('voucher.claimed', 'voucher.cancelled')
+            ORDER BY e.id
+            LIMIT $1
+            "#
+        )
+        .bind(RELAY_BATCH)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut relayed = 0;
+        for event in events {
+            // Mark first so another instance relaying at the same time skips it
+            let marked = sqlx::query(
+                "UPDATE webhook_events SET creator_notified_at = NOW() WHERE id = $1 AND creator_notified_at IS NULL"
+            )
+            .bind(event.id)
+            .execute(&self.pool)
+            .await?;
+            if marked.rows_affected() == 0 {
+                continue;
+            }
+            let Some(creator_address) = event.creator_address else {
+                continue;
+            };
+
+            let Json(data) = event.payload;
+            let notification = CreatorNotification {
+                event: event.event_type,
+                creator_address,
+                voucher_id: data.get("voucher_id").and_then(Value::as_str).unwrap_or_default().to_string(),
+                occurred_at: event.created_at.unwrap_or_else(Utc::now),
+                data,
+            };
+
+            let delivered = match self.notifier.notify(&notification).await {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("Failed to notify creator of {} {}: {}", notification.event, notification.voucher_id, e);
+                    false
+                }
+            };
+            if !delivered {
+                // Leave it for the next pass
+                sqlx::query("UPDATE webhook_events SET creator_notified_at = NULL WHERE id = $1")
+                    .bind(event.id)
+                    .execute(&self.pool)
+                    .await?;
+                continue;
+            }
+            relayed += 1;
+        }
+
+        Ok(relayed)
+    }
+}
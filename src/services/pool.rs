@@ -0,0 +1,424 @@
+use crate::db::pool_models::{ClaimPool, PoolStock};
+use crate::db::voucher_models::{ClaimAuthorization, CreatorAuth};
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::auth;
+use crate::services::codes::CodeGenerator;
+use crate::services::voucher::{VoucherService, DEFAULT_DEADLINE_SECONDS};
+use chrono::DateTime;
+use ethers::types::Address;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+pub const MAX_POOL_VOUCHERS: usize = 1000;
+// Reservations outlive their authorization by this much, so a claim mined
+// just before the deadline is indexed before the voucher is handed out again
+const RESERVATION_GRACE_SECS: i64 = 300;
+
+// What a create_pool signature covers besides the name: the vouchers as
+// sent and the password
+pub fn create_pool_params(voucher_ids: &[String], password: Option<&str>) -> Value {
+    json!({ "voucher_ids": voucher_ids, "password": password })
+}
+
+// Claim pool failures, mapped to distinct statuses by the routes
+#[derive(Debug, thiserror::Error)]
+pub enum PoolError {
+    #[error("Password required")]
+    PasswordRequired,
+    #[error("Invalid password")]
+    InvalidPassword,
+    #[error("No vouchers left in this pool")]
+    Exhausted,
+    #[error("Recipient has already claimed from this pool")]
+    AlreadyClaimed,
+}
+
+// One public code handing out one of many on-chain vouchers per claimer
+#[derive(Clone)]
+pub struct PoolService {
+    pool: PgPool,
+    vouchers: VoucherService,
+    audit: AuditLog,
+    codes: CodeGenerator,
+}
+
+impl PoolService {
+    pub fn new(pool: PgPool, vouchers: VoucherService) -> Self {
+        Self {
+            audit: AuditLog::new(pool.clone()),
+            pool,
+            vouchers,
+            codes: CodeGenerator::default(),
+        }
+    }
+
+    pub fn with_code_generator(mut self, codes: CodeGenerator) -> Self {
+        self.codes = codes;
+        self
+    }
+
+    pub fn normalize_code(&self, code: &str) -> Option<String> {
+        self.codes.normalize(code)
+    }
+
+    pub fn display_code(&self, code: &str) -> String {
+        self.codes.display(code)
+    }
+
+    // Shareable link for a pool, next to the single-voucher claim links
+    pub fn pool_path(&self, code: &str) -> String {
+        format!("/pool/{}", self.display_code(code))
+    }
+
+    pub fn pool_url(&self, code: &str) -> Option<String> {
+        self.vouchers.public_url(&self.pool_path(code))
+    }
+
+    // Create a pool from unclaimed vouchers of the signing creator, handed
+    // out in the order given
+    pub async fn create_pool(
+        &self,
+        name: &str,
+        voucher_ids: &[String],
+        password: Option<&str>,
+        creator: &CreatorAuth,
+        ctx: &AuditContext,
+    ) -> Result<ClaimPool, Box<dyn std::error::Error>> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > 100 {
+            return Err("Pool name must be 1 to 100 characters".into());
+        }
+        if voucher_ids.is_empty() || voucher_ids.len() > MAX_POOL_VOUCHERS {
+            return Err(format!("Between 1 and {} voucher ids are required", MAX_POOL_VOUCHERS).into());
+        }
+
+        let mut ids: Vec<String> = Vec::with_capacity(voucher_ids.len());
+        for voucher_id in voucher_ids {
+            let voucher_id = VoucherService::normalize_voucher_id(voucher_id)?;
+            if !ids.contains(&voucher_id) {
+                ids.push(voucher_id);
+            }
+        }
+
+        auth::verify_action_signature_with_params(
+            &creator.address,
+            "create_pool",
+            name,
+            &create_pool_params(voucher_ids, password),
+            creator.timestamp,
+            &creator.signature,
+        )?;
+        let creator_address = creator.address.to_lowercase();
+
+        // Every voucher must be known, open and the signer's. Pool claims
+        // carry no Merkle proof, so vouchers restricted by a root cannot join
+        let known: Vec<(String, Option<String>, String, bool)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT ON (v.voucher_id) v.voucher_id, v.creator_address, v.status,
+                   r.merkle_root IS NOT NULL
+            FROM voucher_codes v
+            LEFT JOIN voucher_recipient_restrictions r ON r.voucher_id = v.voucher_id
+            WHERE v.voucher_id = ANY($1)
+            ORDER BY v.voucher_id
+            "#
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for voucher_id in &ids {
+            match known.iter().find(|(id, ..)| id == voucher_id) {
+                None => return Err(format!("Voucher {} is not indexed yet", voucher_id).into()),
+                Some((_, owner, ..)) if owner.as_deref().map(str::to_lowercase) != Some(creator_address.clone()) => {
+                    return Err(format!("Voucher {} was not created by the signer", voucher_id).into());
+                }
+                Some((_, _, status, _)) if status != "active" => {
+                    return Err(format!("Voucher {} is {}", voucher_id, status).into());
+                }
+                Some((.., true)) => {
+                    return Err(format!("Voucher {} is restricted by a Merkle root", voucher_id).into());
+                }
+                Some(_) => {}
+            }
+        }
+
+        let password_hash = password.map(VoucherService::hash_password).transpose()?;
+        let code = self.codes.random_code();
+
+        let mut tx = self.pool.begin().await?;
+        let pool: ClaimPool = sqlx::query_as(
+            r#"
+            INSERT INTO claim_pools (code, name, creator_address, password_hash)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, code, name, creator_address, password_hash, created_at
+            "#
+        )
+        .bind(&code)
+        .bind(name)
+        .bind(&creator_address)
+        .bind(&password_hash)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO claim_pool_vouchers (voucher_id, pool_id, position)
+            SELECT voucher_id, $2, position::INTEGER
+            FROM UNNEST($1::TEXT[]) WITH ORDINALITY AS v(voucher_id, position)
+            "#
+        )
+        .bind(&ids)
+        .bind(pool.id)
+        .execute(&mut *tx)
+        .await;
+        match inserted {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(db)) if db.is_unique_violation() => {
+                return Err("A voucher is already in another pool".into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+        tx.commit().await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "pool.created",
+            "claim_pool",
+            &pool.id.to_string(),
+            None,
+            Some(json!({
+                "name": pool.name,
+                "vouchers": ids.len(),
+                "has_password": password_hash.is_some(),
+            })),
+        ).await;
+
+        info!("Created claim pool {} with {} vouchers", pool.id, ids.len());
+        Ok(pool)
+    }
+
+    pub async fn get_pool_by_code(&self, code: &str) -> Result<Option<ClaimPool>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT id, code, name, creator_address, password_hash, created_at FROM claim_pools WHERE code = $1"
+        )
+        .bind(code)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Free reservations whose authorization has run out without a claim
+    async fn release_expired<'e, E>(executor: E, pool_id: i64) -> Result<u64, sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let released = sqlx::query(
+            r#"
+            UPDATE claim_pool_vouchers p
+            SET reserved_by = NULL, reserved_until = NULL
+            WHERE p.pool_id = $1
+              AND p.reserved_until < NOW() - make_interval(secs => $2)
+              AND NOT EXISTS (
+                  SELECT 1 FROM voucher_codes c
+                  WHERE c.voucher_id = p.voucher_id AND (c.claimed = TRUE OR c.claim_tx_status IN ('submitting', 'pending'))
+              )
+            "#
+        )
+        .bind(pool_id)
+        .bind(RESERVATION_GRACE_SECS as f64)
+        .execute(executor)
+        .await?;
+
+        Ok(released.rows_affected())
+    }
+
+    pub async fn stock(&self, pool_id: i64) -> Result<PoolStock, sqlx::Error> {
+        Self::release_expired(&self.pool, pool_id).await?;
+
+        sqlx::query_as(
+            r#"
+            WITH vouchers AS (
+                SELECT p.reserved_by IS NOT NULL AS reserved,
+                       COALESCE(bool_or(c.claimed), FALSE) AS claimed,
+                       -- A withdrawn voucher is as good as cancelled to the pool
+                       COALESCE(bool_or(c.status IN ('cancelled', 'withdrawn')), FALSE) AS cancelled
+                FROM claim_pool_vouchers p
+                LEFT JOIN voucher_codes c ON c.voucher_id = p.voucher_id
+                WHERE p.pool_id = $1
+                GROUP BY p.voucher_id, p.reserved_by
+            )
+            SELECT
+                COUNT(*) AS total,
+                COUNT(*) FILTER (WHERE NOT claimed AND NOT cancelled AND NOT reserved) AS available,
+                COUNT(*) FILTER (WHERE NOT claimed AND NOT cancelled AND reserved) AS reserved,
+                COUNT(*) FILTER (WHERE claimed) AS claimed,
+                COUNT(*) FILTER (WHERE cancelled AND NOT claimed) AS cancelled
+            FROM vouchers
+            "#
+        )
+        .bind(pool_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    // Reserve a voucher of the pool for the recipient and authorize its
+    // claim. A recipient keeps their reservation, and gets it re-signed,
+    // until it expires; once they have claimed they get nothing more
+    pub async fn claim(
+        &self,
+        pool: &ClaimPool,
+        recipient_address: &str,
+        password: Option<&str>,
+        client_ip: &str,
+        ctx: &AuditContext,
+    ) -> Result<ClaimAuthorization, Box<dyn std::error::Error>> {
+        if let Some(password_hash) = &pool.password_hash {
+            let password = password.ok_or(PoolError::PasswordRequired)?;
+            if !VoucherService::verify_password(password, password_hash) {
+                return Err(PoolError::InvalidPassword.into());
+            }
+        }
+
+        let recipient = recipient_address.to_lowercase();
+        let (voucher_id, newly_reserved) = self.reserve(pool.id, &recipient).await?;
+
+        let authorization = match self.authorize(&voucher_id, recipient_address, client_ip).await {
+            Ok(authorization) => authorization,
+            Err(e) => {
+                if newly_reserved {
+                    sqlx::query(
+                        "UPDATE claim_pool_vouchers SET reserved_by = NULL, reserved_until = NULL WHERE voucher_id = $1"
+                    )
+                    .bind(&voucher_id)
+                    .execute(&self.pool)
+                    .await?;
+                }
+                return Err(e);
+            }
+        };
+
+        // The reservation lasts as long as the signature
+        let deadline = DateTime::from_timestamp(authorization.deadline as i64, 0);
+        sqlx::query("UPDATE claim_pool_vouchers SET reserved_until = $1 WHERE voucher_id = $2")
+            .bind(deadline)
+            .bind(&voucher_id)
+            .execute(&self.pool)
+            .await?;
+
+        if newly_reserved {
+            self.audit.record_or_log(
+                ctx,
+                "pool.voucher_reserved",
+                "claim_pool",
+                &pool.id.to_string(),
+                None,
+                Some(json!({ "voucher_id": voucher_id, "recipient": recipient, "reserved_until": deadline })),
+            ).await;
+        }
+
+        Ok(authorization)
+    }
+
+    // The recipient's existing reservation, or the first free voucher that
+    // is open, in its claim window and allowed to go to the recipient. Rows
+    // locked by concurrent claimers are skipped rather than waited on
+    async fn reserve(&self, pool_id: i64, recipient: &str) -> Result<(String, bool), Box<dyn std::error::Error>> {
+        let mut tx = self.pool.begin().await?;
+        Self::release_expired(&mut *tx, pool_id).await?;
+
+        let existing: Option<(String, bool)> = sqlx::query_as(
+            r#"
+            SELECT p.voucher_id,
+                   EXISTS (SELECT 1 FROM voucher_codes c WHERE c.voucher_id = p.voucher_id AND c.claimed = TRUE)
+            FROM claim_pool_vouchers p
+            WHERE p.pool_id = $1 AND p.reserved_by = $2
+            "#
+        )
+        .bind(pool_id)
+        .bind(recipient)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some((voucher_id, claimed)) = existing {
+            if claimed {
+                return Err(PoolError::AlreadyClaimed.into());
+            }
+            tx.commit().await?;
+            return Ok((voucher_id, false));
+        }
+
+        let free: Option<(String,)> = sqlx::query_as(
+            r#"
+            SELECT p.voucher_id FROM claim_pool_vouchers p
+            WHERE p.pool_id = $1
+              AND p.reserved_by IS NULL
+              AND EXISTS (
+                  SELECT 1 FROM voucher_codes c
+                  WHERE c.voucher_id = p.voucher_id
+                    AND c.status = 'active'
+                    AND (c.claimable_from IS NULL OR c.claimable_from <= NOW())
+                    AND (c.claimable_until IS NULL OR c.claimable_until > NOW())
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM voucher_recipient_restrictions r
+                  WHERE r.voucher_id = p.voucher_id
+                    AND (r.merkle_root IS NOT NULL OR NOT ($2 = ANY(r.allowed_addresses)))
+              )
+            ORDER BY p.position
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .bind(pool_id)
+        .bind(recipient)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let (voucher_id,) = free.ok_or(PoolError::Exhausted)?;
+
+        let reserved = sqlx::query(
+            r#"
+            UPDATE claim_pool_vouchers
+            SET reserved_by = $1, reserved_until = NOW() + make_interval(secs => $2)
+            WHERE voucher_id = $3
+            "#
+        )
+        .bind(recipient)
+        .bind(DEFAULT_DEADLINE_SECONDS as f64)
+        .bind(&voucher_id)
+        .execute(&mut *tx)
+        .await;
+        match reserved {
+            Ok(_) => {}
+            // The same recipient reserved concurrently; let that request win
+            Err(sqlx::Error::Database(db)) if db.is_unique_violation() => {
+                warn!("Concurrent pool reservation for {} in pool {}", recipient, pool_id);
+                return Err(PoolError::AlreadyClaimed.into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        tx.commit().await?;
+        Ok((voucher_id, true))
+    }
+
+    async fn authorize(
+        &self,
+        voucher_id: &str,
+        recipient_address: &str,
+        client_ip: &str,
+    ) -> Result<ClaimAuthorization, Box<dyn std::error::Error>> {
+        let voucher = self.vouchers.get_voucher_by_id(voucher_id).await?
+            .ok_or("Pool voucher not found")?;
+        if VoucherService::check_open(&voucher).is_err() {
+            return Err(PoolError::Exhausted.into());
+        }
+        VoucherService::check_claim_window(&voucher)?;
+
+        // An allowlist set after the pool was created still applies
+        let recipient = Address::from_str(recipient_address)?;
+        self.vouchers.check_recipient_allowed(voucher_id, recipient, &[]).await?;
+
+        self.vouchers.authorize_recipient(voucher, recipient_address, client_ip).await
+    }
+}
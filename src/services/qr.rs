@@ -0,0 +1,174 @@
+use qrcode::render::svg;
+use qrcode::{Color, EcLevel, QrCode};
+use serde::Deserialize;
+
+pub const MIN_SIZE: u32 = 64;
+pub const MAX_SIZE: u32 = 2048;
+const DEFAULT_SIZE: u32 = 256;
+// Modules of blank margin on each side, as the QR spec asks
+const QUIET_ZONE: usize = 4;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QrFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+/// How much of the symbol can be damaged and still scan: about 7, 15, 25
+/// or 30 percent. Higher levels make denser codes
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QrErrorCorrection {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+impl From<QrErrorCorrection> for EcLevel {
+    fn from(level: QrErrorCorrection) -> Self {
+        match level {
+            QrErrorCorrection::L => EcLevel::L,
+            QrErrorCorrection::M => EcLevel::M,
+            QrErrorCorrection::Q => EcLevel::Q,
+            QrErrorCorrection::H => EcLevel::H,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+pub struct QrOptions {
+    #[serde(default)]
+    pub format: QrFormat,
+    /// Minimum width and height in pixels, quiet zone included
+    pub size: Option<u32>,
+    #[serde(default)]
+    pub ec: QrErrorCorrection,
+}
+
+impl QrOptions {
+    pub fn size(&self) -> u32 {
+        self.size.unwrap_or(DEFAULT_SIZE)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self.format {
+            QrFormat::Svg => "image/svg+xml",
+            QrFormat::Png => "image/png",
+        }
+    }
+}
+
+// Render `data` as a QR code in the requested format
+pub fn render(data: &str, options: &QrOptions) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let size = options.size();
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        return Err(format!("size must be between {} and {} pixels", MIN_SIZE, MAX_SIZE).into());
+    }
+
+    let code = QrCode::with_error_correction_level(data, options.ec.into())?;
+    match options.format {
+        QrFormat::Svg => Ok(render_svg(&code, size).into_bytes()),
+        QrFormat::Png => render_png(&code, size),
+    }
+}
+
+fn render_svg(code: &QrCode, size: u32) -> String {
+    code.render::<svg::Color>()
+        .min_dimensions(size, size)
+        .quiet_zone(true)
+        .build()
+}
+
+// Grayscale PNG scaled by whole pixels per module, so edges stay sharp
+fn render_png(code: &QrCode, size: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let modules = code.width();
+    let colors = code.to_colors();
+    let total = modules + 2 * QUIET_ZONE;
+    let scale = (size as usize).div_ceil(total);
+    let pixels = total * scale;
+
+    let mut image = vec![255u8; pixels * pixels];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let (x, y) = (i % modules + QUIET_ZONE, i / modules + QUIET_ZONE);
+        for row in y * scale..(y + 1) * scale {
+            image[row * pixels + x * scale..row * pixels + (x + 1) * scale].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, pixels as u32, pixels as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image)?;
+    writer.finish()?;
+
+    Ok(out)
+}
+
+// One voucher on a printable sheet
+pub struct SheetItem {
+    pub url: String,
+    pub code: String,
+    pub caption: String,
+}
+
+// HTML page of QR codes with their codes and captions, laid out to print
+// several per A4 page
+pub fn printable_sheet(title: &str, items: &[SheetItem]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut cards = String::new();
+    for item in items {
+        let code = QrCode::with_error_correction_level(&item.url, EcLevel::M)?;
+        cards.push_str(&format!(
+            "<div class=\"card\">{}<div class=\"code\">{}</div><div class=\"caption\">{}</div></div>\n",
+            // Inline SVG in HTML needs no XML declaration
+            render_svg(&code, 192).split_once("<svg").map(|(_, svg)| format!("<svg{}", svg)).unwrap_or_default(),
+            html_escape(&item.code),
+            html_escape(&item.caption),
+        ));
+    }
+
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+@page {{ size: A4; margin: 10mm; }}
+body {{ font-family: sans-serif; margin: 0; }}
+h1 {{ font-size: 14pt; }}
+.sheet {{ display: grid; grid-template-columns: repeat(3, 1fr); gap: 6mm; }}
+.card {{ border: 1px dashed #999; padding: 4mm; text-align: center; break-inside: avoid; }}
+.card svg {{ width: 45mm; height: 45mm; }}
+.code {{ font-family: monospace; font-size: 11pt; margin-top: 2mm; }}
+.caption {{ font-size: 9pt; color: #444; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div class="sheet">
+{cards}</div>
+</body>
+</html>
+"#,
+        title = html_escape(title),
+        cards = cards,
+    ))
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
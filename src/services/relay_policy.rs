@@ -0,0 +1,118 @@
+use crate::config::RelayConfig;
+use async_trait::async_trait;
+use ethers::types::U256;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayKind {
+    Claim,
+    Creation,
+}
+
+impl RelayKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RelayKind::Claim => "claim",
+            RelayKind::Creation => "creation",
+        }
+    }
+}
+
+// A transaction the relayer is asked to pay gas for
+#[derive(Debug, Clone)]
+pub struct RelayRequest {
+    pub kind: RelayKind,
+    // Voucher code or creation intent hash; None for quotes
+    pub subject: Option<String>,
+    // The voucher's creator, for claims too
+    pub creator: String,
+    pub recipient: Option<String>,
+    pub campaign_id: Option<i64>,
+    pub amount: U256,
+    // Gas limit the transaction is priced at against spend limits
+    pub gas_limit: u64,
+    pub client_ip: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Sponsor { reason: String },
+    // Relay, but only with at least this fee in NBGN wei
+    ChargeFee { fee: U256, reason: String },
+    Reject { reason: String },
+}
+
+impl PolicyDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyDecision::Sponsor { .. } => "sponsored",
+            PolicyDecision::ChargeFee { .. } => "fee",
+            PolicyDecision::Reject { .. } => "rejected",
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        match self {
+            PolicyDecision::Sponsor { reason }
+            | PolicyDecision::ChargeFee { reason, .. }
+            | PolicyDecision::Reject { reason } => reason,
+        }
+    }
+
+    pub fn fee(&self) -> U256 {
+        match self {
+            PolicyDecision::ChargeFee { fee, .. } => *fee,
+            _ => U256::zero(),
+        }
+    }
+}
+
+// Decides whether the relayer pays for a transaction, and at what fee
+#[async_trait]
+pub trait RelayPolicy: Send + Sync {
+    async fn evaluate(&self, request: &RelayRequest) -> PolicyDecision;
+
+    // Evaluate a transaction that is about to be relayed; policies that keep
+    // quotas record the decision here, which `evaluate` alone never does
+    async fn decide(&self, request: &RelayRequest) -> PolicyDecision {
+        self.evaluate(request).await
+    }
+
+    // A decided transaction that failed before it was sent; policies that
+    // keep quotas stop counting it
+    async fn unsent(&self, _request: &RelayRequest, _reason: &str) {}
+}
+
+// Sponsors every creation, or charges each the configured flat fee
+pub struct StaticRelayPolicy {
+    enabled: bool,
+    creation_fee: U256,
+}
+
+impl StaticRelayPolicy {
+    pub fn from_config(config: &RelayConfig) -> Result<Self, String> {
+        let creation_fee = U256::from_dec_str(&config.creation_fee)
+            .map_err(|_| format!("Invalid relay.creation_fee: {}", config.creation_fee))?;
+        Ok(Self { enabled: config.enabled, creation_fee })
+    }
+}
+
+#[async_trait]
+impl RelayPolicy for StaticRelayPolicy {
+    async fn evaluate(&self, request: &RelayRequest) -> PolicyDecision {
+        if !self.enabled {
+            info!(
+                "Refusing relayed creation of {} for {} from {}: relaying is disabled",
+                request.amount, request.creator, request.client_ip
+            );
+            PolicyDecision::Reject { reason: "relayed creation is disabled".to_string() }
+        } else if self.creation_fee.is_zero() {
+            PolicyDecision::Sponsor { reason: "creations are sponsored".to_string() }
+        } else {
+            PolicyDecision::ChargeFee {
+                fee: self.creation_fee,
+                reason: "flat creation fee".to_string(),
+            }
+        }
+    }
+}
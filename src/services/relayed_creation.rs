@@ -0,0 +1,361 @@
+use crate::contracts::nbgn::{NBGNCalls, NoncesCall};
+use crate::contracts::voucher::{CreateVoucherWithPermitCall, VoucherContract};
+use crate::db::relay_models::*;
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::relay_policy::{PolicyDecision, RelayKind, RelayPolicy, RelayRequest};
+use crate::services::signer::SignerRegistry;
+use crate::services::voucher::CHAIN_ID;
+use chrono::Utc;
+use ethers::prelude::*;
+use ethers::utils::{hash_message, keccak256};
+use serde_json::json;
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// Headroom on the creation gas estimate
+const GAS_HEADROOM_PERCENT: u64 = 20;
+
+// Gas a relayed creation is priced at against sponsorship spend limits
+const CREATE_WITH_PERMIT_GAS: u64 = 250_000;
+
+// Earliest a permit may expire, so the transaction has time to be mined
+const MIN_DEADLINE_SECS: i64 = 60;
+
+// Relayed creation failures that clients must be able to tell apart
+#[derive(Debug, thiserror::Error)]
+pub enum RelayError {
+    #[error("Invalid amount")]
+    InvalidAmount,
+    #[error("Deadline must be between {MIN_DEADLINE_SECS} and {max_secs} seconds from now")]
+    InvalidDeadline { max_secs: i64 },
+    #[error("Invalid permit signature")]
+    InvalidPermitSignature,
+    #[error("Creation intent was not signed by the creator")]
+    InvalidIntentSignature,
+    #[error("Relaying refused: {0}")]
+    Rejected(String),
+    #[error("Relayer fee too low; {required} required")]
+    FeeTooLow { required: U256 },
+    #[error("This creation intent was already submitted")]
+    AlreadySubmitted { tx_hash: Option<String> },
+    #[error("Creation would revert: {0}")]
+    WouldRevert(String),
+}
+
+// The creation intent the creator signs (personal_sign), packed like the
+// claim authorization: keccak256(abi.encodePacked(creator, amount,
+// relayerFee, deadline, address(this), block.chainid))
+pub fn creation_intent_hash(
+    creator: Address,
+    amount: U256,
+    relayer_fee: U256,
+    deadline: U256,
+    voucher_contract: Address,
+) -> H256 {
+    let mut encoded = Vec::new();
+    encoded.extend_from_slice(creator.as_bytes());
+    encoded.extend_from_slice(&<[u8; 32]>::from(amount));
+    encoded.extend_from_slice(&<[u8; 32]>::from(relayer_fee));
+    encoded.extend_from_slice(&<[u8; 32]>::from(deadline));
+    encoded.extend_from_slice(voucher_contract.as_bytes());
+    encoded.extend_from_slice(&<[u8; 32]>::from(U256::from(CHAIN_ID)));
+    H256::from(keccak256(&encoded))
+}
+
+// The intent hash, once `signature` is shown to be the creator's over it
+pub fn verify_intent(
+    creator: Address,
+    amount: U256,
+    relayer_fee: U256,
+    deadline: U256,
+    voucher_contract: Address,
+    signature: &str,
+) -> Result<H256, RelayError> {
+    let signature = Signature::from_str(signature).map_err(|_| RelayError::InvalidIntentSignature)?;
+    let intent_hash = creation_intent_hash(creator, amount, relayer_fee, deadline, voucher_contract);
+    if signature.recover(hash_message(intent_hash)).ok() != Some(creator) {
+        return Err(RelayError::InvalidIntentSignature);
+    }
+    Ok(intent_hash)
+}
+
+// Whether deployed bytecode dispatches on `selector`. Solidity compares the
+// calldata selector against a PUSH4 of each external function's
+pub fn bytecode_has_selector(code: &[u8], selector: [u8; 4]) -> bool {
+    code.windows(5).any(|window| window[0] == 0x63 && window[1..] == selector)
+}
+
+// The ABIs above are written by hand, so relaying needs a voucher contract
+// with createVoucherWithPermit and an NBGN token with EIP-2612 nonces;
+// older deployments have neither
+pub async fn contracts_support_relay(
+    provider: &Provider<Http>,
+    voucher_contract: Address,
+    nbgn_contract: Address,
+) -> Result<bool, ProviderError> {
+    let voucher_code = provider.get_code(voucher_contract, None).await?;
+    let nbgn_code = provider.get_code(nbgn_contract, None).await?;
+    Ok(bytecode_has_selector(&voucher_code, CreateVoucherWithPermitCall::selector())
+        && bytecode_has_selector(&nbgn_code, NoncesCall::selector()))
+}
+
+fn parse_amount(amount: &str) -> Result<U256, RelayError> {
+    match U256::from_dec_str(amount.trim()) {
+        Ok(amount) if !amount.is_zero() => Ok(amount),
+        _ => Err(RelayError::InvalidAmount),
+    }
+}
+
+fn creation_request(creator: Address, amount: U256, intent_hash: Option<H256>, client_ip: &str) -> RelayRequest {
+    RelayRequest {
+        kind: RelayKind::Creation,
+        subject: intent_hash.map(|hash| format!("{:?}", hash)),
+        creator: format!("{:?}", creator),
+        recipient: None,
+        campaign_id: None,
+        amount,
+        gas_limit: CREATE_WITH_PERMIT_GAS,
+        client_ip: client_ip.to_string(),
+    }
+}
+
+// Gasless voucher creation: the backend relayer submits the creator's permit
+// and creation in one createVoucherWithPermit transaction
+#[derive(Clone)]
+pub struct RelayedCreationService {
+    pool: PgPool,
+    signers: SignerRegistry,
+    provider: Arc<Provider<Http>>,
+    voucher_contract: Address,
+    nbgn_contract: Address,
+    policy: Arc<dyn RelayPolicy>,
+    max_deadline_secs: i64,
+    audit: AuditLog,
+}
+
+impl RelayedCreationService {
+    pub fn new(
+        pool: PgPool,
+        signers: SignerRegistry,
+        provider: Arc<Provider<Http>>,
+        voucher_contract: Address,
+        nbgn_contract: Address,
+        policy: Arc<dyn RelayPolicy>,
+    ) -> Self {
+        Self {
+            audit: AuditLog::new(pool.clone()),
+            pool,
+            signers,
+            provider,
+            voucher_contract,
+            nbgn_contract,
+            policy,
+            max_deadline_secs: 24 * 3600,
+        }
+    }
+
+    pub fn with_max_deadline(mut self, max_deadline_secs: i64) -> Self {
+        self.max_deadline_secs = max_deadline_secs;
+        self
+    }
+
+    pub fn check_deadline(&self, deadline: i64) -> Result<(), RelayError> {
+        let remaining = deadline - Utc::now().timestamp();
+        if !(MIN_DEADLINE_SECS..=self.max_deadline_secs).contains(&remaining) {
+            return Err(RelayError::InvalidDeadline { max_secs: self.max_deadline_secs });
+        }
+        Ok(())
+    }
+
+    // Quotes only evaluate the policy; a submission with its intent hash is
+    // the decision the policy records
+    async fn decide(&self, request: &RelayRequest) -> Result<PolicyDecision, RelayError> {
+        let decision = match request.subject {
+            Some(_) => self.policy.decide(request).await,
+            None => self.policy.evaluate(request).await,
+        };
+
+        if let PolicyDecision::Reject { reason } = decision {
+            return Err(RelayError::Rejected(reason));
+        }
+        Ok(decision)
+    }
+
+    // The fee, permit value and nonce the creator needs to sign
+    pub async fn quote(
+        &self,
+        creator: Address,
+        amount: &str,
+        client_ip: &str,
+    ) -> Result<CreationQuote, Box<dyn std::error::Error>> {
+        let amount = parse_amount(amount)?;
+        let decision = self.decide(&creation_request(creator, amount, None, client_ip)).await?;
+        let relayer_fee = decision.fee();
+
+        let permit_nonce = NBGNCalls::new(self.nbgn_contract, self.provider.clone())
+            .nonces(creator)
+            .call()
+            .await?;
+
+        Ok(CreationQuote {
+            sponsored: relayer_fee.is_zero(),
+            relayer_fee: relayer_fee.to_string(),
+            reason: decision.reason().to_string(),
+            chain_id: CHAIN_ID,
+            voucher_contract: format!("{:?}", self.voucher_contract),
+            permit_token: format!("{:?}", self.nbgn_contract),
+            permit_value: (amount + relayer_fee).to_string(),
+            permit_nonce: permit_nonce.to_string(),
+            max_deadline: Utc::now().timestamp() + self.max_deadline_secs,
+        })
+    }
+
+    pub async fn submit(
+        &self,
+        req: &RelayedCreationRequest,
+        client_ip: &str,
+        ctx: &AuditContext,
+    ) -> Result<RelayedCreation, Box<dyn std::error::Error>> {
+        let creator = Address::from_str(&req.creator).map_err(|_| "Invalid creator address")?;
+        let amount = parse_amount(&req.amount)?;
+        let relayer_fee = U256::from_dec_str(req.relayer_fee.trim()).map_err(|_| RelayError::InvalidAmount)?;
+        self.check_deadline(req.deadline)?;
+        let deadline = U256::from(req.deadline);
+
+        let permit = Signature::from_str(&req.permit_signature).map_err(|_| RelayError::InvalidPermitSignature)?;
+        let intent_hash = verify_intent(
+            creator,
+            amount,
+            relayer_fee,
+            deadline,
+            self.voucher_contract,
+            &req.intent_signature,
+        )?;
+
+        let request = creation_request(creator, amount, Some(intent_hash), client_ip);
+        let decision = self.decide(&request).await?;
+        if relayer_fee < decision.fee() {
+            return Err(RelayError::FeeTooLow { required: decision.fee() }.into());
+        }
+
+        // Claim the intent before spending gas on it; keyed by the signed
+        // message since a signature can be re-encoded
+        let intent_hash = format!("{:?}", intent_hash);
+        let inserted: Option<RelayedCreation> = sqlx::query_as(
+            r#"
+            INSERT INTO relayed_creations
+                (creator_address, amount, relayer_fee, deadline, intent_hash,
+                 policy_decision, policy_reason, client_ip)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (intent_hash) DO NOTHING
+            RETURNING *
+            "#
+        )
+        .bind(format!("{:?}", creator))
+        .bind(amount.to_string())
+        .bind(relayer_fee.to_string())
+        .bind(req.deadline)
+        .bind(&intent_hash)
+        .bind(decision.as_str())
+        .bind(decision.reason())
+        .bind(client_ip)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(creation) = inserted else {
+            self.policy.unsent(&request, "intent already submitted").await;
+            let existing: Option<(Option<String>,)> = sqlx::query_as(
+                "SELECT tx_hash FROM relayed_creations WHERE intent_hash = $1"
+            )
+            .bind(&intent_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+            return Err(RelayError::AlreadySubmitted { tx_hash: existing.and_then(|(hash,)| hash) }.into());
+        };
+
+        let client = Arc::new(SignerMiddleware::new(self.provider.clone(), self.signers.signing()));
+        let contract = VoucherContract::new(self.voucher_contract, client);
+        let call = contract.create_voucher_with_permit(
+            creator,
+            amount,
+            relayer_fee,
+            deadline,
+            permit.v as u8,
+            permit.r.into(),
+            permit.s.into(),
+            hex::decode(req.intent_signature.trim_start_matches("0x"))?.into(),
+        );
+
+        // A bad permit, low balance or used nonce shows up here, before any gas is spent
+        let gas = match call.estimate_gas().await {
+            Ok(estimate) => estimate * (100 + GAS_HEADROOM_PERCENT) / 100,
+            Err(e) => {
+                let reason = e.to_string();
+                self.policy.unsent(&request, &reason).await;
+                self.mark_failed(creation.id, &reason).await?;
+                return Err(RelayError::WouldRevert(reason).into());
+            }
+        };
+        let tx_hash = match call.gas(gas).send().await {
+            Ok(pending) => format!("{:?}", pending.tx_hash()),
+            Err(e) => {
+                let reason = e.to_string();
+                self.policy.unsent(&request, &reason).await;
+                self.mark_failed(creation.id, &reason).await?;
+                return Err(format!("Failed to submit creation: {}", reason).into());
+            }
+        };
+
+        let creation: RelayedCreation = sqlx::query_as(
+            r#"
+            UPDATE relayed_creations
+            SET tx_hash = $1, submitted_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#
+        )
+        .bind(&tx_hash)
+        .bind(creation.id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "voucher.creation_relayed",
+            "relayed_creation",
+            &creation.id.to_string(),
+            None,
+            Some(json!({
+                "creator": creation.creator_address,
+                "amount": creation.amount,
+                "relayer_fee": creation.relayer_fee,
+                "policy_decision": creation.policy_decision,
+                "tx_hash": tx_hash,
+                "tx_status": "pending",
+            })),
+        ).await;
+
+        info!("Relayed voucher creation {} for {} in tx {}", creation.id, creation.creator_address, tx_hash);
+        Ok(creation)
+    }
+
+    async fn mark_failed(&self, id: i64, reason: &str) -> Result<(), sqlx::Error> {
+        warn!("Relayed creation {} failed before submission: {}", id, reason);
+        sqlx::query(
+            "UPDATE relayed_creations SET tx_status = 'failed', error = $1, settled_at = NOW() WHERE id = $2"
+        )
+        .bind(reason)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_creation(&self, id: i64) -> Result<Option<RelayedCreation>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM relayed_creations WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+}
@@ -0,0 +1,289 @@
+use crate::config::{BackendConfig, SignerConfig, SignerKind, SignerRole};
+use async_trait::async_trait;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::utils::rlp::Rlp;
+use serde_json::json;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignerError {
+    #[error(transparent)]
+    Wallet(#[from] WalletError),
+    #[error("Remote signer request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Remote signer error: {0}")]
+    Remote(String),
+    #[error("Invalid signer configuration: {0}")]
+    Config(String),
+    #[error("{0} is not supported by the remote signer")]
+    Unsupported(&'static str),
+    #[error("Signer rotation error: {0}")]
+    Rotation(String),
+}
+
+// Backend key used for claim signatures and relayed transactions
+#[derive(Clone, Debug)]
+pub enum BackendSigner {
+    // Raw private key or a decrypted JSON keystore
+    Local(LocalWallet),
+    Remote(RemoteSigner),
+}
+
+impl BackendSigner {
+    pub fn from_config(config: &SignerConfig, chain_id: u64) -> Result<Self, SignerError> {
+        match config.signer {
+            SignerKind::RawKey => {
+                let key = config.private_key.as_deref()
+                    .ok_or_else(|| SignerError::Config("backend.private_key is required for the raw_key signer".into()))?;
+                let wallet = key.parse::<LocalWallet>()?;
+                Ok(Self::Local(wallet.with_chain_id(chain_id)))
+            }
+            SignerKind::Keystore => {
+                let path = config.keystore_path.as_deref()
+                    .ok_or_else(|| SignerError::Config("backend.keystore_path is required for the keystore signer".into()))?;
+                let password = config.keystore_password.as_deref()
+                    .ok_or_else(|| SignerError::Config("backend.keystore_password is required for the keystore signer".into()))?;
+                let wallet = LocalWallet::decrypt_keystore(path, password)?;
+                Ok(Self::Local(wallet.with_chain_id(chain_id)))
+            }
+            SignerKind::Remote => {
+                let url = config.remote_signer_url.as_deref()
+                    .ok_or_else(|| SignerError::Config("backend.remote_signer_url is required for the remote signer".into()))?;
+                let address = config.remote_signer_address.as_deref()
+                    .ok_or_else(|| SignerError::Config("backend.remote_signer_address is required for the remote signer".into()))?;
+                let address = Address::from_str(address)
+                    .map_err(|e| SignerError::Config(format!("invalid remote_signer_address: {}", e)))?;
+                let identifier = config.remote_signer_identifier.clone()
+                    .unwrap_or_else(|| format!("{:?}", address));
+                Ok(Self::Remote(RemoteSigner::new(url, &identifier, address, chain_id)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for BackendSigner {
+    type Error = SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(&self, message: S) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            Self::Remote(remote) => remote.sign_message(message.as_ref()).await,
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(wallet) => Ok(wallet.sign_transaction(tx).await?),
+            Self::Remote(remote) => remote.sign_transaction(tx).await,
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(&self, payload: &T) -> Result<Signature, Self::Error> {
+        match self {
+            Self::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            Self::Remote(_) => Err(SignerError::Unsupported("Typed data signing")),
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            Self::Local(wallet) => wallet.address(),
+            Self::Remote(remote) => remote.address,
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            Self::Local(wallet) => wallet.chain_id(),
+            Self::Remote(remote) => remote.chain_id,
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            Self::Local(wallet) => Self::Local(wallet.with_chain_id(chain_id)),
+            Self::Remote(remote) => Self::Remote(RemoteSigner { chain_id: chain_id.into(), ..remote }),
+        }
+    }
+}
+
+// All configured backend keys and their rotation roles
+#[derive(Clone, Debug)]
+pub struct SignerRegistry {
+    signers: Arc<RwLock<Vec<(BackendSigner, SignerRole)>>>,
+}
+
+impl SignerRegistry {
+    pub fn new(signers: Vec<(BackendSigner, SignerRole)>) -> Result<Self, SignerError> {
+        Self::validate(&signers)?;
+        Ok(Self { signers: Arc::new(RwLock::new(signers)) })
+    }
+
+    pub fn from_config(config: &BackendConfig, chain_id: u64) -> Result<Self, SignerError> {
+        let configs = std::iter::once(&config.key)
+            .filter(|key| key.is_configured())
+            .chain(config.signers.iter());
+
+        let signers = configs
+            .map(|key| Ok((BackendSigner::from_config(key, chain_id)?, key.role)))
+            .collect::<Result<Vec<_>, SignerError>>()?;
+
+        Self::new(signers)
+    }
+
+    fn validate(signers: &[(BackendSigner, SignerRole)]) -> Result<(), SignerError> {
+        let signing = signers.iter().filter(|(_, role)| *role == SignerRole::Signing).count();
+        if signing != 1 {
+            return Err(SignerError::Config(format!(
+                "exactly one backend signer must have the signing role, found {}", signing
+            )));
+        }
+
+        let mut addresses: Vec<Address> = signers.iter().map(|(signer, _)| signer.address()).collect();
+        addresses.sort();
+        addresses.dedup();
+        if addresses.len() != signers.len() {
+            return Err(SignerError::Config("backend signers must have distinct addresses".into()));
+        }
+
+        Ok(())
+    }
+
+    // The key currently signing authorizations and relayed transactions
+    pub fn signing(&self) -> BackendSigner {
+        let signers = self.signers.read().unwrap();
+        signers.iter()
+            .find(|(_, role)| *role == SignerRole::Signing)
+            .map(|(signer, _)| signer.clone())
+            .expect("registry always holds a signing key")
+    }
+
+    pub fn role_of(&self, address: Address) -> Option<SignerRole> {
+        let signers = self.signers.read().unwrap();
+        signers.iter()
+            .find(|(signer, _)| signer.address() == address)
+            .map(|(_, role)| *role)
+    }
+
+    pub fn list(&self) -> Vec<(Address, SignerRole)> {
+        let signers = self.signers.read().unwrap();
+        signers.iter().map(|(signer, role)| (signer.address(), *role)).collect()
+    }
+
+    // Change a key's role. Promoting a key to signing demotes the current
+    // signing key to verifying; the signing key itself can only be replaced
+    pub fn set_role(&self, address: Address, role: SignerRole) -> Result<(), SignerError> {
+        let mut signers = self.signers.write().unwrap();
+        let mut updated = signers.clone();
+
+        let index = updated.iter()
+            .position(|(signer, _)| signer.address() == address)
+            .ok_or_else(|| SignerError::Rotation(format!("{:?} is not a configured backend signer", address)))?;
+
+        if role == SignerRole::Signing {
+            for (_, current) in updated.iter_mut().filter(|(_, r)| *r == SignerRole::Signing) {
+                *current = SignerRole::Verifying;
+            }
+        } else if updated[index].1 == SignerRole::Signing {
+            return Err(SignerError::Rotation("activate another key before demoting the signing key".into()));
+        }
+        updated[index].1 = role;
+
+        Self::validate(&updated)?;
+        *signers = updated;
+        Ok(())
+    }
+}
+
+// Client for a Web3Signer instance holding the backend key
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: String,
+    // Key identifier in the Web3Signer URL path (its public key)
+    identifier: String,
+    address: Address,
+    chain_id: u64,
+}
+
+impl RemoteSigner {
+    pub fn new(url: &str, identifier: &str, address: Address, chain_id: u64) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            identifier: identifier.to_string(),
+            address,
+            chain_id,
+        }
+    }
+
+    // POST /api/v1/eth1/sign/{identifier} signs data with the EIP-191 prefix, like eth_sign
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let response = self.client
+            .post(format!("{}/api/v1/eth1/sign/{}", self.url, self.identifier))
+            .json(&json!({ "data": format!("0x{}", hex::encode(message)) }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(SignerError::Remote(format!("{}: {}", status, body)));
+        }
+
+        Signature::from_str(body.trim())
+            .map_err(|e| SignerError::Remote(format!("invalid signature returned: {}", e)))
+    }
+
+    // eth_signTransaction returns the signed RLP; the signature is decoded back out of it
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, SignerError> {
+        let mut params = json!({
+            "from": self.address,
+            "to": tx.to_addr(),
+            "gas": tx.gas(),
+            "value": tx.value(),
+            "data": tx.data(),
+            "nonce": tx.nonce(),
+            "chainId": U64::from(tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id)),
+        });
+        match tx {
+            TypedTransaction::Eip1559(inner) => {
+                params["maxFeePerGas"] = json!(inner.max_fee_per_gas);
+                params["maxPriorityFeePerGas"] = json!(inner.max_priority_fee_per_gas);
+            }
+            _ => {
+                params["gasPrice"] = json!(tx.gas_price());
+            }
+        }
+
+        let response: serde_json::Value = self.client
+            .post(&self.url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_signTransaction",
+                "params": [params],
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            return Err(SignerError::Remote(error.to_string()));
+        }
+
+        let signed = response["result"].as_str()
+            .ok_or_else(|| SignerError::Remote("missing result".into()))?;
+        let raw = hex::decode(signed.trim_start_matches("0x"))
+            .map_err(|e| SignerError::Remote(format!("invalid signed transaction: {}", e)))?;
+        let (_, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw))
+            .map_err(|e| SignerError::Remote(format!("invalid signed transaction: {}", e)))?;
+
+        Ok(signature)
+    }
+}
@@ -0,0 +1,395 @@
+use crate::config::{RelayConfig, SponsorshipConfig};
+use crate::db::sponsorship_models::{CampaignSponsorship, DecisionQuery, SponsorshipDecision};
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::relay_policy::{PolicyDecision, RelayKind, RelayPolicy, RelayRequest, StaticRelayPolicy};
+use async_trait::async_trait;
+use ethers::prelude::*;
+use serde_json::json;
+use sqlx::pool::PoolConnection;
+use sqlx::{PgConnection, PgPool, Postgres};
+use std::sync::Arc;
+use tracing::{error, info};
+
+// A policy decision with the rule that made it and the gas it was priced at
+struct Verdict {
+    decision: PolicyDecision,
+    rule: Option<&'static str>,
+    estimated_cost: Option<U256>,
+}
+
+impl Verdict {
+    fn reject(rule: &'static str, reason: String, estimated_cost: Option<U256>) -> Self {
+        Self { decision: PolicyDecision::Reject { reason }, rule: Some(rule), estimated_cost }
+    }
+}
+
+// Where the rules read from: the transaction a decision is taken in, or
+// for a mere evaluation a connection taken once a rule needs one
+enum RuleSource<'t> {
+    Transaction(&'t mut PgConnection),
+    Pool(&'t PgPool, Option<Box<PoolConnection<Postgres>>>),
+}
+
+impl RuleSource<'_> {
+    async fn conn(&mut self) -> Result<&mut PgConnection, sqlx::Error> {
+        match self {
+            RuleSource::Transaction(conn) => Ok(&mut **conn),
+            RuleSource::Pool(pool, slot) => {
+                if slot.is_none() {
+                    *slot = Some(Box::new(pool.acquire().await?));
+                }
+                Ok(&mut ***slot.as_mut().unwrap())
+            }
+        }
+    }
+}
+
+fn parse_wei(value: &str, field: &str) -> Result<U256, String> {
+    U256::from_dec_str(value.trim()).map_err(|_| format!("Invalid sponsorship.{}: {}", field, value))
+}
+
+// Decides which relayed claims and creations the backend pays gas for.
+// Creations the relay config charges a fee for, or refuses, are left to it;
+// everything else must pass the minimum amount, the daily recipient and
+// creator quotas, the campaign budget and the global daily spend cap, in
+// that order. Every decision taken for a relayed transaction is recorded
+// with its rule and reason; decisions are taken one at a time, so
+// concurrent requests cannot all pass the same remaining quota, and a
+// sponsored transaction that is never sent is marked unsent to free it
+#[derive(Clone)]
+pub struct SponsorshipPolicy {
+    pool: PgPool,
+    provider: Option<Arc<Provider<Http>>>,
+    creation: Arc<StaticRelayPolicy>,
+    min_amount: U256,
+    recipient_daily_quota: i64,
+    creator_daily_quota: i64,
+    daily_spend_cap: U256,
+    audit: AuditLog,
+}
+
+impl SponsorshipPolicy {
+    pub fn from_config(pool: PgPool, config: &SponsorshipConfig, relay: &RelayConfig) -> Result<Self, String> {
+        Ok(Self {
+            audit: AuditLog::new(pool.clone()),
+            pool,
+            provider: None,
+            creation: Arc::new(StaticRelayPolicy::from_config(relay)?),
+            min_amount: parse_wei(&config.min_amount, "min_amount")?,
+            recipient_daily_quota: config.recipient_daily_quota,
+            creator_daily_quota: config.creator_daily_quota,
+            daily_spend_cap: parse_wei(&config.daily_spend_cap_wei, "daily_spend_cap_wei")?,
+        })
+    }
+
+    // Prices transactions against budgets and the spend cap; without it
+    // only the amount and quota rules can pass
+    pub fn with_provider(mut self, provider: Arc<Provider<Http>>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    async fn estimate_cost(&self, gas_limit: u64) -> Option<U256> {
+        let provider = self.provider.as_ref()?;
+        match provider.get_gas_price().await {
+            Ok(gas_price) => Some(gas_price * gas_limit),
+            Err(e) => {
+                error!("Failed to read gas price for sponsorship: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn sponsored_today(source: &mut RuleSource<'_>, column: &str, address: &str) -> Result<i64, sqlx::Error> {
+        let (count,): (i64,) = sqlx::query_as(&format!(
+            r#"
+            SELECT COUNT(*) FROM sponsorship_decisions
+            WHERE decision = 'sponsored'
+              AND LOWER({}) = LOWER($1)
+              AND created_at >= date_trunc('day', NOW() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC'
+            "#,
+            column
+        ))
+        .bind(address)
+        .fetch_one(source.conn().await?)
+        .await?;
+        Ok(count)
+    }
+
+    async fn spent_today(source: &mut RuleSource<'_>) -> Result<U256, Box<dyn std::error::Error>> {
+        let (spent,): (String,) = sqlx::query_as(
+            r#"
+            SELECT COALESCE(SUM(estimated_cost_wei), 0)::TEXT FROM sponsorship_decisions
+            WHERE decision = 'sponsored'
+              AND created_at >= date_trunc('day', NOW() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC'
+            "#
+        )
+        .fetch_one(source.conn().await?)
+        .await?;
+        Ok(U256::from_dec_str(&spent)?)
+    }
+
+    pub async fn campaign_sponsorship(&self, campaign_id: i64) -> Result<Option<CampaignSponsorship>, sqlx::Error> {
+        Self::load_campaign(&mut *self.pool.acquire().await?, campaign_id).await
+    }
+
+    async fn load_campaign(conn: &mut PgConnection, campaign_id: i64) -> Result<Option<CampaignSponsorship>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT c.id AS campaign_id,
+                   c.sponsorship_budget_wei::TEXT AS budget_wei,
+                   COALESCE(SUM(d.estimated_cost_wei), 0)::TEXT AS spent_wei,
+                   COUNT(d.id) AS sponsored
+            FROM campaigns c
+            LEFT JOIN sponsorship_decisions d ON d.campaign_id = c.id AND d.decision = 'sponsored'
+            WHERE c.id = $1
+            GROUP BY c.id
+            "#
+        )
+        .bind(campaign_id)
+        .fetch_optional(conn)
+        .await
+    }
+
+    // `estimated_cost` is priced before any lock is taken
+    async fn evaluate_rules(
+        &self,
+        source: &mut RuleSource<'_>,
+        request: &RelayRequest,
+        estimated_cost: Option<U256>,
+    ) -> Result<Verdict, Box<dyn std::error::Error>> {
+        if request.kind == RelayKind::Creation {
+            let decision = self.creation.evaluate(request).await;
+            if !matches!(decision, PolicyDecision::Sponsor { .. }) {
+                return Ok(Verdict { decision, rule: Some("relay_config"), estimated_cost: None });
+            }
+        }
+
+        if request.amount < self.min_amount {
+            return Ok(Verdict::reject(
+                "min_amount",
+                format!("amount is below the sponsored minimum of {} wei", self.min_amount),
+                None,
+            ));
+        }
+
+        if let Some(recipient) = &request.recipient {
+            if self.recipient_daily_quota > 0
+                && Self::sponsored_today(source, "recipient_address", recipient).await? >= self.recipient_daily_quota
+            {
+                return Ok(Verdict::reject(
+                    "recipient_quota",
+                    format!("recipient reached the daily quota of {} sponsored claims", self.recipient_daily_quota),
+                    None,
+                ));
+            }
+        }
+
+        if self.creator_daily_quota > 0
+            && Self::sponsored_today(source, "creator_address", &request.creator).await? >= self.creator_daily_quota
+        {
+            return Ok(Verdict::reject(
+                "creator_quota",
+                format!("creator reached the daily quota of {} sponsored transactions", self.creator_daily_quota),
+                None,
+            ));
+        }
+
+        let campaign = match request.campaign_id {
+            Some(id) => Self::load_campaign(source.conn().await?, id).await?,
+            None => None,
+        };
+        let budget = campaign.as_ref()
+            .and_then(|c| c.budget_wei.as_deref())
+            .map(U256::from_dec_str)
+            .transpose()?;
+
+        if estimated_cost.is_none() && (budget.is_some() || !self.daily_spend_cap.is_zero()) {
+            return Ok(Verdict::reject(
+                "gas_price",
+                "gas price unavailable to check the sponsorship budget".to_string(),
+                None,
+            ));
+        }
+        let cost = estimated_cost.unwrap_or_default();
+
+        if let (Some(budget), Some(campaign)) = (budget, &campaign) {
+            if U256::from_dec_str(&campaign.spent_wei)? + cost > budget {
+                return Ok(Verdict::reject(
+                    "campaign_budget",
+                    format!("campaign {} exhausted its gas budget of {} wei", campaign.campaign_id, budget),
+                    estimated_cost,
+                ));
+            }
+        }
+
+        if !self.daily_spend_cap.is_zero() && Self::spent_today(source).await? + cost > self.daily_spend_cap {
+            return Ok(Verdict::reject(
+                "daily_spend_cap",
+                format!("daily sponsorship spend cap of {} wei reached", self.daily_spend_cap),
+                estimated_cost,
+            ));
+        }
+
+        Ok(Verdict {
+            decision: PolicyDecision::Sponsor { reason: "within sponsorship limits".to_string() },
+            rule: None,
+            estimated_cost,
+        })
+    }
+
+    // Refuse when the limits cannot be checked rather than sponsor blindly
+    async fn verdict(&self, source: &mut RuleSource<'_>, request: &RelayRequest, estimated_cost: Option<U256>) -> Verdict {
+        match self.evaluate_rules(source, request, estimated_cost).await {
+            Ok(verdict) => verdict,
+            Err(e) => {
+                error!("Failed to check sponsorship limits: {}", e);
+                Verdict::reject("unavailable", "sponsorship limits could not be checked".to_string(), None)
+            }
+        }
+    }
+
+    // Check the limits and record the decision in one transaction; the
+    // advisory lock keeps another decision from reading the same totals
+    // before this one is counted
+    async fn decide_and_record(&self, request: &RelayRequest, estimated_cost: Option<U256>) -> Result<Verdict, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('sponsorship_decisions'))")
+            .execute(&mut *tx)
+            .await?;
+
+        let verdict = self.verdict(&mut RuleSource::Transaction(&mut tx), request, estimated_cost).await;
+        Self::record(&mut tx, request, &verdict).await?;
+        tx.commit().await?;
+        Ok(verdict)
+    }
+
+    async fn record(conn: &mut PgConnection, request: &RelayRequest, verdict: &Verdict) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO sponsorship_decisions
+                (kind, subject, creator_address, recipient_address, campaign_id, amount,
+                 estimated_cost_wei, decision, rule, reason, client_ip)
+            VALUES ($1, $2, LOWER($3), LOWER($4), $5, $6::NUMERIC, $7::NUMERIC, $8, $9, $10, $11)
+            "#
+        )
+        .bind(request.kind.as_str())
+        .bind(request.subject.as_deref().unwrap_or_default())
+        .bind(&request.creator)
+        .bind(&request.recipient)
+        .bind(request.campaign_id)
+        .bind(request.amount.to_string())
+        .bind(verdict.estimated_cost.map(|cost| cost.to_string()))
+        .bind(verdict.decision.as_str())
+        .bind(verdict.rule)
+        .bind(verdict.decision.reason())
+        .bind(&request.client_ip)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_decisions(&self, query: &DecisionQuery) -> Result<Vec<SponsorshipDecision>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, kind, subject, creator_address, recipient_address, campaign_id,
+                   amount::TEXT AS amount, estimated_cost_wei::TEXT AS estimated_cost_wei,
+                   decision, rule, reason, client_ip, created_at
+            FROM sponsorship_decisions
+            WHERE ($1::text IS NULL OR kind = $1)
+              AND ($2::text IS NULL OR decision = $2)
+              AND ($3::text IS NULL OR creator_address = LOWER($3))
+              AND ($4::bigint IS NULL OR campaign_id = $4)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $5 OFFSET $6
+            "#
+        )
+        .bind(&query.kind)
+        .bind(&query.decision)
+        .bind(&query.creator)
+        .bind(query.campaign_id)
+        .bind(query.limit.unwrap_or(50).clamp(1, 500))
+        .bind(query.offset.unwrap_or(0).max(0))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn set_campaign_budget(
+        &self,
+        campaign_id: i64,
+        budget_wei: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<Option<CampaignSponsorship>, Box<dyn std::error::Error>> {
+        let budget = budget_wei
+            .map(|budget| parse_wei(budget, "budget_wei"))
+            .transpose()?;
+
+        let Some(before) = self.campaign_sponsorship(campaign_id).await? else {
+            return Ok(None);
+        };
+
+        sqlx::query("UPDATE campaigns SET sponsorship_budget_wei = $1::NUMERIC WHERE id = $2")
+            .bind(budget.map(|budget| budget.to_string()))
+            .bind(campaign_id)
+            .execute(&self.pool)
+            .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "campaign.sponsorship_budget_updated",
+            "campaign",
+            &campaign_id.to_string(),
+            Some(json!({ "sponsorship_budget_wei": before.budget_wei })),
+            Some(json!({ "sponsorship_budget_wei": budget.map(|budget| budget.to_string()) })),
+        ).await;
+
+        Ok(self.campaign_sponsorship(campaign_id).await?)
+    }
+}
+
+#[async_trait]
+impl RelayPolicy for SponsorshipPolicy {
+    async fn evaluate(&self, request: &RelayRequest) -> PolicyDecision {
+        let estimated_cost = self.estimate_cost(request.gas_limit).await;
+        self.verdict(&mut RuleSource::Pool(&self.pool, None), request, estimated_cost).await.decision
+    }
+
+    async fn decide(&self, request: &RelayRequest) -> PolicyDecision {
+        let estimated_cost = self.estimate_cost(request.gas_limit).await;
+        let verdict = match self.decide_and_record(request, estimated_cost).await {
+            Ok(verdict) => verdict,
+            Err(e) => {
+                error!("Failed to record sponsorship decision for {:?}: {}", request.subject, e);
+                // Unrecorded spend would slip past the quotas and caps
+                Verdict::reject("unavailable", "sponsorship could not be recorded".to_string(), None)
+            }
+        };
+        if let PolicyDecision::Reject { reason } = &verdict.decision {
+            info!("Not sponsoring {} {:?}: {}", request.kind.as_str(), request.subject, reason);
+        }
+        verdict.decision
+    }
+
+    async fn unsent(&self, request: &RelayRequest, reason: &str) {
+        let result = sqlx::query(
+            r#"
+            UPDATE sponsorship_decisions
+            SET decision = 'unsent', reason = $3
+            WHERE id = (
+                SELECT id FROM sponsorship_decisions
+                WHERE kind = $1 AND subject = $2 AND decision = 'sponsored'
+                ORDER BY id DESC
+                LIMIT 1
+            )
+            "#
+        )
+        .bind(request.kind.as_str())
+        .bind(request.subject.as_deref().unwrap_or_default())
+        .bind(format!("not sent: {}", reason))
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = result {
+            error!("Failed to mark sponsorship of {:?} unsent: {}", request.subject, e);
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use crate::db::relay_models::RelayedCreation;
+use crate::db::voucher_models::{ClaimAttempt, IssuedAuthorization, VoucherCode, VoucherEvent};
+use crate::services::audit::AuditRecord;
+use crate::services::lifecycle::VoucherLifecycle;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+// Claim attempts listed per voucher; a code tried this often is being
+// guessed at, and its newest attempts tell enough
+const MAX_ATTEMPTS: i64 = 500;
+
+// Audited changes that explain a claim going wrong, and their timeline names
+const AUDITED_ACTIONS: [(&str, &str); 7] = [
+    ("voucher.claim_submitted", "claim_submitted"),
+    ("voucher.claim_settled", "claim_settled"),
+    ("voucher.claim_status_updated", "claim_reported"),
+    ("voucher.window_updated", "window_updated"),
+    ("voucher.recipients_updated", "recipients_updated"),
+    ("voucher.password_updated", "password_updated"),
+    ("voucher.note_updated", "note_updated"),
+];
+
+// One thing that happened to a voucher
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry {
+    pub at: DateTime<Utc>,
+    pub event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub detail: Value,
+}
+
+// A voucher from its creation to its current state, for support
+#[derive(Debug, Serialize)]
+pub struct VoucherTimeline {
+    pub voucher_id: String,
+    pub state: Value,
+    pub entries: Vec<TimelineEntry>,
+    // Only the newest MAX_ATTEMPTS verifies and claim requests are listed
+    pub attempts_truncated: bool,
+}
+
+// The receipt of a relayed transaction, as recorded by gas accounting
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct RelayerReceipt {
+    tx_hash: String,
+    succeeded: bool,
+    block_number: Option<i64>,
+    gas_used: String,
+    l1_gas_used: String,
+    effective_gas_price: String,
+    cost_wei: String,
+}
+
+impl TimelineEntry {
+    // The claim_attempts log: password failures carry a reason, claim
+    // requests a recipient, and verifies neither
+    pub fn from_attempt(attempt: &ClaimAttempt) -> Self {
+        let ip = attempt.ip_address.as_deref().map(anonymize_ip);
+        let (event, detail) = match (&attempt.failure_reason, &attempt.recipient_address) {
+            (Some(reason), _) => ("password_failed", json!({ "reason": reason, "ip": ip })),
+            (None, Some(recipient)) => ("claim_requested", json!({ "recipient": recipient, "ip": ip })),
+            (None, None) => ("verify", json!({ "success": attempt.success, "ip": ip })),
+        };
+        Self { at: attempt.attempted_at, event, code: attempt.voucher_code.clone(), detail }
+    }
+
+    fn from_authorization(authorization: &IssuedAuthorization) -> Self {
+        Self {
+            at: authorization.issued_at,
+            event: "authorization_issued",
+            code: Some(authorization.voucher_code.clone()),
+            detail: json!({
+                "recipient": authorization.recipient_address,
+                "deadline": authorization.deadline,
+                "status": authorization.status,
+                "superseded_at": authorization.superseded_at,
+                "signer": authorization.signer_address,
+                "ip": authorization.ip_address.as_deref().map(anonymize_ip),
+            }),
+        }
+    }
+
+    fn from_event(event: &VoucherEvent) -> Self {
+        Self {
+            at: event.occurred_at,
+            event: "status_changed",
+            code: None,
+            detail: json!({
+                "from": event.from_status,
+                "to": event.to_status,
+                "actor": actor(&event.actor_type, &event.actor),
+                "tx_hash": event.tx_hash,
+                "detail": event.detail,
+            }),
+        }
+    }
+
+    // A settled claim comes with the receipt of its transaction, whose
+    // decoded revert the claim monitor kept in `after.error`
+    fn from_audit(record: &AuditRecord, receipts: &[RelayerReceipt]) -> Option<Self> {
+        let (_, event) = AUDITED_ACTIONS.iter().find(|(action, _)| *action == record.action)?;
+        let mut detail = json!({
+            "actor": actor(&record.actor_type, &record.actor),
+            "before": record.before_value,
+            "after": record.after_value,
+        });
+        if *event == "claim_settled" {
+            let tx_hash = record.after_value.as_ref()
+                .and_then(|after| after.get("claim_tx_hash"))
+                .and_then(Value::as_str);
+            detail["receipt"] = json!(receipts.iter().find(|r| Some(r.tx_hash.as_str()) == tx_hash));
+        }
+        let code = (record.target_type == "voucher_code").then(|| record.target_id.clone());
+        Some(Self { at: record.created_at, event, code, detail })
+    }
+}
+
+// Keep the network, drop the host: the last octet of an IPv4 address and
+// all but the first 48 bits of an IPv6 one
+pub fn anonymize_ip(ip: &str) -> String {
+    let parsed = ip.parse::<IpAddr>()
+        .or_else(|_| ip.parse::<SocketAddr>().map(|addr| addr.ip()));
+    match parsed {
+        Ok(IpAddr::V4(v4)) => {
+            let [a, b, c, _] = v4.octets();
+            Ipv4Addr::new(a, b, c, 0).to_string()
+        }
+        Ok(IpAddr::V6(v6)) => match v6.to_ipv4_mapped() {
+            Some(v4) => anonymize_ip(&v4.to_string()),
+            None => {
+                let s = v6.segments();
+                Ipv6Addr::new(s[0], s[1], s[2], 0, 0, 0, 0, 0).to_string()
+            }
+        },
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+// Actors known only by their IP are shown by the anonymized IP
+fn actor(actor_type: &str, actor: &str) -> Value {
+    let id = if actor_type == "ip" { anonymize_ip(actor) } else { actor.to_string() };
+    json!({ "type": actor_type, "id": id })
+}
+
+// Assembles a voucher's history from the tables each step is recorded in
+#[derive(Clone)]
+pub struct TimelineService {
+    pool: PgPool,
+    lifecycle: VoucherLifecycle,
+}
+
+impl TimelineService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            lifecycle: VoucherLifecycle::new(pool.clone()),
+            pool,
+        }
+    }
+
+    // The timeline of the voucher `voucher` is a code of, across all its codes
+    pub async fn for_voucher(&self, voucher: &VoucherCode) -> Result<VoucherTimeline, sqlx::Error> {
+        let voucher_id = &voucher.voucher_id;
+        let mut entries = Vec::new();
+
+        if let Some(at) = voucher.on_chain_created_at {
+            entries.push(TimelineEntry {
+                at,
+                event: "created_on_chain",
+                code: None,
+                detail: json!({ "creator_address": voucher.creator_address, "amount": voucher.amount }),
+            });
+        }
+
+        let creations: Vec<RelayedCreation> = sqlx::query_as(
+            "SELECT * FROM relayed_creations WHERE voucher_id = $1 ORDER BY id"
+        )
+        .bind(voucher_id)
+        .fetch_all(&self.pool)
+        .await?;
+        entries.extend(creations.iter().map(|creation| TimelineEntry {
+            at: creation.submitted_at.unwrap_or(creation.created_at),
+            event: "creation_relayed",
+            code: None,
+            detail: json!({
+                "tx_hash": creation.tx_hash,
+                "tx_status": creation.tx_status,
+                "error": creation.error,
+                "relayer_fee": creation.relayer_fee,
+                "settled_at": creation.settled_at,
+            }),
+        }));
+
+        let codes: Vec<VoucherCode> = sqlx::query_as(
+            "SELECT * FROM voucher_codes WHERE voucher_id = $1 ORDER BY created_at"
+        )
+        .bind(voucher_id)
+        .fetch_all(&self.pool)
+        .await?;
+        for code in &codes {
+            entries.push(TimelineEntry {
+                at: code.created_at,
+                event: "link_issued",
+                code: Some(code.code.clone()),
+                detail: json!({ "label": code.label, "has_password": code.password_hash.is_some() }),
+            });
+            if let Some(at) = code.revoked_at {
+                entries.push(TimelineEntry {
+                    at,
+                    event: "code_revoked",
+                    code: Some(code.code.clone()),
+                    detail: json!({ "replaced_by": code.replaced_by }),
+                });
+            }
+        }
+        let code_ids: Vec<String> = codes.into_iter().map(|code| code.code).collect();
+
+        let attempts: Vec<ClaimAttempt> = sqlx::query_as(
+            r#"
+            SELECT * FROM claim_attempts
+            WHERE voucher_code = ANY($1)
+            ORDER BY attempted_at DESC, id DESC
+            LIMIT $2
+            "#
+        )
+        .bind(&code_ids)
+        .bind(MAX_ATTEMPTS)
+        .fetch_all(&self.pool)
+        .await?;
+        let attempts_truncated = attempts.len() as i64 == MAX_ATTEMPTS;
+        entries.extend(attempts.iter().map(TimelineEntry::from_attempt));
+
+        let authorizations: Vec<IssuedAuthorization> = sqlx::query_as(
+            "SELECT * FROM claim_authorizations WHERE voucher_id = $1 ORDER BY id"
+        )
+        .bind(voucher_id)
+        .fetch_all(&self.pool)
+        .await?;
+        entries.extend(authorizations.iter().map(TimelineEntry::from_authorization));
+
+        let receipts: Vec<RelayerReceipt> = sqlx::query_as(
+            r#"
+            SELECT tx_hash, succeeded, block_number, gas_used::TEXT, l1_gas_used::TEXT,
+                   effective_gas_price::TEXT, cost_wei::TEXT
+            FROM relayer_gas_costs
+            WHERE voucher_id = $1
+            "#
+        )
+        .bind(voucher_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let actions: Vec<&str> = AUDITED_ACTIONS.iter().map(|(action, _)| *action).collect();
+        let audited: Vec<AuditRecord> = sqlx::query_as(
+            r#"
+            SELECT * FROM audit_log
+            WHERE action = ANY($3)
+            AND ((target_type = 'voucher' AND target_id = $1)
+                 OR (target_type = 'voucher_code' AND target_id = ANY($2)))
+            ORDER BY id
+            "#
+        )
+        .bind(voucher_id)
+        .bind(&code_ids)
+        .bind(&actions)
+        .fetch_all(&self.pool)
+        .await?;
+        entries.extend(audited.iter().filter_map(|record| TimelineEntry::from_audit(record, &receipts)));
+
+        let events = self.lifecycle.events(voucher_id).await?;
+        entries.extend(events.iter().map(TimelineEntry::from_event));
+
+        // Stable, so same-instant entries keep the order they were added in
+        entries.sort_by_key(|entry| entry.at);
+
+        Ok(VoucherTimeline {
+            voucher_id: voucher_id.clone(),
+            state: json!({
+                "status": voucher.status,
+                "claimed_by": voucher.claimed_by,
+                "claimed_at": voucher.claimed_at,
+                "claim_tx_hash": voucher.claim_tx_hash,
+                "claim_tx_status": voucher.claim_tx_status,
+                "cancelled_at": voucher.cancelled_at,
+                "cancel_tx_hash": voucher.cancel_tx_hash,
+                "claimable_from": voucher.claimable_from,
+                "claimable_until": voucher.claimable_until,
+            }),
+            entries,
+            attempts_truncated,
+        })
+    }
+}
@@ -0,0 +1,200 @@
+use crate::contracts::erc20::ApproveCall;
+use crate::contracts::nbgn::{MintCall, RedeemCall};
+use crate::contracts::voucher::{CancelVoucherCall, CreateVoucherCall};
+use crate::db::tx_models::{ApprovalSpender, ApprovalToken};
+use crate::services::key_rotation::UnsignedTransaction;
+use crate::services::voucher::CHAIN_ID;
+use ethers::abi::AbiEncode;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::debug;
+
+// Gas limits for when the node cannot estimate, e.g. createVoucher before
+// the NBGN approval is mined
+const CREATE_VOUCHER_GAS: u64 = 150_000;
+const CANCEL_VOUCHER_GAS: u64 = 100_000;
+const MINT_GAS: u64 = 150_000;
+const REDEEM_GAS: u64 = 150_000;
+const APPROVE_GAS: u64 = 60_000;
+
+// Headroom on estimates for state changing before inclusion
+const GAS_HEADROOM_PERCENT: u64 = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TxBuildError {
+    #[error("Invalid amount")]
+    InvalidAmount,
+    #[error("EURe token address is not configured")]
+    EureNotConfigured,
+    #[error("Failed to fetch fee data: {0}")]
+    Fees(String),
+}
+
+// A contract call before gas and fees are known
+#[derive(Debug, Clone)]
+pub struct ContractCall {
+    pub to: Address,
+    pub data: Bytes,
+    pub description: String,
+    pub fallback_gas: u64,
+}
+
+// An EIP-1559 transaction request ready for the user's wallet to sign
+#[derive(Debug, Serialize)]
+pub struct PreparedTransaction {
+    #[serde(flatten)]
+    pub tx: UnsignedTransaction,
+    pub from: String,
+    pub chain_id: u64,
+    #[serde(rename = "type")]
+    pub tx_type: u8,
+    pub gas: String,
+    // False when the node could not estimate and `gas` is a fixed limit;
+    // estimate_error then says why, often a missing approval
+    pub gas_estimated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_error: Option<String>,
+    pub max_fee_per_gas: String,
+    pub max_priority_fee_per_gas: String,
+}
+
+// Positive amount in the token's smallest unit
+pub fn parse_amount(amount: &str) -> Result<U256, TxBuildError> {
+    match U256::from_dec_str(amount.trim()) {
+        Ok(amount) if !amount.is_zero() => Ok(amount),
+        _ => Err(TxBuildError::InvalidAmount),
+    }
+}
+
+// Like parse_amount, but "0" revokes and "max" is unlimited
+pub fn parse_allowance(amount: &str) -> Result<U256, TxBuildError> {
+    match amount.trim() {
+        "max" => Ok(U256::MAX),
+        amount => U256::from_dec_str(amount).map_err(|_| TxBuildError::InvalidAmount),
+    }
+}
+
+// Encodes the calls frontends submit themselves and fills in gas and fees
+#[derive(Clone)]
+pub struct TxBuilder {
+    provider: Arc<Provider<Http>>,
+    voucher_contract: Address,
+    nbgn_contract: Address,
+    eure_token: Option<Address>,
+}
+
+impl TxBuilder {
+    pub fn new(provider: Arc<Provider<Http>>, voucher_contract: Address, nbgn_contract: Address) -> Self {
+        Self {
+            provider,
+            voucher_contract,
+            nbgn_contract,
+            eure_token: None,
+        }
+    }
+
+    pub fn with_eure_token(mut self, eure_token: Address) -> Self {
+        self.eure_token = Some(eure_token);
+        self
+    }
+
+    pub fn create_voucher(&self, amount: U256) -> ContractCall {
+        ContractCall {
+            to: self.voucher_contract,
+            data: CreateVoucherCall { amount }.encode().into(),
+            description: format!("createVoucher({})", amount),
+            fallback_gas: CREATE_VOUCHER_GAS,
+        }
+    }
+
+    pub fn cancel_voucher(&self, voucher_id: H256) -> ContractCall {
+        ContractCall {
+            to: self.voucher_contract,
+            data: CancelVoucherCall { voucher_id: voucher_id.0 }.encode().into(),
+            description: format!("cancelVoucher({:?})", voucher_id),
+            fallback_gas: CANCEL_VOUCHER_GAS,
+        }
+    }
+
+    pub fn mint(&self, eure_amount: U256) -> ContractCall {
+        ContractCall {
+            to: self.nbgn_contract,
+            data: MintCall { eure_amount }.encode().into(),
+            description: format!("mint({})", eure_amount),
+            fallback_gas: MINT_GAS,
+        }
+    }
+
+    pub fn redeem(&self, nbgn_amount: U256) -> ContractCall {
+        ContractCall {
+            to: self.nbgn_contract,
+            data: RedeemCall { nbgn_amount }.encode().into(),
+            description: format!("redeem({})", nbgn_amount),
+            fallback_gas: REDEEM_GAS,
+        }
+    }
+
+    pub fn approve(
+        &self,
+        token: ApprovalToken,
+        spender: ApprovalSpender,
+        amount: U256,
+    ) -> Result<ContractCall, TxBuildError> {
+        let token = match token {
+            ApprovalToken::Nbgn => self.nbgn_contract,
+            ApprovalToken::Eure => self.eure_token.ok_or(TxBuildError::EureNotConfigured)?,
+        };
+        let spender = match spender {
+            ApprovalSpender::VoucherContract => self.voucher_contract,
+            ApprovalSpender::NbgnContract => self.nbgn_contract,
+        };
+
+        Ok(ContractCall {
+            to: token,
+            data: ApproveCall { spender, amount }.encode().into(),
+            description: format!("approve({:?}, {})", spender, amount),
+            fallback_gas: APPROVE_GAS,
+        })
+    }
+
+    pub async fn prepare(&self, from: Address, call: ContractCall) -> Result<PreparedTransaction, TxBuildError> {
+        let request: TypedTransaction = Eip1559TransactionRequest::new()
+            .from(from)
+            .to(call.to)
+            .data(call.data.clone())
+            .chain_id(CHAIN_ID)
+            .into();
+
+        let (gas, estimate_error) = match self.provider.estimate_gas(&request, None).await {
+            Ok(estimate) => (estimate * (100 + GAS_HEADROOM_PERCENT) / 100, None),
+            Err(e) => {
+                debug!("Gas estimate for {} from {:?} failed: {}", call.description, from, e);
+                (U256::from(call.fallback_gas), Some(e.to_string()))
+            }
+        };
+
+        let (max_fee_per_gas, max_priority_fee_per_gas) = self.provider
+            .estimate_eip1559_fees(None)
+            .await
+            .map_err(|e| TxBuildError::Fees(e.to_string()))?;
+
+        Ok(PreparedTransaction {
+            tx: UnsignedTransaction {
+                to: format!("{:?}", call.to),
+                data: call.data.to_string(),
+                value: "0".to_string(),
+                description: call.description,
+            },
+            from: format!("{:?}", from),
+            chain_id: CHAIN_ID,
+            tx_type: 2,
+            gas: gas.to_string(),
+            gas_estimated: estimate_error.is_none(),
+            estimate_error,
+            max_fee_per_gas: max_fee_per_gas.to_string(),
+            max_priority_fee_per_gas: max_priority_fee_per_gas.to_string(),
+        })
+    }
+}
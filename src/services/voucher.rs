@@ -1,235 +1,1383 @@
-use crate::db::voucher_models::{VoucherCode, ClaimAuthorization};
+use crate::config::{AuthorizationConfig, LockoutConfig, SupersedePolicy};
+use crate::db::voucher_models::{VoucherCode, ClaimAuthorization, ClaimWindow, CreatorAuth, GiftNote, IssuedAuthorization, LockoutStatus, RecipientRestriction, VoucherCodeSummary};
+use crate::services::allowlist;
+use crate::services::audit::{AuditContext, AuditLog};
+use crate::services::auth;
+use crate::services::claim_batcher::{ClaimBatcher, ClaimCall};
+use crate::services::claim_monitor::claim_log;
+use crate::services::codes::CodeGenerator;
+use crate::services::lifecycle::{LifecycleError, StatusChange, VoucherLifecycle, VoucherStatus};
+use crate::services::notes;
+use crate::services::relay_policy::{PolicyDecision, RelayKind, RelayPolicy, RelayRequest};
+use crate::services::signer::SignerRegistry;
+use crate::services::webhooks::{WebhookEvent, WebhookService};
+use chrono::{DateTime, Utc};
 use ethers::prelude::*;
 use ethers::utils::keccak256;
+use serde_json::{json, Value};
 use sqlx::PgPool;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use argon2::password_hash::{rand_core::OsRng, SaltString};
 use std::str::FromStr;
 use std::sync::Arc;
 
-const VOUCHER_CONTRACT: &str = "0x66Eb0Aa46827e5F3fFcb6Dea23C309CB401690B6";
-const CHAIN_ID: u64 = 42161; // Arbitrum One
-const DEFAULT_DEADLINE_SECONDS: u64 = 3600; // 1 hour
+pub const VOUCHER_CONTRACT: &str = "0x66Eb0Aa46827e5F3fFcb6Dea23C309CB401690B6";
+pub const CHAIN_ID: u64 = 42161; // Arbitrum One
+pub const DEFAULT_DEADLINE_SECONDS: u64 = 3600; // 1 hour
+pub const MAX_ACTIVE_CODES_PER_VOUCHER: i64 = 20;
+const MAX_ALLOWED_RECIPIENTS: usize = 1000;
 
-// Gas limits for frontend reference
-const CREATE_VOUCHER_GAS: u64 = 150_000;
+// Gas limit for relayed claims
 const CLAIM_VOUCHER_GAS: u64 = 200_000;
 
+// A claim lock left 'submitting' this long belongs to a submission that died
+// before sending, and may be taken over
+const CLAIM_LOCK_SECS: i64 = 300;
+
+// Claim failures that clients must be able to tell apart
+#[derive(Debug, thiserror::Error)]
+pub enum ClaimError {
+    #[error("Password required")]
+    PasswordRequired,
+    #[error("Invalid password")]
+    InvalidPassword,
+    #[error("Too many failed password attempts")]
+    Locked { locked_until: DateTime<Utc> },
+    #[error("A claim authorization for this voucher is already live")]
+    AuthorizationLive { deadline: DateTime<Utc> },
+    #[error("Voucher is not claimable yet")]
+    NotYetClaimable { claimable_from: DateTime<Utc> },
+    #[error("Voucher has expired")]
+    Expired { claimable_until: DateTime<Utc> },
+    #[error("Recipient is not allowed to claim this voucher")]
+    RecipientNotAllowed,
+    #[error("Recipient has already claimed a voucher of this campaign")]
+    RecipientLimitReached,
+    #[error("Gas for this claim is not sponsored: {reason}")]
+    NotSponsored { reason: String },
+    #[error("A claim transaction for this voucher is already in progress")]
+    ClaimInProgress { tx_hash: Option<String> },
+    #[error("Transaction has not claimed this voucher")]
+    ClaimNotOnChain,
+}
+
+#[derive(sqlx::FromRow)]
+struct ClaimState {
+    voucher_id: String,
+    status: String,
+    claim_tx_status: Option<String>,
+    claim_tx_hash: Option<String>,
+    claim_tx_submitted_at: Option<DateTime<Utc>>,
+}
+
+// A voucher's claim state before execute_claim locked it, restored if the
+// claim is never sent
+struct ClaimLock {
+    voucher_id: String,
+    previous_status: Option<String>,
+    previous_submitted_at: Option<DateTime<Utc>>,
+}
+
+// Link creation failures, mapped to distinct statuses by the route
+#[derive(Debug, thiserror::Error)]
+pub enum LinkError {
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("Voucher does not exist on-chain")]
+    NotFound,
+    #[error("Voucher already claimed")]
+    AlreadyClaimed,
+    #[error("Amount does not match the on-chain voucher")]
+    AmountMismatch { on_chain: String },
+    #[error("{0}")]
+    InvalidWindow(String),
+    #[error("{0}")]
+    InvalidNote(String),
+}
+
 #[derive(Clone)]
 pub struct VoucherService {
     pool: PgPool,
-    wallet: LocalWallet,
+    signers: SignerRegistry,
     provider: Option<Arc<Provider<Http>>>,
+    lockout: LockoutConfig,
+    authorizations: AuthorizationConfig,
+    audit: AuditLog,
+    lifecycle: VoucherLifecycle,
+    codes: CodeGenerator,
+    public_base_url: Option<String>,
+    webhooks: Option<WebhookService>,
+    relay_policy: Option<Arc<dyn RelayPolicy>>,
+    claim_batcher: Option<ClaimBatcher>,
 }
 
 impl VoucherService {
-    pub fn new(pool: PgPool, private_key: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let wallet = private_key.parse::<LocalWallet>()?
-            .with_chain_id(CHAIN_ID);
-        
-        Ok(Self { pool, wallet, provider: None })
+    pub fn new(pool: PgPool, signers: SignerRegistry) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            audit: AuditLog::new(pool.clone()),
+            lifecycle: VoucherLifecycle::new(pool.clone()),
+            pool,
+            signers,
+            provider: None,
+            lockout: LockoutConfig::default(),
+            authorizations: AuthorizationConfig::default(),
+            codes: CodeGenerator::default(),
+            public_base_url: None,
+            webhooks: None,
+            relay_policy: None,
+            claim_batcher: None,
+        })
     }
     
     pub fn with_provider(mut self, provider: Arc<Provider<Http>>) -> Self {
         self.provider = Some(provider);
         self
     }
+
+    pub fn with_lockout_policy(mut self, lockout: LockoutConfig) -> Self {
+        self.lockout = lockout;
+        self
+    }
+
+    pub fn with_authorization_policy(mut self, authorizations: AuthorizationConfig) -> Self {
+        self.authorizations = authorizations;
+        self
+    }
+
+    pub fn with_code_generator(mut self, codes: CodeGenerator) -> Self {
+        self.codes = codes;
+        self
+    }
+
+    // Canonical form of a code typed by a user, None if it cannot be valid
+    pub fn normalize_code(&self, code: &str) -> Option<String> {
+        self.codes.normalize(code)
+    }
+
+    pub fn display_code(&self, code: &str) -> String {
+        self.codes.display(code)
+    }
+
+    pub fn with_public_base_url(mut self, public_base_url: Option<String>) -> Self {
+        self.public_base_url = public_base_url.map(|url| url.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: WebhookService) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    // Decides which gasless claims the relayer pays for; all of them without it
+    pub fn with_relay_policy(mut self, relay_policy: Arc<dyn RelayPolicy>) -> Self {
+        self.relay_policy = Some(relay_policy);
+        self
+    }
+
+    // Send gasless claims in Multicall3 batches instead of one transaction each
+    pub fn with_claim_batcher(mut self, claim_batcher: ClaimBatcher) -> Self {
+        self.claim_batcher = Some(claim_batcher);
+        self
+    }
+
+    // Relative claim link for a code, as clients have always received it
+    pub fn claim_path(&self, code: &str) -> String {
+        format!("/claim/{}", self.display_code(code))
+    }
+
+    // Absolute claim URL for a code, if the public base URL is configured
+    pub fn claim_url(&self, code: &str) -> Option<String> {
+        self.public_url(&self.claim_path(code))
+    }
+
+    // Absolute URL for a frontend path, if the public base URL is configured
+    pub fn public_url(&self, path: &str) -> Option<String> {
+        self.public_base_url.as_ref()
+            .map(|base| format!("{}{}", base, path))
+    }
+
+    // Hold the voucher's codes until the transaction ends, so concurrent
+    // additions see each other's codes when numbering and counting them
+    async fn lock_codes(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, voucher_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("voucher_codes:{}", voucher_id))
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    // Put every code of a voucher in a campaign; a voucher belongs to at
+    // most one, which the code lock keeps true for concurrent links
+    async fn attach_campaign(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        voucher_id: &str,
+        campaign_id: i64,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::lock_codes(tx, voucher_id).await?;
+        let (elsewhere,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM voucher_codes WHERE voucher_id = $1 AND campaign_id <> $2)"
+        )
+        .bind(voucher_id)
+        .bind(campaign_id)
+        .fetch_one(&mut **tx)
+        .await?;
+        if elsewhere {
+            return Err("Voucher belongs to another campaign".into());
+        }
+
+        sqlx::query("UPDATE voucher_codes SET campaign_id = $1 WHERE voucher_id = $2")
+            .bind(campaign_id)
+            .bind(voucher_id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    // Next code for a voucher, numbered by how many codes it already has
+    async fn next_code(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        voucher_id: &str,
+    ) -> Result<String, sqlx::Error> {
+        Self::lock_codes(tx, voucher_id).await?;
+        let (existing,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM voucher_codes WHERE voucher_id = $1"
+        )
+        .bind(voucher_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(self.codes.code_for(voucher_id, existing))
+    }
     
+    pub fn lifecycle(&self) -> &VoucherLifecycle {
+        &self.lifecycle
+    }
+
     // Get wallet address for debugging
     pub fn get_wallet_address(&self) -> String {
-        format!("{:?}", self.wallet.address())
+        format!("{:?}", self.signers.signing().address())
+    }
+
+    // Generate bytes32 voucher ID from user-friendly code
+    pub fn code_to_voucher_id(code: &str) -> H256 {
+        H256::from(keccak256(code.as_bytes()))
+    }
+
+    // Hash password for storage
+    pub fn hash_password(password: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2 = Argon2::default();
+        let password_hash = argon2.hash_password(password.as_bytes(), &salt)
+            .map_err(|e| format!("Password hashing failed: {}", e))?;
+        Ok(password_hash.to_string())
+    }
+
+    // Verify password
+    pub fn verify_password(password: &str, password_hash: &str) -> bool {
+        if let Ok(parsed_hash) = PasswordHash::new(password_hash) {
+            let argon2 = Argon2::default();
+            argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok()
+        } else {
+            false
+        }
+    }
+
+    // Lockout length after `failed_attempts` wrong passwords, doubling past the free attempts
+    pub fn lockout_duration(policy: &LockoutConfig, failed_attempts: u32) -> Option<chrono::Duration> {
+        if failed_attempts < policy.free_attempts {
+            return None;
+        }
+
+        let exponent = (failed_attempts - policy.free_attempts).min(32);
+        let secs = policy.base_lockout_secs
+            .saturating_mul(1u64 << exponent)
+            .min(policy.max_lockout_secs);
+
+        Some(chrono::Duration::seconds(secs as i64))
+    }
+
+    // Current password lockout state for a code, counted across all IPs
+    pub async fn get_lockout_status(&self, code: &str) -> Result<LockoutStatus, sqlx::Error> {
+        let (failed_attempts, last_failed_at): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), MAX(a.attempted_at)
+            FROM claim_attempts a
+            JOIN voucher_codes v ON v.code = a.voucher_code
+            WHERE a.voucher_code = $1
+            AND a.failure_reason = 'invalid_password'
+            AND a.attempted_at > COALESCE(v.lockout_reset_at, '-infinity'::timestamptz)
+            "#
+        )
+        .bind(code)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let locked_until = last_failed_at.and_then(|last| {
+            Self::lockout_duration(&self.lockout, failed_attempts.min(u32::MAX as i64) as u32)
+                .map(|duration| last + duration)
+        });
+
+        Ok(LockoutStatus {
+            code: code.to_string(),
+            failed_attempts,
+            last_failed_at,
+            locked_until,
+            locked: locked_until.is_some_and(|until| until > Utc::now()),
+        })
+    }
+
+    async fn record_password_failure(&self, code: &str, client_ip: &str, reason: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO claim_attempts (voucher_code, ip_address, success, failure_reason) VALUES ($1, $2, false, $3)"
+        )
+        .bind(code)
+        .bind(client_ip)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // Refuse vouchers that are done with; expiry is left to the claim window
+    pub fn check_open(voucher: &VoucherCode) -> Result<(), Box<dyn std::error::Error>> {
+        match VoucherStatus::parse(&voucher.status) {
+            Some(VoucherStatus::Claimed) => Err("Voucher already claimed".into()),
+            Some(VoucherStatus::Cancelled) => Err("Voucher has been cancelled".into()),
+            Some(VoucherStatus::Withdrawn) => Err("Voucher has been withdrawn by its creator".into()),
+            _ => Ok(()),
+        }
+    }
+
+    // Reject vouchers outside their claim window
+    pub fn check_claim_window(voucher: &VoucherCode) -> Result<(), ClaimError> {
+        let now = Utc::now();
+        if let Some(claimable_from) = voucher.claimable_from {
+            if now < claimable_from {
+                return Err(ClaimError::NotYetClaimable { claimable_from });
+            }
+        }
+        if let Some(claimable_until) = voucher.claimable_until {
+            if now >= claimable_until {
+                return Err(ClaimError::Expired { claimable_until });
+            }
+        }
+        Ok(())
+    }
+
+    // Check the voucher password, enforcing the per-code lockout
+    pub async fn check_password(
+        &self,
+        voucher: &VoucherCode,
+        password: Option<&str>,
+        client_ip: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(hash) = &voucher.password_hash else {
+            return Ok(());
+        };
+        let password = password.ok_or(ClaimError::PasswordRequired)?;
+
+        let status = self.get_lockout_status(&voucher.code).await?;
+
+        // Always run the hash check and record the attempt so a locked
+        // response takes as long as a wrong-password one
+        let valid = Self::verify_password(password, hash);
+
+        if status.locked {
+            self.record_password_failure(&voucher.code, client_ip, "locked").await?;
+            return Err(ClaimError::Locked {
+                locked_until: status.locked_until.unwrap_or_else(Utc::now),
+            }.into());
+        }
+
+        if !valid {
+            self.record_password_failure(&voucher.code, client_ip, "invalid_password").await?;
+            return Err(ClaimError::InvalidPassword.into());
+        }
+
+        Ok(())
+    }
+
+    // Verify a signed creator request for one code against the voucher's recorded creator
+    pub fn authorize_creator(
+        voucher: &VoucherCode,
+        action: &str,
+        creator: &CreatorAuth,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::check_creator(voucher, action, &voucher.code, creator)
+    }
+
+    // Same, for actions on the voucher as a whole rather than one of its codes
+    pub fn authorize_voucher_creator(
+        voucher: &VoucherCode,
+        action: &str,
+        creator: &CreatorAuth,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::check_creator(voucher, action, &voucher.voucher_id, creator)
+    }
+
+    fn check_creator(
+        voucher: &VoucherCode,
+        action: &str,
+        target: &str,
+        creator: &CreatorAuth,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let owner = voucher.creator_address.as_deref()
+            .ok_or("Voucher has no recorded creator")?;
+
+        if owner.to_lowercase() != creator.address.to_lowercase() {
+            return Err("Only the voucher creator can do this".into());
+        }
+
+        auth::verify_action_signature(&creator.address, action, target, creator.timestamp, &creator.signature)
+    }
+
+    // Clear the failed password counter for a code
+    pub async fn reset_lockout(&self, code: &str, ctx: &AuditContext) -> Result<(), sqlx::Error> {
+        let before = self.get_lockout_status(code).await?;
+
+        sqlx::query(
+            "UPDATE voucher_codes SET lockout_reset_at = NOW() WHERE code = $1"
+        )
+        .bind(code)
+        .execute(&self.pool)
+        .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "lockout.reset",
+            "voucher_code",
+            code,
+            Some(json!({ "failed_attempts": before.failed_attempts, "locked_until": before.locked_until })),
+            Some(json!({ "failed_attempts": 0, "locked_until": null })),
+        ).await;
+
+        info!("Password lockout reset for voucher {}", code);
+        Ok(())
+    }
+
+    // Get voucher by code; revoked codes are treated as unknown
+    pub async fn get_voucher_by_code(&self, code: &str) -> Result<Option<VoucherCode>, sqlx::Error> {
+        sqlx::query_as::<_, VoucherCode>(
+            "SELECT * FROM voucher_codes WHERE code = $1 AND revoked_at IS NULL"
+        )
+        .bind(code)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Any code row for a voucher, preferring active codes. Voucher-level
+    // fields are the same on every row
+    pub async fn get_voucher_by_id(&self, voucher_id: &str) -> Result<Option<VoucherCode>, sqlx::Error> {
+        sqlx::query_as::<_, VoucherCode>(
+            r#"
+            SELECT * FROM voucher_codes
+            WHERE voucher_id = $1
+            ORDER BY (revoked_at IS NULL) DESC, created_at ASC
+            LIMIT 1
+            "#
+        )
+        .bind(voucher_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Create claim authorization with signature
+    pub async fn create_claim_authorization(
+        &self,
+        voucher_code: &str,
+        recipient_address: &str,
+        password: Option<&str>,
+        merkle_proof: &[String],
+        client_ip: &str,
+    ) -> Result<ClaimAuthorization, Box<dyn std::error::Error>> {
+        // Get voucher from DB
+        let voucher = self.get_voucher_by_code(voucher_code).await?
+            .ok_or("Voucher not found")?;
+
+        Self::check_open(&voucher)?;
+        Self::check_claim_window(&voucher)?;
+
+        // Verify password if set
+        self.check_password(&voucher, password, client_ip).await?;
+
+        let recipient = Address::from_str(recipient_address)?;
+        self.check_recipient_allowed(&voucher.voucher_id, recipient, merkle_proof).await?;
+
+        self.authorize_recipient(voucher, recipient_address, client_ip).await
+    }
+
+    // Sign and record a claim of `voucher` for `recipient_address` once the
+    // caller has checked its code, password and allowlist
+    pub async fn authorize_recipient(
+        &self,
+        voucher: VoucherCode,
+        recipient_address: &str,
+        client_ip: &str,
+    ) -> Result<ClaimAuthorization, Box<dyn std::error::Error>> {
+        // Parse addresses and values
+        let voucher_id = H256::from_str(&voucher.voucher_id)?;
+        let recipient = Address::from_str(recipient_address)?;
+
+        // The signature must not outlive the claim window
+        let mut deadline_secs = chrono::Utc::now().timestamp() as u64 + DEFAULT_DEADLINE_SECONDS;
+        if let Some(claimable_until) = voucher.claimable_until {
+            deadline_secs = deadline_secs.min(claimable_until.timestamp() as u64);
+        }
+        let deadline = U256::from(deadline_secs);
+        let deadline_at = DateTime::from_timestamp(deadline.as_u64() as i64, 0)
+            .ok_or("Invalid deadline")?;
+
+        // Enforce one live authorization per voucher before signing anything
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE claim_authorizations SET status = 'expired' WHERE voucher_id = $1 AND status = 'live' AND deadline <= NOW()"
+        )
+        .bind(&voucher.voucher_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let live: Option<(i64, String, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT id, recipient_address, deadline FROM claim_authorizations WHERE voucher_id = $1 AND status = 'live' FOR UPDATE"
+        )
+        .bind(&voucher.voucher_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(campaign_id) = voucher.campaign_id {
+            self.check_campaign_recipient_limit(&mut tx, campaign_id, &voucher.voucher_id, recipient).await?;
+        }
+
+        if let Some((_, live_recipient, live_deadline)) = &live {
+            let same_recipient = live_recipient.to_lowercase() == recipient_address.to_lowercase();
+            let may_supersede = match self.authorizations.supersede {
+                SupersedePolicy::Never => false,
+                SupersedePolicy::SameRecipient => same_recipient,
+                SupersedePolicy::Always => true,
+            };
+            if !may_supersede {
+                return Err(ClaimError::AuthorizationLive { deadline: *live_deadline }.into());
+            }
+        }
+
+        let (signature, signer_address) = self.sign_claim(voucher_id, recipient, deadline).await?;
+        let signature_hex = format!("0x{}", hex::encode(signature.to_vec()));
+        let signature_hash = format!("0x{}", hex::encode(keccak256(signature.to_vec())));
+
+        // Free the live slot first; only one row may hold it at a time
+        if let Some((live_id, _, _)) = &live {
+            sqlx::query(
+                "UPDATE claim_authorizations SET status = 'superseded', superseded_at = NOW() WHERE id = $1"
+            )
+            .bind(live_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let inserted: Result<(i64,), sqlx::Error> = sqlx::query_as(
+            r#"
+            INSERT INTO claim_authorizations
+            (voucher_id, voucher_code, recipient_address, deadline, signature_hash, ip_address, signer_address, status)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, 'live')
+            RETURNING id
+            "#
+        )
+        .bind(&voucher.voucher_id)
+        .bind(&voucher.code)
+        .bind(recipient_address)
+        .bind(deadline_at)
+        .bind(&signature_hash)
+        .bind(client_ip)
+        .bind(format!("{:?}", signer_address))
+        .fetch_one(&mut *tx)
+        .await;
+
+        let authorization_id = match inserted {
+            Ok((id,)) => id,
+            // A concurrent request took the live slot first; report its deadline
+            Err(sqlx::Error::Database(db)) if db.is_unique_violation() => {
+                tx.rollback().await?;
+                let live_deadline: Option<(DateTime<Utc>,)> = sqlx::query_as(
+                    "SELECT deadline FROM claim_authorizations WHERE voucher_id = $1 AND status = 'live'"
+                )
+                .bind(&voucher.voucher_id)
+                .fetch_optional(&self.pool)
+                .await?;
+                let deadline = live_deadline.map_or(deadline_at, |(deadline,)| deadline);
+                return Err(ClaimError::AuthorizationLive { deadline }.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some((live_id, _, _)) = live {
+            sqlx::query("UPDATE claim_authorizations SET superseded_by = $1 WHERE id = $2")
+            .bind(authorization_id)
+            .bind(live_id)
+            .execute(&mut *tx)
+            .await?;
+
+            info!("Authorization {} for voucher {} superseded by {}", live_id, voucher.voucher_id, authorization_id);
+        }
+
+        tx.commit().await?;
+
+        Ok(ClaimAuthorization {
+            voucher_id: voucher.voucher_id,
+            recipient: recipient_address.to_string(),
+            amount: voucher.amount.unwrap_or_else(|| "0".to_string()),
+            deadline: deadline.as_u64(),
+            signature: signature_hex,
+            contract_address: VOUCHER_CONTRACT.to_string(),
+        })
+    }
+
+    // Enforce the voucher's allowlist, if it has one
+    pub async fn check_recipient_allowed(
+        &self,
+        voucher_id: &str,
+        recipient: Address,
+        merkle_proof: &[String],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(restriction) = self.get_recipient_restriction(voucher_id).await? else {
+            return Ok(());
+        };
+
+        let allowed = if let Some(addresses) = &restriction.allowed_addresses {
+            addresses.contains(&format!("{:?}", recipient))
+        } else if let Some(root) = &restriction.merkle_root {
+            let proof: Vec<H256> = merkle_proof.iter()
+                .map(|node| H256::from_str(node))
+                .collect::<Result<_, _>>()
+                .map_err(|_| ClaimError::RecipientNotAllowed)?;
+            allowlist::verify_proof(H256::from_str(root)?, recipient, &proof)
+        } else {
+            true
+        };
+
+        if !allowed {
+            return Err(ClaimError::RecipientNotAllowed.into());
+        }
+        Ok(())
+    }
+
+    // One claim per recipient across a campaign that asks for it. A claim
+    // counts once it lands on-chain or while its authorization is live, and
+    // the advisory lock keeps two vouchers from being authorized at once
+    async fn check_campaign_recipient_limit(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        campaign_id: i64,
+        voucher_id: &str,
+        recipient: Address,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let recipient = format!("{:?}", recipient);
+
+        let (limited,): (bool,) = sqlx::query_as(
+            "SELECT one_claim_per_recipient FROM campaigns WHERE id = $1"
+        )
+        .bind(campaign_id)
+        .fetch_one(&mut **tx)
+        .await?;
+        if !limited {
+            return Ok(());
+        }
+
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("campaign_recipient:{}:{}", campaign_id, recipient))
+            .execute(&mut **tx)
+            .await?;
+
+        let (taken,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM voucher_codes
+                WHERE campaign_id = $1 AND voucher_id <> $2 AND claimed = TRUE AND LOWER(claimed_by) = $3
+            ) OR EXISTS (
+                SELECT 1 FROM claim_authorizations a
+                WHERE a.status = 'live' AND a.deadline > NOW() AND a.voucher_id <> $2
+                  AND LOWER(a.recipient_address) = $3
+                  AND a.voucher_id IN (SELECT voucher_id FROM voucher_codes WHERE campaign_id = $1)
+            )
+            "#
+        )
+        .bind(campaign_id)
+        .bind(voucher_id)
+        .bind(&recipient)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        if taken {
+            return Err(ClaimError::RecipientLimitReached.into());
+        }
+        Ok(())
+    }
+
+    pub async fn get_recipient_restriction(&self, voucher_id: &str) -> Result<Option<RecipientRestriction>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT voucher_id, allowed_addresses, merkle_root, updated_at FROM voucher_recipient_restrictions WHERE voucher_id = $1"
+        )
+        .bind(voucher_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Restrict a voucher to a list of addresses or to a Merkle root of them;
+    // passing neither lifts the restriction
+    pub async fn set_recipient_restriction(
+        &self,
+        voucher_id: &str,
+        addresses: Option<&[String]>,
+        merkle_root: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<Option<RecipientRestriction>, Box<dyn std::error::Error>> {
+        let addresses = addresses
+            .map(|addresses| {
+                if addresses.is_empty() || addresses.len() > MAX_ALLOWED_RECIPIENTS {
+                    return Err(format!("Between 1 and {} addresses are required", MAX_ALLOWED_RECIPIENTS));
+                }
+                addresses.iter()
+                    .map(|address| Address::from_str(address)
+                        .map(|address| format!("{:?}", address))
+                        .map_err(|_| format!("Invalid address: {}", address)))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let merkle_root = merkle_root
+            .map(|root| H256::from_str(root)
+                .map(|root| format!("{:?}", root))
+                .map_err(|_| "Invalid Merkle root"))
+            .transpose()?;
+        if addresses.is_some() && merkle_root.is_some() {
+            return Err("Set either addresses or merkle_root, not both".into());
+        }
+
+        let before = self.get_recipient_restriction(voucher_id).await?;
+
+        let after = if addresses.is_none() && merkle_root.is_none() {
+            sqlx::query("DELETE FROM voucher_recipient_restrictions WHERE voucher_id = $1")
+                .bind(voucher_id)
+                .execute(&self.pool)
+                .await?;
+            None
+        } else {
+            let restriction: RecipientRestriction = sqlx::query_as(
+                r#"
+                INSERT INTO voucher_recipient_restrictions (voucher_id, allowed_addresses, merkle_root)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (voucher_id) DO UPDATE
+                SET allowed_addresses = EXCLUDED.allowed_addresses,
+                    merkle_root = EXCLUDED.merkle_root,
+                    updated_at = NOW()
+                RETURNING voucher_id, allowed_addresses, merkle_root, updated_at
+                "#
+            )
+            .bind(voucher_id)
+            .bind(&addresses)
+            .bind(&merkle_root)
+            .fetch_one(&self.pool)
+            .await?;
+            Some(restriction)
+        };
+
+        // Counts rather than full lists keep audit entries small
+        let summary = |r: &RecipientRestriction| json!({
+            "addresses": r.allowed_addresses.as_ref().map(|a| a.len()),
+            "merkle_root": r.merkle_root,
+        });
+        self.audit.record_or_log(
+            ctx,
+            "voucher.recipients_updated",
+            "voucher",
+            voucher_id,
+            before.as_ref().map(summary),
+            after.as_ref().map(summary),
+        ).await;
+
+        Ok(after)
+    }
+
+    // Sign a claim for the voucher contract with the current signing key
+    async fn sign_claim(
+        &self,
+        voucher_id: H256,
+        recipient: Address,
+        deadline: U256,
+    ) -> Result<(Signature, Address), Box<dyn std::error::Error>> {
+        let contract_address = Address::from_str(VOUCHER_CONTRACT)?;
+        let chain_id = U256::from(CHAIN_ID);
+
+        // Create message hash exactly as contract expects
+        // The contract expects: keccak256(abi.encodePacked(voucherId, recipient, deadline, address(this), block.chainid))
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(voucher_id.as_bytes()); // bytes32
+        encoded.extend_from_slice(recipient.as_bytes()); // address (20 bytes)
+        let deadline_bytes: [u8; 32] = deadline.into();
+        encoded.extend_from_slice(&deadline_bytes); // uint256 (full 32 bytes)
+        encoded.extend_from_slice(contract_address.as_bytes()); // address (20 bytes)
+        let chain_id_bytes: [u8; 32] = chain_id.into();
+        encoded.extend_from_slice(&chain_id_bytes); // uint256 (full 32 bytes)
+
+        let message_hash = keccak256(&encoded);
+
+        // Contract uses MessageHashUtils.toEthSignedMessageHash(message) and then recovers the signature
+        // toEthSignedMessageHash adds the EIP-191 prefix: "\x19Ethereum Signed Message:\n32" + message
+        // sign_message applies the same prefix, locally or in the remote signer
+        let signer = self.signers.signing();
+        let signature = signer.sign_message(message_hash).await?;
+
+        Ok((signature, signer.address()))
+    }
+
+    // Authorization history for a voucher, newest first
+    pub async fn list_authorizations(&self, voucher_id: &str) -> Result<Vec<IssuedAuthorization>, sqlx::Error> {
+        sqlx::query_as::<_, IssuedAuthorization>(
+            "SELECT * FROM claim_authorizations WHERE voucher_id = $1 ORDER BY issued_at DESC, id DESC"
+        )
+        .bind(voucher_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    // All codes of a voucher, including revoked ones, with their usage
+    pub async fn list_codes(&self, voucher_id: &str) -> Result<Vec<VoucherCodeSummary>, sqlx::Error> {
+        sqlx::query_as::<_, VoucherCodeSummary>(
+            r#"
+            SELECT
+                v.code,
+                v.label,
+                v.password_hash IS NOT NULL AS has_password,
+                v.created_at,
+                v.revoked_at,
+                v.replaced_by,
+                (SELECT COUNT(*) FROM claim_attempts a WHERE a.voucher_code = v.code) AS attempts,
+                (SELECT COUNT(*) FROM claim_attempts a
+                    WHERE a.voucher_code = v.code AND a.failure_reason = 'invalid_password') AS failed_password_attempts,
+                (SELECT COUNT(*) FROM claim_authorizations c WHERE c.voucher_code = v.code) AS authorizations_issued,
+                GREATEST(
+                    (SELECT MAX(a.attempted_at) FROM claim_attempts a WHERE a.voucher_code = v.code),
+                    (SELECT MAX(c.issued_at) FROM claim_authorizations c WHERE c.voucher_code = v.code)
+                ) AS last_used_at
+            FROM voucher_codes v
+            WHERE v.voucher_id = $1
+            ORDER BY v.created_at ASC
+            "#
+        )
+        .bind(voucher_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    // Issue another code for a voucher, copying the voucher-level fields
+    // from an existing code
+    pub async fn add_code(
+        &self,
+        voucher: &VoucherCode,
+        label: Option<&str>,
+        password: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        Self::check_open(voucher)?;
+        let password_hash = password.map(Self::hash_password).transpose()?;
+
+        let mut tx = self.pool.begin().await?;
+        Self::lock_codes(&mut tx, &voucher.voucher_id).await?;
+        let (active,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM voucher_codes WHERE voucher_id = $1 AND revoked_at IS NULL"
+        )
+        .bind(&voucher.voucher_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if active >= MAX_ACTIVE_CODES_PER_VOUCHER {
+            return Err(format!("A voucher can have at most {} active codes", MAX_ACTIVE_CODES_PER_VOUCHER).into());
+        }
+
+        let code = self.next_code(&mut tx, &voucher.voucher_id).await?;
+        Self::insert_code_like(&mut tx, &voucher.code, &code, password_hash.as_deref(), label).await?;
+        tx.commit().await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "code.added",
+            "voucher_code",
+            &code,
+            None,
+            Some(json!({
+                "voucher_id": voucher.voucher_id,
+                "label": label,
+                "has_password": password_hash.is_some(),
+            })),
+        ).await;
+
+        info!("Added code {} for voucher {}", code, voucher.voucher_id);
+        Ok(code)
+    }
+
+    // Revoke a single code; the voucher and its other codes are unaffected
+    pub async fn revoke_code(&self, code: &str, ctx: &AuditContext) -> Result<(), Box<dyn std::error::Error>> {
+        let result = sqlx::query(
+            "UPDATE voucher_codes SET revoked_at = NOW() WHERE code = $1 AND revoked_at IS NULL"
+        )
+        .bind(code)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err("Code not found or already revoked".into());
+        }
+
+        self.audit.record_or_log(
+            ctx,
+            "code.revoked",
+            "voucher_code",
+            code,
+            Some(json!({ "revoked": false })),
+            Some(json!({ "revoked": true })),
+        ).await;
+
+        info!("Revoked voucher code {}", code);
+        Ok(())
+    }
+
+    // Replace a leaked code with a fresh one that keeps its label and password
+    pub async fn rotate_code(
+        &self,
+        code: &str,
+        password: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut tx = self.pool.begin().await?;
+
+        let old: Option<VoucherCode> = sqlx::query_as(
+            "SELECT * FROM voucher_codes WHERE code = $1 AND revoked_at IS NULL FOR UPDATE"
+        )
+        .bind(code)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let old = old.ok_or("Code not found or already revoked")?;
+
+        // The note is keyed by the code, so it is opened and sealed again for
+        // the new one; a protected code's password is part of the key
+        let note = match &old.note_ciphertext {
+            Some(sealed) => {
+                let password = match &old.password_hash {
+                    Some(hash) => {
+                        let password = password.ok_or_else(|| {
+                            LinkError::InvalidNote("The code's password is needed to carry its note over".to_string())
+                        })?;
+                        if !Self::verify_password(password, hash) {
+                            return Err(LinkError::InvalidNote("Password does not match the code".to_string()).into());
+                        }
+                        Some(password)
+                    }
+                    None => None,
+                };
+                let content_type = old.note_content_type.as_deref().unwrap_or("text/plain");
+                Some((notes::open(code, password, content_type, sealed)?, password))
+            }
+            None => None,
+        };
+
+        let new_code = self.next_code(&mut tx, &old.voucher_id).await?;
+
+        Self::insert_code_like(&mut tx, code, &new_code, old.password_hash.as_deref(), old.label.as_deref()).await?;
+
+        if let Some((note, password)) = &note {
+            sqlx::query(
+                "UPDATE voucher_codes SET note_ciphertext = $1, note_content_type = $2 WHERE code = $3"
+            )
+            .bind(notes::seal(&new_code, *password, note)?)
+            .bind(&note.content_type)
+            .bind(&new_code)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            "UPDATE voucher_codes SET revoked_at = NOW(), replaced_by = $1 WHERE code = $2"
+        )
+        .bind(&new_code)
+        .bind(code)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "code.rotated",
+            "voucher_code",
+            code,
+            Some(json!({ "revoked": false })),
+            Some(json!({ "revoked": true, "replaced_by": new_code })),
+        ).await;
+
+        info!("Rotated voucher code {} to {}", code, new_code);
+        Ok(new_code)
+    }
+
+    async fn insert_code_like(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        source_code: &str,
+        code: &str,
+        password_hash: Option<&str>,
+        label: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO voucher_codes
+            (code, voucher_id, password_hash, label, creator_address, amount, on_chain_created_at,
+             claimed, claimed_by, claimed_at, claim_tx_hash, cancelled, cancelled_at, cancel_tx_hash,
+             claim_tx_status, claim_tx_submitted_at, claimable_from, claimable_until, expiry_notified_at,
+             campaign_id, status)
+            SELECT $1, voucher_id, $2, $3, creator_address, amount, on_chain_created_at,
+                   claimed, claimed_by, claimed_at, claim_tx_hash, cancelled, cancelled_at, cancel_tx_hash,
+                   claim_tx_status, claim_tx_submitted_at, claimable_from, claimable_until, expiry_notified_at,
+                   campaign_id, status
+            FROM voucher_codes
+            WHERE code = $4
+            "#
+        )
+        .bind(code)
+        .bind(password_hash)
+        .bind(label)
+        .bind(source_code)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
     }
 
-    // Generate bytes32 voucher ID from user-friendly code
-    pub fn code_to_voucher_id(code: &str) -> H256 {
-        H256::from(keccak256(code.as_bytes()))
+    // Create or update the shareable link for an on-chain voucher. Only the
+    // on-chain creator may do this, and only while the voucher is unclaimed
+    pub async fn create_voucher_link(
+        &self,
+        voucher_id: &str,
+        password: Option<&str>,
+        amount: Option<&str>,
+        window: &ClaimWindow,
+        creator: &CreatorAuth,
+        ctx: &AuditContext,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let voucher_id = Self::normalize_voucher_id(voucher_id)?;
+        Self::validate_claim_window(window)?;
+
+        auth::verify_action_signature_with_params(
+            &creator.address,
+            "create_link",
+            &voucher_id,
+            &create_link_params(password, amount, window),
+            creator.timestamp,
+            &creator.signature,
+        ).map_err(|e| LinkError::Unauthorized(e.to_string()))?;
+
+        let password_hash = password.map(Self::hash_password).transpose()?;
+        self.link_for_creator(&voucher_id, password_hash.as_deref(), amount, window, &creator.address, None, ctx).await
     }
 
-    // Hash password for storage
-    pub fn hash_password(password: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let password_hash = argon2.hash_password(password.as_bytes(), &salt)
-            .map_err(|e| format!("Password hashing failed: {}", e))?;
-        Ok(password_hash.to_string())
+    // Lowercase 0x-prefixed form of a bytes32 voucher id
+    pub fn normalize_voucher_id(voucher_id: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let voucher_id_bytes = H256::from_str(voucher_id)
+            .map_err(|_| "Invalid voucher ID format")?;
+        Ok(format!("0x{}", hex::encode(voucher_id_bytes.as_bytes())))
     }
 
-    // Verify password
-    pub fn verify_password(password: &str, password_hash: &str) -> bool {
-        if let Ok(parsed_hash) = PasswordHash::new(password_hash) {
-            let argon2 = Argon2::default();
-            argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok()
-        } else {
-            false
+    pub fn validate_claim_window(window: &ClaimWindow) -> Result<(), LinkError> {
+        if let (Some(from), Some(until)) = (window.claimable_from, window.claimable_until) {
+            if until <= from {
+                return Err(LinkError::InvalidWindow("claimable_until must be after claimable_from".to_string()));
+            }
+        }
+        if window.claimable_until.is_some_and(|until| until <= Utc::now()) {
+            return Err(LinkError::InvalidWindow("claimable_until must be in the future".to_string()));
         }
+        Ok(())
     }
 
-    // Get voucher by code
-    pub async fn get_voucher_by_code(&self, code: &str) -> Result<Option<VoucherCode>, sqlx::Error> {
-        sqlx::query_as::<_, VoucherCode>(
-            "SELECT * FROM voucher_codes WHERE code = $1"
-        )
-        .bind(code)
-        .fetch_optional(&self.pool)
-        .await
+    pub fn validate_note(note: &GiftNote) -> Result<(), LinkError> {
+        notes::validate(note).map_err(LinkError::InvalidNote)
     }
 
-    // Create claim authorization with signature
-    pub async fn create_claim_authorization(
+    // Encrypt and store the note of a code, or remove it. The key needs the
+    // code's password, so a protected code's password is checked here
+    pub async fn set_note(
         &self,
-        voucher_code: &str,
-        recipient_address: &str,
+        code: &str,
+        note: Option<&GiftNote>,
         password: Option<&str>,
-    ) -> Result<ClaimAuthorization, Box<dyn std::error::Error>> {
-        // Get voucher from DB
-        let voucher = self.get_voucher_by_code(voucher_code).await?
-            .ok_or("Voucher not found")?;
-
-        // Check if already claimed
-        if voucher.claimed {
-            return Err("Voucher already claimed".into());
-        }
-        
-        // Check if cancelled
-        if voucher.cancelled {
-            return Err("Voucher has been cancelled".into());
-        }
+        ctx: &AuditContext,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let voucher = self.get_voucher_by_code(code).await?
+            .ok_or("Code not found")?;
 
-        // Verify password if set
-        if let Some(hash) = &voucher.password_hash {
-            if password.is_none() || !Self::verify_password(password.unwrap(), hash) {
-                return Err("Invalid password".into());
+        let sealed = match note {
+            Some(note) => {
+                Self::validate_note(note)?;
+                let password = match &voucher.password_hash {
+                    Some(hash) => {
+                        let password = password.ok_or_else(|| {
+                            LinkError::InvalidNote("The code's password is needed to encrypt its note".to_string())
+                        })?;
+                        if !Self::verify_password(password, hash) {
+                            return Err(LinkError::InvalidNote("Password does not match the code".to_string()).into());
+                        }
+                        Some(password)
+                    }
+                    None => None,
+                };
+                Some(notes::seal(code, password, note)?)
             }
-        }
+            None => None,
+        };
 
-        // Parse addresses and values
-        let voucher_id = H256::from_str(&voucher.voucher_id)?;
-        let recipient = Address::from_str(recipient_address)?;
-        let deadline = U256::from(chrono::Utc::now().timestamp() as u64 + DEFAULT_DEADLINE_SECONDS);
-        let contract_address = Address::from_str(VOUCHER_CONTRACT)?;
-        let chain_id = U256::from(CHAIN_ID);
+        sqlx::query(
+            "UPDATE voucher_codes SET note_ciphertext = $1, note_content_type = $2 WHERE code = $3"
+        )
+        .bind(&sealed)
+        .bind(note.map(|note| note.content_type.as_str()))
+        .bind(code)
+        .execute(&self.pool)
+        .await?;
 
-        // Create message hash exactly as contract expects
-        // The contract expects: keccak256(abi.encodePacked(voucherId, recipient, deadline, address(this), block.chainid))
-        let mut encoded = Vec::new();
-        encoded.extend_from_slice(voucher_id.as_bytes()); // bytes32
-        encoded.extend_from_slice(recipient.as_bytes()); // address (20 bytes)
-        let deadline_bytes: [u8; 32] = deadline.into();
-        encoded.extend_from_slice(&deadline_bytes); // uint256 (full 32 bytes)
-        encoded.extend_from_slice(contract_address.as_bytes()); // address (20 bytes)
-        let chain_id_bytes: [u8; 32] = chain_id.into();
-        encoded.extend_from_slice(&chain_id_bytes); // uint256 (full 32 bytes)
+        // The note itself never reaches the audit log
+        self.audit.record_or_log(
+            ctx,
+            "voucher.note_updated",
+            "voucher_code",
+            code,
+            Some(json!({ "has_note": voucher.note_ciphertext.is_some() })),
+            Some(json!({
+                "has_note": note.is_some(),
+                "content_type": note.map(|note| note.content_type.as_str()),
+                "bytes": note.map(|note| note.text.len()),
+            })),
+        ).await;
 
-        let message_hash = keccak256(&encoded);
+        Ok(())
+    }
 
-        // Contract uses MessageHashUtils.toEthSignedMessageHash(message) and then recovers the signature
-        // toEthSignedMessageHash adds the EIP-191 prefix: "\x19Ethereum Signed Message:\n32" + message
-        // So we need to create the same prefixed hash and sign it
-        let eth_message_prefix = "\x19Ethereum Signed Message:\n32";
-        let prefixed_message = [eth_message_prefix.as_bytes(), &message_hash].concat();
-        let eth_signed_message_hash = keccak256(&prefixed_message);
-        
-        // Sign the prefixed hash directly (no additional prefix)
-        let signature = self.wallet.sign_hash(H256::from(eth_signed_message_hash))?;
+    // Decrypt the note of a code whose password, if any, was already checked
+    pub fn open_note(voucher: &VoucherCode, password: Option<&str>) -> Option<GiftNote> {
+        let sealed = voucher.note_ciphertext.as_ref()?;
+        let content_type = voucher.note_content_type.as_deref().unwrap_or("text/plain");
+        let password = voucher.password_hash.as_ref().and(password);
 
-        Ok(ClaimAuthorization {
-            voucher_id: voucher.voucher_id,
-            recipient: recipient_address.to_string(),
-            amount: voucher.amount.unwrap_or_else(|| "0".to_string()),
-            deadline: deadline.as_u64(),
-            signature: format!("0x{}", hex::encode(signature.to_vec())),
-            contract_address: VOUCHER_CONTRACT.to_string(),
-        })
+        match notes::open(&voucher.code, password, content_type, sealed) {
+            Ok(note) => Some(note),
+            Err(e) => {
+                warn!("Note of voucher code {} could not be opened: {}", voucher.code, e);
+                None
+            }
+        }
     }
 
-    // Create shareable link for existing on-chain voucher
-    pub async fn create_voucher_link(
+    // Link creation once the caller has proven control of `creator_address`;
+    // the voucher must be unclaimed and created on-chain by that address
+    #[allow(clippy::too_many_arguments)]
+    pub async fn link_for_creator(
         &self,
         voucher_id: &str,
-        password: Option<&str>,
-        creator_address: Option<&str>,
+        password_hash: Option<&str>,
         amount: Option<&str>,
+        window: &ClaimWindow,
+        creator_address: &str,
+        campaign_id: Option<i64>,
+        ctx: &AuditContext,
     ) -> Result<String, Box<dyn std::error::Error>> {
+        let provider = self.provider.as_ref()
+            .ok_or("Provider not configured for on-chain voucher checks")?;
+        let (on_chain_creator, on_chain_amount, claimed) =
+            self.fetch_voucher_from_blockchain(voucher_id, provider).await?;
+
+        // Cancelled vouchers are zeroed on-chain like vouchers that never existed
+        if on_chain_creator == Address::zero() {
+            return Err(LinkError::NotFound.into());
+        }
+        if claimed {
+            return Err(LinkError::AlreadyClaimed.into());
+        }
+        if format!("{:?}", on_chain_creator) != creator_address.to_lowercase() {
+            return Err(LinkError::Unauthorized("Only the voucher creator can manage its link".to_string()).into());
+        }
+        if let Some(amount) = amount {
+            if U256::from_dec_str(amount).ok() != Some(on_chain_amount) {
+                return Err(LinkError::AmountMismatch { on_chain: on_chain_amount.to_string() }.into());
+            }
+        }
+
         // Check if we already have a code for this voucher_id
-        let existing: Option<(String,)> = sqlx::query_as(
-            "SELECT code FROM voucher_codes WHERE voucher_id = $1"
+        let existing: Option<(String, bool)> = sqlx::query_as(
+            r#"
+            SELECT code, password_hash IS NOT NULL FROM voucher_codes
+            WHERE voucher_id = $1 AND revoked_at IS NULL
+            ORDER BY created_at ASC
+            LIMIT 1
+            "#
         )
         .bind(voucher_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some((code,)) = existing {
+        if let Some((code, had_password)) = existing {
+            if let Some(campaign_id) = campaign_id {
+                let mut tx = self.pool.begin().await?;
+                Self::attach_campaign(&mut tx, voucher_id, campaign_id).await?;
+                tx.commit().await?;
+            }
+
             // Update password if provided
-            if let Some(pwd) = password {
-                let password_hash = Self::hash_password(pwd)?;
+            if let Some(password_hash) = password_hash {
+                // The note was sealed under the old password and cannot be read any more
                 sqlx::query(
-                    "UPDATE voucher_codes SET password_hash = $1 WHERE code = $2"
+                    "UPDATE voucher_codes SET password_hash = $1, note_ciphertext = NULL, note_content_type = NULL WHERE code = $2"
                 )
-                .bind(&password_hash)
+                .bind(password_hash)
                 .bind(&code)
                 .execute(&self.pool)
                 .await?;
+
+                // Only whether a password is set is recorded, never the hash
+                self.audit.record_or_log(
+                    ctx,
+                    "voucher.password_updated",
+                    "voucher_code",
+                    &code,
+                    Some(json!({ "has_password": had_password })),
+                    Some(json!({ "has_password": true })),
+                ).await;
+            }
+
+            if window.is_set() {
+                self.update_claim_window(voucher_id, &code, window, ctx).await?;
             }
             return Ok(code);
         }
 
-        // Generate new code
-        let code = crate::services::event_indexer::generate_voucher_code();
-        let password_hash = password.map(Self::hash_password).transpose()?;
+        let creator_address = format!("{:?}", on_chain_creator);
+        let amount = on_chain_amount.to_string();
 
-        // If amount not provided, fetch from blockchain
-        let final_amount = if amount.is_some() {
-            amount.map(|s| s.to_string())
-        } else if let Some(provider) = &self.provider {
-            // Fetch voucher data from blockchain
-            match self.fetch_voucher_from_blockchain(voucher_id, provider).await {
-                Ok((creator, amount_u256, _claimed)) => {
-                    Some(amount_u256.to_string())
-                }
-                Err(e) => {
-                    info!("Failed to fetch amount from blockchain for {}: {}", voucher_id, e);
-                    None
-                }
-            }
-        } else {
-            None
-        };
+        // Generate new code and store mapping
+        let mut tx = self.pool.begin().await?;
+        let code = self.next_code(&mut tx, voucher_id).await?;
 
-        // If creator not provided, fetch from blockchain
-        let final_creator = if creator_address.is_some() {
-            creator_address.map(|s| s.to_string())
-        } else if let Some(provider) = &self.provider {
-            // Fetch voucher data from blockchain
-            match self.fetch_voucher_from_blockchain(voucher_id, provider).await {
-                Ok((creator, _amount_u256, _claimed)) => {
-                    Some(format!("{:?}", creator))
-                }
-                Err(e) => {
-                    info!("Failed to fetch creator from blockchain for {}: {}", voucher_id, e);
-                    None
-                }
-            }
+        // Every code was revoked: the new one joins the voucher as it stands
+        let revoked: Option<VoucherCode> = sqlx::query_as(
+            "SELECT * FROM voucher_codes WHERE voucher_id = $1 ORDER BY created_at ASC LIMIT 1"
+        )
+        .bind(voucher_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(revoked) = &revoked {
+            Self::check_open(revoked)?;
+            Self::insert_code_like(&mut tx, &revoked.code, &code, password_hash, None).await?;
         } else {
-            None
+            sqlx::query(
+                r#"
+                INSERT INTO voucher_codes
+                (code, voucher_id, password_hash, creator_address, amount, claimable_from, claimable_until)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#
+            )
+            .bind(&code)
+            .bind(voucher_id)
+            .bind(password_hash)
+            .bind(&creator_address)
+            .bind(&amount)
+            .bind(window.claimable_from)
+            .bind(window.claimable_until)
+            .execute(&mut *tx)
+            .await?;
+            VoucherLifecycle::record_created(&mut *tx, voucher_id, None, ctx).await?;
+        }
+        // Attached with the insert, so a lost race leaves no code behind
+        if let Some(campaign_id) = campaign_id {
+            Self::attach_campaign(&mut tx, voucher_id, campaign_id).await?;
+        }
+        tx.commit().await?;
+
+        if revoked.is_some() && window.is_set() {
+            self.update_claim_window(voucher_id, &code, window, ctx).await?;
+        }
+
+        self.audit.record_or_log(
+            ctx,
+            "voucher.link_created",
+            "voucher_code",
+            &code,
+            None,
+            Some(json!({
+                "voucher_id": voucher_id,
+                "has_password": password_hash.is_some(),
+                "creator_address": creator_address,
+                "amount": amount,
+                "claimable_from": window.claimable_from,
+                "claimable_until": window.claimable_until,
+            })),
+        ).await;
+
+        Ok(code)
+    }
+
+    // Set the supplied ends of the claim window on every code of a voucher;
+    // an end left out keeps its current value
+    async fn update_claim_window(
+        &self,
+        voucher_id: &str,
+        code: &str,
+        window: &ClaimWindow,
+        ctx: &AuditContext,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let before: Option<ClaimWindow> = sqlx::query_as(
+            "SELECT claimable_from, claimable_until FROM voucher_codes WHERE code = $1"
+        )
+        .bind(code)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let window = ClaimWindow {
+            claimable_from: window.claimable_from.or(before.as_ref().and_then(|before| before.claimable_from)),
+            claimable_until: window.claimable_until.or(before.as_ref().and_then(|before| before.claimable_until)),
         };
+        if let (Some(from), Some(until)) = (window.claimable_from, window.claimable_until) {
+            if until <= from {
+                return Err(LinkError::InvalidWindow("claimable_until must be after claimable_from".to_string()).into());
+            }
+        }
 
-        // Store mapping
         sqlx::query(
             r#"
-            INSERT INTO voucher_codes (code, voucher_id, password_hash, creator_address, amount)
-            VALUES ($1, $2, $3, $4, $5)
+            UPDATE voucher_codes
+            SET claimable_from = $1, claimable_until = $2, expiry_notified_at = NULL
+            WHERE voucher_id = $3
             "#
         )
-        .bind(&code)
+        .bind(window.claimable_from)
+        .bind(window.claimable_until)
         .bind(voucher_id)
-        .bind(&password_hash)
-        .bind(&final_creator)
-        .bind(&final_amount)
         .execute(&self.pool)
         .await?;
 
-        Ok(code)
+        self.audit.record_or_log(
+            ctx,
+            "voucher.window_updated",
+            "voucher",
+            voucher_id,
+            before.map(|before| json!(before)),
+            Some(json!(window)),
+        ).await;
+
+        // A later end date reopens an expired voucher
+        if window.claimable_until.is_none_or(|until| until > Utc::now()) {
+            self.lifecycle.transition_from(
+                voucher_id,
+                VoucherStatus::Expired,
+                VoucherStatus::Active,
+                &StatusChange { detail: Some(json!({ "claimable_until": window.claimable_until })), ..Default::default() },
+                ctx,
+            ).await?;
+        }
+
+        Ok(())
     }
     
     // Helper method to fetch voucher data from blockchain
@@ -266,24 +1414,57 @@ impl VoucherService {
         code: &str,
         tx_hash: &str,
         success: bool,
-        claimed_by: &str,
+        ctx: &AuditContext,
     ) -> Result<(), Box<dyn std::error::Error>> {
         if success {
-            sqlx::query(
-                r#"
-                UPDATE voucher_codes 
-                SET claimed = true, 
-                    claimed_by = $1, 
-                    claimed_at = NOW(), 
-                    claim_tx_hash = $2
-                WHERE code = $3
-                "#
-            )
-            .bind(claimed_by)
-            .bind(tx_hash)
-            .bind(code)
-            .execute(&self.pool)
-            .await?;
+            let before = self.get_voucher_by_code(code).await?;
+            let voucher_id = before.as_ref()
+                .map(|v| v.voucher_id.clone())
+                .ok_or(LifecycleError::NotFound)?;
+
+            // Claimed is final, so only the chain may say so; the recipient
+            // comes from the log rather than the caller
+            let claimed_by = self.verify_claim_tx(&voucher_id, tx_hash).await?;
+
+            self.lifecycle.transition(
+                &voucher_id,
+                VoucherStatus::Claimed,
+                &StatusChange {
+                    tx_hash: Some(tx_hash.to_string()),
+                    claimed_by: Some(claimed_by.to_string()),
+                    ..Default::default()
+                },
+                ctx,
+            ).await?;
+
+            self.audit.record_or_log(
+                ctx,
+                "voucher.claim_status_updated",
+                "voucher_code",
+                code,
+                before.map(|v| json!({
+                    "claimed": v.claimed,
+                    "claimed_by": v.claimed_by,
+                    "claim_tx_hash": v.claim_tx_hash,
+                })),
+                Some(json!({
+                    "claimed": true,
+                    "claimed_by": claimed_by,
+                    "claim_tx_hash": tx_hash,
+                })),
+            ).await;
+
+            if let Some(webhooks) = &self.webhooks {
+                webhooks.emit_or_log(
+                    WebhookEvent::VoucherClaimed,
+                    &format!("voucher.claimed:{}", voucher_id),
+                    json!({
+                        "voucher_id": voucher_id,
+                        "claimed_by": claimed_by,
+                        "tx_hash": tx_hash,
+                    }),
+                ).await;
+            }
 
             info!("Voucher {} successfully claimed by {} in tx {}", code, claimed_by, tx_hash);
         }
@@ -291,6 +1472,24 @@ impl VoucherService {
         Ok(())
     }
 
+    // The recipient of a mined transaction that claimed the voucher on the
+    // voucher contract
+    async fn verify_claim_tx(&self, voucher_id: &str, tx_hash: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let provider = self.provider.as_ref()
+            .ok_or("Provider not configured to verify claims")?;
+        let receipt = provider.get_transaction_receipt(H256::from_str(tx_hash)?).await?;
+
+        let contract_address: Address = VOUCHER_CONTRACT.parse()?;
+        let voucher_id = H256::from_str(voucher_id)?;
+        let recipient = receipt.as_ref()
+            .filter(|receipt| receipt.status == Some(1.into()))
+            .and_then(|receipt| claim_log(receipt, voucher_id))
+            .filter(|log| log.address == contract_address)
+            .and_then(|log| log.topics.get(2))
+            .ok_or(ClaimError::ClaimNotOnChain)?;
+        Ok(format!("{:?}", Address::from(*recipient)))
+    }
+
     // List vouchers for a user
     pub async fn list_user_vouchers(
         &self,
@@ -305,9 +1504,12 @@ impl VoucherService {
             "created" => {
                 sqlx::query_as::<_, VoucherCode>(
                     r#"
-                    SELECT * FROM voucher_codes 
-                    WHERE LOWER(creator_address) = LOWER($1)
-                    AND cancelled = FALSE
+                    SELECT * FROM (
+                        SELECT DISTINCT ON (voucher_id) * FROM voucher_codes
+                        WHERE LOWER(creator_address) = LOWER($1)
+                        AND status NOT IN ('cancelled', 'withdrawn')
+                        ORDER BY voucher_id, (revoked_at IS NULL) DESC, created_at ASC
+                    ) v
                     ORDER BY created_at DESC
                     LIMIT $2 OFFSET $3
                     "#
@@ -321,8 +1523,11 @@ impl VoucherService {
             "received" => {
                 sqlx::query_as::<_, VoucherCode>(
                     r#"
-                    SELECT * FROM voucher_codes 
-                    WHERE LOWER(claimed_by) = LOWER($1)
+                    SELECT * FROM (
+                        SELECT DISTINCT ON (voucher_id) * FROM voucher_codes
+                        WHERE LOWER(claimed_by) = LOWER($1)
+                        ORDER BY voucher_id, (revoked_at IS NULL) DESC, created_at ASC
+                    ) v
                     ORDER BY claimed_at DESC
                     LIMIT $2 OFFSET $3
                     "#
@@ -347,63 +1552,300 @@ impl VoucherService {
         voucher_code: &str,
         recipient_address: &str,
         password: Option<&str>,
+        merkle_proof: &[String],
+        client_ip: &str,
+        ctx: &AuditContext,
     ) -> Result<String, Box<dyn std::error::Error>> {
-        // First create the claim authorization to validate everything
-        let auth = self.create_claim_authorization(voucher_code, recipient_address, password).await?;
-        
-        // Get provider
-        let provider = self.provider.as_ref()
-            .ok_or("Provider not configured for gasless claims")?;
-        
-        // Create signer
-        let signer = SignerMiddleware::new(provider.clone(), self.wallet.clone());
-        
-        // Parse contract ABI
-        let abi = ethers::abi::parse_abi(&[
-            "function claimVoucher(bytes32 voucherId, address recipient, uint256 deadline, bytes memory signature) external"
-        ])?;
-        
-        // Create contract instance
-        let contract_address: Address = VOUCHER_CONTRACT.parse()?;
-        let contract = Contract::new(contract_address, abi, Arc::new(signer));
-        
-        // Parse parameters
-        let voucher_id = H256::from_str(&auth.voucher_id)?;
-        let recipient = Address::from_str(&auth.recipient)?;
-        let deadline = U256::from(auth.deadline);
-        let signature_bytes = hex::decode(&auth.signature[2..])?; // Remove 0x prefix
-        
-        // Build and send transaction
-        let tx_call = contract
-            .method::<_, ()>("claimVoucher", (voucher_id, recipient, deadline, signature_bytes))?
-            .gas(CLAIM_VOUCHER_GAS);
-            
-        let pending_tx = tx_call.send().await?;
-        let tx_hash_bytes = pending_tx.tx_hash();
-        let tx_hash = format!("0x{}", hex::encode(tx_hash_bytes));
+        // Refuse unsponsored claims before an authorization is issued, so the
+        // recipient can still claim from their own wallet
+        let sponsorship = match &self.relay_policy {
+            Some(policy) => match self.get_voucher_by_code(voucher_code).await? {
+                Some(voucher) => {
+                    let request = sponsorship_request(&voucher, recipient_address, client_ip);
+                    check_sponsored(policy.evaluate(&request).await)?;
+                    Some((policy, request))
+                }
+                None => None,
+            },
+            None => None,
+        };
+
+        // One submission at a time per voucher; a second would only revert
+        let lock = self.lock_claim(voucher_code, ctx).await?;
+        let (tx_hash, batch_id) = match self.relay_claim(
+            voucher_code, recipient_address, password, merkle_proof, client_ip, sponsorship,
+        ).await {
+            Ok(sent) => sent,
+            Err(e) => {
+                if let Some(lock) = &lock {
+                    self.release_claim_lock(lock, ctx).await;
+                }
+                return Err(e);
+            }
+        };
         
         info!(
             "Submitted gasless claim transaction {} for voucher {} to recipient {}",
             tx_hash, voucher_code, recipient_address
         );
         
-        // Update claim status to pending
+        // Update claim status to pending, which holds the lock until the
+        // monitor settles the transaction; a batch shares its tx hash
         sqlx::query(
             r#"
             UPDATE voucher_codes 
             SET claim_tx_hash = $1,
                 claim_tx_status = 'pending',
-                claim_tx_submitted_at = NOW()
-            WHERE code = $2
+                claim_tx_submitted_at = NOW(),
+                claim_batch_id = $3
+            WHERE voucher_id = (SELECT voucher_id FROM voucher_codes WHERE code = $2)
             "#
         )
         .bind(&tx_hash)
         .bind(voucher_code)
+        .bind(batch_id)
         .execute(&self.pool)
         .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "voucher.claim_submitted",
+            "voucher_code",
+            voucher_code,
+            None,
+            Some(json!({
+                "recipient": recipient_address,
+                "claim_tx_hash": tx_hash,
+                "claim_tx_status": "pending",
+                "claim_batch_id": batch_id,
+            })),
+        ).await;
         
         // Return immediately - we'll monitor the transaction status via another endpoint
         // In production, you'd want a background worker to monitor pending transactions
         Ok(tx_hash)
     }
-}
\ No newline at end of file
+
+    // Move the voucher to claiming, and its claim to 'submitting', under a
+    // row lock so concurrent submissions see each other. None for unknown or
+    // closed vouchers, which fail authorization anyway
+    async fn lock_claim(
+        &self,
+        voucher_code: &str,
+        ctx: &AuditContext,
+    ) -> Result<Option<ClaimLock>, Box<dyn std::error::Error>> {
+        let mut tx = self.pool.begin().await?;
+
+        let current: Option<ClaimState> = sqlx::query_as(
+            r#"
+            SELECT voucher_id, status, claim_tx_status, claim_tx_hash, claim_tx_submitted_at
+            FROM voucher_codes
+            WHERE voucher_id = (SELECT voucher_id FROM voucher_codes WHERE code = $1)
+            ORDER BY (revoked_at IS NULL) DESC, created_at ASC
+            FOR UPDATE
+            "#
+        )
+        .bind(voucher_code)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(ClaimState {
+            voucher_id,
+            status,
+            claim_tx_status,
+            claim_tx_hash,
+            claim_tx_submitted_at,
+        }) = current else {
+            return Ok(None);
+        };
+        let status = VoucherStatus::parse(&status);
+        if !matches!(status, Some(VoucherStatus::Active | VoucherStatus::Claiming)) {
+            return Ok(None);
+        }
+
+        check_claim_lock(claim_tx_status.as_deref(), claim_tx_hash, claim_tx_submitted_at)?;
+        if status == Some(VoucherStatus::Claiming) {
+            warn!("Taking over stale claim submission for voucher {}", voucher_id);
+        }
+
+        VoucherLifecycle::transition_in(
+            &mut tx,
+            &voucher_id,
+            Some(VoucherStatus::Active),
+            VoucherStatus::Claiming,
+            &StatusChange { detail: Some(json!({ "code": voucher_code })), ..Default::default() },
+            ctx,
+        ).await?;
+        sqlx::query(
+            "UPDATE voucher_codes SET claim_tx_status = 'submitting', claim_tx_submitted_at = NOW() WHERE voucher_id = $1"
+        )
+        .bind(&voucher_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        // A stale lock was never sent, so there is nothing to go back to
+        let (previous_status, previous_submitted_at) = match claim_tx_status.as_deref() {
+            Some("submitting") => (None, None),
+            _ => (claim_tx_status, claim_tx_submitted_at),
+        };
+        Ok(Some(ClaimLock { voucher_id, previous_status, previous_submitted_at }))
+    }
+
+    async fn release_claim_lock(&self, lock: &ClaimLock, ctx: &AuditContext) {
+        let released: Result<(), Box<dyn std::error::Error>> = async {
+            let mut tx = self.pool.begin().await?;
+            let restored = sqlx::query(
+                r#"
+                UPDATE voucher_codes
+                SET claim_tx_status = $2, claim_tx_submitted_at = $3
+                WHERE voucher_id = $1 AND claim_tx_status = 'submitting'
+                "#
+            )
+            .bind(&lock.voucher_id)
+            .bind(&lock.previous_status)
+            .bind(lock.previous_submitted_at)
+            .execute(&mut *tx)
+            .await?;
+            if restored.rows_affected() > 0 {
+                VoucherLifecycle::transition_in(
+                    &mut tx,
+                    &lock.voucher_id,
+                    Some(VoucherStatus::Claiming),
+                    VoucherStatus::Active,
+                    &StatusChange { detail: Some(json!({ "reason": "claim not sent" })), ..Default::default() },
+                    ctx,
+                ).await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        }.await;
+        if let Err(e) = released {
+            error!("Failed to release claim lock of voucher {}: {}", lock.voucher_id, e);
+        }
+    }
+
+    // Authorize the claim and send it, alone or in a batch
+    async fn relay_claim(
+        &self,
+        voucher_code: &str,
+        recipient_address: &str,
+        password: Option<&str>,
+        merkle_proof: &[String],
+        client_ip: &str,
+        sponsorship: Option<(&Arc<dyn RelayPolicy>, RelayRequest)>,
+    ) -> Result<(String, Option<i64>), Box<dyn std::error::Error>> {
+        // First create the claim authorization to validate everything
+        let auth = self.create_claim_authorization(voucher_code, recipient_address, password, merkle_proof, client_ip).await?;
+
+        // Only a claim about to be relayed counts against the quotas
+        if let Some((policy, request)) = &sponsorship {
+            check_sponsored(policy.decide(request).await)?;
+        }
+
+        let sent = self.send_claim(&auth).await;
+        if let (Err(e), Some((policy, request))) = (&sent, &sponsorship) {
+            policy.unsent(request, &e.to_string()).await;
+        }
+        sent
+    }
+
+    async fn send_claim(&self, auth: &ClaimAuthorization) -> Result<(String, Option<i64>), Box<dyn std::error::Error>> {
+        // Parse parameters
+        let voucher_id = H256::from_str(&auth.voucher_id)?;
+        let recipient = Address::from_str(&auth.recipient)?;
+        let deadline = U256::from(auth.deadline);
+        let signature_bytes = hex::decode(&auth.signature[2..])?; // Remove 0x prefix
+
+        let (tx_hash, batch_id) = match &self.claim_batcher {
+            Some(batcher) => {
+                let batched = batcher.submit(ClaimCall {
+                    voucher_id,
+                    recipient,
+                    deadline,
+                    signature: signature_bytes.into(),
+                }).await?;
+                (batched.tx_hash, batched.batch_id)
+            }
+            None => {
+                // Get provider
+                let provider = self.provider.as_ref()
+                    .ok_or("Provider not configured for gasless claims")?;
+
+                // Create signer
+                let signer = SignerMiddleware::new(provider.clone(), self.signers.signing());
+
+                // Parse contract ABI
+                let abi = ethers::abi::parse_abi(&[
+                    "function claimVoucher(bytes32 voucherId, address recipient, uint256 deadline, bytes memory signature) external"
+                ])?;
+
+                // Create contract instance
+                let contract_address: Address = VOUCHER_CONTRACT.parse()?;
+                let contract = Contract::new(contract_address, abi, Arc::new(signer));
+
+                // Build and send transaction; the signature goes as bytes, not uint8[]
+                let tx_call = contract
+                    .method::<_, ()>("claimVoucher", (voucher_id, recipient, deadline, Bytes::from(signature_bytes)))?
+                    .gas(CLAIM_VOUCHER_GAS);
+
+                let pending_tx = tx_call.send().await?;
+                let tx_hash_bytes = pending_tx.tx_hash();
+                (format!("0x{}", hex::encode(tx_hash_bytes)), None)
+            }
+        };
+        Ok((tx_hash, batch_id))
+    }
+}
+
+// What a create_link signature covers besides the voucher id; window ends
+// are unix seconds
+pub fn create_link_params(password: Option<&str>, amount: Option<&str>, window: &ClaimWindow) -> Value {
+    json!({
+        "password": password,
+        "amount": amount,
+        "claimable_from": window.claimable_from.map(|at| at.timestamp()),
+        "claimable_until": window.claimable_until.map(|at| at.timestamp()),
+    })
+}
+
+// A voucher is locked while its claim is being sent ('submitting', until
+// that goes stale) and while the sent transaction is 'pending'
+pub fn check_claim_lock(
+    status: Option<&str>,
+    tx_hash: Option<String>,
+    submitted_at: Option<DateTime<Utc>>,
+) -> Result<(), ClaimError> {
+    match status {
+        Some("pending") => Err(ClaimError::ClaimInProgress { tx_hash }),
+        Some("submitting") => {
+            let stale = submitted_at
+                .map(|at| (Utc::now() - at).num_seconds() >= CLAIM_LOCK_SECS)
+                .unwrap_or(true);
+            if stale { Ok(()) } else { Err(ClaimError::ClaimInProgress { tx_hash: None }) }
+        }
+        _ => Ok(()),
+    }
+}
+
+fn sponsorship_request(voucher: &VoucherCode, recipient_address: &str, client_ip: &str) -> RelayRequest {
+    RelayRequest {
+        kind: RelayKind::Claim,
+        subject: Some(voucher.code.clone()),
+        creator: voucher.creator_address.clone().unwrap_or_default(),
+        recipient: Some(recipient_address.to_string()),
+        campaign_id: voucher.campaign_id,
+        amount: voucher.amount.as_deref()
+            .and_then(|amount| U256::from_dec_str(amount).ok())
+            .unwrap_or_default(),
+        gas_limit: CLAIM_VOUCHER_GAS,
+        client_ip: client_ip.to_string(),
+    }
+}
+
+// Claims cannot pay the relayer, so anything short of sponsorship refuses
+fn check_sponsored(decision: PolicyDecision) -> Result<(), ClaimError> {
+    match decision {
+        PolicyDecision::Sponsor { .. } => Ok(()),
+        decision => Err(ClaimError::NotSponsored { reason: decision.reason().to_string() }),
+    }
+}
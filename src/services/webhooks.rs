@@ -0,0 +1,438 @@
+use crate::config::WebhookConfig;
+use crate::db::webhook_models::*;
+use crate::services::audit::{AuditContext, AuditLog};
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+// Events integrators can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    VoucherCreated,
+    VoucherClaimed,
+    VoucherCancelled,
+    ClaimTxConfirmed,
+    ClaimTxFailed,
+    NbgnMinted,
+    NbgnRedeemed,
+    NbgnBurned,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 8] = [
+        WebhookEvent::VoucherCreated,
+        WebhookEvent::VoucherClaimed,
+        WebhookEvent::VoucherCancelled,
+        WebhookEvent::ClaimTxConfirmed,
+        WebhookEvent::ClaimTxFailed,
+        WebhookEvent::NbgnMinted,
+        WebhookEvent::NbgnRedeemed,
+        WebhookEvent::NbgnBurned,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::VoucherCreated => "voucher.created",
+            WebhookEvent::VoucherClaimed => "voucher.claimed",
+            WebhookEvent::VoucherCancelled => "voucher.cancelled",
+            WebhookEvent::ClaimTxConfirmed => "claim_tx.confirmed",
+            WebhookEvent::ClaimTxFailed => "claim_tx.failed",
+            WebhookEvent::NbgnMinted => "nbgn.minted",
+            WebhookEvent::NbgnRedeemed => "nbgn.redeemed",
+            WebhookEvent::NbgnBurned => "nbgn.burned",
+        }
+    }
+
+    pub fn parse(event: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.as_str() == event)
+    }
+}
+
+// Subscribes to every event, including ones added later
+pub const ALL_EVENTS: &str = "*";
+
+// HMAC-SHA256 over "{timestamp}.{body}", sent as X-NBGN-Signature so
+// receivers can check both origin and freshness
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// Webhook endpoints must be absolute http(s) URLs
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "Invalid webhook URL".to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err("Webhook URL must be http or https".to_string());
+    }
+    Ok(())
+}
+
+// Delay before the next attempt after `attempts` failed ones: doubling from
+// the initial backoff, capped at the maximum
+pub fn backoff(config: &WebhookConfig, attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 30) as u32;
+    let secs = config.initial_backoff_secs.saturating_mul(1u64 << exponent);
+    Duration::from_secs(secs.min(config.max_backoff_secs))
+}
+
+// The body every subscriber receives for an event
+pub fn envelope(event_id: i64, event_type: &str, created_at: DateTime<Utc>, data: &Value) -> Value {
+    json!({
+        "id": format!("evt_{}", event_id),
+        "type": event_type,
+        "created_at": created_at,
+        "data": data,
+    })
+}
+
+// Outbound webhooks: events are stored once, fanned out to a delivery row per
+// matching subscription and sent by a worker that retries with backoff
+#[derive(Clone)]
+pub struct WebhookService {
+    pool: PgPool,
+    client: reqwest::Client,
+    config: WebhookConfig,
+    audit: AuditLog,
+}
+
+impl WebhookService {
+    pub fn new(pool: PgPool, config: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_secs))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            audit: AuditLog::new(pool.clone()),
+            pool,
+            client,
+            config,
+        }
+    }
+
+    pub fn validate_subscription(url: &str, events: &[String]) -> Result<(), String> {
+        validate_url(url)?;
+        if events.is_empty() {
+            return Err("At least one event is required".to_string());
+        }
+        if let Some(unknown) = events.iter().find(|e| *e != ALL_EVENTS && WebhookEvent::parse(e).is_none()) {
+            return Err(format!("Unknown event: {}", unknown));
+        }
+        Ok(())
+    }
+
+    // The signing secret is only ever returned here, on the new subscription
+    pub async fn create_subscription(
+        &self,
+        url: &str,
+        events: &[String],
+        description: Option<&str>,
+        ctx: &AuditContext,
+    ) -> Result<WebhookSubscription, Box<dyn std::error::Error>> {
+        Self::validate_subscription(url, events)?;
+
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let secret = format!("whsec_{}", hex::encode(secret));
+
+        let mut events = events.to_vec();
+        events.sort();
+        events.dedup();
+
+        let subscription: WebhookSubscription = sqlx::query_as(
+            r#"
+            INSERT INTO webhook_subscriptions (url, secret, events, description)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#
+        )
+        .bind(url)
+        .bind(&secret)
+        .bind(&events)
+        .bind(description)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.audit.record_or_log(
+            ctx,
+            "webhook.subscription_created",
+            "webhook_subscription",
+            &subscription.id.to_string(),
+            None,
+            Some(json!({ "url": subscription.url, "events": subscription.events })),
+        ).await;
+
+        info!("Created webhook subscription {} for {}", subscription.id, subscription.url);
+        Ok(subscription)
+    }
+
+    pub async fn list_subscriptions(&self) -> Result<Vec<WebhookSubscription>, sqlx::Error> {
+        sqlx::query_as("SELECT * FROM webhook_subscriptions ORDER BY id")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    // Stop delivering to a subscription; its delivery log is kept
+    pub async fn deactivate_subscription(&self, id: i64, ctx: &AuditContext) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE webhook_subscriptions SET active = FALSE WHERE id = $1 AND active")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() > 0 {
+            self.audit.record_or_log(
+                ctx,
+                "webhook.subscription_deactivated",
+                "webhook_subscription",
+                &id.to_string(),
+                Some(json!({ "active": true })),
+                Some(json!({ "active": false })),
+            ).await;
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Store an event and queue it for every matching subscription. The dedupe
+    // key makes re-emitting the same event, e.g. on an indexer replay, a no-op
+    pub async fn emit(&self, event: WebhookEvent, dedupe_key: &str, data: Value) -> Result<Option<i64>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let event_id: Option<(i64,)> = sqlx::query_as(
+            r#"
+            INSERT INTO webhook_events (event_type, dedupe_key, payload)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (dedupe_key) DO NOTHING
+            RETURNING id
+            "#
+        )
+        .bind(event.as_str())
+        .bind(dedupe_key)
+        .bind(Json(&data))
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((event_id,)) = event_id else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (event_id, subscription_id)
+            SELECT $1, id FROM webhook_subscriptions
+            WHERE active AND ($2 = ANY(events) OR $3 = ANY(events))
+            "#
+        )
+        .bind(event_id)
+        .bind(event.as_str())
+        .bind(ALL_EVENTS)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(event_id))
+    }
+
+    // Emit after the change has already been applied: a failed write is
+    // logged rather than failing the operation that produced the event
+    pub async fn emit_or_log(&self, event: WebhookEvent, dedupe_key: &str, data: Value) {
+        if let Err(e) = self.emit(event, dedupe_key, data).await {
+            error!("Failed to queue webhook event {} ({}): {}", event.as_str(), dedupe_key, e);
+        }
+    }
+
+    pub async fn run_delivery_loop(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.delivery_interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            match self.deliver_due().await {
+                Ok(0) => debug!("No webhook deliveries due"),
+                Ok(count) => info!("Attempted {} webhook deliveries", count),
+                Err(e) => error!("Error in webhook delivery cycle: {}", e),
+            }
+        }
+    }
+
+    // Send every due delivery once. Claimed rows are leased by pushing their
+    // next attempt past the request timeout, so other instances skip them
+    pub async fn deliver_due(&self) -> Result<usize, sqlx::Error> {
+        let due: Vec<PendingDelivery> = sqlx::query_as(
+            r#"
+            WITH due AS (
+                SELECT d.id FROM webhook_deliveries d
+                JOIN webhook_subscriptions s ON s.id = d.subscription_id
+                WHERE d.status = 'pending' AND d.next_attempt_at <= NOW() AND s.active
+                ORDER BY d.next_attempt_at
+                LIMIT $1
+                FOR UPDATE OF d SKIP LOCKED
+            )
+            UPDATE webhook_deliveries d
+            SET next_attempt_at = NOW() + make_interval(secs => $2)
+            FROM due, webhook_events e, webhook_subscriptions s
+            WHERE d.id = due.id AND e.id = d.event_id AND s.id = d.subscription_id
+            RETURNING d.id, d.event_id, e.event_type, d.attempts, s.url, s.secret, e.payload,
+                      e.created_at AS event_created_at
+            "#
+        )
+        .bind(self.config.batch_size)
+        .bind((self.config.request_timeout_secs * 2 + 30) as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let count = due.len();
+        for result in join_all(due.iter().map(|delivery| self.attempt(delivery))).await {
+            result?;
+        }
+        Ok(count)
+    }
+
+    async fn attempt(&self, delivery: &PendingDelivery) -> Result<(), sqlx::Error> {
+        let body = envelope(delivery.event_id, &delivery.event_type, delivery.event_created_at, &delivery.payload.0);
+        let body = serde_json::to_vec(&body).unwrap_or_default();
+        let timestamp = Utc::now().timestamp();
+
+        let started = Instant::now();
+        let response = self.client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header("X-NBGN-Event", &delivery.event_type)
+            .header("X-NBGN-Delivery", delivery.id.to_string())
+            .header("X-NBGN-Timestamp", timestamp.to_string())
+            .header("X-NBGN-Signature", sign_payload(&delivery.secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await;
+        let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+        let (status_code, error) = match response {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16() as i32), None),
+            Ok(response) => (Some(response.status().as_u16() as i32), Some(format!("HTTP {}", response.status()))),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        sqlx::query(
+            "INSERT INTO webhook_delivery_attempts (delivery_id, status_code, error, duration_ms) VALUES ($1, $2, $3, $4)"
+        )
+        .bind(delivery.id)
+        .bind(status_code)
+        .bind(&error)
+        .bind(duration_ms)
+        .execute(&self.pool)
+        .await?;
+
+        let attempts = delivery.attempts + 1;
+        match &error {
+            None => {
+                sqlx::query(
+                    r#"
+                    UPDATE webhook_deliveries
+                    SET status = 'delivered', attempts = $1, last_status_code = $2, last_error = NULL, delivered_at = NOW()
+                    WHERE id = $3
+                    "#
+                )
+                .bind(attempts)
+                .bind(status_code)
+                .bind(delivery.id)
+                .execute(&self.pool)
+                .await?;
+            }
+            Some(error) => {
+                let exhausted = attempts >= self.config.max_attempts;
+                if exhausted {
+                    warn!("Webhook delivery {} failed after {} attempts: {}", delivery.id, attempts, error);
+                }
+                sqlx::query(
+                    r#"
+                    UPDATE webhook_deliveries
+                    SET status = CASE WHEN $1 THEN 'failed' ELSE 'pending' END,
+                        attempts = $2,
+                        last_status_code = $3,
+                        last_error = $4,
+                        next_attempt_at = NOW() + make_interval(secs => $5)
+                    WHERE id = $6
+                    "#
+                )
+                .bind(exhausted)
+                .bind(attempts)
+                .bind(status_code)
+                .bind(error)
+                .bind(backoff(&self.config, attempts).as_secs() as f64)
+                .bind(delivery.id)
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_deliveries(&self, subscription_id: i64, query: &DeliveryQuery) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT d.id, d.event_id, d.subscription_id, e.event_type, d.status, d.attempts, d.next_attempt_at,
+                   d.last_status_code, d.last_error, d.delivered_at, d.created_at
+            FROM webhook_deliveries d
+            JOIN webhook_events e ON e.id = d.event_id
+            WHERE d.subscription_id = $1
+              AND ($2::text IS NULL OR d.status = $2)
+            ORDER BY d.created_at DESC, d.id DESC
+            LIMIT $3 OFFSET $4
+            "#
+        )
+        .bind(subscription_id)
+        .bind(&query.status)
+        .bind(query.limit.unwrap_or(50).clamp(1, 500))
+        .bind(query.offset.unwrap_or(0).max(0))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn list_attempts(&self, delivery_id: i64) -> Result<Vec<WebhookDeliveryAttempt>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT * FROM webhook_delivery_attempts WHERE delivery_id = $1 ORDER BY attempted_at DESC, id DESC"
+        )
+        .bind(delivery_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    // Queue a delivery again, whatever its state, with a fresh retry budget
+    pub async fn redeliver(&self, delivery_id: i64, ctx: &AuditContext) -> Result<bool, sqlx::Error> {
+        let previous: Option<(String, i32)> = sqlx::query_as(
+            r#"
+            UPDATE webhook_deliveries d
+            SET status = 'pending', attempts = 0, next_attempt_at = NOW()
+            FROM webhook_deliveries prev
+            WHERE d.id = $1 AND prev.id = d.id
+            RETURNING prev.status, prev.attempts
+            "#
+        )
+        .bind(delivery_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((status, attempts)) = previous else {
+            return Ok(false);
+        };
+
+        self.audit.record_or_log(
+            ctx,
+            "webhook.redelivery_requested",
+            "webhook_delivery",
+            &delivery_id.to_string(),
+            Some(json!({ "status": status, "attempts": attempts })),
+            Some(json!({ "status": "pending", "attempts": 0 })),
+        ).await;
+        Ok(true)
+    }
+}
@@ -0,0 +1,68 @@
+use ethers::prelude::*;
+use nbgn_backend::db::voucher_models::ClaimRequest;
+use nbgn_backend::services::allowlist::{hash_pair, recipient_leaf, verify_proof};
+use nbgn_backend::services::audit::AuditContext;
+use serde_json::json;
+use test_utils::{lazy_pool, voucher_service};
+
+mod test_utils;
+
+const VOUCHER_ID: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+fn address(n: u8) -> Address {
+    Address::from_low_u64_be(n as u64 + 1)
+}
+
+#[test]
+fn test_merkle_proof_verification() {
+    // Four leaves: root = H(H(a, b), H(c, d))
+    let leaves: Vec<H256> = (0..4).map(|n| recipient_leaf(address(n))).collect();
+    let left = hash_pair(leaves[0], leaves[1]);
+    let right = hash_pair(leaves[2], leaves[3]);
+    let root = hash_pair(left, right);
+
+    assert!(verify_proof(root, address(0), &[leaves[1], right]));
+    assert!(verify_proof(root, address(3), &[leaves[2], left]));
+
+    // Pairs are sorted, so sibling order in the proof does not depend on position
+    assert_eq!(hash_pair(left, right), hash_pair(right, left));
+
+    // Someone else's proof, a truncated proof or an outsider all fail
+    assert!(!verify_proof(root, address(1), &[leaves[1], right]));
+    assert!(!verify_proof(root, address(0), &[leaves[1]]));
+    assert!(!verify_proof(root, address(9), &[leaves[1], right]));
+}
+
+#[test]
+fn test_claim_request_proof_is_optional() {
+    let req: ClaimRequest = serde_json::from_value(json!({
+        "code": "ABCD1234EFGH5678",
+        "recipient_address": "0x1111111111111111111111111111111111111111"
+    })).unwrap();
+    assert!(req.merkle_proof.is_empty());
+}
+
+#[actix_rt::test]
+async fn test_recipient_restriction_validation() {
+    // Invalid input is rejected before the database is touched
+    let service = voucher_service(lazy_pool());
+    let ctx = AuditContext::system("test");
+    let root = format!("{:?}", H256::repeat_byte(7));
+
+    let bad = vec!["0x1234".to_string()];
+    let err = service.set_recipient_restriction(VOUCHER_ID, Some(&bad), None, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("Invalid address"));
+
+    let err = service.set_recipient_restriction(VOUCHER_ID, Some(&[]), None, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("addresses are required"));
+
+    let too_many: Vec<String> = (0..1001u64).map(|n| format!("{:?}", Address::from_low_u64_be(n))).collect();
+    assert!(service.set_recipient_restriction(VOUCHER_ID, Some(&too_many), None, &ctx).await.is_err());
+
+    let one = vec![format!("{:?}", address(0))];
+    let err = service.set_recipient_restriction(VOUCHER_ID, Some(&one), Some(&root), &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("not both"));
+
+    let err = service.set_recipient_restriction(VOUCHER_ID, None, Some("0xnotahash"), &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("Merkle root"));
+}
@@ -0,0 +1,199 @@
+use chrono::{TimeZone, Utc};
+use ethers::prelude::*;
+use nbgn_backend::db::campaign_models::{BulkLinkRequest, Campaign, CampaignExportRow, PasswordPolicy};
+use nbgn_backend::db::voucher_models::ClaimWindow;
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::campaign::{bulk_link_params, export_csv, CampaignService, MAX_BULK_LINKS};
+use serde_json::json;
+use nbgn_backend::services::lifecycle::{StatusChange, VoucherLifecycle, VoucherStatus};
+use test_utils::{creator_auth as sign, creator_auth_with_params, insert_voucher, lazy_pool, test_database, voucher_service};
+
+mod test_utils;
+
+const VOUCHER_ID: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+fn campaign_of(creator: &LocalWallet) -> Campaign {
+    Campaign {
+        id: 7,
+        name: "Sofia meetup".to_string(),
+        description: None,
+        creator_address: format!("{:?}", creator.address()),
+        created_at: Utc::now(),
+        one_claim_per_recipient: false,
+    }
+}
+
+// Every case here fails before the database is touched
+fn campaign_service() -> CampaignService {
+    let pool = lazy_pool();
+    CampaignService::new(pool.clone(), voucher_service(pool))
+}
+
+#[actix_rt::test]
+async fn test_campaign_actions_need_creator_signature() {
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let other = LocalWallet::new(&mut rand::thread_rng());
+    let campaign = campaign_of(&creator);
+
+    let auth = sign(&creator, "export_campaign", "7").await;
+    assert!(CampaignService::authorize_campaign_creator(&campaign, "export_campaign", None, &auth).is_ok());
+
+    // Bound to the action and the campaign
+    assert!(CampaignService::authorize_campaign_creator(&campaign, "bulk_link", None, &auth).is_err());
+    let auth = sign(&creator, "export_campaign", "8").await;
+    assert!(CampaignService::authorize_campaign_creator(&campaign, "export_campaign", None, &auth).is_err());
+
+    let auth = sign(&other, "export_campaign", "7").await;
+    assert!(CampaignService::authorize_campaign_creator(&campaign, "export_campaign", None, &auth).is_err());
+}
+
+#[actix_rt::test]
+async fn test_bulk_link_signature_covers_the_request() {
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let campaign = campaign_of(&creator);
+    let ids = vec![VOUCHER_ID.to_string()];
+    let window = ClaimWindow {
+        claimable_from: Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()),
+        claimable_until: None,
+    };
+    let params = bulk_link_params(&ids, PasswordPolicy::Shared, Some("meetup"), &window);
+    let auth = creator_auth_with_params(&creator, "bulk_link", "7", &params).await;
+    assert!(CampaignService::authorize_campaign_creator(&campaign, "bulk_link", Some(&params), &auth).is_ok());
+
+    // Other vouchers, another password policy or a changed window are refused
+    let other_ids = vec![VOUCHER_ID.to_string(), VOUCHER_ID.replace('1', "2")];
+    for changed in [
+        bulk_link_params(&other_ids, PasswordPolicy::Shared, Some("meetup"), &window),
+        bulk_link_params(&ids, PasswordPolicy::None, None, &window),
+        bulk_link_params(&ids, PasswordPolicy::Shared, Some("meetup"), &ClaimWindow::default()),
+    ] {
+        assert!(CampaignService::authorize_campaign_creator(&campaign, "bulk_link", Some(&changed), &auth).is_err());
+    }
+    // Nor does a signature without params pass
+    let plain = sign(&creator, "bulk_link", "7").await;
+    assert!(CampaignService::authorize_campaign_creator(&campaign, "bulk_link", Some(&params), &plain).is_err());
+}
+
+#[actix_rt::test]
+async fn test_bulk_link_validates_request() {
+    let service = campaign_service();
+    let campaign = campaign_of(&LocalWallet::new(&mut rand::thread_rng()));
+    let ctx = AuditContext::system("test");
+    let ids = vec![VOUCHER_ID.to_string()];
+    let window = ClaimWindow::default();
+
+    let err = service.bulk_link(&campaign, &[], PasswordPolicy::None, None, &window, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("voucher ids"));
+
+    let too_many = vec![VOUCHER_ID.to_string(); MAX_BULK_LINKS + 1];
+    assert!(service.bulk_link(&campaign, &too_many, PasswordPolicy::None, None, &window, &ctx).await.is_err());
+
+    let err = service.bulk_link(&campaign, &ids, PasswordPolicy::Shared, None, &window, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("password is required"));
+
+    let err = service.bulk_link(&campaign, &ids, PasswordPolicy::Generated, Some("pw"), &window, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("only accepted"));
+}
+
+#[test]
+fn test_bulk_link_request_defaults() {
+    let req: BulkLinkRequest = serde_json::from_value(json!({
+        "voucher_ids": [VOUCHER_ID],
+        "address": "0x1111111111111111111111111111111111111111",
+        "timestamp": 1700000000,
+        "signature": "0xabc"
+    })).unwrap();
+    assert_eq!(req.password_policy, PasswordPolicy::None);
+    assert!(!req.window.is_set());
+    assert_eq!(req.creator.timestamp, 1700000000);
+
+    let req: BulkLinkRequest = serde_json::from_value(json!({
+        "voucher_ids": [VOUCHER_ID],
+        "password_policy": "generated",
+        "claimable_until": "2030-01-01T00:00:00Z",
+        "address": "0x1111111111111111111111111111111111111111",
+        "timestamp": 1700000000,
+        "signature": "0xabc"
+    })).unwrap();
+    assert_eq!(req.password_policy, PasswordPolicy::Generated);
+    assert!(req.window.claimable_until.is_some());
+}
+
+#[test]
+fn test_export_csv_escapes_fields() {
+    let created_at = Utc.with_ymd_and_hms(2024, 3, 21, 12, 0, 0).unwrap();
+    let row = |label: &str| CampaignExportRow {
+        voucher_id: VOUCHER_ID.to_string(),
+        code: "ABCD1234EFGH5678".to_string(),
+        link: "/claim/ABCD1234EFGH5678".to_string(),
+        label: Some(label.to_string()),
+        has_password: true,
+        amount: Some("1000".to_string()),
+        claimed: false,
+        claimed_by: None,
+        claimed_at: None,
+        cancelled: false,
+        claimable_from: None,
+        claimable_until: None,
+        created_at,
+    };
+
+    let csv = export_csv(&[row("booth, \"A\""), row("=HYPERLINK(\"x\")")]);
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("voucher_id,code,link,label"));
+    assert_eq!(
+        lines[1],
+        format!("{},ABCD1234EFGH5678,/claim/ABCD1234EFGH5678,\"booth, \"\"A\"\"\",true,1000,false,,,false,,,2024-03-21T12:00:00+00:00", VOUCHER_ID)
+    );
+    // Formulas are neutralised before quoting
+    assert!(lines[2].contains(",\"'=HYPERLINK(\"\"x\"\")\","));
+}
+
+#[actix_rt::test]
+async fn test_stats_follow_voucher_status() {
+    let Some(pool) = test_database().await else { return };
+    let creator = format!("{:?}", Address::random());
+    let (campaign_id,): (i64,) = sqlx::query_as(
+        "INSERT INTO campaigns (name, creator_address) VALUES ('Sofia meetup', $1) RETURNING id"
+    )
+    .bind(&creator)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let lifecycle = VoucherLifecycle::new(pool.clone());
+    let ctx = AuditContext::system("test");
+    for status in [
+        None,
+        Some(VoucherStatus::Claiming),
+        Some(VoucherStatus::Claimed),
+        Some(VoucherStatus::Expired),
+        Some(VoucherStatus::Withdrawn),
+        Some(VoucherStatus::Cancelled),
+    ] {
+        let (voucher_id, _) = insert_voucher(&pool, &creator).await;
+        sqlx::query("UPDATE voucher_codes SET campaign_id = $1 WHERE voucher_id = $2")
+            .bind(campaign_id)
+            .bind(&voucher_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        if let Some(status) = status {
+            lifecycle.transition(&voucher_id, status, &StatusChange::default(), &ctx).await.unwrap();
+        }
+    }
+
+    let service = CampaignService::new(pool.clone(), voucher_service(pool));
+    let stats = service.stats(campaign_id).await.unwrap();
+    assert_eq!(stats.vouchers, 6);
+    assert_eq!(stats.claimed, 1);
+    // Withdrawn counts as cancelled; neither it nor an expired voucher is outstanding
+    assert_eq!(stats.cancelled, 2);
+    assert_eq!(stats.expired, 1);
+    assert_eq!(stats.outstanding, 2);
+    assert_eq!(stats.total_value, "6000000000000000000");
+    assert_eq!(stats.outstanding_value, "2000000000000000000");
+    assert_eq!(stats.expired_value, "1000000000000000000");
+}
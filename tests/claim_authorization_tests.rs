@@ -0,0 +1,116 @@
+use nbgn_backend::config::{AuthorizationConfig, SupersedePolicy};
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::pool::PoolService;
+use nbgn_backend::services::voucher::{ClaimError, VoucherService};
+use sqlx::PgPool;
+use test_utils::{insert_pool, insert_voucher, test_database, voucher_service};
+
+mod test_utils;
+
+const CREATOR: &str = "0x1111111111111111111111111111111111111111";
+const ALICE: &str = "0x2222222222222222222222222222222222222222";
+const BOB: &str = "0x3333333333333333333333333333333333333333";
+
+fn service(pool: &PgPool, supersede: SupersedePolicy) -> VoucherService {
+    voucher_service(pool.clone()).with_authorization_policy(AuthorizationConfig { supersede })
+}
+
+// (id, recipient, status, superseded_by), oldest first
+async fn authorizations(pool: &PgPool, voucher_id: &str) -> Vec<(i64, String, String, Option<i64>)> {
+    sqlx::query_as(
+        "SELECT id, recipient_address, status, superseded_by FROM claim_authorizations WHERE voucher_id = $1 ORDER BY id"
+    )
+    .bind(voucher_id)
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+fn claim_error(e: Box<dyn std::error::Error>) -> ClaimError {
+    *e.downcast::<ClaimError>().expect("expected a ClaimError")
+}
+
+#[actix_rt::test]
+async fn test_same_recipient_policy() {
+    let Some(pool) = test_database().await else { return };
+    let service = service(&pool, SupersedePolicy::SameRecipient);
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    let voucher = || async { service.get_voucher_by_code(&code).await.unwrap().unwrap() };
+
+    let first = service.authorize_recipient(voucher().await, ALICE, "203.0.113.1").await.unwrap();
+    service.authorize_recipient(voucher().await, ALICE, "203.0.113.1").await.unwrap();
+
+    let rows = authorizations(&pool, &voucher_id).await;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].2, "superseded");
+    assert_eq!(rows[0].3, Some(rows[1].0));
+    assert_eq!(rows[1].2, "live");
+
+    // Another recipient waits for the live one, and is told until when
+    let err = claim_error(service.authorize_recipient(voucher().await, BOB, "203.0.113.2").await.unwrap_err());
+    match err {
+        ClaimError::AuthorizationLive { deadline } => assert_eq!(deadline.timestamp() as u64, first.deadline),
+        other => panic!("unexpected error: {}", other),
+    }
+    assert_eq!(authorizations(&pool, &voucher_id).await.len(), 2);
+}
+
+#[actix_rt::test]
+async fn test_always_policy() {
+    let Some(pool) = test_database().await else { return };
+    let service = service(&pool, SupersedePolicy::Always);
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+
+    for recipient in [ALICE, BOB, ALICE] {
+        let voucher = service.get_voucher_by_code(&code).await.unwrap().unwrap();
+        service.authorize_recipient(voucher, recipient, "203.0.113.1").await.unwrap();
+    }
+
+    let rows = authorizations(&pool, &voucher_id).await;
+    let statuses: Vec<&str> = rows.iter().map(|row| row.2.as_str()).collect();
+    assert_eq!(statuses, vec!["superseded", "superseded", "live"]);
+    assert_eq!(rows[0].3, Some(rows[1].0));
+    assert_eq!(rows[1].3, Some(rows[2].0));
+    assert_eq!(rows[2].1, ALICE);
+}
+
+#[actix_rt::test]
+async fn test_concurrent_requests_share_one_live_slot() {
+    let Some(pool) = test_database().await else { return };
+    let service = service(&pool, SupersedePolicy::Never);
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    let voucher = service.get_voucher_by_code(&code).await.unwrap().unwrap();
+    let other = service.get_voucher_by_code(&code).await.unwrap().unwrap();
+
+    let (a, b) = tokio::join!(
+        service.authorize_recipient(voucher, ALICE, "203.0.113.1"),
+        service.authorize_recipient(other, BOB, "203.0.113.2"),
+    );
+    let (won, lost) = match (a, b) {
+        (Ok(won), Err(lost)) | (Err(lost), Ok(won)) => (won, claim_error(lost)),
+        _ => panic!("exactly one request should be authorized"),
+    };
+    match lost {
+        ClaimError::AuthorizationLive { deadline } => assert_eq!(deadline.timestamp() as u64, won.deadline),
+        other => panic!("unexpected error: {}", other),
+    }
+    assert_eq!(authorizations(&pool, &voucher_id).await.len(), 1);
+}
+
+#[actix_rt::test]
+async fn test_pool_reservation_is_re_signed() {
+    let Some(pool) = test_database().await else { return };
+    let (voucher_id, _) = insert_voucher(&pool, CREATOR).await;
+    let claim_pool = insert_pool(&pool, CREATOR, std::slice::from_ref(&voucher_id)).await;
+    let pools = PoolService::new(pool.clone(), service(&pool, SupersedePolicy::SameRecipient));
+    let ctx = AuditContext::system("test");
+
+    // Asking again re-signs the reservation instead of failing on the live slot
+    let first = pools.claim(&claim_pool, ALICE, None, "203.0.113.1", &ctx).await.unwrap();
+    let second = pools.claim(&claim_pool, ALICE, None, "203.0.113.1", &ctx).await.unwrap();
+    assert_eq!(first.voucher_id, voucher_id);
+    assert_eq!(second.voucher_id, voucher_id);
+
+    let statuses: Vec<String> = authorizations(&pool, &voucher_id).await.into_iter().map(|row| row.2).collect();
+    assert_eq!(statuses, vec!["superseded", "live"]);
+}
@@ -0,0 +1,198 @@
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use nbgn_backend::config::ClaimBatchConfig;
+use nbgn_backend::services::claim_batcher::{ClaimBatcher, ClaimCall};
+use nbgn_backend::services::claim_monitor::{ClaimOutcome, ClaimTxMonitor};
+use nbgn_backend::services::event_indexer::VoucherClaimed;
+use serde_json::json;
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+use test_utils::{insert_voucher, lazy_pool, test_database, test_signers};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+const VOUCHER_CONTRACT: &str = "0x66Eb0Aa46827e5F3fFcb6Dea23C309CB401690B6";
+const BATCH_TX: &str = "0x7777777777777777777777777777777777777777777777777777777777777777";
+
+fn rpc_result(result: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+fn receipt_with_logs(logs: Vec<Log>) -> TransactionReceipt {
+    TransactionReceipt {
+        transaction_hash: BATCH_TX.parse().unwrap(),
+        status: Some(1.into()),
+        logs,
+        ..Default::default()
+    }
+}
+
+fn claimed_log(voucher_id: H256) -> Log {
+    Log {
+        topics: vec![VoucherClaimed::signature(), voucher_id, H256::repeat_byte(0x22)],
+        ..Default::default()
+    }
+}
+
+fn call(byte: u8) -> ClaimCall {
+    ClaimCall {
+        voucher_id: H256::repeat_byte(byte),
+        recipient: Address::repeat_byte(0x22),
+        deadline: U256::from(1_900_000_000u64),
+        signature: vec![0x11; 65].into(),
+    }
+}
+
+#[test]
+fn test_batched_call_outcome() {
+    let claimed = H256::repeat_byte(0x01);
+    let failed = H256::repeat_byte(0x02);
+    let receipt = receipt_with_logs(vec![claimed_log(claimed)]);
+
+    assert_eq!(ClaimOutcome::Confirmed.for_batched_call(Some(&receipt), claimed), ClaimOutcome::Confirmed);
+    // The batch went through but this voucher's call did not
+    assert_eq!(ClaimOutcome::Confirmed.for_batched_call(Some(&receipt), failed), ClaimOutcome::Reverted);
+    assert_eq!(ClaimOutcome::Dropped.for_batched_call(None, claimed), ClaimOutcome::Dropped);
+    assert_eq!(ClaimOutcome::Reverted.for_batched_call(Some(&receipt), claimed), ClaimOutcome::Reverted);
+}
+
+#[actix_rt::test]
+async fn test_invalid_config() {
+    let pool = lazy_pool();
+    let signers = test_signers();
+    let provider = Arc::new(Provider::<Http>::try_from("http://127.0.0.1:9").unwrap());
+
+    let config = ClaimBatchConfig { max_size: 0, ..ClaimBatchConfig::default() };
+    assert!(ClaimBatcher::new(pool.clone(), signers.clone(), provider.clone(), VOUCHER_CONTRACT.parse().unwrap(), &config).is_err());
+
+    let config = ClaimBatchConfig { multicall_address: "0x1234".to_string(), ..ClaimBatchConfig::default() };
+    assert!(ClaimBatcher::new(pool, signers, provider, VOUCHER_CONTRACT.parse().unwrap(), &config).is_err());
+}
+
+#[actix_rt::test]
+async fn test_claims_share_one_transaction() {
+    let mock_server = MockServer::start().await;
+
+    // The second claim would revert, so only the first is sent
+    let simulation = encode(&[Token::Array(vec![
+        Token::Tuple(vec![Token::Bool(true), Token::Bytes(vec![])]),
+        Token::Tuple(vec![Token::Bool(false), Token::Bytes(vec![])]),
+    ])]);
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_call" })))
+        .respond_with(rpc_result(json!(format!("0x{}", hex::encode(simulation)))))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_getTransactionCount" })))
+        .respond_with(rpc_result(json!("0x0")))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_getBlockByNumber" })))
+        .respond_with(rpc_result(json!({
+            "hash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+            "parentHash": "0x3333333333333333333333333333333333333333333333333333333333333333",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "miner": "0x0000000000000000000000000000000000000000",
+            "stateRoot": "0x4444444444444444444444444444444444444444444444444444444444444444",
+            "transactionsRoot": "0x5555555555555555555555555555555555555555555555555555555555555555",
+            "receiptsRoot": "0x6666666666666666666666666666666666666666666666666666666666666666",
+            "number": "0x100",
+            "gasUsed": "0x0",
+            "gasLimit": "0x4000000000000",
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x65fc1a00",
+            "difficulty": "0x1",
+            "uncles": [],
+            "transactions": [],
+            "baseFeePerGas": "0x989680"
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_feeHistory" })))
+        .respond_with(rpc_result(json!({
+            "oldestBlock": "0xf7",
+            "baseFeePerGas": ["0x989680", "0x989680"],
+            "gasUsedRatio": [0.1],
+            "reward": [["0x0"]]
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_sendRawTransaction" })))
+        .respond_with(rpc_result(json!(BATCH_TX)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // Nothing listens here: the batch is sent but cannot be recorded
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_secs(1))
+        .connect_lazy("postgres://localhost:1/nbgn_unused")
+        .unwrap();
+    let signers = test_signers();
+    let provider = Arc::new(Provider::<Http>::try_from(mock_server.uri()).unwrap());
+    let config = ClaimBatchConfig { window_ms: 200, ..ClaimBatchConfig::default() };
+    let (batcher, worker) = ClaimBatcher::new(pool, signers, provider, VOUCHER_CONTRACT.parse().unwrap(), &config).unwrap();
+    tokio::spawn(worker.run());
+
+    let (first, second) = tokio::join!(batcher.submit(call(0x01)), batcher.submit(call(0x02)));
+
+    let first = first.unwrap();
+    assert_eq!(first.tx_hash, BATCH_TX);
+    assert_eq!(first.batch_id, None);
+    assert_eq!(second.unwrap_err().to_string(), "Claim would revert");
+}
+
+#[actix_rt::test]
+async fn test_unbatched_claim_needs_its_own_log() {
+    let Some(pool) = test_database().await else { return };
+    let (voucher_id, _) = insert_voucher(&pool, "0x1111111111111111111111111111111111111111").await;
+    let tx_hash = H256::random();
+    sqlx::query(
+        r#"
+        UPDATE voucher_codes
+        SET status = 'claiming', claim_tx_status = 'pending', claim_tx_hash = $1, claim_tx_submitted_at = NOW()
+        WHERE voucher_id = $2
+        "#
+    )
+    .bind(format!("{:?}", tx_hash))
+    .bind(&voucher_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // The transaction went through without claiming this voucher
+    let receipt = TransactionReceipt { transaction_hash: tx_hash, ..receipt_with_logs(vec![]) };
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_getTransactionReceipt", "params": [tx_hash] })))
+        .respond_with(rpc_result(json!(receipt)))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(rpc_result(json!(null)))
+        .with_priority(10)
+        .mount(&mock_server)
+        .await;
+
+    let provider = Arc::new(Provider::<Http>::try_from(mock_server.uri()).unwrap());
+    ClaimTxMonitor::new(pool.clone(), provider, 600).check_pending().await.unwrap();
+
+    let (status, claim_tx_status): (String, String) = sqlx::query_as(
+        "SELECT status, claim_tx_status FROM voucher_codes WHERE voucher_id = $1"
+    )
+    .bind(&voucher_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(status, "active");
+    assert_eq!(claim_tx_status, "failed");
+}
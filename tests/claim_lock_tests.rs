@@ -0,0 +1,140 @@
+use actix_web::{body::to_bytes, test::TestRequest, web};
+use chrono::{Duration, Utc};
+use ethers::prelude::*;
+use futures_util::future::join_all;
+use nbgn_backend::api::voucher_routes::execute_claim;
+use nbgn_backend::db::voucher_models::ClaimRequest;
+use nbgn_backend::middleware::rate_limiter::RedisRateLimiter;
+use nbgn_backend::services::voucher::{check_claim_lock, ClaimError, VoucherService};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::sync::Arc;
+use test_utils::{insert_voucher, test_database, voucher_service};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+const TX_HASH: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+const CREATOR: &str = "0x2222222222222222222222222222222222222222";
+const RECIPIENT: &str = "0x3333333333333333333333333333333333333333";
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+// A node that accepts any claim transaction under TX_HASH
+async fn mock_node() -> MockServer {
+    let mock_server = MockServer::start().await;
+    let responses = [
+        ("eth_getTransactionCount", json!("0x0")),
+        ("eth_chainId", json!("0x1")),
+        ("eth_feeHistory", json!({
+            "oldestBlock": "0xf7",
+            "baseFeePerGas": ["0x989680", "0x989680"],
+            "gasUsedRatio": [0.1],
+            "reward": [["0x0"]]
+        })),
+        ("eth_getBlockByNumber", json!({
+            "hash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+            "parentHash": "0x3333333333333333333333333333333333333333333333333333333333333333",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "miner": "0x0000000000000000000000000000000000000000",
+            "stateRoot": "0x4444444444444444444444444444444444444444444444444444444444444444",
+            "transactionsRoot": "0x5555555555555555555555555555555555555555555555555555555555555555",
+            "receiptsRoot": "0x6666666666666666666666666666666666666666666666666666666666666666",
+            "number": "0x100",
+            "gasUsed": "0x0",
+            "gasLimit": "0x4000000000000",
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x65fc1a00",
+            "difficulty": "0x1",
+            "uncles": [],
+            "transactions": [],
+            "baseFeePerGas": "0x989680"
+        })),
+    ];
+    for (rpc_method, result) in responses {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": rpc_method })))
+            .respond_with(rpc_result(result))
+            .mount(&mock_server)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_sendRawTransaction" })))
+        .respond_with(rpc_result(json!(TX_HASH)))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    mock_server
+}
+
+async fn claim(pool: &PgPool, service: &web::Data<VoucherService>, code: &str) -> (u16, Value) {
+    let request: ClaimRequest = serde_json::from_value(json!({ "code": code, "recipient_address": RECIPIENT })).unwrap();
+    // Local callers skip the rate limit, so Redis is never reached
+    let http_req = TestRequest::default().peer_addr("127.0.0.1:40000".parse().unwrap()).to_http_request();
+    let response = execute_claim(
+        web::Data::new(pool.clone()),
+        service.clone(),
+        web::Data::new(RedisRateLimiter::new("redis://127.0.0.1:1").unwrap()),
+        web::Json(request),
+        http_req,
+    ).await.unwrap();
+    let status = response.status().as_u16();
+    let body = to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[test]
+fn test_pending_claim_returns_its_tx_hash() {
+    let err = check_claim_lock(Some("pending"), Some(TX_HASH.to_string()), Some(Utc::now())).unwrap_err();
+    match err {
+        ClaimError::ClaimInProgress { tx_hash } => assert_eq!(tx_hash.as_deref(), Some(TX_HASH)),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_submitting_claim_locks_until_stale() {
+    // Still being sent: no hash to hand out yet
+    let err = check_claim_lock(Some("submitting"), None, Some(Utc::now() - Duration::seconds(5))).unwrap_err();
+    assert!(matches!(err, ClaimError::ClaimInProgress { tx_hash: None }));
+
+    // A submission that died before sending is taken over
+    assert!(check_claim_lock(Some("submitting"), None, Some(Utc::now() - Duration::minutes(10))).is_ok());
+    assert!(check_claim_lock(Some("submitting"), None, None).is_ok());
+}
+
+#[test]
+fn test_settled_claims_do_not_lock() {
+    assert!(check_claim_lock(None, None, None).is_ok());
+    // A failed transaction may be retried
+    assert!(check_claim_lock(Some("failed"), Some(TX_HASH.to_string()), Some(Utc::now())).is_ok());
+}
+
+#[actix_rt::test]
+async fn test_concurrent_claims_send_one_transaction() {
+    let Some(pool) = test_database().await else { return };
+    let (_, code) = insert_voucher(&pool, CREATOR).await;
+    let mock_server = mock_node().await;
+    let provider = Arc::new(Provider::<Http>::try_from(mock_server.uri()).unwrap());
+    let service = web::Data::new(voucher_service(pool.clone()).with_provider(provider));
+
+    let responses = join_all((0..5).map(|_| claim(&pool, &service, &code))).await;
+    let sent: Vec<_> = responses.iter().filter(|(status, _)| *status == 200).collect();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].1["tx_hash"], TX_HASH);
+    // The others lost the race while the claim was still being sent
+    for (status, body) in responses.iter().filter(|(status, _)| *status != 200) {
+        assert_eq!(*status, 409);
+        assert_eq!(body["error_code"], "claim_in_progress");
+    }
+
+    // Once sent, a retry is pointed at the pending transaction
+    let (status, body) = claim(&pool, &service, &code).await;
+    assert_eq!(status, 409);
+    assert_eq!(body["error_code"], "claim_in_progress");
+    assert_eq!(body["tx_hash"], TX_HASH);
+}
@@ -0,0 +1,268 @@
+use chrono::Utc;
+use nbgn_backend::config::{NotificationConfig, SmtpConfig, SmtpTls};
+use nbgn_backend::db::notification_models::NotificationChannel;
+use ethers::signers::LocalWallet;
+use nbgn_backend::services::auth;
+use nbgn_backend::services::mailer::Mailer;
+use nbgn_backend::services::notification_channels::{is_public_ip, resolve_public_target, NotificationChannelService};
+use nbgn_backend::services::notifier::{render_email, CreatorNotification, Notifier};
+use nbgn_backend::services::webhooks::sign_payload;
+use serde_json::json;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use test_utils::creator_auth_with_params;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+const CREATOR: &str = "0x1111111111111111111111111111111111111111";
+
+// Minimal SMTP server that accepts every message and keeps the DATA section
+async fn start_smtp_stand_in() -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let received = Arc::new(Mutex::new(Vec::new()));
+
+    let inbox = received.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else { return };
+            let inbox = inbox.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = stream.into_split();
+                let mut lines = BufReader::new(read).lines();
+                write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let command = line.to_uppercase();
+                    if command.starts_with("EHLO") || command.starts_with("HELO") {
+                        write.write_all(b"250 localhost\r\n").await.unwrap();
+                    } else if command.starts_with("DATA") {
+                        write.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await.unwrap();
+                        let mut data = String::new();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            if line == "." {
+                                break;
+                            }
+                            data.push_str(&line);
+                            data.push('\n');
+                        }
+                        inbox.lock().unwrap().push(data);
+                        write.write_all(b"250 Queued\r\n").await.unwrap();
+                    } else if command.starts_with("QUIT") {
+                        write.write_all(b"221 Bye\r\n").await.unwrap();
+                        return;
+                    } else {
+                        write.write_all(b"250 OK\r\n").await.unwrap();
+                    }
+                }
+            });
+        }
+    });
+
+    (port, received)
+}
+
+fn channel(kind: &str, target: &str, muted_events: &[&str]) -> NotificationChannel {
+    NotificationChannel {
+        id: 1,
+        creator_address: CREATOR.to_string(),
+        kind: kind.to_string(),
+        target: target.to_string(),
+        secret: (kind == "webhook").then(|| "whsec_test".to_string()),
+        muted_events: muted_events.iter().map(|e| e.to_string()).collect(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+// The mock servers listen on loopback, which real deliveries refuse
+fn local_delivery() -> NotificationConfig {
+    NotificationConfig { allow_private_channel_targets: true, ..Default::default() }
+}
+
+fn claimed_notification() -> CreatorNotification {
+    CreatorNotification {
+        event: "voucher.claimed".to_string(),
+        creator_address: CREATOR.to_string(),
+        voucher_id: "0x2222222222222222222222222222222222222222222222222222222222222222".to_string(),
+        occurred_at: Utc::now(),
+        data: json!({
+            "claimed_by": "0x3333333333333333333333333333333333333333",
+            "tx_hash": "0x4444444444444444444444444444444444444444444444444444444444444444",
+        }),
+    }
+}
+
+#[test]
+fn test_channel_validation() {
+    let none: Vec<String> = vec![];
+    assert!(NotificationChannelService::validate_channel("webhook", "https://creator.example/hooks", &none, false).is_ok());
+    assert!(NotificationChannelService::validate_channel("email", "creator@example.com", &none, true).is_ok());
+
+    // Email channels need SMTP
+    assert!(NotificationChannelService::validate_channel("email", "creator@example.com", &none, false).is_err());
+
+    assert!(NotificationChannelService::validate_channel("email", "not an address", &none, true).is_err());
+    assert!(NotificationChannelService::validate_channel("webhook", "ftp://creator.example/hooks", &none, false).is_err());
+    assert!(NotificationChannelService::validate_channel("sms", "+359888000000", &none, true).is_err());
+
+    let muted = vec!["voucher.expired".to_string()];
+    assert!(NotificationChannelService::validate_channel("webhook", "https://creator.example/hooks", &muted, false).is_ok());
+    let unknown = vec!["nbgn.minted".to_string()];
+    assert!(NotificationChannelService::validate_channel("webhook", "https://creator.example/hooks", &unknown, false).is_err());
+}
+
+#[test]
+fn test_email_rendering() {
+    let (subject, body) = render_email(&claimed_notification());
+    assert_eq!(subject, "Your NBGN voucher was claimed");
+    assert!(body.contains("Claimed by: 0x3333333333333333333333333333333333333333"));
+    assert!(body.contains("Transaction: 0x4444"));
+    assert!(body.contains("Mute \"voucher.claimed\""));
+
+    let expired = CreatorNotification {
+        event: "voucher.expired".to_string(),
+        data: json!({ "amount": "1000", "claimable_until": "2024-03-21T12:00:00Z" }),
+        ..claimed_notification()
+    };
+    let (subject, body) = render_email(&expired);
+    assert_eq!(subject, "Your NBGN voucher expired unclaimed");
+    assert!(body.contains("Amount: 1000"));
+    assert!(body.contains("Claim window closed: 2024-03-21T12:00:00Z"));
+}
+
+#[actix_rt::test]
+async fn test_delivers_to_webhook_and_email_channels() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hooks/creator"))
+        .and(header_exists("X-NBGN-Signature"))
+        .and(header_exists("X-NBGN-Timestamp"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let (port, inbox) = start_smtp_stand_in().await;
+    let mailer = Mailer::from_config(&SmtpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        username: None,
+        password: None,
+        from: "NBGN Vouchers <vouchers@example.com>".to_string(),
+        tls: SmtpTls::None,
+    }).unwrap();
+    let notifier = Notifier::from_config(&local_delivery()).with_mailer(mailer);
+
+    let channels = vec![
+        channel("webhook", &format!("{}/hooks/creator", mock_server.uri()), &[]),
+        channel("email", "creator@example.com", &[]),
+    ];
+    assert_eq!(notifier.deliver(&channels, &claimed_notification()).await, 2);
+
+    let inbox = inbox.lock().unwrap().clone();
+    assert_eq!(inbox.len(), 1);
+    assert!(inbox[0].contains("To: creator@example.com"));
+    assert!(inbox[0].contains("Subject: Your NBGN voucher was claimed"));
+
+    // The creator can check the payload came from us
+    let request = &mock_server.received_requests().await.unwrap()[0];
+    let timestamp: i64 = request.headers.get("X-NBGN-Timestamp").unwrap().to_str().unwrap().parse().unwrap();
+    assert_eq!(
+        request.headers.get("X-NBGN-Signature").unwrap().to_str().unwrap(),
+        sign_payload("whsec_test", timestamp, &request.body)
+    );
+}
+
+#[actix_rt::test]
+async fn test_muted_and_failing_channels() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let notifier = Notifier::from_config(&local_delivery());
+
+    // Muted for claims: nothing is sent
+    let muted = vec![channel("webhook", &format!("{}/hooks/creator", mock_server.uri()), &["voucher.claimed"])];
+    assert_eq!(notifier.deliver(&muted, &claimed_notification()).await, 0);
+
+    // An email channel without SMTP, or an unreachable webhook, is skipped
+    // without failing the other channels
+    let broken = vec![
+        channel("email", "creator@example.com", &[]),
+        channel("webhook", "http://127.0.0.1:9/hooks", &[]),
+    ];
+    assert_eq!(notifier.deliver(&broken, &claimed_notification()).await, 0);
+}
+
+#[test]
+fn test_public_ip_check() {
+    for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "::ffff:10.0.0.1", "fd00::1", "fe80::1"] {
+        assert!(!is_public_ip(ip.parse::<IpAddr>().unwrap()), "{} should not be public", ip);
+    }
+    for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+        assert!(is_public_ip(ip.parse::<IpAddr>().unwrap()), "{} should be public", ip);
+    }
+}
+
+#[actix_rt::test]
+async fn test_private_webhook_targets_are_refused() {
+    assert!(resolve_public_target("http://127.0.0.1:9/hooks").await.is_err());
+    assert!(resolve_public_target("http://169.254.169.254/latest/meta-data").await.is_err());
+    assert!(resolve_public_target("http://localhost:8080/hooks").await.is_err());
+    assert!(resolve_public_target("http://[::ffff:192.168.0.1]/hooks").await.is_err());
+
+    // Nor does delivery reach them, even for a channel stored earlier
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+    let notifier = Notifier::from_config(&NotificationConfig::default());
+    let channels = vec![channel("webhook", &format!("{}/hooks/creator", mock_server.uri()), &[])];
+    assert_eq!(notifier.deliver(&channels, &claimed_notification()).await, 0);
+}
+
+#[actix_rt::test]
+async fn test_redirects_are_not_followed() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hooks/creator"))
+        .respond_with(ResponseTemplate::new(307).insert_header("Location", "http://169.254.169.254/"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let notifier = Notifier::from_config(&local_delivery());
+    let channels = vec![channel("webhook", &format!("{}/hooks/creator", mock_server.uri()), &[])];
+    assert_eq!(notifier.deliver(&channels, &claimed_notification()).await, 0);
+}
+
+#[actix_rt::test]
+async fn test_channel_signature_covers_kind_and_muted_events() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let target = "https://creator.example/hooks";
+    let params = json!({ "kind": "webhook", "muted_events": ["voucher.expired"] });
+    let signed = creator_auth_with_params(&wallet, "set_notification_channel", target, &params).await;
+    let verify = |params: &serde_json::Value| {
+        auth::verify_action_signature_with_params(
+            &signed.address,
+            "set_notification_channel",
+            target,
+            params,
+            signed.timestamp,
+            &signed.signature,
+        )
+    };
+
+    assert!(verify(&params).is_ok());
+    assert!(verify(&json!({ "kind": "webhook", "muted_events": [] })).is_err());
+    assert!(verify(&json!({ "kind": "email", "muted_events": ["voucher.expired"] })).is_err());
+}
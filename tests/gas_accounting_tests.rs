@@ -0,0 +1,84 @@
+use ethers::types::{TransactionReceipt, U256};
+use nbgn_backend::db::gas_models::{CreatorGasCost, GasCostTotals};
+use nbgn_backend::services::gas_accounting::GasCost;
+use serde_json::json;
+
+fn receipt(extra: serde_json::Value) -> TransactionReceipt {
+    let mut receipt = json!({
+        "transactionHash": "0x1111111111111111111111111111111111111111111111111111111111111111",
+        "transactionIndex": "0x1",
+        "blockHash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+        "blockNumber": "0xbc614e",
+        "from": "0x3333333333333333333333333333333333333333",
+        "to": "0x66eb0aa46827e5f3ffcb6dea23c309cb401690b6",
+        "cumulativeGasUsed": "0x30d40",
+        "gasUsed": "0x30d40",
+        "logs": [],
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+        "status": "0x1",
+        "type": "0x2",
+        "effectiveGasPrice": "0x989680"
+    });
+    receipt.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(receipt).unwrap()
+}
+
+#[test]
+fn test_arbitrum_receipt_cost() {
+    // 200000 gas at 0.01 gwei, 50000 of it for the L1 data
+    let cost = GasCost::from_receipt(&receipt(json!({ "gasUsedForL1": "0xc350" }))).unwrap();
+
+    assert_eq!(cost.gas_used, U256::from(200_000));
+    assert_eq!(cost.l1_gas_used, U256::from(50_000));
+    assert_eq!(cost.effective_gas_price, U256::from(10_000_000));
+    assert_eq!(cost.cost_wei, U256::from(2_000_000_000_000u64));
+    assert_eq!(cost.l1_cost_wei, U256::from(500_000_000_000u64));
+}
+
+#[test]
+fn test_receipt_without_l1_component() {
+    let cost = GasCost::from_receipt(&receipt(json!({}))).unwrap();
+    assert_eq!(cost.l1_gas_used, U256::zero());
+    assert_eq!(cost.l1_cost_wei, U256::zero());
+    assert_eq!(cost.cost_wei, U256::from(2_000_000_000_000u64));
+
+    // Without a price the cost is unknown
+    let mut unpriced = receipt(json!({}));
+    unpriced.effective_gas_price = None;
+    assert_eq!(GasCost::from_receipt(&unpriced), None);
+}
+
+#[test]
+fn test_batch_shares_add_up() {
+    // 200000 gas split over three batched claims
+    let cost = GasCost::from_receipt(&receipt(json!({ "gasUsedForL1": "0xc350" }))).unwrap();
+    let shares: Vec<GasCost> = (0..3).map(|i| cost.share(i, 3)).collect();
+
+    assert_eq!(shares[0].gas_used, U256::from(66_668));
+    assert_eq!(shares[1].gas_used, U256::from(66_666));
+    assert_eq!(shares.iter().fold(U256::zero(), |sum, s| sum + s.gas_used), cost.gas_used);
+    assert_eq!(shares.iter().fold(U256::zero(), |sum, s| sum + s.l1_gas_used), cost.l1_gas_used);
+    assert_eq!(shares.iter().fold(U256::zero(), |sum, s| sum + s.cost_wei), cost.cost_wei);
+    assert_eq!(cost.share(0, 1), cost);
+}
+
+#[test]
+fn test_report_row_shape() {
+    let row = CreatorGasCost {
+        creator_address: Some("0x3333333333333333333333333333333333333333".to_string()),
+        totals: GasCostTotals {
+            transactions: 2,
+            failed: 1,
+            gas_used: "400000".to_string(),
+            l1_gas_used: "100000".to_string(),
+            cost_wei: "4000000000000".to_string(),
+            l1_cost_wei: "1000000000000".to_string(),
+        },
+    };
+
+    // Totals sit beside the group key
+    let value = serde_json::to_value(&row).unwrap();
+    assert_eq!(value["creator_address"], "0x3333333333333333333333333333333333333333");
+    assert_eq!(value["transactions"], 2);
+    assert_eq!(value["l1_cost_wei"], "1000000000000");
+}
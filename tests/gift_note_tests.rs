@@ -0,0 +1,127 @@
+use chrono::Utc;
+use nbgn_backend::db::voucher_models::{CreateLinkRequest, GiftNote, VoucherCode};
+use nbgn_backend::services::notes::{self, MAX_NOTE_BYTES};
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::voucher::{LinkError, VoucherService};
+use serde_json::json;
+use test_utils::{insert_voucher, test_database, voucher_service};
+
+mod test_utils;
+
+const CODE: &str = "ABCD1234EFGH5678";
+
+fn note(text: &str) -> GiftNote {
+    GiftNote {
+        text: text.to_string(),
+        content_type: "text/plain".to_string(),
+    }
+}
+
+fn voucher_with_note(password_hash: Option<String>, sealed: Vec<u8>) -> VoucherCode {
+    serde_json::from_value(json!({
+        "code": CODE,
+        "voucher_id": "0x1111111111111111111111111111111111111111111111111111111111111111",
+        "password_hash": password_hash,
+        "created_at": Utc::now(),
+        "claimed": false,
+        "cancelled": false,
+        "note_ciphertext": sealed,
+        "note_content_type": "text/plain",
+        "status": "active"
+    })).unwrap()
+}
+
+#[test]
+fn test_note_opens_only_with_its_code_and_password() {
+    let sealed = notes::seal(CODE, Some("sesame"), &note("Happy birthday!")).unwrap();
+    assert!(!sealed.windows(5).any(|w| w == b"Happy"));
+
+    let opened = notes::open(CODE, Some("sesame"), "text/plain", &sealed).unwrap();
+    assert_eq!(opened.text, "Happy birthday!");
+
+    assert!(notes::open(CODE, Some("wrong"), "text/plain", &sealed).is_err());
+    assert!(notes::open(CODE, None, "text/plain", &sealed).is_err());
+    assert!(notes::open("ABCD1234EFGH5679", Some("sesame"), "text/plain", &sealed).is_err());
+
+    // The content type is authenticated along with the text
+    assert!(notes::open(CODE, Some("sesame"), "text/markdown", &sealed).is_err());
+}
+
+#[test]
+fn test_open_note_uses_password_only_for_protected_codes() {
+    let sealed = notes::seal(CODE, None, &note("Enjoy")).unwrap();
+    let voucher = voucher_with_note(None, sealed);
+    assert_eq!(VoucherService::open_note(&voucher, Some("ignored")).unwrap().text, "Enjoy");
+
+    let sealed = notes::seal(CODE, Some("sesame"), &note("Enjoy")).unwrap();
+    let voucher = voucher_with_note(Some(VoucherService::hash_password("sesame").unwrap()), sealed);
+    assert_eq!(VoucherService::open_note(&voucher, Some("sesame")).unwrap().text, "Enjoy");
+    assert!(VoucherService::open_note(&voucher, None).is_none());
+}
+
+#[test]
+fn test_note_limits() {
+    assert!(VoucherService::validate_note(&note("Thanks for everything\nSee you soon")).is_ok());
+    assert!(VoucherService::validate_note(&GiftNote {
+        text: "**Thanks**".to_string(),
+        content_type: "text/markdown".to_string(),
+    }).is_ok());
+
+    let invalid = [
+        note("   "),
+        note(&"x".repeat(MAX_NOTE_BYTES + 1)),
+        note("bell\u{7}"),
+        GiftNote { text: "<b>hi</b>".to_string(), content_type: "text/html".to_string() },
+    ];
+    for note in &invalid {
+        assert!(matches!(VoucherService::validate_note(note), Err(LinkError::InvalidNote(_))));
+    }
+
+    // The limit is in bytes, not characters
+    assert!(VoucherService::validate_note(&note(&"я".repeat(MAX_NOTE_BYTES / 2 + 1))).is_err());
+}
+
+#[test]
+fn test_link_request_note_defaults_to_plain_text() {
+    let req: CreateLinkRequest = serde_json::from_value(json!({
+        "voucher_id": "0x1111111111111111111111111111111111111111111111111111111111111111",
+        "creator_address": "0x2222222222222222222222222222222222222222",
+        "timestamp": 1700000000,
+        "signature": "0x00",
+        "note": { "text": "Happy birthday!" }
+    })).unwrap();
+    assert_eq!(req.note.unwrap().content_type, "text/plain");
+}
+
+#[actix_rt::test]
+async fn test_rotation_carries_the_note_over() {
+    let Some(pool) = test_database().await else { return };
+    let service = voucher_service(pool.clone());
+    let ctx = AuditContext::system("test");
+    let (_, code) = insert_voucher(&pool, "0x1111111111111111111111111111111111111111").await;
+    let lookup = |code: String| {
+        let service = service.clone();
+        async move { service.get_voucher_by_code(&code).await.unwrap().unwrap() }
+    };
+
+    // An unprotected note is sealed again under the new code
+    service.set_note(&code, Some(&note("Happy birthday")), None, &ctx).await.unwrap();
+    let rotated = service.rotate_code(&code, None, &ctx).await.unwrap();
+    let opened = VoucherService::open_note(&lookup(rotated.clone()).await, None);
+    assert_eq!(opened.map(|note| note.text).as_deref(), Some("Happy birthday"));
+
+    // A protected one needs the password, and nothing is rotated without it
+    let protected = service.add_code(&lookup(rotated).await, None, Some("pw"), &ctx).await.unwrap();
+    service.set_note(&protected, Some(&note("For your eyes")), Some("pw"), &ctx).await.unwrap();
+    for password in [None, Some("wrong")] {
+        let err = service.rotate_code(&protected, password, &ctx).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<LinkError>(), Some(LinkError::InvalidNote(_))));
+    }
+    assert!(service.get_voucher_by_code(&protected).await.unwrap().is_some());
+
+    let rotated = service.rotate_code(&protected, Some("pw"), &ctx).await.unwrap();
+    let voucher = lookup(rotated).await;
+    assert!(VoucherService::open_note(&voucher, None).is_none());
+    let opened = VoucherService::open_note(&voucher, Some("pw"));
+    assert_eq!(opened.map(|note| note.text).as_deref(), Some("For your eyes"));
+}
@@ -0,0 +1,184 @@
+use chrono::Utc;
+use ethers::prelude::*;
+use nbgn_backend::db::pool_models::{ClaimPool, PoolClaimRequest};
+use nbgn_backend::db::voucher_models::CreatorAuth;
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::codes::CodeGenerator;
+use nbgn_backend::services::pool::{create_pool_params, PoolError, PoolService, MAX_POOL_VOUCHERS};
+use nbgn_backend::services::voucher::VoucherService;
+use serde_json::json;
+use test_utils::{creator_auth_with_params, insert_pool, insert_voucher, lazy_pool, test_database, voucher_service};
+
+mod test_utils;
+
+const VOUCHER_ID: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+const CREATOR: &str = "0x2222222222222222222222222222222222222222";
+const ALICE: &str = "0x3333333333333333333333333333333333333333";
+const BOB: &str = "0x4444444444444444444444444444444444444444";
+
+async fn sign(creator: &LocalWallet, name: &str, voucher_ids: &[String], password: Option<&str>) -> CreatorAuth {
+    creator_auth_with_params(creator, "create_pool", name, &create_pool_params(voucher_ids, password)).await
+}
+
+// Every case here fails before the database is touched
+fn pool_service() -> PoolService {
+    let pool = lazy_pool();
+    let vouchers = voucher_service(pool.clone())
+        .with_public_base_url(Some("https://nbgn.example/".to_string()));
+    PoolService::new(pool, vouchers)
+}
+
+#[actix_rt::test]
+async fn test_create_pool_validates_request() {
+    let service = pool_service();
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let ctx = AuditContext::system("test");
+    let ids = vec![VOUCHER_ID.to_string()];
+    let auth = sign(&creator, "Launch party", &ids, None).await;
+
+    let err = service.create_pool("  ", &ids, None, &auth, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("name"));
+
+    let err = service.create_pool("Launch party", &[], None, &auth, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("voucher ids"));
+
+    let too_many = vec![VOUCHER_ID.to_string(); MAX_POOL_VOUCHERS + 1];
+    assert!(service.create_pool("Launch party", &too_many, None, &auth, &ctx).await.is_err());
+
+    let malformed = vec!["0x1234".to_string()];
+    assert!(service.create_pool("Launch party", &malformed, None, &auth, &ctx).await.is_err());
+
+    // Signed for a different pool name, other vouchers or another password
+    let other = sign(&creator, "Other party", &ids, None).await;
+    assert!(service.create_pool("Launch party", &ids, None, &other, &ctx).await.is_err());
+    let other_ids = vec![VOUCHER_ID.replace('1', "5")];
+    let err = service.create_pool("Launch party", &other_ids, None, &auth, &ctx).await.unwrap_err();
+    assert_eq!(err.to_string(), "Invalid signature");
+    let err = service.create_pool("Launch party", &ids, Some("secret"), &auth, &ctx).await.unwrap_err();
+    assert_eq!(err.to_string(), "Invalid signature");
+}
+
+#[actix_rt::test]
+async fn test_pool_password_is_checked_first() {
+    let service = pool_service();
+    let ctx = AuditContext::system("test");
+    let pool = ClaimPool {
+        id: 3,
+        code: CodeGenerator::default().random_code(),
+        name: "Launch party".to_string(),
+        creator_address: "0x2222222222222222222222222222222222222222".to_string(),
+        password_hash: Some(VoucherService::hash_password("sesame").unwrap()),
+        created_at: Utc::now(),
+    };
+    let recipient = "0x3333333333333333333333333333333333333333";
+
+    let err = service.claim(&pool, recipient, None, "10.0.0.1", &ctx).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<PoolError>(), Some(PoolError::PasswordRequired)));
+
+    let err = service.claim(&pool, recipient, Some("wrong"), "10.0.0.1", &ctx).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<PoolError>(), Some(PoolError::InvalidPassword)));
+}
+
+#[actix_rt::test]
+async fn test_pool_links_use_pool_code() {
+    let service = pool_service();
+    let code = CodeGenerator::default().random_code();
+    let display = service.display_code(&code);
+
+    assert_eq!(service.normalize_code(&display.to_lowercase()), Some(code.clone()));
+    assert_eq!(service.pool_path(&code), format!("/pool/{}", display));
+    assert_eq!(service.pool_url(&code), Some(format!("https://nbgn.example/pool/{}", display)));
+
+    // Password hashes never leave the server
+    let pool = ClaimPool {
+        id: 1,
+        code,
+        name: "Launch party".to_string(),
+        creator_address: "0x2222222222222222222222222222222222222222".to_string(),
+        password_hash: Some("hash".to_string()),
+        created_at: Utc::now(),
+    };
+    assert!(serde_json::to_value(&pool).unwrap().get("password_hash").is_none());
+}
+
+#[test]
+fn test_pool_claim_request_deserializes() {
+    let req: PoolClaimRequest = serde_json::from_value(json!({
+        "recipient_address": "0x3333333333333333333333333333333333333333"
+    })).unwrap();
+    assert!(req.password.is_none());
+}
+
+#[actix_rt::test]
+async fn test_closed_vouchers_are_skipped() {
+    let Some(pool) = test_database().await else { return };
+    let service = PoolService::new(pool.clone(), voucher_service(pool.clone()));
+    let ctx = AuditContext::system("test");
+
+    let (expired, _) = insert_voucher(&pool, CREATOR).await;
+    let (not_yet, _) = insert_voucher(&pool, CREATOR).await;
+    let (open, _) = insert_voucher(&pool, CREATOR).await;
+    sqlx::query("UPDATE voucher_codes SET claimable_until = NOW() - INTERVAL '1 day' WHERE voucher_id = $1")
+        .bind(&expired)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE voucher_codes SET claimable_from = NOW() + INTERVAL '1 day' WHERE voucher_id = $1")
+        .bind(&not_yet)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let claim_pool = insert_pool(&pool, CREATOR, &[expired, not_yet, open.clone()]).await;
+
+    // Vouchers ahead of it in the pool that cannot be claimed do not block it
+    let authorization = service.claim(&claim_pool, ALICE, None, "10.0.0.1", &ctx).await.unwrap();
+    assert_eq!(authorization.voucher_id, open);
+
+    let err = service.claim(&claim_pool, BOB, None, "10.0.0.2", &ctx).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<PoolError>(), Some(PoolError::Exhausted)));
+
+    let stock = service.stock(claim_pool.id).await.unwrap();
+    assert_eq!((stock.total, stock.reserved), (3, 1));
+}
+
+#[actix_rt::test]
+async fn test_allowlists_apply_to_pool_claims() {
+    let Some(pool) = test_database().await else { return };
+    let vouchers = voucher_service(pool.clone());
+    let service = PoolService::new(pool.clone(), vouchers.clone());
+    let ctx = AuditContext::system("test");
+
+    let (for_bob, _) = insert_voucher(&pool, CREATOR).await;
+    let (anyone, _) = insert_voucher(&pool, CREATOR).await;
+    vouchers.set_recipient_restriction(&for_bob, Some(&[BOB.to_string()]), None, &ctx).await.unwrap();
+    let claim_pool = insert_pool(&pool, CREATOR, &[for_bob.clone(), anyone.clone()]).await;
+
+    let alice = service.claim(&claim_pool, ALICE, None, "10.0.0.1", &ctx).await.unwrap();
+    assert_eq!(alice.voucher_id, anyone);
+    let bob = service.claim(&claim_pool, BOB, None, "10.0.0.2", &ctx).await.unwrap();
+    assert_eq!(bob.voucher_id, for_bob);
+
+    // A restriction added after the reservation is enforced on re-signing
+    let carol = "0x5555555555555555555555555555555555555555";
+    vouchers.set_recipient_restriction(&anyone, Some(&[carol.to_string()]), None, &ctx).await.unwrap();
+    assert!(service.claim(&claim_pool, ALICE, None, "10.0.0.1", &ctx).await.is_err());
+}
+
+#[actix_rt::test]
+async fn test_merkle_restricted_vouchers_cannot_join() {
+    let Some(pool) = test_database().await else { return };
+    let vouchers = voucher_service(pool.clone());
+    let service = PoolService::new(pool.clone(), vouchers.clone());
+    let ctx = AuditContext::system("test");
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let creator_address = format!("{:?}", creator.address());
+
+    let (voucher_id, _) = insert_voucher(&pool, &creator_address).await;
+    let root = format!("{:?}", H256::repeat_byte(7));
+    vouchers.set_recipient_restriction(&voucher_id, None, Some(&root), &ctx).await.unwrap();
+
+    let ids = vec![voucher_id];
+    let auth = sign(&creator, "Launch party", &ids, None).await;
+    let err = service.create_pool("Launch party", &ids, None, &auth, &ctx).await.unwrap_err();
+    assert!(err.to_string().contains("Merkle root"));
+}
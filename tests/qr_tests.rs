@@ -0,0 +1,59 @@
+use nbgn_backend::services::qr::{self, QrErrorCorrection, QrFormat, QrOptions, SheetItem, MAX_SIZE};
+use test_utils::{lazy_pool, voucher_service};
+
+mod test_utils;
+
+const URL: &str = "https://app.example.com/claim/ABCD1234EFGH5678";
+
+#[actix_rt::test]
+async fn test_claim_url_needs_public_base_url() {
+    let service = voucher_service(lazy_pool());
+    assert_eq!(service.claim_path("ABCD1234EFGH5678"), "/claim/ABCD1234EFGH5678");
+    assert!(service.claim_url("ABCD1234EFGH5678").is_none());
+
+    let service = service.with_public_base_url(Some("https://app.example.com/".to_string()));
+    assert_eq!(service.claim_url("ABCD1234EFGH5678").as_deref(), Some(URL));
+}
+
+#[test]
+fn test_render_svg_and_png() {
+    let svg = qr::render(URL, &QrOptions::default()).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.contains("<svg"));
+
+    let options = QrOptions { format: QrFormat::Png, size: Some(300), ec: QrErrorCorrection::H };
+    let png = qr::render(URL, &options).unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(options.content_type(), "image/png");
+
+    // Width from the IHDR chunk: at least the requested size
+    let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+    assert!(width >= 300);
+
+    // Denser error correction makes a bigger symbol for the same data
+    let low = qr::render(URL, &QrOptions { format: QrFormat::Png, size: Some(64), ec: QrErrorCorrection::L }).unwrap();
+    let high = qr::render(URL, &QrOptions { format: QrFormat::Png, size: Some(64), ec: QrErrorCorrection::H }).unwrap();
+    let low_width = u32::from_be_bytes(low[16..20].try_into().unwrap());
+    let high_width = u32::from_be_bytes(high[16..20].try_into().unwrap());
+    assert!(high_width > low_width);
+}
+
+#[test]
+fn test_render_rejects_bad_size() {
+    assert!(qr::render(URL, &QrOptions { size: Some(10), ..Default::default() }).is_err());
+    assert!(qr::render(URL, &QrOptions { size: Some(MAX_SIZE + 1), ..Default::default() }).is_err());
+}
+
+#[test]
+fn test_printable_sheet_escapes_text() {
+    let html = qr::printable_sheet("Launch <party>", &[SheetItem {
+        url: URL.to_string(),
+        code: "ABCD1234EFGH5678".to_string(),
+        caption: "5 NBGN · \"VIP\"".to_string(),
+    }]).unwrap();
+
+    assert!(html.contains("<title>Launch &lt;party&gt;</title>"));
+    assert!(html.contains("5 NBGN · &quot;VIP&quot;"));
+    assert!(html.contains("<svg"));
+    assert!(!html.contains("<?xml"));
+}
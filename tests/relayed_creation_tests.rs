@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use ethers::prelude::*;
+use nbgn_backend::config::RelayConfig;
+use nbgn_backend::db::relay_models::RelayedCreationRequest;
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::relay_policy::{PolicyDecision, RelayKind, RelayPolicy, RelayRequest, StaticRelayPolicy};
+use nbgn_backend::services::relayed_creation::{
+    bytecode_has_selector, contracts_support_relay, creation_intent_hash, verify_intent, RelayError,
+    RelayedCreationService,
+};
+use serde_json::json;
+use std::sync::Arc;
+use test_utils::{lazy_pool, test_signers};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+const VOUCHER_CONTRACT: &str = "0x66Eb0Aa46827e5F3fFcb6Dea23C309CB401690B6";
+const NBGN_CONTRACT: &str = "0x47F9CF7043C8A059f82a988C0B9fF73F0c3e6067";
+
+// Refuses everything, to show the hook is consulted
+struct RejectAll;
+
+#[async_trait]
+impl RelayPolicy for RejectAll {
+    async fn evaluate(&self, _request: &RelayRequest) -> PolicyDecision {
+        PolicyDecision::Reject { reason: "creations paused".to_string() }
+    }
+}
+
+fn service(rpc_url: &str, policy: Arc<dyn RelayPolicy>) -> RelayedCreationService {
+    let provider = Arc::new(Provider::<Http>::try_from(rpc_url).unwrap());
+    RelayedCreationService::new(
+        lazy_pool(),
+        test_signers(),
+        provider,
+        VOUCHER_CONTRACT.parse().unwrap(),
+        NBGN_CONTRACT.parse().unwrap(),
+        policy,
+    )
+    .with_max_deadline(3600)
+}
+
+fn flat_fee(fee: &str) -> Arc<dyn RelayPolicy> {
+    Arc::new(StaticRelayPolicy::from_config(&RelayConfig {
+        enabled: true,
+        creation_fee: fee.to_string(),
+        ..RelayConfig::default()
+    }).unwrap())
+}
+
+async fn signed_request(creator: &LocalWallet, amount: u64, relayer_fee: u64) -> RelayedCreationRequest {
+    let deadline = Utc::now().timestamp() + 600;
+    let hash = creation_intent_hash(
+        creator.address(),
+        U256::from(amount),
+        U256::from(relayer_fee),
+        U256::from(deadline),
+        VOUCHER_CONTRACT.parse().unwrap(),
+    );
+    let intent = creator.sign_message(hash).await.unwrap();
+    RelayedCreationRequest {
+        creator: format!("{:?}", creator.address()),
+        amount: amount.to_string(),
+        relayer_fee: relayer_fee.to_string(),
+        deadline,
+        // Only checked on chain
+        permit_signature: format!("0x{}", "11".repeat(65)),
+        intent_signature: intent.to_string(),
+    }
+}
+
+#[actix_rt::test]
+async fn test_intent_signature() {
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let contract: Address = VOUCHER_CONTRACT.parse().unwrap();
+    let (amount, fee, deadline) = (U256::from(1000), U256::from(10), U256::from(1_900_000_000u64));
+
+    let hash = creation_intent_hash(creator.address(), amount, fee, deadline, contract);
+    let signature = creator.sign_message(hash).await.unwrap().to_string();
+    assert_eq!(verify_intent(creator.address(), amount, fee, deadline, contract, &signature).unwrap(), hash);
+
+    // Bound to every value the creator agreed to
+    assert!(verify_intent(creator.address(), amount, U256::from(11), deadline, contract, &signature).is_err());
+    assert!(verify_intent(creator.address(), U256::from(999), fee, deadline, contract, &signature).is_err());
+    let other = LocalWallet::new(&mut rand::thread_rng());
+    assert!(verify_intent(other.address(), amount, fee, deadline, contract, &signature).is_err());
+    assert!(matches!(
+        verify_intent(creator.address(), amount, fee, deadline, contract, "0xnope"),
+        Err(RelayError::InvalidIntentSignature)
+    ));
+}
+
+#[actix_rt::test]
+async fn test_static_policy() {
+    let request = RelayRequest {
+        kind: RelayKind::Creation,
+        subject: None,
+        creator: "0x1111111111111111111111111111111111111111".to_string(),
+        recipient: None,
+        campaign_id: None,
+        amount: U256::from(1000),
+        gas_limit: 250_000,
+        client_ip: "10.0.0.1".to_string(),
+    };
+
+    let sponsored = StaticRelayPolicy::from_config(&RelayConfig { enabled: true, ..RelayConfig::default() }).unwrap();
+    let decision = sponsored.evaluate(&request).await;
+    assert_eq!(decision.as_str(), "sponsored");
+    assert_eq!(decision.fee(), U256::zero());
+
+    let charged = StaticRelayPolicy::from_config(&RelayConfig {
+        enabled: true,
+        creation_fee: "5000".to_string(),
+        ..RelayConfig::default()
+    }).unwrap();
+    let decision = charged.evaluate(&request).await;
+    assert_eq!(decision.as_str(), "fee");
+    assert_eq!(decision.fee(), U256::from(5000));
+
+    assert!(StaticRelayPolicy::from_config(&RelayConfig {
+        creation_fee: "lots".to_string(),
+        ..RelayConfig::default()
+    }).is_err());
+
+    // Off unless configured
+    let disabled = StaticRelayPolicy::from_config(&RelayConfig::default()).unwrap();
+    assert_eq!(disabled.evaluate(&request).await.as_str(), "rejected");
+}
+
+#[actix_rt::test]
+async fn test_deadline_window() {
+    let service = service("http://127.0.0.1:9", flat_fee("0"));
+    let now = Utc::now().timestamp();
+
+    assert!(service.check_deadline(now + 600).is_ok());
+    assert!(service.check_deadline(now + 10).is_err());
+    assert!(service.check_deadline(now - 600).is_err());
+    assert!(matches!(service.check_deadline(now + 7200), Err(RelayError::InvalidDeadline { max_secs: 3600 })));
+}
+
+#[actix_rt::test]
+async fn test_relay_needs_permit_contracts() {
+    let create_with_permit = ethers::utils::id(
+        "createVoucherWithPermit(address,uint256,uint256,uint256,uint8,bytes32,bytes32,bytes)"
+    );
+    let nonces = ethers::utils::id("nonces(address)");
+    assert!(bytecode_has_selector(&[0x60, 0x00, 0x63, nonces[0], nonces[1], nonces[2], nonces[3], 0x14], nonces));
+    // The selector as data, not pushed for a comparison
+    assert!(!bytecode_has_selector(&[0x60, nonces[0], nonces[1], nonces[2], nonces[3]], nonces));
+
+    // An NBGN token without EIP-2612 permit cannot be relayed through
+    let code = |selector: [u8; 4]| format!("0x608063{}14", hex::encode(selector));
+    for (nbgn_selector, supported) in [(nonces, true), (ethers::utils::id("mint(uint256)"), false)] {
+        let mock_server = MockServer::start().await;
+        for (contract, code) in [(VOUCHER_CONTRACT, code(create_with_permit)), (NBGN_CONTRACT, code(nbgn_selector))] {
+            Mock::given(method("POST"))
+                .and(body_partial_json(json!({ "method": "eth_getCode", "params": [contract.to_lowercase(), "latest"] })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": code })))
+                .mount(&mock_server)
+                .await;
+        }
+        let provider = Provider::<Http>::try_from(mock_server.uri()).unwrap();
+        let result = contracts_support_relay(&provider, VOUCHER_CONTRACT.parse().unwrap(), NBGN_CONTRACT.parse().unwrap()).await;
+        assert_eq!(result.unwrap(), supported);
+    }
+}
+
+#[actix_rt::test]
+async fn test_quote_reads_permit_nonce() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_call" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": "0x0000000000000000000000000000000000000000000000000000000000000005"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let service = service(&mock_server.uri(), flat_fee("250"));
+    let creator: Address = "0x1111111111111111111111111111111111111111".parse().unwrap();
+    let quote = service.quote(creator, "1000", "10.0.0.1").await.unwrap();
+
+    assert!(!quote.sponsored);
+    assert_eq!(quote.relayer_fee, "250");
+    // The permit covers the voucher and the fee
+    assert_eq!(quote.permit_value, "1250");
+    assert_eq!(quote.permit_nonce, "5");
+    assert_eq!(quote.voucher_contract, VOUCHER_CONTRACT.to_lowercase());
+}
+
+#[actix_rt::test]
+async fn test_submit_checks_before_relaying() {
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let ctx = AuditContext::system("test");
+
+    // Intent signed by someone else
+    let mut request = signed_request(&creator, 1000, 0).await;
+    request.creator = format!("{:?}", LocalWallet::new(&mut rand::thread_rng()).address());
+    let err = service("http://127.0.0.1:9", flat_fee("0")).submit(&request, "10.0.0.1", &ctx).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<RelayError>(), Some(RelayError::InvalidIntentSignature)));
+
+    // Signed fee below what the policy asks
+    let request = signed_request(&creator, 1000, 100).await;
+    let err = service("http://127.0.0.1:9", flat_fee("250")).submit(&request, "10.0.0.1", &ctx).await.unwrap_err();
+    match err.downcast_ref::<RelayError>() {
+        Some(RelayError::FeeTooLow { required }) => assert_eq!(*required, U256::from(250)),
+        other => panic!("expected FeeTooLow, got {:?}", other),
+    }
+
+    // The policy hook can refuse outright
+    let request = signed_request(&creator, 1000, 0).await;
+    let err = service("http://127.0.0.1:9", Arc::new(RejectAll)).submit(&request, "10.0.0.1", &ctx).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<RelayError>(), Some(RelayError::Rejected(reason)) if reason == "creations paused"));
+}
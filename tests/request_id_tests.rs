@@ -0,0 +1,50 @@
+use actix_web::{test, web, App, HttpRequest, HttpResponse};
+use nbgn_backend::api::request_context;
+use nbgn_backend::middleware::request_id::RequestIdMiddleware;
+
+async fn echo_request_id(req: HttpRequest) -> HttpResponse {
+    HttpResponse::Ok().body(request_context::request_id(&req).unwrap_or_default())
+}
+
+#[actix_rt::test]
+async fn test_request_id_generated_and_echoed() {
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestIdMiddleware)
+            .route("/", web::get().to(echo_request_id))
+    ).await;
+
+    let res = test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+    let header = res.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+    let body = test::read_body(res).await;
+
+    assert_eq!(header.len(), 32);
+    assert_eq!(body, header.as_bytes());
+}
+
+#[actix_rt::test]
+async fn test_request_id_from_header() {
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestIdMiddleware)
+            .route("/", web::get().to(echo_request_id))
+    ).await;
+
+    // A well-formed caller id is kept so logs can be correlated across services
+    let req = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("X-Request-Id", "frontend-1234_abcd"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get("x-request-id").unwrap(), "frontend-1234_abcd");
+
+    // Anything else is replaced rather than written into the audit log
+    let req = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("X-Request-Id", "not valid; drop table"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    let header = res.headers().get("x-request-id").unwrap().to_str().unwrap();
+    assert_ne!(header, "not valid; drop table");
+    assert_eq!(header.len(), 32);
+}
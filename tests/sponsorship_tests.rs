@@ -0,0 +1,180 @@
+use ethers::prelude::*;
+use nbgn_backend::config::{RelayConfig, SponsorshipConfig};
+use nbgn_backend::services::relay_policy::{RelayKind, RelayPolicy, RelayRequest};
+use nbgn_backend::services::sponsorship::SponsorshipPolicy;
+use serde_json::json;
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+use test_utils::{lazy_pool, test_database};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+fn policy(config: SponsorshipConfig, relay: RelayConfig) -> SponsorshipPolicy {
+    // Nothing listens here: rules that need the database fail
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_secs(2))
+        .connect_lazy("postgres://localhost:1/nbgn_unused")
+        .unwrap();
+    SponsorshipPolicy::from_config(pool, &config, &relay).unwrap()
+}
+
+fn claim(amount: u64) -> RelayRequest {
+    RelayRequest {
+        kind: RelayKind::Claim,
+        subject: Some("ABCD1234EFGH5678".to_string()),
+        creator: "0x1111111111111111111111111111111111111111".to_string(),
+        recipient: Some("0x2222222222222222222222222222222222222222".to_string()),
+        campaign_id: None,
+        amount: U256::from(amount),
+        gas_limit: 200_000,
+        client_ip: "10.0.0.1".to_string(),
+    }
+}
+
+#[actix_rt::test]
+async fn test_invalid_config() {
+    let pool = lazy_pool();
+    let config = SponsorshipConfig {
+        daily_spend_cap_wei: "a lot".to_string(),
+        ..SponsorshipConfig::default()
+    };
+    assert!(SponsorshipPolicy::from_config(pool.clone(), &config, &RelayConfig::default()).is_err());
+
+    let config = SponsorshipConfig {
+        min_amount: "-1".to_string(),
+        ..SponsorshipConfig::default()
+    };
+    assert!(SponsorshipPolicy::from_config(pool, &config, &RelayConfig::default()).is_err());
+}
+
+#[actix_rt::test]
+async fn test_unlimited_by_default() {
+    let policy = policy(SponsorshipConfig::default(), RelayConfig::default());
+    let decision = policy.evaluate(&claim(1)).await;
+    assert_eq!(decision.as_str(), "sponsored");
+    assert_eq!(decision.reason(), "within sponsorship limits");
+}
+
+#[actix_rt::test]
+async fn test_minimum_amount() {
+    let policy = policy(SponsorshipConfig {
+        min_amount: "1000".to_string(),
+        ..SponsorshipConfig::default()
+    }, RelayConfig::default());
+
+    let decision = policy.evaluate(&claim(999)).await;
+    assert_eq!(decision.as_str(), "rejected");
+    assert!(decision.reason().contains("minimum of 1000 wei"));
+    assert_eq!(policy.evaluate(&claim(1000)).await.as_str(), "sponsored");
+}
+
+#[actix_rt::test]
+async fn test_creations_follow_the_relay_config_first() {
+    let request = RelayRequest { kind: RelayKind::Creation, recipient: None, ..claim(1) };
+    let min_amount = SponsorshipConfig {
+        min_amount: "1000".to_string(),
+        ..SponsorshipConfig::default()
+    };
+
+    // A creation that pays its own fee is not sponsorship and skips the rules
+    let charged = policy(min_amount.clone(), RelayConfig {
+        enabled: true,
+        creation_fee: "500".to_string(),
+        ..RelayConfig::default()
+    });
+    let decision = charged.evaluate(&request).await;
+    assert_eq!(decision.as_str(), "fee");
+    assert_eq!(decision.fee(), U256::from(500));
+
+    // Off unless configured
+    let disabled = policy(min_amount.clone(), RelayConfig::default());
+    assert_eq!(disabled.evaluate(&request).await.reason(), "relayed creation is disabled");
+
+    // Sponsored creations are held to the same rules as claims
+    let sponsored = policy(min_amount, RelayConfig { enabled: true, ..RelayConfig::default() });
+    let decision = sponsored.evaluate(&request).await;
+    assert_eq!(decision.as_str(), "rejected");
+    assert!(decision.reason().contains("minimum of 1000 wei"));
+}
+
+#[actix_rt::test]
+async fn test_spend_cap_fails_closed() {
+    let capped = SponsorshipConfig {
+        daily_spend_cap_wei: "1000000000000000".to_string(),
+        ..SponsorshipConfig::default()
+    };
+
+    // Without a gas price the spend cannot be bounded
+    let decision = policy(capped.clone(), RelayConfig::default()).evaluate(&claim(1)).await;
+    assert_eq!(decision.as_str(), "rejected");
+    assert_eq!(decision.reason(), "gas price unavailable to check the sponsorship budget");
+
+    // Nor when the day's spend cannot be read
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_gasPrice" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": "0x989680"
+        })))
+        .mount(&mock_server)
+        .await;
+    let provider = Arc::new(Provider::<Http>::try_from(mock_server.uri()).unwrap());
+    let decision = policy(capped, RelayConfig::default())
+        .with_provider(provider)
+        .evaluate(&claim(1))
+        .await;
+    assert_eq!(decision.as_str(), "rejected");
+    assert_eq!(decision.reason(), "sponsorship limits could not be checked");
+}
+
+fn random_address() -> String {
+    format!("{:?}", Address::random())
+}
+
+#[actix_rt::test]
+async fn test_concurrent_decisions_share_the_quota() {
+    let Some(pool) = test_database().await else { return };
+    let config = SponsorshipConfig { creator_daily_quota: 1, ..SponsorshipConfig::default() };
+    let policy = SponsorshipPolicy::from_config(pool, &config, &RelayConfig::default()).unwrap();
+    let creator = random_address();
+    let request = RelayRequest { creator: creator.clone(), recipient: Some(random_address()), ..claim(1) };
+
+    let decisions = futures_util::future::join_all((0..5).map(|_| policy.decide(&request))).await;
+    let sponsored = decisions.iter().filter(|decision| decision.as_str() == "sponsored").count();
+    assert_eq!(sponsored, 1);
+    assert!(decisions.iter().any(|decision| decision.reason().contains("creator reached the daily quota")));
+}
+
+#[actix_rt::test]
+async fn test_unsent_decisions_stop_counting() {
+    let Some(pool) = test_database().await else { return };
+    let config = SponsorshipConfig { creator_daily_quota: 1, ..SponsorshipConfig::default() };
+    let policy = SponsorshipPolicy::from_config(pool.clone(), &config, &RelayConfig::default()).unwrap();
+    let creator = random_address();
+    let request = RelayRequest {
+        creator: creator.clone(),
+        subject: Some(format!("{:?}", H256::random())),
+        ..claim(1)
+    };
+
+    assert_eq!(policy.decide(&request).await.as_str(), "sponsored");
+    assert_eq!(policy.decide(&request).await.as_str(), "rejected");
+
+    // The send failed: the quota is free again
+    policy.unsent(&request, "nonce too low").await;
+    let (decision, reason): (String, String) = sqlx::query_as(
+        "SELECT decision, reason FROM sponsorship_decisions WHERE creator_address = LOWER($1) ORDER BY id LIMIT 1"
+    )
+    .bind(&creator)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(decision, "unsent");
+    assert_eq!(reason, "not sent: nonce too low");
+    assert_eq!(policy.decide(&request).await.as_str(), "sponsored");
+}
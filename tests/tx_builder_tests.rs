@@ -0,0 +1,164 @@
+use ethers::abi::AbiDecode;
+use ethers::prelude::*;
+use ethers::utils::id;
+use nbgn_backend::contracts::erc20::ApproveCall;
+use nbgn_backend::contracts::voucher::{CancelVoucherCall, CreateVoucherCall};
+use nbgn_backend::db::tx_models::{ApprovalSpender, ApprovalToken};
+use nbgn_backend::services::tx_builder::{parse_allowance, parse_amount, TxBuildError, TxBuilder};
+use serde_json::json;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const VOUCHER_CONTRACT: &str = "0x66Eb0Aa46827e5F3fFcb6Dea23C309CB401690B6";
+const NBGN_CONTRACT: &str = "0x47F9CF7043C8A059f82a988C0B9fF73F0c3e6067";
+const FROM: &str = "0x1111111111111111111111111111111111111111";
+
+fn builder(rpc_url: &str) -> TxBuilder {
+    let provider = Arc::new(Provider::<Http>::try_from(rpc_url).unwrap());
+    TxBuilder::new(provider, VOUCHER_CONTRACT.parse().unwrap(), NBGN_CONTRACT.parse().unwrap())
+}
+
+fn rpc_result(result: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+async fn mock_fee_data(mock_server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_getBlockByNumber" })))
+        .respond_with(rpc_result(json!({
+            "hash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+            "parentHash": "0x3333333333333333333333333333333333333333333333333333333333333333",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "miner": "0x0000000000000000000000000000000000000000",
+            "stateRoot": "0x4444444444444444444444444444444444444444444444444444444444444444",
+            "transactionsRoot": "0x5555555555555555555555555555555555555555555555555555555555555555",
+            "receiptsRoot": "0x6666666666666666666666666666666666666666666666666666666666666666",
+            "number": "0x100",
+            "gasUsed": "0x0",
+            "gasLimit": "0x4000000000000",
+            "extraData": "0x",
+            "logsBloom": null,
+            "timestamp": "0x65fc1a00",
+            "difficulty": "0x1",
+            "uncles": [],
+            "transactions": [],
+            "baseFeePerGas": "0x989680"
+        })))
+        .mount(mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_feeHistory" })))
+        .respond_with(rpc_result(json!({
+            "oldestBlock": "0xf7",
+            "baseFeePerGas": ["0x989680", "0x989680"],
+            "gasUsedRatio": [0.1],
+            "reward": [["0x0"]]
+        })))
+        .mount(mock_server)
+        .await;
+}
+
+#[test]
+fn test_calldata_is_abi_encoded() {
+    let builder = builder("http://127.0.0.1:9");
+
+    let amount = U256::exp10(18);
+    let call = builder.create_voucher(amount);
+    assert_eq!(call.to, VOUCHER_CONTRACT.parse::<Address>().unwrap());
+    assert_eq!(&call.data[..4], &id("createVoucher(uint256)"));
+    assert_eq!(CreateVoucherCall::decode(&call.data).unwrap().amount, amount);
+
+    let voucher_id = H256::repeat_byte(0xab);
+    let call = builder.cancel_voucher(voucher_id);
+    assert_eq!(&call.data[..4], &id("cancelVoucher(bytes32)"));
+    assert_eq!(CancelVoucherCall::decode(&call.data).unwrap().voucher_id, voucher_id.0);
+
+    let call = builder.mint(amount);
+    assert_eq!(call.to, NBGN_CONTRACT.parse::<Address>().unwrap());
+    assert_eq!(&call.data[..4], &id("mint(uint256)"));
+    assert_eq!(&builder.redeem(amount).data[..4], &id("redeem(uint256)"));
+}
+
+#[test]
+fn test_approvals() {
+    let builder = builder("http://127.0.0.1:9");
+
+    let call = builder.approve(ApprovalToken::Nbgn, ApprovalSpender::VoucherContract, U256::MAX).unwrap();
+    assert_eq!(call.to, NBGN_CONTRACT.parse::<Address>().unwrap());
+    let decoded = ApproveCall::decode(&call.data).unwrap();
+    assert_eq!(decoded.spender, VOUCHER_CONTRACT.parse::<Address>().unwrap());
+    assert_eq!(decoded.amount, U256::MAX);
+
+    // EURe approvals need the token address
+    assert!(matches!(
+        builder.approve(ApprovalToken::Eure, ApprovalSpender::NbgnContract, U256::one()),
+        Err(TxBuildError::EureNotConfigured)
+    ));
+    let eure: Address = "0x0c06cCF38114ddfc35e07427B9424adcca9F44F8".parse().unwrap();
+    let call = builder.with_eure_token(eure)
+        .approve(ApprovalToken::Eure, ApprovalSpender::NbgnContract, U256::one())
+        .unwrap();
+    assert_eq!(call.to, eure);
+}
+
+#[test]
+fn test_amount_parsing() {
+    assert_eq!(parse_amount("1000").unwrap(), U256::from(1000));
+    assert!(parse_amount("0").is_err());
+    assert!(parse_amount("-1").is_err());
+    assert!(parse_amount("1.5").is_err());
+    assert!(parse_amount("0x10").is_err());
+
+    assert_eq!(parse_allowance("max").unwrap(), U256::MAX);
+    assert_eq!(parse_allowance("0").unwrap(), U256::zero());
+}
+
+#[actix_rt::test]
+async fn test_prepare_fills_gas_and_fees() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_estimateGas" })))
+        .respond_with(rpc_result(json!("0x186a0")))
+        .mount(&mock_server)
+        .await;
+    mock_fee_data(&mock_server).await;
+
+    let builder = builder(&mock_server.uri());
+    let tx = builder.prepare(FROM.parse().unwrap(), builder.mint(U256::from(1000))).await.unwrap();
+    let tx = serde_json::to_value(tx).unwrap();
+
+    assert_eq!(tx["to"], NBGN_CONTRACT.to_lowercase());
+    assert_eq!(tx["from"], FROM);
+    assert_eq!(tx["value"], "0");
+    assert_eq!(tx["chain_id"], 42161);
+    assert_eq!(tx["type"], 2);
+    // 100000 estimated plus 20% headroom
+    assert_eq!(tx["gas"], "120000");
+    assert_eq!(tx["gas_estimated"], true);
+    assert!(tx.get("estimate_error").is_none());
+    assert!(tx["data"].as_str().unwrap().starts_with("0xa0712d68"));
+    assert!(tx["max_fee_per_gas"].as_str().unwrap().parse::<u64>().unwrap() >= 10_000_000);
+}
+
+#[actix_rt::test]
+async fn test_prepare_falls_back_when_estimate_reverts() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_estimateGas" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": 3, "message": "execution reverted: ERC20: insufficient allowance" }
+        })))
+        .mount(&mock_server)
+        .await;
+    mock_fee_data(&mock_server).await;
+
+    let builder = builder(&mock_server.uri());
+    let tx = builder.prepare(FROM.parse().unwrap(), builder.create_voucher(U256::from(1000))).await.unwrap();
+
+    assert_eq!(tx.gas, "150000");
+    assert!(!tx.gas_estimated);
+    assert!(tx.estimate_error.unwrap().contains("insufficient allowance"));
+}
@@ -0,0 +1,257 @@
+use actix_web::{body::to_bytes, web};
+use nbgn_backend::api::voucher_routes::get_voucher_details;
+use nbgn_backend::config::{CodeConfig, CodeDerivation, CodeFormat};
+use nbgn_backend::db::voucher_models::VoucherCode;
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::codes::CodeGenerator;
+use nbgn_backend::services::voucher::{VoucherService, MAX_ACTIVE_CODES_PER_VOUCHER};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use test_utils::{insert_voucher, test_database, voucher_service};
+
+mod test_utils;
+
+const VOUCHER_ID: &str = "0xabababababababababababababababababababababababababababababababab";
+const SECRET: &str = "0123456789abcdef0123456789abcdef";
+const CREATOR: &str = "0x1111111111111111111111111111111111111111";
+
+fn hmac_generator(secret: &str) -> CodeGenerator {
+    CodeGenerator::from_config(&CodeConfig {
+        derivation: CodeDerivation::Hmac,
+        secret: Some(secret.to_string()),
+        ..Default::default()
+    }).unwrap()
+}
+
+fn crockford_generator() -> CodeGenerator {
+    CodeGenerator::from_config(&CodeConfig {
+        format: CodeFormat::Crockford,
+        ..Default::default()
+    }).unwrap()
+}
+
+#[test]
+fn test_hmac_codes_are_deterministic() {
+    let codes = hmac_generator(SECRET);
+    assert!(codes.is_deterministic());
+
+    let code = codes.code_for(VOUCHER_ID, 0);
+    assert_eq!(code.len(), 16);
+    assert!(code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()));
+
+    // Same input, same code, regardless of voucher id case
+    assert_eq!(code, codes.code_for(VOUCHER_ID, 0));
+    assert_eq!(code, codes.code_for(&VOUCHER_ID.to_uppercase().replace("0X", "0x"), 0));
+
+    // Each further code of a voucher, each voucher and each secret differ
+    assert_ne!(code, codes.code_for(VOUCHER_ID, 1));
+    assert_ne!(code, codes.code_for("0x2222222222222222222222222222222222222222222222222222222222222222", 0));
+    assert_ne!(code, hmac_generator("fedcba9876543210fedcba9876543210").code_for(VOUCHER_ID, 0));
+}
+
+#[test]
+fn test_code_config_validation() {
+    assert!(!CodeGenerator::from_config(&CodeConfig::default()).unwrap().is_deterministic());
+
+    let missing = CodeConfig { derivation: CodeDerivation::Hmac, ..Default::default() };
+    assert!(CodeGenerator::from_config(&missing).is_err());
+
+    let short = CodeConfig {
+        derivation: CodeDerivation::Hmac,
+        secret: Some("too short".to_string()),
+        ..Default::default()
+    };
+    assert!(CodeGenerator::from_config(&short).is_err());
+}
+
+#[test]
+fn test_random_codes() {
+    let codes = CodeGenerator::default();
+    let code = codes.code_for(VOUCHER_ID, 0);
+    assert_eq!(code.len(), 16);
+    assert_ne!(code, codes.code_for(VOUCHER_ID, 0));
+}
+
+#[test]
+fn test_crockford_codes_round_trip() {
+    let codes = crockford_generator();
+    let code = codes.code_for(VOUCHER_ID, 0);
+    assert_eq!(code.len(), 20);
+    assert!(!code.contains(['I', 'L', 'O', 'U']));
+
+    let shown = codes.display(&code);
+    assert_eq!(shown.len(), 23);
+    assert_eq!(shown.matches('-').count(), 3);
+
+    // Case, dashes and spaces do not matter
+    assert_eq!(codes.normalize(&shown).as_deref(), Some(code.as_str()));
+    assert_eq!(codes.normalize(&shown.to_lowercase()).as_deref(), Some(code.as_str()));
+    assert_eq!(codes.normalize(&shown.replace('-', " ")).as_deref(), Some(code.as_str()));
+}
+
+#[test]
+fn test_crockford_check_symbol_catches_typos() {
+    let codes = crockford_generator();
+    let alphabet = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+    for _ in 0..20 {
+        let code = codes.code_for(VOUCHER_ID, 0);
+
+        // Every single-symbol substitution is rejected
+        for (i, original) in code.char_indices() {
+            for replacement in alphabet.chars().filter(|c| *c != original) {
+                let mut typo = code.clone();
+                typo.replace_range(i..i + 1, &replacement.to_string());
+                assert!(codes.normalize(&typo).is_none(), "accepted {} for {}", typo, code);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_crockford_reads_ambiguous_characters() {
+    let codes = crockford_generator();
+
+    // Find a code containing 0 and 1 and type them as O and l
+    let code = std::iter::repeat_with(|| codes.code_for(VOUCHER_ID, 0))
+        .find(|c| c.contains('0') && c.contains('1'))
+        .unwrap();
+    let typed = code.replace('0', "O").replace('1', "l");
+    assert_eq!(codes.normalize(&typed).as_deref(), Some(code.as_str()));
+}
+
+#[test]
+fn test_legacy_codes_accepted_in_any_format() {
+    let legacy = CodeGenerator::default();
+    let crockford = crockford_generator();
+
+    assert_eq!(legacy.normalize("abcd-1234-efgh-5678").as_deref(), Some("ABCD1234EFGH5678"));
+    assert_eq!(crockford.normalize("ABCD1234EFGH5678").as_deref(), Some("ABCD1234EFGH5678"));
+    assert_eq!(legacy.display("ABCD1234EFGH5678"), "ABCD1234EFGH5678");
+
+    // Wrong length or characters never reach the database
+    assert!(legacy.normalize("ABCD1234").is_none());
+    assert!(legacy.normalize("ABCD1234EFGH567!").is_none());
+    assert!(legacy.normalize(&crockford.code_for(VOUCHER_ID, 0)).is_none());
+}
+
+// Derived codes are numbered, so two additions that count alike collide
+fn derived_codes(pool: &PgPool) -> VoucherService {
+    voucher_service(pool.clone()).with_code_generator(hmac_generator(SECRET))
+}
+
+async fn codes_of(pool: &PgPool, voucher_id: &str) -> Vec<String> {
+    sqlx::query_scalar("SELECT code FROM voucher_codes WHERE voucher_id = $1 ORDER BY created_at, code")
+        .bind(voucher_id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+async fn voucher(service: &VoucherService, code: &str) -> VoucherCode {
+    service.get_voucher_by_code(code).await.unwrap().unwrap()
+}
+
+#[actix_rt::test]
+async fn test_concurrent_additions_get_distinct_codes() {
+    let Some(pool) = test_database().await else { return };
+    let service = derived_codes(&pool);
+    let ctx = AuditContext::system("test");
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    let first = voucher(&service, &code).await;
+
+    let added = service.add_code(&first, Some("spare"), None, &ctx).await.unwrap();
+    let rotated = service.rotate_code(&added, None, &ctx);
+    let additions = futures_util::future::join_all((0..6).map(|_| service.add_code(&first, None, None, &ctx)));
+    let (rotated, additions) = tokio::join!(rotated, additions);
+
+    let mut new_codes = vec![rotated.unwrap()];
+    new_codes.extend(additions.into_iter().map(Result::unwrap));
+    let codes = codes_of(&pool, &voucher_id).await;
+    assert_eq!(codes.len(), 9);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), 9);
+    for new_code in &new_codes {
+        assert!(codes.contains(new_code));
+    }
+}
+
+#[actix_rt::test]
+async fn test_add_revoke_rotate_and_list_codes() {
+    let Some(pool) = test_database().await else { return };
+    let service = voucher_service(pool.clone());
+    let ctx = AuditContext::system("test");
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    let first = voucher(&service, &code).await;
+
+    // An added code shares the voucher but has its own label and password
+    let sms = service.add_code(&first, Some("sent by SMS"), Some("pw"), &ctx).await.unwrap();
+    let added = voucher(&service, &sms).await;
+    assert_eq!(added.voucher_id, voucher_id);
+    assert_eq!(added.amount, first.amount);
+    assert!(added.password_hash.is_some());
+
+    service.revoke_code(&code, &ctx).await.unwrap();
+    assert!(service.revoke_code(&code, &ctx).await.is_err());
+
+    // Rotating keeps the label and password and points the old code at the new one
+    let rotated = service.rotate_code(&sms, None, &ctx).await.unwrap();
+    assert!(service.rotate_code(&sms, None, &ctx).await.is_err());
+    assert!(service.rotate_code(&code, None, &ctx).await.is_err());
+
+    let listed = service.list_codes(&voucher_id).await.unwrap();
+    let summary = |code: &str| listed.iter().find(|summary| summary.code == code).unwrap();
+    assert_eq!(listed.len(), 3);
+    assert_eq!(listed[0].code, code);
+    assert!(summary(&code).revoked_at.is_some());
+    assert_eq!(summary(&code).replaced_by, None);
+    assert!(summary(&sms).revoked_at.is_some());
+    assert_eq!(summary(&sms).replaced_by.as_deref(), Some(rotated.as_str()));
+    let new = summary(&rotated);
+    assert!(new.revoked_at.is_none());
+    assert_eq!(new.label.as_deref(), Some("sent by SMS"));
+    assert!(new.has_password);
+    assert_eq!((new.attempts, new.authorizations_issued), (0, 0));
+}
+
+#[actix_rt::test]
+async fn test_active_code_limit_holds_under_concurrency() {
+    let Some(pool) = test_database().await else { return };
+    let service = voucher_service(pool.clone());
+    let ctx = AuditContext::system("test");
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    let first = voucher(&service, &code).await;
+
+    // One short of the limit, then several additions at once
+    for _ in 0..MAX_ACTIVE_CODES_PER_VOUCHER - 2 {
+        service.add_code(&first, None, None, &ctx).await.unwrap();
+    }
+    let additions = futures_util::future::join_all((0..4).map(|_| service.add_code(&first, None, None, &ctx))).await;
+    assert_eq!(additions.iter().filter(|added| added.is_ok()).count(), 1);
+    for refused in additions.iter().filter_map(|added| added.as_ref().err()) {
+        assert!(refused.to_string().contains("at most"));
+    }
+
+    let active = service.list_codes(&voucher_id).await.unwrap()
+        .into_iter()
+        .filter(|summary| summary.revoked_at.is_none())
+        .count();
+    assert_eq!(active as i64, MAX_ACTIVE_CODES_PER_VOUCHER);
+
+    // Revoking one frees a slot
+    service.revoke_code(&code, &ctx).await.unwrap();
+    assert!(service.add_code(&first, None, None, &ctx).await.is_ok());
+}
+
+#[actix_rt::test]
+async fn test_details_show_a_live_code() {
+    let Some(pool) = test_database().await else { return };
+    let service = voucher_service(pool.clone());
+    let ctx = AuditContext::system("test");
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    let added = service.add_code(&voucher(&service, &code).await, None, None, &ctx).await.unwrap();
+    service.revoke_code(&code, &ctx).await.unwrap();
+
+    let response = get_voucher_details(web::Data::new(service), web::Path::from(voucher_id)).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+    assert_eq!(body["code"], added);
+}
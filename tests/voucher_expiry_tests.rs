@@ -0,0 +1,155 @@
+use chrono::{Duration, Utc};
+use nbgn_backend::config::NotificationConfig;
+use nbgn_backend::db::voucher_models::{ClaimWindow, CreateLinkRequest, CreatorAuth, VoucherCode};
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::notifier::{CreatorNotification, Notifier};
+use nbgn_backend::services::voucher::{ClaimError, LinkError, VoucherService};
+use serde_json::json;
+use test_utils::{lazy_pool, voucher_service};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+const VOUCHER_ID: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+fn voucher_with_window(window: ClaimWindow) -> VoucherCode {
+    serde_json::from_value(json!({
+        "code": "ABCD1234EFGH5678",
+        "voucher_id": VOUCHER_ID,
+        "password_hash": null,
+        "created_at": Utc::now(),
+        "creator_address": null,
+        "amount": "1000",
+        "on_chain_created_at": null,
+        "claimed": false,
+        "claimed_by": null,
+        "claimed_at": null,
+        "claim_tx_hash": null,
+        "cancelled": false,
+        "cancelled_at": null,
+        "cancel_tx_hash": null,
+        "claim_tx_status": null,
+        "claim_tx_submitted_at": null,
+        "lockout_reset_at": null,
+        "label": null,
+        "revoked_at": null,
+        "replaced_by": null,
+        "claimable_from": window.claimable_from,
+        "claimable_until": window.claimable_until,
+        "expiry_notified_at": null,
+        "campaign_id": null,
+        "status": "active"
+    })).unwrap()
+}
+
+#[test]
+fn test_claim_window_enforced() {
+    let now = Utc::now();
+
+    assert!(VoucherService::check_claim_window(&voucher_with_window(ClaimWindow::default())).is_ok());
+    assert!(VoucherService::check_claim_window(&voucher_with_window(ClaimWindow {
+        claimable_from: Some(now - Duration::hours(1)),
+        claimable_until: Some(now + Duration::hours(1)),
+    })).is_ok());
+
+    let err = VoucherService::check_claim_window(&voucher_with_window(ClaimWindow {
+        claimable_from: Some(now + Duration::hours(1)),
+        claimable_until: None,
+    })).unwrap_err();
+    assert!(matches!(err, ClaimError::NotYetClaimable { .. }));
+
+    let err = VoucherService::check_claim_window(&voucher_with_window(ClaimWindow {
+        claimable_from: None,
+        claimable_until: Some(now - Duration::seconds(1)),
+    })).unwrap_err();
+    assert!(matches!(err, ClaimError::Expired { .. }));
+}
+
+#[actix_rt::test]
+async fn test_link_rejects_invalid_window() {
+    // Rejected before any signature check, chain call or query
+    let service = voucher_service(lazy_pool());
+    let ctx = AuditContext::system("test");
+    let auth = CreatorAuth {
+        address: "0x1111111111111111111111111111111111111111".to_string(),
+        timestamp: Utc::now().timestamp(),
+        signature: "0x00".to_string(),
+    };
+    let now = Utc::now();
+
+    let backwards = ClaimWindow {
+        claimable_from: Some(now + Duration::days(2)),
+        claimable_until: Some(now + Duration::days(1)),
+    };
+    let err = service.create_voucher_link(VOUCHER_ID, None, None, &backwards, &auth, &ctx).await.unwrap_err();
+    assert!(matches!(*err.downcast::<LinkError>().unwrap(), LinkError::InvalidWindow(_)));
+
+    let past = ClaimWindow {
+        claimable_from: None,
+        claimable_until: Some(now - Duration::days(1)),
+    };
+    let err = service.create_voucher_link(VOUCHER_ID, None, None, &past, &auth, &ctx).await.unwrap_err();
+    assert!(matches!(*err.downcast::<LinkError>().unwrap(), LinkError::InvalidWindow(_)));
+}
+
+#[test]
+fn test_link_request_window_is_optional() {
+    let req: CreateLinkRequest = serde_json::from_value(json!({
+        "voucher_id": VOUCHER_ID,
+        "creator_address": "0x1111111111111111111111111111111111111111",
+        "timestamp": 1700000000,
+        "signature": "0xabc"
+    })).unwrap();
+    assert!(!req.window.is_set());
+
+    let req: CreateLinkRequest = serde_json::from_value(json!({
+        "voucher_id": VOUCHER_ID,
+        "creator_address": "0x1111111111111111111111111111111111111111",
+        "claimable_until": "2030-01-01T00:00:00Z",
+        "timestamp": 1700000000,
+        "signature": "0xabc"
+    })).unwrap();
+    assert!(req.window.claimable_from.is_none());
+    assert_eq!(req.window.claimable_until.unwrap().to_rfc3339(), "2030-01-01T00:00:00+00:00");
+}
+
+#[actix_rt::test]
+async fn test_notifier_posts_to_webhook() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hooks/creators"))
+        .and(body_partial_json(json!({
+            "event": "voucher.expired",
+            "voucher_id": VOUCHER_ID
+        })))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let notification = CreatorNotification {
+        event: "voucher.expired".to_string(),
+        creator_address: "0x1111111111111111111111111111111111111111".to_string(),
+        voucher_id: VOUCHER_ID.to_string(),
+        occurred_at: Utc::now(),
+        data: json!({ "amount": "1000" }),
+    };
+
+    let notifier = Notifier::from_config(&NotificationConfig {
+        webhook_url: Some(format!("{}/hooks/creators", mock_server.uri())),
+        ..NotificationConfig::default()
+    });
+    notifier.notify(&notification).await.unwrap();
+
+    // Delivery failures surface so the scanner can retry
+    let failing = Notifier::from_config(&NotificationConfig {
+        webhook_url: Some(format!("{}/missing", mock_server.uri())),
+        ..NotificationConfig::default()
+    });
+    assert!(failing.notify(&notification).await.is_err());
+
+    // Without a channel the notification is only logged
+    let silent = Notifier::from_config(&NotificationConfig::default());
+    assert!(silent.notify(&notification).await.is_ok());
+}
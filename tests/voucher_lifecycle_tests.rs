@@ -0,0 +1,378 @@
+use actix_web::{body::to_bytes, test::TestRequest, web};
+use chrono::Utc;
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use nbgn_backend::api::voucher_routes::{delete_voucher, verify_voucher};
+use nbgn_backend::config::WebhookConfig;
+use nbgn_backend::db::voucher_models::VoucherCode;
+use nbgn_backend::db::voucher_models::{CreatorAuth, VerifyRequest};
+use nbgn_backend::services::audit::{AuditContext, AuditLog};
+use nbgn_backend::services::event_indexer::{EventIndexer, VoucherClaimed};
+use nbgn_backend::services::lifecycle::{LifecycleError, StatusChange, VoucherLifecycle, VoucherStatus};
+use nbgn_backend::services::voucher::{ClaimError, VoucherService, VOUCHER_CONTRACT};
+use nbgn_backend::middleware::rate_limiter::RedisRateLimiter;
+use nbgn_backend::services::webhooks::WebhookService;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use test_utils::{creator_auth, insert_voucher, test_database, voucher_service};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+const CREATOR: &str = "0x1111111111111111111111111111111111111111";
+
+const ALL: [VoucherStatus; 6] = [
+    VoucherStatus::Active,
+    VoucherStatus::Claiming,
+    VoucherStatus::Claimed,
+    VoucherStatus::Expired,
+    VoucherStatus::Withdrawn,
+    VoucherStatus::Cancelled,
+];
+
+fn voucher_with_status(status: &str) -> VoucherCode {
+    serde_json::from_value(json!({
+        "code": "ABCD1234EFGH5678",
+        "voucher_id": "0x1111111111111111111111111111111111111111111111111111111111111111",
+        "created_at": Utc::now(),
+        "claimed": status == "claimed",
+        "cancelled": status == "cancelled",
+        "status": status
+    })).unwrap()
+}
+
+#[test]
+fn test_status_names_round_trip() {
+    for status in ALL {
+        assert_eq!(VoucherStatus::parse(status.as_str()), Some(status));
+        assert_eq!(json!(status), json!(status.as_str()));
+    }
+    assert_eq!(VoucherStatus::parse("deleted"), None);
+}
+
+#[test]
+fn test_claimed_and_cancelled_are_final() {
+    for next in ALL {
+        assert!(!VoucherStatus::Claimed.can_become(next));
+        assert!(!VoucherStatus::Cancelled.can_become(next));
+    }
+    for status in ALL {
+        assert!(!status.can_become(status));
+    }
+
+    // A withdrawn voucher stays funded on-chain until one of them happens
+    assert!(VoucherStatus::Withdrawn.can_become(VoucherStatus::Cancelled));
+    assert!(VoucherStatus::Withdrawn.can_become(VoucherStatus::Claimed));
+    assert!(!VoucherStatus::Withdrawn.can_become(VoucherStatus::Active));
+
+    // A claim in flight cannot be withdrawn or expired from under it
+    assert!(!VoucherStatus::Claiming.can_become(VoucherStatus::Withdrawn));
+    assert!(!VoucherStatus::Claiming.can_become(VoucherStatus::Expired));
+    assert!(VoucherStatus::Claiming.can_become(VoucherStatus::Active));
+}
+
+#[test]
+fn test_trigger_allows_the_same_transitions() {
+    let migration = include_str!("../migrations/20240321000018_voucher_lifecycle.sql");
+    let body = &migration[migration.find("NOT IN (").unwrap()..];
+    let body = &body[..body.find(") THEN").unwrap()];
+
+    let in_trigger: BTreeSet<(String, String)> = body
+        .split("('")
+        .skip(1)
+        .map(|pair| {
+            let mut names = pair.split('\'').filter(|part| part.chars().all(|c| c.is_ascii_lowercase()) && !part.is_empty());
+            (names.next().unwrap().to_string(), names.next().unwrap().to_string())
+        })
+        .collect();
+
+    let in_code: BTreeSet<(String, String)> = ALL.iter()
+        .flat_map(|from| ALL.iter().map(move |to| (*from, *to)))
+        .filter(|(from, to)| from.can_become(*to))
+        .map(|(from, to)| (from.as_str().to_string(), to.as_str().to_string()))
+        .collect();
+
+    assert_eq!(in_trigger, in_code);
+}
+
+#[test]
+fn test_only_open_vouchers_are_claimable() {
+    assert!(VoucherService::check_open(&voucher_with_status("active")).is_ok());
+    // Expiry is reported by the claim window check with its end date
+    assert!(VoucherService::check_open(&voucher_with_status("expired")).is_ok());
+
+    for (status, message) in [
+        ("claimed", "Voucher already claimed"),
+        ("cancelled", "Voucher has been cancelled"),
+        ("withdrawn", "Voucher has been withdrawn by its creator"),
+    ] {
+        let err = VoucherService::check_open(&voucher_with_status(status)).unwrap_err();
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn test_invalid_transition_message() {
+    let err = LifecycleError::InvalidTransition { from: VoucherStatus::Claimed, to: VoucherStatus::Withdrawn };
+    assert_eq!(err.to_string(), "Voucher is claimed and cannot become withdrawn");
+}
+
+#[actix_rt::test]
+async fn test_transitions_are_recorded() {
+    let Some(pool) = test_database().await else { return };
+    let lifecycle = VoucherLifecycle::new(pool.clone());
+    let ctx = AuditContext::system("test");
+    let (voucher_id, _) = insert_voucher(&pool, CREATOR).await;
+
+    let change = StatusChange { tx_hash: Some(format!("{:?}", ethers::types::H256::random())), ..Default::default() };
+    let from = lifecycle.transition(&voucher_id, VoucherStatus::Claiming, &StatusChange::default(), &ctx).await.unwrap();
+    assert_eq!(from, Some(VoucherStatus::Active));
+    // Already there: nothing moves and nothing is recorded
+    assert_eq!(lifecycle.transition(&voucher_id, VoucherStatus::Claiming, &StatusChange::default(), &ctx).await.unwrap(), None);
+    // Only from the expected status
+    assert!(!lifecycle.transition_from(&voucher_id, VoucherStatus::Active, VoucherStatus::Expired, &change, &ctx).await.unwrap());
+    assert!(lifecycle.transition_from(&voucher_id, VoucherStatus::Claiming, VoucherStatus::Claimed, &change, &ctx).await.unwrap());
+
+    let (status, claimed, claim_tx_hash): (String, bool, Option<String>) = sqlx::query_as(
+        "SELECT status, claimed, claim_tx_hash FROM voucher_codes WHERE voucher_id = $1"
+    )
+    .bind(&voucher_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((status.as_str(), claimed), ("claimed", true));
+    assert_eq!(claim_tx_hash, change.tx_hash);
+
+    let err = lifecycle.transition(&voucher_id, VoucherStatus::Withdrawn, &StatusChange::default(), &ctx).await.unwrap_err();
+    assert!(matches!(err, LifecycleError::InvalidTransition { from: VoucherStatus::Claimed, to: VoucherStatus::Withdrawn }));
+
+    let moves: Vec<(Option<String>, String)> = lifecycle.events(&voucher_id).await.unwrap()
+        .into_iter()
+        .map(|event| (event.from_status, event.to_status))
+        .collect();
+    assert_eq!(moves, vec![
+        (Some("active".to_string()), "claiming".to_string()),
+        (Some("claiming".to_string()), "claimed".to_string()),
+    ]);
+}
+
+#[actix_rt::test]
+async fn test_trigger_rejects_other_writers() {
+    let Some(pool) = test_database().await else { return };
+    let lifecycle = VoucherLifecycle::new(pool.clone());
+    let ctx = AuditContext::system("test");
+    let (voucher_id, _) = insert_voucher(&pool, CREATOR).await;
+    lifecycle.transition(&voucher_id, VoucherStatus::Cancelled, &StatusChange::default(), &ctx).await.unwrap();
+
+    // Writes that skip VoucherLifecycle are held to the same transitions
+    let err = sqlx::query("UPDATE voucher_codes SET status = 'active', cancelled = FALSE WHERE voucher_id = $1")
+        .bind(&voucher_id)
+        .execute(&pool)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("cannot go from cancelled to active"));
+
+    let err = sqlx::query("DELETE FROM voucher_events WHERE voucher_id = $1")
+        .bind(&voucher_id)
+        .execute(&pool)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("append-only"));
+}
+
+#[actix_rt::test]
+async fn test_indexer_marks_wallet_claims() {
+    let Some(pool) = test_database().await else { return };
+    let (voucher_id, _) = insert_voucher(&pool, CREATOR).await;
+    let recipient = Address::random();
+    let tx_hash = H256::random();
+
+    // A claim sent from the recipient's own wallet, never seen by the relayer
+    let log = Log {
+        address: VOUCHER_CONTRACT.parse().unwrap(),
+        topics: vec![VoucherClaimed::signature(), voucher_id.parse().unwrap(), H256::from(recipient)],
+        data: encode(&[Token::Uint(U256::exp10(18))]).into(),
+        block_number: Some(256.into()),
+        transaction_hash: Some(tx_hash),
+        ..Default::default()
+    };
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_getLogs" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": [log] })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_getBlockByNumber" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": {
+            "hash": "0x2222222222222222222222222222222222222222222222222222222222222222",
+            "parentHash": "0x3333333333333333333333333333333333333333333333333333333333333333",
+            "number": "0x100",
+            "gasUsed": "0x0",
+            "gasLimit": "0x4000000000000",
+            "extraData": "0x",
+            "timestamp": "0x65fc1a00",
+            "uncles": [],
+            "transactions": []
+        } })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = Arc::new(Provider::<Http>::try_from(mock_server.uri()).unwrap());
+    let indexer = EventIndexer::new(pool.clone(), provider, VOUCHER_CONTRACT.parse().unwrap())
+        .with_webhooks(WebhookService::new(pool.clone(), WebhookConfig::default()));
+    // Replaying the range changes nothing
+    indexer.index_voucher_events(256, 256).await.unwrap();
+    indexer.index_voucher_events(256, 256).await.unwrap();
+
+    let voucher = VoucherService::new(pool.clone(), test_utils::test_signers()).unwrap()
+        .get_voucher_by_id(&voucher_id).await.unwrap().unwrap();
+    assert_eq!(voucher.status, "claimed");
+    assert_eq!(voucher.claimed_by.as_deref(), Some(format!("{:?}", recipient).as_str()));
+    assert_eq!(voucher.claim_tx_hash.as_deref(), Some(format!("{:?}", tx_hash).as_str()));
+
+    let events = VoucherLifecycle::new(pool.clone()).events(&voucher_id).await.unwrap();
+    assert_eq!(events.iter().filter(|event| event.to_status == "claimed").count(), 1);
+
+    let (payload,): (serde_json::Value,) = sqlx::query_as(
+        "SELECT payload FROM webhook_events WHERE dedupe_key = $1"
+    )
+    .bind(format!("voucher.claimed:{}", voucher_id))
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(payload["claimed_by"], json!(format!("{:?}", recipient)));
+}
+
+async fn delete_status(pool: &sqlx::PgPool, voucher_id: &str, creator: Option<CreatorAuth>) -> u16 {
+    let response = delete_voucher(
+        web::Data::new(AuditLog::new(pool.clone())),
+        web::Data::new(voucher_service(pool.clone())),
+        web::Path::from(voucher_id.to_string()),
+        creator.map(web::Query),
+        TestRequest::default().to_http_request(),
+    ).await.unwrap();
+    response.status().as_u16()
+}
+
+#[actix_rt::test]
+async fn test_only_the_signed_creator_withdraws() {
+    let Some(pool) = test_database().await else { return };
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let (voucher_id, _) = insert_voucher(&pool, &format!("{:?}", creator.address())).await;
+
+    assert_eq!(delete_status(&pool, &voucher_id, None).await, 401);
+    let stranger = LocalWallet::new(&mut rand::thread_rng());
+    let signed = creator_auth(&stranger, "delete_voucher", &voucher_id).await;
+    assert_eq!(delete_status(&pool, &voucher_id, Some(signed)).await, 401);
+    // A signature over another action does not carry over
+    let signed = creator_auth(&creator, "list_codes", &voucher_id).await;
+    assert_eq!(delete_status(&pool, &voucher_id, Some(signed)).await, 401);
+
+    let voucher = voucher_service(pool.clone()).get_voucher_by_id(&voucher_id).await.unwrap().unwrap();
+    assert_eq!(voucher.status, "active");
+
+    let signed = creator_auth(&creator, "delete_voucher", &voucher_id).await;
+    assert_eq!(delete_status(&pool, &voucher_id, Some(signed)).await, 200);
+    let voucher = voucher_service(pool.clone()).get_voucher_by_id(&voucher_id).await.unwrap().unwrap();
+    assert_eq!(voucher.status, "withdrawn");
+}
+
+#[actix_rt::test]
+async fn test_reported_claims_are_checked_on_chain() {
+    let Some(pool) = test_database().await else { return };
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    let recipient = Address::random();
+    let claimed_log = |address: Address| Log {
+        address,
+        topics: vec![VoucherClaimed::signature(), voucher_id.parse().unwrap(), H256::from(recipient)],
+        ..Default::default()
+    };
+
+    // One transaction claims the voucher; the other only emits a lookalike
+    // log from another contract
+    let real = H256::random();
+    let forged = H256::random();
+    let mock_server = MockServer::start().await;
+    for (tx_hash, address) in [(real, VOUCHER_CONTRACT.parse().unwrap()), (forged, Address::random())] {
+        let receipt = TransactionReceipt {
+            transaction_hash: tx_hash,
+            status: Some(1.into()),
+            logs: vec![claimed_log(address)],
+            ..Default::default()
+        };
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": "eth_getTransactionReceipt", "params": [tx_hash] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": receipt })))
+            .mount(&mock_server)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "jsonrpc": "2.0", "id": 1, "result": null })))
+        .with_priority(10)
+        .mount(&mock_server)
+        .await;
+
+    let provider = Arc::new(Provider::<Http>::try_from(mock_server.uri()).unwrap());
+    let service = voucher_service(pool.clone()).with_provider(provider);
+    let ctx = AuditContext::system("test");
+
+    for tx_hash in [forged, H256::random()] {
+        let err = service.update_claim_status(&code, &format!("{:?}", tx_hash), true, &ctx).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<ClaimError>(), Some(ClaimError::ClaimNotOnChain)));
+    }
+    let voucher = service.get_voucher_by_id(&voucher_id).await.unwrap().unwrap();
+    assert_eq!(voucher.status, "active");
+
+    service.update_claim_status(&code, &format!("{:?}", real), true, &ctx).await.unwrap();
+    let voucher = service.get_voucher_by_id(&voucher_id).await.unwrap().unwrap();
+    assert_eq!(voucher.status, "claimed");
+    assert_eq!(voucher.claimed_by.as_deref(), Some(format!("{:?}", recipient).as_str()));
+}
+
+async fn verify(pool: &sqlx::PgPool, code: &str) -> (u16, serde_json::Value) {
+    let response = verify_voucher(
+        web::Data::new(pool.clone()),
+        web::Data::new(voucher_service(pool.clone())),
+        // Local callers skip the rate limit, so Redis is never reached
+        web::Data::new(RedisRateLimiter::new("redis://127.0.0.1:1").unwrap()),
+        web::Json(VerifyRequest { code: code.to_string(), password: None }),
+        TestRequest::default().peer_addr("127.0.0.1:40000".parse().unwrap()).to_http_request(),
+    ).await.unwrap();
+    let status = response.status().as_u16();
+    let body = to_bytes(response.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[actix_rt::test]
+async fn test_verify_reports_closed_vouchers() {
+    let Some(pool) = test_database().await else { return };
+    let lifecycle = VoucherLifecycle::new(pool.clone());
+    let ctx = AuditContext::system("test");
+
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    assert_eq!(verify(&pool, &code).await.0, 200);
+
+    lifecycle.transition(&voucher_id, VoucherStatus::Withdrawn, &StatusChange::default(), &ctx).await.unwrap();
+    let (status, body) = verify(&pool, &code).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "Voucher has been withdrawn by its creator");
+    assert_eq!(body["status"], "withdrawn");
+
+    // Outside the claim window it gets the claim path's errors
+    let (voucher_id, code) = insert_voucher(&pool, CREATOR).await;
+    sqlx::query("UPDATE voucher_codes SET claimable_from = NOW() + INTERVAL '1 day' WHERE voucher_id = $1")
+        .bind(&voucher_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(verify(&pool, &code).await.0, 403);
+    sqlx::query("UPDATE voucher_codes SET claimable_from = NULL, claimable_until = NOW() - INTERVAL '1 day' WHERE voucher_id = $1")
+        .bind(&voucher_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(verify(&pool, &code).await.0, 410);
+}
@@ -0,0 +1,241 @@
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use nbgn_backend::db::voucher_models::{AddCodeRequest, ClaimWindow, CreatorAuth};
+use nbgn_backend::services::audit::AuditContext;
+use nbgn_backend::services::lifecycle::{StatusChange, VoucherLifecycle, VoucherStatus};
+use nbgn_backend::services::voucher::{create_link_params, LinkError, VoucherService};
+use serde_json::json;
+use std::sync::Arc;
+use test_utils::{creator_auth_with_params, insert_voucher, lazy_pool, test_database, voucher_service};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod test_utils;
+
+const VOUCHER_ID: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+
+// Provider answering vouchers(bytes32) with the given tuple
+async fn provider_with_voucher(creator: Address, amount: u64, claimed: bool) -> (Arc<Provider<Http>>, MockServer) {
+    let mock_server = MockServer::start().await;
+    let result = encode(&[
+        Token::Address(creator),
+        Token::Uint(U256::from(amount)),
+        Token::Bool(claimed),
+    ]);
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({ "method": "eth_call" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": format!("0x{}", hex::encode(result))
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let provider = Provider::<Http>::try_from(mock_server.uri()).unwrap();
+    (Arc::new(provider), mock_server)
+}
+
+// Every case using this fails before the database is touched, so the pool never connects
+async fn service_with_voucher(creator: Address, amount: u64, claimed: bool) -> (VoucherService, MockServer) {
+    let (provider, mock_server) = provider_with_voucher(creator, amount, claimed).await;
+    (voucher_service(lazy_pool()).with_provider(provider), mock_server)
+}
+
+async fn sign_link(wallet: &LocalWallet, voucher_id: &str, password: Option<&str>, amount: Option<&str>) -> CreatorAuth {
+    let params = create_link_params(password, amount, &ClaimWindow::default());
+    creator_auth_with_params(wallet, "create_link", voucher_id, &params).await
+}
+
+fn link_error(e: Box<dyn std::error::Error>) -> LinkError {
+    *e.downcast::<LinkError>().expect("expected a LinkError")
+}
+
+#[actix_rt::test]
+async fn test_link_requires_on_chain_creator() {
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let other = LocalWallet::new(&mut rand::thread_rng());
+    let (service, _server) = service_with_voucher(creator.address(), 1000, false).await;
+    let ctx = AuditContext::system("test");
+
+    // Signed by someone other than the on-chain creator
+    let auth = sign_link(&other, VOUCHER_ID, Some("pw"), None).await;
+    let err = service.create_voucher_link(VOUCHER_ID, Some("pw"), None, &ClaimWindow::default(), &auth, &ctx).await.unwrap_err();
+    assert!(matches!(link_error(err), LinkError::Unauthorized(_)));
+
+    // Claiming to be the creator with someone else's signature
+    let mut auth = sign_link(&other, VOUCHER_ID, Some("pw"), None).await;
+    auth.address = format!("{:?}", creator.address());
+    let err = service.create_voucher_link(VOUCHER_ID, Some("pw"), None, &ClaimWindow::default(), &auth, &ctx).await.unwrap_err();
+    assert!(matches!(link_error(err), LinkError::Unauthorized(_)));
+
+    // A signature for a different voucher does not carry over
+    let other_id = "0x2222222222222222222222222222222222222222222222222222222222222222";
+    let auth = sign_link(&creator, other_id, Some("pw"), None).await;
+    let err = service.create_voucher_link(VOUCHER_ID, Some("pw"), None, &ClaimWindow::default(), &auth, &ctx).await.unwrap_err();
+    assert!(matches!(link_error(err), LinkError::Unauthorized(_)));
+
+    // Nor does one for another password, amount or claim window
+    let auth = sign_link(&creator, VOUCHER_ID, Some("pw"), None).await;
+    for (password, amount) in [(Some("other"), None), (None, None), (Some("pw"), Some("1000"))] {
+        let err = service.create_voucher_link(VOUCHER_ID, password, amount, &ClaimWindow::default(), &auth, &ctx).await.unwrap_err();
+        assert!(matches!(link_error(err), LinkError::Unauthorized(_)));
+    }
+    let window = ClaimWindow { claimable_from: Some(chrono::Utc::now()), claimable_until: None };
+    let err = service.create_voucher_link(VOUCHER_ID, Some("pw"), None, &window, &auth, &ctx).await.unwrap_err();
+    assert!(matches!(link_error(err), LinkError::Unauthorized(_)));
+
+    // The creator's own signature passes, but the amount must match the chain
+    let auth = sign_link(&creator, VOUCHER_ID, None, Some("999")).await;
+    let err = service.create_voucher_link(VOUCHER_ID, None, Some("999"), &ClaimWindow::default(), &auth, &ctx).await.unwrap_err();
+    match link_error(err) {
+        LinkError::AmountMismatch { on_chain } => assert_eq!(on_chain, "1000"),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[actix_rt::test]
+async fn test_link_rejects_missing_or_claimed_vouchers() {
+    let creator = LocalWallet::new(&mut rand::thread_rng());
+    let ctx = AuditContext::system("test");
+    let auth = sign_link(&creator, VOUCHER_ID, None, None).await;
+
+    let (service, _server) = service_with_voucher(Address::zero(), 0, false).await;
+    let err = service.create_voucher_link(VOUCHER_ID, None, None, &ClaimWindow::default(), &auth, &ctx).await.unwrap_err();
+    assert!(matches!(link_error(err), LinkError::NotFound));
+
+    let (service, _server) = service_with_voucher(creator.address(), 1000, true).await;
+    let err = service.create_voucher_link(VOUCHER_ID, None, None, &ClaimWindow::default(), &auth, &ctx).await.unwrap_err();
+    assert!(matches!(link_error(err), LinkError::AlreadyClaimed));
+}
+
+#[test]
+fn test_add_code_request_carries_creator_auth() {
+    let req: AddCodeRequest = serde_json::from_value(json!({
+        "label": "sent to Maria by SMS",
+        "address": "0x1111111111111111111111111111111111111111",
+        "timestamp": 1700000000,
+        "signature": "0xabc"
+    })).unwrap();
+
+    assert_eq!(req.label.as_deref(), Some("sent to Maria by SMS"));
+    assert!(req.password.is_none());
+    assert_eq!(req.creator.timestamp, 1700000000);
+    assert_eq!(req.creator.address, "0x1111111111111111111111111111111111111111");
+}
+
+#[actix_rt::test]
+async fn test_window_update_keeps_the_end_left_out() {
+    let Some(pool) = test_database().await else { return };
+    let creator = format!("{:?}", Address::random());
+    let (provider, _server) = provider_with_voucher(creator.parse().unwrap(), 1000, false).await;
+    let service = voucher_service(pool.clone()).with_provider(provider);
+    let ctx = AuditContext::system("test");
+    let (voucher_id, code) = insert_voucher(&pool, &creator).await;
+
+    let from = chrono::Utc::now() + chrono::Duration::hours(1);
+    let until = from + chrono::Duration::days(7);
+    let window = |claimable_from, claimable_until| ClaimWindow { claimable_from, claimable_until };
+    let stored = || async {
+        let (from, until): (Option<chrono::DateTime<chrono::Utc>>, Option<chrono::DateTime<chrono::Utc>>) = sqlx::query_as(
+            "SELECT claimable_from, claimable_until FROM voucher_codes WHERE code = $1"
+        )
+        .bind(&code)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        (from.map(|at| at.timestamp()), until.map(|at| at.timestamp()))
+    };
+
+    service.link_for_creator(&voucher_id, None, None, &window(Some(from), None), &creator, None, &ctx).await.unwrap();
+    assert_eq!(stored().await, (Some(from.timestamp()), None));
+
+    // Setting the end keeps the start
+    service.link_for_creator(&voucher_id, None, None, &window(None, Some(until)), &creator, None, &ctx).await.unwrap();
+    assert_eq!(stored().await, (Some(from.timestamp()), Some(until.timestamp())));
+
+    // And a start past the kept end is refused
+    let late = until + chrono::Duration::days(1);
+    let err = service.link_for_creator(&voucher_id, None, None, &window(Some(late), None), &creator, None, &ctx).await.unwrap_err();
+    assert!(matches!(link_error(err), LinkError::InvalidWindow(_)));
+    assert_eq!(stored().await, (Some(from.timestamp()), Some(until.timestamp())));
+}
+
+#[actix_rt::test]
+async fn test_relinking_a_fully_revoked_voucher_keeps_its_status() {
+    let Some(pool) = test_database().await else { return };
+    let creator = format!("{:?}", Address::random());
+    let (provider, _server) = provider_with_voucher(creator.parse().unwrap(), 1000, false).await;
+    let service = voucher_service(pool.clone()).with_provider(provider);
+    let ctx = AuditContext::system("test");
+    let no_window = ClaimWindow { claimable_from: None, claimable_until: None };
+
+    // A voucher whose only code was revoked gets a new one, without a second creation
+    let (voucher_id, code) = insert_voucher(&pool, &creator).await;
+    VoucherLifecycle::record_created(&pool, &voucher_id, None, &ctx).await.unwrap();
+    service.revoke_code(&code, &ctx).await.unwrap();
+    let relinked = service.link_for_creator(&voucher_id, None, None, &no_window, &creator, None, &ctx).await.unwrap();
+    assert_ne!(relinked, code);
+    let created = service.lifecycle().events(&voucher_id).await.unwrap()
+        .iter()
+        .filter(|event| event.from_status.is_none())
+        .count();
+    assert_eq!(created, 1);
+
+    // A withdrawn voucher is not reopened by relinking it
+    service.lifecycle().transition(&voucher_id, VoucherStatus::Withdrawn, &StatusChange::default(), &ctx).await.unwrap();
+    service.revoke_code(&relinked, &ctx).await.unwrap();
+    assert!(service.link_for_creator(&voucher_id, None, None, &no_window, &creator, None, &ctx).await.is_err());
+    let statuses: Vec<(String,)> = sqlx::query_as("SELECT status FROM voucher_codes WHERE voucher_id = $1")
+        .bind(&voucher_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(statuses.len(), 2);
+    assert!(statuses.iter().all(|(status,)| status == "withdrawn"));
+}
+
+#[actix_rt::test]
+async fn test_failed_attach_leaves_no_live_code() {
+    let Some(pool) = test_database().await else { return };
+    let creator = format!("{:?}", Address::random());
+    let mut campaigns = Vec::new();
+    for name in ["Plovdiv meetup", "Varna meetup"] {
+        let (id,): (i64,) = sqlx::query_as(
+            "INSERT INTO campaigns (name, creator_address) VALUES ($1, $2) RETURNING id"
+        )
+        .bind(name)
+        .bind(&creator)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        campaigns.push(id);
+    }
+
+    // The only code is revoked, and the voucher already sits in the first campaign
+    let (voucher_id, _) = insert_voucher(&pool, &creator).await;
+    sqlx::query("UPDATE voucher_codes SET campaign_id = $1, revoked_at = NOW() WHERE voucher_id = $2")
+        .bind(campaigns[0])
+        .bind(&voucher_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (provider, _server) = provider_with_voucher(creator.parse().unwrap(), 1000, false).await;
+    let service = voucher_service(pool.clone()).with_provider(provider);
+    let ctx = AuditContext::system("test");
+    let err = service
+        .link_for_creator(&voucher_id, None, None, &ClaimWindow::default(), &creator, Some(campaigns[1]), &ctx)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Voucher belongs to another campaign");
+
+    let (live,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM voucher_codes WHERE voucher_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(&voucher_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(live, 0);
+}
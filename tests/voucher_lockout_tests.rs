@@ -0,0 +1,54 @@
+use ethers::signers::{LocalWallet, Signer};
+use nbgn_backend::config::LockoutConfig;
+use nbgn_backend::services::auth::{creator_action_message, verify_action_signature};
+use nbgn_backend::services::voucher::VoucherService;
+
+#[test]
+fn test_lockout_duration_backoff() {
+    let policy = LockoutConfig {
+        free_attempts: 3,
+        base_lockout_secs: 60,
+        max_lockout_secs: 600,
+    };
+
+    assert!(VoucherService::lockout_duration(&policy, 0).is_none());
+    assert!(VoucherService::lockout_duration(&policy, 2).is_none());
+
+    // Doubles with every failure past the free attempts
+    assert_eq!(VoucherService::lockout_duration(&policy, 3).unwrap().num_seconds(), 60);
+    assert_eq!(VoucherService::lockout_duration(&policy, 4).unwrap().num_seconds(), 120);
+    assert_eq!(VoucherService::lockout_duration(&policy, 5).unwrap().num_seconds(), 240);
+
+    // Capped at the maximum
+    assert_eq!(VoucherService::lockout_duration(&policy, 7).unwrap().num_seconds(), 600);
+    assert_eq!(VoucherService::lockout_duration(&policy, u32::MAX).unwrap().num_seconds(), 600);
+}
+
+#[actix_rt::test]
+async fn test_creator_action_signature() {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let address = format!("{:?}", wallet.address());
+    let timestamp = chrono::Utc::now().timestamp();
+
+    let message = creator_action_message("reset_lockout", "ABCD1234EFGH5678", timestamp);
+    let signature = wallet.sign_message(&message).await.unwrap().to_string();
+
+    assert!(verify_action_signature(&address, "reset_lockout", "ABCD1234EFGH5678", timestamp, &signature).is_ok());
+
+    // Signature is bound to the action, the target and the signer
+    assert!(verify_action_signature(&address, "view_lockout", "ABCD1234EFGH5678", timestamp, &signature).is_err());
+    assert!(verify_action_signature(&address, "reset_lockout", "OTHERCODE0000000", timestamp, &signature).is_err());
+    assert!(verify_action_signature(
+        "0x0000000000000000000000000000000000000001",
+        "reset_lockout",
+        "ABCD1234EFGH5678",
+        timestamp,
+        &signature
+    ).is_err());
+
+    // Stale signatures are rejected
+    let stale = timestamp - 3600;
+    let message = creator_action_message("reset_lockout", "ABCD1234EFGH5678", stale);
+    let signature = wallet.sign_message(&message).await.unwrap().to_string();
+    assert!(verify_action_signature(&address, "reset_lockout", "ABCD1234EFGH5678", stale, &signature).is_err());
+}
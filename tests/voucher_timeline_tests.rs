@@ -0,0 +1,107 @@
+use actix_web::{test::TestRequest, web};
+use chrono::Utc;
+use ethers::abi::{encode, Token};
+use ethers::utils::id;
+use nbgn_backend::contracts::errors::{DecodedRevert, VoucherError};
+use nbgn_backend::db::voucher_models::ClaimAttempt;
+use nbgn_backend::api::voucher_routes::get_timeline;
+use nbgn_backend::config::AdminConfig;
+use nbgn_backend::services::timeline::{anonymize_ip, TimelineEntry, TimelineService};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::time::Duration;
+use test_utils::{test_database, voucher_service};
+
+mod test_utils;
+
+async fn timeline_status(pool: PgPool, code: &str) -> u16 {
+    let response = get_timeline(
+        web::Data::new(voucher_service(pool.clone())),
+        web::Data::new(TimelineService::new(pool)),
+        web::Data::new(AdminConfig::default()),
+        web::Path::from(code.to_string()),
+        None,
+        TestRequest::default().to_http_request(),
+    ).await.unwrap();
+    response.status().as_u16()
+}
+
+fn attempt(recipient: Option<&str>, failure_reason: Option<&str>) -> ClaimAttempt {
+    ClaimAttempt {
+        id: 1,
+        voucher_code: Some("ABCD1234EFGH5678".to_string()),
+        ip_address: Some("203.0.113.57".to_string()),
+        attempted_at: Utc::now(),
+        success: Some(false),
+        recipient_address: recipient.map(str::to_string),
+        failure_reason: failure_reason.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_custom_errors_decode_by_selector() {
+    let data = id("SignatureExpired()").to_vec();
+    assert_eq!(VoucherError::from_revert_data(&data), Some(VoucherError::SignatureExpired));
+
+    let decoded = DecodedRevert::decode(&data);
+    assert_eq!(decoded.error, "SignatureExpired");
+    assert_eq!(decoded.message, "The claim authorization has expired - please request a new one");
+    assert_eq!(decoded.data, "0x0819bdcd");
+
+    assert_eq!(
+        VoucherError::from_revert_data(&id("VoucherAlreadyClaimed()")),
+        Some(VoucherError::VoucherAlreadyClaimed)
+    );
+    assert_eq!(VoucherError::from_revert_data(&[0x08, 0x19]), None);
+}
+
+#[test]
+fn test_require_messages_and_unknown_reverts() {
+    let mut data = id("Error(string)").to_vec();
+    data.extend(encode(&[Token::String("Voucher cancelled".to_string())]));
+    let decoded = DecodedRevert::decode(&data);
+    assert_eq!(decoded.error, "Error");
+    assert_eq!(decoded.message, "Voucher cancelled");
+
+    let decoded = DecodedRevert::decode(&[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(decoded.error, "Unknown");
+    assert_eq!(decoded.data, "0xdeadbeef");
+}
+
+#[test]
+fn test_ips_keep_only_their_network() {
+    assert_eq!(anonymize_ip("203.0.113.57"), "203.0.113.0");
+    assert_eq!(anonymize_ip("203.0.113.57:40112"), "203.0.113.0");
+    assert_eq!(anonymize_ip("2001:db8:85a3:8d3:1319:8a2e:370:7348"), "2001:db8:85a3::");
+    assert_eq!(anonymize_ip("::ffff:198.51.100.7"), "198.51.100.0");
+    assert_eq!(anonymize_ip("unknown"), "unknown");
+}
+
+#[test]
+fn test_attempts_are_told_apart() {
+    let verify = TimelineEntry::from_attempt(&attempt(None, None));
+    assert_eq!(verify.event, "verify");
+    assert_eq!(verify.detail["ip"], "203.0.113.0");
+    assert_eq!(verify.code.as_deref(), Some("ABCD1234EFGH5678"));
+
+    let claim = TimelineEntry::from_attempt(&attempt(Some("0x2222222222222222222222222222222222222222"), None));
+    assert_eq!(claim.event, "claim_requested");
+    assert_eq!(claim.detail["recipient"], "0x2222222222222222222222222222222222222222");
+
+    let password = TimelineEntry::from_attempt(&attempt(None, Some("invalid_password")));
+    assert_eq!(password.event, "password_failed");
+    assert_eq!(password.detail["reason"], "invalid_password");
+}
+
+#[actix_rt::test]
+async fn test_lookup_failures_are_not_reported_as_missing() {
+    // Nothing listens here, so the lookup itself fails
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_secs(1))
+        .connect_lazy("postgres://localhost:1/nbgn_unused")
+        .unwrap();
+    assert_eq!(timeline_status(pool, "ABCD1234EFGH5678").await, 500);
+
+    let Some(pool) = test_database().await else { return };
+    assert_eq!(timeline_status(pool, "ZZZZ9999ZZZZ9999").await, 404);
+}
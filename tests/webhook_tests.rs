@@ -0,0 +1,77 @@
+use chrono::{Duration, TimeZone, Utc};
+use nbgn_backend::config::WebhookConfig;
+use nbgn_backend::services::claim_monitor::ClaimOutcome;
+use nbgn_backend::services::webhooks::{backoff, envelope, sign_payload, WebhookEvent, WebhookService};
+use serde_json::json;
+
+#[test]
+fn test_payload_signature() {
+    let signature = sign_payload("whsec_test", 1700000000, br#"{"id":"evt_1"}"#);
+    assert_eq!(signature, "v1=c89214b5b5da833daed6f0b8c5bb6bd58cea9022bd80ccc78230f3942d632925");
+
+    // Bound to the timestamp and the secret
+    assert_ne!(sign_payload("whsec_test", 1700000001, br#"{"id":"evt_1"}"#), signature);
+    assert_ne!(sign_payload("whsec_other", 1700000000, br#"{"id":"evt_1"}"#), signature);
+}
+
+#[test]
+fn test_backoff_doubles_up_to_the_cap() {
+    let config = WebhookConfig {
+        initial_backoff_secs: 30,
+        max_backoff_secs: 3600,
+        ..WebhookConfig::default()
+    };
+
+    let delays: Vec<u64> = (1..=9).map(|attempts| backoff(&config, attempts).as_secs()).collect();
+    assert_eq!(delays, vec![30, 60, 120, 240, 480, 960, 1920, 3600, 3600]);
+
+    // No overflow however many attempts were made
+    assert_eq!(backoff(&config, i32::MAX).as_secs(), 3600);
+}
+
+#[test]
+fn test_event_names_round_trip() {
+    for event in WebhookEvent::ALL {
+        assert_eq!(WebhookEvent::parse(event.as_str()), Some(event));
+    }
+    assert_eq!(WebhookEvent::parse("voucher.expired"), None);
+}
+
+#[test]
+fn test_subscription_validation() {
+    let events = vec!["voucher.claimed".to_string(), "nbgn.minted".to_string()];
+    assert!(WebhookService::validate_subscription("https://partner.example/hooks", &events).is_ok());
+    assert!(WebhookService::validate_subscription("https://partner.example/hooks", &["*".to_string()]).is_ok());
+
+    assert!(WebhookService::validate_subscription("ftp://partner.example/hooks", &events).is_err());
+    assert!(WebhookService::validate_subscription("not a url", &events).is_err());
+    assert!(WebhookService::validate_subscription("https://partner.example/hooks", &[]).is_err());
+    assert!(WebhookService::validate_subscription("https://partner.example/hooks", &["voucher.exploded".to_string()]).is_err());
+}
+
+#[test]
+fn test_envelope() {
+    let created_at = Utc.with_ymd_and_hms(2024, 3, 21, 12, 0, 0).unwrap();
+    let body = envelope(42, "voucher.claimed", created_at, &json!({ "voucher_id": "0x11" }));
+
+    assert_eq!(body["id"], "evt_42");
+    assert_eq!(body["type"], "voucher.claimed");
+    assert_eq!(body["created_at"], "2024-03-21T12:00:00Z");
+    assert_eq!(body["data"]["voucher_id"], "0x11");
+}
+
+#[test]
+fn test_claim_outcome() {
+    let just_now = Some(Utc::now());
+    let long_ago = Some(Utc::now() - Duration::hours(2));
+
+    assert_eq!(ClaimOutcome::from_chain(Some(1), true, just_now, 1800), Some(ClaimOutcome::Confirmed));
+    assert_eq!(ClaimOutcome::from_chain(Some(0), true, just_now, 1800), Some(ClaimOutcome::Reverted));
+
+    // Still in the mempool, however old
+    assert_eq!(ClaimOutcome::from_chain(None, true, long_ago, 1800), None);
+
+    // Unknown to the node: give it time to propagate before calling it dropped
+    assert_eq!(ClaimOutcome::from_chain(None, false, just_now, 1800), None);
+    assert_eq!(ClaimOutcome::from_chain(None, false, long_ago, 1800), Some(ClaimOutcome::Dropped));
+}